@@ -0,0 +1,97 @@
+//! RowBinary benchmark: `Value` rows vs serde rows
+
+use clickhouse_client::{
+    orm::prelude::*,
+    query::{from_rowbin, to_rowbin, Format, QueryData},
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde::{Deserialize, Serialize};
+
+/// Number of rows
+const N_ROWS: usize = 10_000;
+
+/// Benchmark row
+#[derive(Debug, Clone, AsChRecord, Serialize, Deserialize)]
+#[ch(table = "bench")]
+struct BenchRow {
+    #[ch(primary_key)]
+    id: u64,
+    name: String,
+    score: f64,
+    flag: Option<u8>,
+    tags: Vec<String>,
+}
+
+/// Creates the benchmark rows
+fn rows() -> Vec<BenchRow> {
+    (0..N_ROWS)
+        .map(|i| BenchRow {
+            id: i as u64,
+            name: format!("name_{i}"),
+            score: i as f64 / 3.0,
+            flag: if i % 2 == 0 { Some(1) } else { None },
+            tags: vec!["a".to_string(), format!("tag_{i}")],
+        })
+        .collect()
+}
+
+fn bench_serialize(c: &mut Criterion) {
+    let rows = rows();
+    let types = BenchRow::ch_schema()
+        .columns
+        .iter()
+        .map(|c| c.ty.clone())
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("rowbin_serialize");
+    group.bench_function("value", |b| {
+        b.iter(|| {
+            let data = BenchRow::to_query_data(black_box(rows.clone()));
+            data.to_bytes(Format::RowBinary).unwrap()
+        })
+    });
+    group.bench_function("serde", |b| {
+        b.iter(|| to_rowbin(black_box(&rows), &types).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_deserialize(c: &mut Criterion) {
+    let schema = BenchRow::ch_schema();
+    let mapping = schema
+        .columns
+        .iter()
+        .map(|c| (c.id.as_str(), c.ty.clone()))
+        .collect::<Vec<_>>();
+    let types = mapping.iter().map(|(_, ty)| ty.clone()).collect::<Vec<_>>();
+    let bytes = to_rowbin(&rows(), &types).unwrap();
+
+    // NB: records are mapped by column names, which are read from the header
+    // (the lengths fit in 1 byte)
+    let mut bytes_with_names = vec![mapping.len() as u8];
+    for (name, _) in &mapping {
+        bytes_with_names.push(name.len() as u8);
+        bytes_with_names.extend_from_slice(name.as_bytes());
+    }
+    bytes_with_names.extend_from_slice(&bytes);
+
+    let mut group = c.benchmark_group("rowbin_deserialize");
+    group.bench_function("value", |b| {
+        b.iter(|| {
+            let data = QueryData::from_bytes(
+                black_box(&bytes_with_names),
+                Format::RowBinaryWithNames,
+                Some(&mapping),
+            )
+            .unwrap();
+            BenchRow::from_query_data(data).unwrap()
+        })
+    });
+    group.bench_function("serde", |b| {
+        b.iter(|| from_rowbin::<BenchRow>(black_box(&bytes), &types).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_serialize, bench_deserialize);
+criterion_main!(benches);
@@ -3,11 +3,23 @@
 //! # [derive(AsChRecord)]
 //!
 //! This macro parses a struct and implements the trait `clickhouse-client::orm::ChRecord`
+//!
+//! # [derive(ChEnum)]
+//!
+//! This macro parses an enum and implements the trait `clickhouse-client::value::ChValue`
+//!
+//! # [derive(ChTuple)]
+//!
+//! This macro parses a struct and implements the trait `clickhouse-client::value::ChValue`,
+//! mapping the struct to a named tuple
 
 use proc_macro::TokenStream;
 use proc_macro_error::{abort, proc_macro_error, OptionExt};
 use quote::quote;
-use syn::{parse_macro_input, spanned::Spanned, Field, Ident, ItemStruct, LitBool, LitStr};
+use syn::{
+    parse_macro_input, spanned::Spanned, Expr, Field, Fields, Ident, ItemEnum, ItemStruct, LitBool,
+    LitStr, UnOp, Variant,
+};
 
 /// A macro to derive the trait `ChRecord`
 ///
@@ -21,18 +33,21 @@ use syn::{parse_macro_input, spanned::Spanned, Field, Ident, ItemStruct, LitBool
 ///     - `Type`
 ///     - `TableSchema`
 ///     - `Error`
+///     - `ChNested` (if a field is nested)
 ///
 /// # Attributes
 ///
 /// This macro accepts struct and field level attribute called `ch`.
 ///
 /// ## Struct level attributes:
-/// - **table**: table name (mandatory)
+/// - **table**: table name (mandatory, unless the struct is nested)
+/// - **nested**: the struct is the element of a `Nested` column (optional, no primary key required)
 ///
 /// ## Field level attributes:
 /// - **name**: column name (optional)
 /// - **primary_key**: indicates a primary key (optional)
 /// - **skip**: field is skipped (optional)
+/// - **nested**: field is a `Nested` column, whose type must implement `ChNested` (optional)
 ///
 /// # Example
 ///
@@ -46,6 +61,14 @@ use syn::{parse_macro_input, spanned::Spanned, Field, Ident, ItemStruct, LitBool
 ///   id: u32,
 ///   #[ch(skip)]
 ///   other: String,
+///   #[ch(nested)]
+///   items: Vec<MyItem>,
+/// }
+///
+/// #[derive(AsChRecord)]
+/// #[ch(nested)]
+/// struct MyItem {
+///   name: String,
 /// }
 /// ```
 #[proc_macro_error]
@@ -64,6 +87,7 @@ pub fn derive_db_record(input: TokenStream) -> TokenStream {
     let attrs = StructAttrs::parse("ch", &input);
     // eprintln!("struct_attrs= {:#?}", struct_attrs);
     let table_name = attrs.table_name;
+    let is_nested = attrs.nested;
 
     // parse fields
     let mut schema_entries = vec![];
@@ -80,7 +104,28 @@ pub fn derive_db_record(input: TokenStream) -> TokenStream {
         let col_name = &attrs.col_name;
         let col_primary = &attrs.primary;
 
-        if !attrs.skip.value {
+        if !attrs.skip.value && attrs.nested {
+            // .column("items", <Vec<Item>>::ch_nested_type(), false)
+            schema_entries.push(quote! {
+                .column(#col_name, <#field_type as ChNested>::ch_nested_type(), #col_primary)
+            });
+
+            // .field("items", false, <Vec<Item>>::ch_nested_type(), self.items.into_ch_nested())
+            into_record_entries.push(quote! {
+                .field(#col_name, #col_primary, <#field_type as ChNested>::ch_nested_type(), ChNested::into_ch_nested(self.#field_id))
+            });
+
+            from_record_entries.push(quote! {
+                #field_id: match record.remove_field(#col_name) {
+                    Some(field) => <#field_type as ChNested>::from_ch_nested(field.value)?,
+                    None => return Err(Error::new(format!("Missing field '{}'", #col_name).as_str())),
+                }
+            });
+
+            if col_primary.value {
+                has_primary_key = true;
+            }
+        } else if !attrs.skip.value {
             // .column("id", Uuid::ch_type(), true)
             schema_entries.push(quote! {
                 .column(#col_name, <#field_type>::ch_type(), #col_primary)
@@ -109,7 +154,7 @@ pub fn derive_db_record(input: TokenStream) -> TokenStream {
     }
 
     // check that there is at least 1 primary key
-    if !has_primary_key {
+    if !has_primary_key && !is_nested {
         abort!(input.span(), "There must at least 1 primary key");
     }
 
@@ -135,15 +180,334 @@ pub fn derive_db_record(input: TokenStream) -> TokenStream {
     .into()
 }
 
+/// A macro to derive the trait `ChValue` for an enum
+///
+/// The enum maps to an `Enum8` column, or to an `Enum16` column if a variant value does not fit
+/// in an `i8`. `Option<T>` is also implemented, for `Nullable` columns.
+///
+/// # Prerequisites
+///
+/// - the enum must only have unit variants
+/// - The following types must be in scope:
+///     - `ChValue`
+///     - `Value`
+///     - `Type`
+///     - `Error`
+///
+/// # Attributes
+///
+/// This macro accepts enum and variant level attribute called `ch`.
+///
+/// ## Enum level attributes:
+/// - **enum16**: maps to an `Enum16` column (optional)
+///
+/// ## Variant level attributes:
+/// - **name**: variant name (optional, defaults to the variant identifier)
+/// - **value**: variant value (optional, defaults to the variant discriminant)
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(ChEnum)]
+/// enum Status {
+///   #[ch(name = "active")]
+///   Active,
+///   #[ch(value = 10)]
+///   Inactive,
+/// }
+/// ```
+#[proc_macro_error]
+#[proc_macro_derive(ChEnum, attributes(ch))]
+pub fn derive_ch_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as ItemEnum);
+
+    let ident = &input.ident;
+    if input.variants.is_empty() {
+        abort!(input.span(), "The enum must have at least 1 variant");
+    }
+
+    // parse enum attributes
+    let attrs = EnumAttrs::parse("ch", &input);
+
+    // parse variants
+    let mut variants = vec![];
+    let mut next_value = 0_i64;
+    for variant in &input.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            abort!(variant.span(), "Only unit variants are supported");
+        }
+
+        let attrs = VariantAttrs::parse("ch", variant);
+        let value = match (attrs.value, &variant.discriminant) {
+            (Some(value), _) => value,
+            (None, Some((_, expr))) => parse_discriminant(expr),
+            (None, None) => next_value,
+        };
+        next_value = value + 1;
+
+        if variants
+            .iter()
+            .any(|(_, name, _): &(Ident, LitStr, i64)| name.value() == attrs.name.value())
+        {
+            abort!(variant.span(), "duplicate variant name");
+        }
+        if variants.iter().any(|(_, _, v)| *v == value) {
+            abort!(variant.span(), "duplicate variant value");
+        }
+        if i16::try_from(value).is_err() {
+            abort!(variant.span(), "variant value must fit in an i16");
+        }
+        variants.push((variant.ident.clone(), attrs.name, value));
+    }
+
+    // Enum16 if forced, or if a value does not fit in an i8
+    let is_enum16 = attrs.enum16 || variants.iter().any(|(_, _, v)| i8::try_from(*v).is_err());
+    let (enum_variant, nullable_variant) = if is_enum16 {
+        (quote! { Enum16 }, quote! { NullableEnum16 })
+    } else {
+        (quote! { Enum8 }, quote! { NullableEnum8 })
+    };
+
+    let mut type_entries = vec![];
+    let mut into_value_entries = vec![];
+    let mut from_value_entries = vec![];
+    for (variant_id, name, value) in &variants {
+        let value = if is_enum16 {
+            let value = *value as i16;
+            quote! { #value }
+        } else {
+            let value = *value as i8;
+            quote! { #value }
+        };
+
+        // ("active".to_string(), 1_i8)
+        type_entries.push(quote! {
+            (#name.to_string(), #value)
+        });
+
+        // Self::Active => Value::Enum8(1_i8, Some("active".to_string()))
+        into_value_entries.push(quote! {
+            Self::#variant_id => Value::#enum_variant(#value, Some(#name.to_string()))
+        });
+
+        // v if v == 1_i8 => Ok(Self::Active)
+        from_value_entries.push(quote! {
+            v if v == #value => Ok(Self::#variant_id)
+        });
+    }
+
+    quote! {
+        impl ChValue for #ident {
+            fn ch_type() -> Type {
+                Type::#enum_variant(::std::collections::BTreeMap::from([
+                    #(#type_entries),*
+                ]))
+            }
+
+            fn into_ch_value(self) -> Value {
+                match self {
+                    #(#into_value_entries),*
+                }
+            }
+
+            fn from_ch_value(value: Value) -> Result<Self, Error> {
+                match value {
+                    Value::#enum_variant(v, _) => match v {
+                        #(#from_value_entries,)*
+                        _ => Err(Error::new(format!("Invalid enum value '{}'", v).as_str())),
+                    },
+                    _ => Err(Error::new("Cannot convert Value to base type")),
+                }
+            }
+        }
+
+        // NB: the impl is generated, because Option<T> is not a local type of the user crate
+        impl ChValue for Option<#ident> {
+            fn ch_type() -> Type {
+                Type::#nullable_variant(::std::collections::BTreeMap::from([
+                    #(#type_entries),*
+                ]))
+            }
+
+            fn into_ch_value(self) -> Value {
+                match self.map(|v| v.into_ch_value()) {
+                    Some(Value::#enum_variant(v, name)) => Value::#nullable_variant(Some(v), name),
+                    _ => Value::#nullable_variant(None, None),
+                }
+            }
+
+            fn from_ch_value(value: Value) -> Result<Self, Error> {
+                match value {
+                    Value::#nullable_variant(Some(v), name) => {
+                        <#ident>::from_ch_value(Value::#enum_variant(v, name)).map(Some)
+                    }
+                    Value::#nullable_variant(None, _) => Ok(None),
+                    _ => Err(Error::new("Cannot convert Value to base type")),
+                }
+            }
+        }
+    }
+    .into()
+}
+
+/// A macro to derive the trait `ChValue` for a struct mapping to a named tuple
+///
+/// The struct maps to a `Tuple(name1 T1, name2 T2, ...)` column.
+///
+/// # Prerequisites
+///
+/// - each field type must implement the trait `ChValue`
+/// - The following types must be in scope:
+///     - `ChValue`
+///     - `Value`
+///     - `Type`
+///     - `Error`
+///
+/// # Attributes
+///
+/// This macro accepts field level attribute called `ch`.
+///
+/// ## Field level attributes:
+/// - **name**: tuple element name (optional)
+/// - **skip**: field is skipped, and set to its default value when decoded (optional)
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(ChTuple)]
+/// struct Point {
+///   lat: f64,
+///   #[ch(name = "lon")]
+///   longitude: f64,
+/// }
+/// ```
+#[proc_macro_error]
+#[proc_macro_derive(ChTuple, attributes(ch))]
+pub fn derive_ch_tuple(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as ItemStruct);
+
+    let ident = &input.ident;
+
+    // parse fields
+    let mut type_entries = vec![];
+    let mut into_value_entries = vec![];
+    let mut from_named_entries = vec![];
+    let mut from_tuple_entries = vec![];
+    for field in &input.fields {
+        let attrs = FieldAttrs::parse("ch", field);
+        if attrs.primary.value || attrs.nested {
+            abort!(
+                field.span(),
+                "Only the attributes 'name' and 'skip' are supported"
+            );
+        }
+
+        let field_id = &attrs.field_id;
+        let field_type = &field.ty;
+        let el_name = &attrs.col_name;
+
+        if attrs.skip.value {
+            from_named_entries.push(quote! {
+                #field_id: Default::default()
+            });
+            from_tuple_entries.push(quote! {
+                #field_id: Default::default()
+            });
+            continue;
+        }
+
+        // ("lat".to_string(), <f64>::ch_type())
+        type_entries.push(quote! {
+            (#el_name.to_string(), <#field_type>::ch_type())
+        });
+
+        // ("lat".to_string(), self.lat.into_ch_value())
+        into_value_entries.push(quote! {
+            (#el_name.to_string(), self.#field_id.into_ch_value())
+        });
+
+        // lat: match values.iter().position(|(n, _)| n == "lat") { ... }
+        from_named_entries.push(quote! {
+            #field_id: match values.iter().position(|(n, _)| n == #el_name) {
+                Some(i) => <#field_type>::from_ch_value(values.swap_remove(i).1)?,
+                None => return Err(Error::new(format!("Missing tuple element '{}'", #el_name).as_str())),
+            }
+        });
+
+        // lat: <f64>::from_ch_value(iter.next().unwrap())?
+        from_tuple_entries.push(quote! {
+            #field_id: <#field_type>::from_ch_value(iter.next().unwrap())?
+        });
+    }
+
+    if type_entries.is_empty() {
+        abort!(input.span(), "The struct must have at least 1 field");
+    }
+    let n_elements = type_entries.len();
+
+    quote! {
+        impl ChValue for #ident {
+            fn ch_type() -> Type {
+                Type::NamedTuple(vec![
+                    #(#type_entries),*
+                ])
+            }
+
+            fn into_ch_value(self) -> Value {
+                Value::NamedTuple(vec![
+                    #(#into_value_entries),*
+                ])
+            }
+
+            fn from_ch_value(value: Value) -> Result<Self, Error> {
+                match value {
+                    Value::NamedTuple(mut values) => Ok(Self {
+                        #(#from_named_entries),*
+                    }),
+                    Value::Tuple(values) if values.len() == #n_elements => {
+                        let mut iter = values.into_iter();
+                        Ok(Self {
+                            #(#from_tuple_entries),*
+                        })
+                    }
+                    _ => Err(Error::new("Cannot convert Value to named tuple")),
+                }
+            }
+        }
+    }
+    .into()
+}
+
+/// Parses an enum discriminant (integer literal)
+fn parse_discriminant(expr: &Expr) -> i64 {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(lit),
+            ..
+        }) => match lit.base10_parse::<i64>() {
+            Ok(value) => value,
+            Err(_) => abort!(expr.span(), "invalid discriminant"),
+        },
+        Expr::Unary(syn::ExprUnary {
+            op: UnOp::Neg(_),
+            expr,
+            ..
+        }) => -parse_discriminant(expr),
+        _ => abort!(expr.span(), "discriminant must be an integer literal"),
+    }
+}
+
 /// Struct attributes
 struct StructAttrs {
     table_name: LitStr,
+    nested: bool,
 }
 
 impl StructAttrs {
     /// Parses the struct attribute
     fn parse(attr_key: &str, item: &ItemStruct) -> Self {
         let mut table_name: Option<LitStr> = None;
+        let mut nested = false;
 
         let attrs = &item.attrs;
         for attr in attrs.iter() {
@@ -155,6 +519,11 @@ impl StructAttrs {
                     if list.path.is_ident(attr_key) {
                         let tokens = list.tokens.to_string();
                         for part in tokens.split(',') {
+                            if part.trim() == "nested" {
+                                nested = true;
+                                continue;
+                            }
+
                             match part.trim().split_once('=') {
                                 Some((key, val)) => {
                                     let key = key.trim();
@@ -184,7 +553,7 @@ impl StructAttrs {
                                         _ => {
                                             abort!(
                                                 list.tokens.span(),
-                                                "invalid key (valid: table)"
+                                                "invalid key (valid: table, nested)"
                                             );
                                         }
                                     }
@@ -202,9 +571,13 @@ impl StructAttrs {
             }
         }
 
-        Self {
-            table_name: table_name.expect_or_abort("missing attribute 'table'"),
-        }
+        let table_name = match (table_name, nested) {
+            (Some(table_name), _) => table_name,
+            (None, true) => LitStr::new("", item.span()),
+            (None, false) => abort!(item.span(), "missing attribute 'table'"),
+        };
+
+        Self { table_name, nested }
     }
 }
 
@@ -214,6 +587,7 @@ struct FieldAttrs {
     col_name: LitStr,
     skip: LitBool,
     primary: LitBool,
+    nested: bool,
 }
 
 impl FieldAttrs {
@@ -223,6 +597,7 @@ impl FieldAttrs {
         let mut col_name = LitStr::new(field_id.to_string().as_str(), field.span());
         let mut skip = LitBool::new(false, field.span());
         let mut primary = LitBool::new(false, field.span());
+        let mut nested = false;
 
         for attr in field.attrs.iter() {
             // eprintln!("ATTR: {:#?}", attr);
@@ -233,7 +608,7 @@ impl FieldAttrs {
                     if list.path.is_ident(attr_key) {
                         let tokens = list.tokens.to_string();
                         for part in tokens.split(',') {
-                            match part {
+                            match part.trim() {
                                 "skip" => {
                                     skip = LitBool::new(true, list.tokens.span());
                                     continue;
@@ -242,6 +617,10 @@ impl FieldAttrs {
                                     primary = LitBool::new(true, list.tokens.span());
                                     continue;
                                 }
+                                "nested" => {
+                                    nested = true;
+                                    continue;
+                                }
                                 _ => {}
                             }
 
@@ -295,6 +674,125 @@ impl FieldAttrs {
             col_name,
             skip,
             primary,
+            nested,
         }
     }
 }
+
+/// Enum attributes
+struct EnumAttrs {
+    enum16: bool,
+}
+
+impl EnumAttrs {
+    /// Parses the enum attribute
+    fn parse(attr_key: &str, item: &ItemEnum) -> Self {
+        let mut enum16 = false;
+
+        for attr in item.attrs.iter() {
+            match &attr.meta {
+                syn::Meta::Path(_) => continue,
+                syn::Meta::NameValue(_) => continue,
+                syn::Meta::List(list) => {
+                    if list.path.is_ident(attr_key) {
+                        let tokens = list.tokens.to_string();
+                        for part in tokens.split(',') {
+                            match part.trim() {
+                                "enum16" => {
+                                    enum16 = true;
+                                }
+                                _ => {
+                                    abort!(list.tokens.span(), "invalid key (valid: enum16)");
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Self { enum16 }
+    }
+}
+
+/// Enum variant attributes
+struct VariantAttrs {
+    name: LitStr,
+    value: Option<i64>,
+}
+
+impl VariantAttrs {
+    /// Parses a variant
+    fn parse(attr_key: &str, variant: &Variant) -> Self {
+        let mut name = LitStr::new(variant.ident.to_string().as_str(), variant.span());
+        let mut value = None;
+
+        for attr in variant.attrs.iter() {
+            match &attr.meta {
+                syn::Meta::Path(_) => continue,
+                syn::Meta::NameValue(_) => continue,
+                syn::Meta::List(list) => {
+                    if list.path.is_ident(attr_key) {
+                        let tokens = list.tokens.to_string();
+                        for part in tokens.split(',') {
+                            match part.trim().split_once('=') {
+                                Some((key, val)) => {
+                                    let key = key.trim();
+                                    let val = val.trim();
+                                    if val.is_empty() {
+                                        abort!(list.tokens.span(), "missing value");
+                                    }
+
+                                    match key {
+                                        "name" => {
+                                            let val_lit = match syn::parse_str::<LitStr>(val) {
+                                                Ok(ok) => ok,
+                                                Err(_) => {
+                                                    abort!(
+                                                        list.tokens.span(),
+                                                        "value must be quoted"
+                                                    );
+                                                }
+                                            };
+                                            if val_lit.value().is_empty() {
+                                                abort!(list.tokens.span(), "value is empty");
+                                            }
+                                            name = val_lit;
+                                        }
+                                        "value" => {
+                                            // NB: negative values are tokenized as `- 1`
+                                            let val = val.replace(' ', "");
+                                            match val.parse::<i64>() {
+                                                Ok(ok) => value = Some(ok),
+                                                Err(_) => {
+                                                    abort!(
+                                                        list.tokens.span(),
+                                                        "value must be an integer"
+                                                    );
+                                                }
+                                            }
+                                        }
+                                        _ => {
+                                            abort!(
+                                                list.tokens.span(),
+                                                "invalid key (valid: name, value)"
+                                            );
+                                        }
+                                    }
+                                }
+                                None => {
+                                    abort!(
+                                        list.tokens.span(),
+                                        "invalid attribute (must be key=val)"
+                                    );
+                                }
+                            };
+                        }
+                    }
+                }
+            }
+        }
+
+        Self { name, value }
+    }
+}
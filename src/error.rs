@@ -17,6 +17,19 @@ impl Error {
     }
 }
 
+/// Unsupported format error
+///
+/// NB: the format has no built-in formatter, and no formatter is registered for it.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Unsupported format: {0}")]
+pub struct UnsupportedFormat(pub String);
+
+impl From<UnsupportedFormat> for Error {
+    fn from(value: UnsupportedFormat) -> Self {
+        Error(value.to_string())
+    }
+}
+
 impl From<hyper::http::Error> for Error {
     fn from(value: hyper::http::Error) -> Self {
         Error(value.to_string())
@@ -106,3 +119,38 @@ impl From<std::array::TryFromSliceError> for Error {
         Error(value.to_string())
     }
 }
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Error(value.to_string())
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl From<arrow::error::ArrowError> for Error {
+    fn from(value: arrow::error::ArrowError) -> Self {
+        Error(value.to_string())
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for Error {
+    fn from(value: parquet::errors::ParquetError) -> Self {
+        Error(value.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
@@ -100,16 +100,38 @@ impl Interface for Http {
                 .authority()
                 .ok_or(Error::new("missing authority"))?
                 .clone();
-            let pq = format!("/?query={}", urlencoding::encode(&query.statement));
+            let mut pq = format!("/?query={}", urlencoding::encode(&query.statement));
+            // NB: the query settings override the formatter settings
+            let formatter_settings = query
+                .formatter
+                .as_ref()
+                .map(|f| f.settings())
+                .unwrap_or_default();
+            for (name, value) in formatter_settings
+                .iter()
+                .filter(|(name, _)| !query.settings.iter().any(|(n, _)| n == name))
+                .chain(query.settings.iter())
+            {
+                pq.push_str(&format!(
+                    "&{}={}",
+                    urlencoding::encode(name),
+                    urlencoding::encode(value)
+                ));
+            }
             Uri::builder()
                 .scheme(scheme)
                 .authority(auth)
                 .path_and_query(pq)
                 .build()?
         };
-        let body = if let Some(data) = query.data {
-            let format = query.format.unwrap_or(HTTP_DEFAULT_FORMAT);
-            let bytes: Vec<u8> = data.to_bytes(format)?;
+        let body = if let Some(bytes) = query.raw_data {
+            req_builder = req_builder.header("Content-Length", bytes.len());
+            Body::from(bytes)
+        } else if let Some(data) = query.data {
+            let bytes: Vec<u8> = match &query.formatter {
+                Some(formatter) => formatter.serialize_query_data(data)?,
+                None => data.to_bytes(query.format.unwrap_or(HTTP_DEFAULT_FORMAT))?,
+            };
             req_builder = req_builder.header("Content-Length", bytes.len());
             Body::from(bytes)
         } else {
@@ -124,10 +146,13 @@ impl Interface for Http {
         let res_body = hyper::body::to_bytes(res.into_body()).await?;
 
         if res_status.is_success() {
-            let res = QueryResponse::new(
+            // NB: the metadata of a JSON document is parsed once, with the response
+            let mut res = QueryResponse::new(
                 query.format.unwrap_or(HTTP_DEFAULT_FORMAT),
                 res_body.to_vec(),
-            );
+            )
+            .with_metadata()?;
+            res.formatter = query.formatter;
             Ok(res)
         } else {
             let res_body_str = String::from_utf8(res_body.to_vec())?;
@@ -33,7 +33,10 @@ where
     }
 
     /// Sends a query
-    pub async fn send(&self, query: Query) -> Result<QueryResponse, Error> {
+    ///
+    /// NB: the query formatter is resolved with the formatters registered on the client.
+    pub async fn send(&self, mut query: Query) -> Result<QueryResponse, Error> {
+        self.formatters.resolve(&mut query)?;
         self.interface.send(query).await
     }
 }
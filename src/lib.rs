@@ -10,6 +10,9 @@
 //!
 //! - **time**: support for the `time` crate types
 //! - **uuid**: support for the `uuid` crate types
+//! - **tz**: timezone database for `DateTime('tz')` columns (via `time-tz`)
+//! - **serde**: RowBinary serialization of `serde` rows, bypassing `Value`
+//! - **chrono**: support for the `chrono` crate types (timezones via `chrono-tz`)
 
 #![deny(missing_docs)]
 
@@ -17,6 +20,7 @@
 mod tests;
 
 use intf::{http::Http, Interface};
+use query::{Format, Formatter, FormatterRegistry};
 
 pub mod error;
 pub mod intf;
@@ -36,6 +40,8 @@ where
     pub credentials: Option<(String, String)>,
     /// Interface
     pub interface: T,
+    /// Registered formatters
+    pub formatters: FormatterRegistry,
 }
 
 impl Default for Client<Http> {
@@ -45,6 +51,7 @@ impl Default for Client<Http> {
             db: None,
             credentials: Default::default(),
             interface,
+            formatters: FormatterRegistry::default(),
         }
     }
 }
@@ -58,6 +65,7 @@ where
             .field("db", &self.db)
             .field("credentials", &self.credentials)
             .field("interface", &self.interface)
+            .field("formatters", &self.formatters)
             .finish()
     }
 }
@@ -71,6 +79,7 @@ where
             db: self.db.clone(),
             credentials: self.credentials.clone(),
             interface: self.interface.clone(),
+            formatters: self.formatters.clone(),
         }
     }
 }
@@ -90,6 +99,27 @@ where
         self.credentials = Some((username.to_string(), password.to_string()));
         self
     }
+
+    /// Registers the formatter of a format
+    ///
+    /// NB: the formatter takes precedence over the built-in formatter of the format.
+    pub fn formatter(mut self, format: Format, formatter: impl Formatter + 'static) -> Self {
+        self.formatters.register(format, formatter);
+        self
+    }
+
+    /// Registers a custom format, sent to the server with the Clickhouse `format`
+    ///
+    /// Queries select the custom format by its name (eg. [query::Query::custom_format]).
+    pub fn custom_format(
+        mut self,
+        name: &str,
+        format: Format,
+        formatter: impl Formatter + 'static,
+    ) -> Self {
+        self.formatters.register_custom(name, format, formatter);
+        self
+    }
 }
 
 /// Client with the HTTP interface
@@ -16,13 +16,13 @@ pub use query::*;
 
 /// ORM prelude
 pub mod prelude {
-    pub use super::{ChRecord, Record};
+    pub use super::{ChNested, ChRecord, Record};
     pub use crate::{
         error::Error,
         schema::TableSchema,
-        value::{ChValue, Type, Value},
+        value::{ChValue, OrDefault, Type, Value},
     };
-    pub use clickhouse_client_macros::AsChRecord;
+    pub use clickhouse_client_macros::{AsChRecord, ChEnum, ChTuple};
 }
 
 /// A DB record
@@ -168,6 +168,79 @@ pub trait ChRecord: Sized {
     }
 }
 
+/// A trait to convert from/to a Clickhouse `Nested` column
+///
+/// Each field of a nested record is a column of the `Nested` type.
+pub trait ChNested: Sized {
+    /// Returns the `Nested` type
+    fn ch_nested_type() -> Type;
+
+    /// Converts to a `Nested` [Value]
+    fn into_ch_nested(self) -> Value;
+
+    /// Converts from a `Nested` [Value]
+    fn from_ch_nested(value: Value) -> Result<Self, Error>;
+}
+
+impl<T> ChNested for Vec<T>
+where
+    T: ChRecord,
+{
+    fn ch_nested_type() -> Type {
+        let schema = T::ch_schema();
+        Type::Nested(schema.columns.into_iter().map(|c| (c.id, c.ty)).collect())
+    }
+
+    fn into_ch_nested(self) -> Value {
+        let schema = T::ch_schema();
+        let mut cols = schema
+            .columns
+            .into_iter()
+            .map(|c| (c.id, vec![]))
+            .collect::<Vec<_>>();
+        for record in self {
+            let record = record.into_ch_record();
+            for field in record.fields {
+                if let Some((_, values)) = cols.iter_mut().find(|(id, _)| *id == field.id) {
+                    values.push(field.value);
+                }
+            }
+        }
+        Value::Nested(cols)
+    }
+
+    fn from_ch_nested(value: Value) -> Result<Self, Error> {
+        let cols = match value {
+            Value::Nested(cols) => cols,
+            _ => return Err(Error::new("Invalid Nested value")),
+        };
+        let n = cols.first().map(|(_, values)| values.len()).unwrap_or(0);
+        if cols.iter().any(|(_, values)| values.len() != n) {
+            return Err(Error::new("Nested columns must have the same length"));
+        }
+
+        let schema = T::ch_schema();
+        let mut cols = cols
+            .into_iter()
+            .map(|(id, values)| (id, values.into_iter()))
+            .collect::<Vec<_>>();
+        let mut records = vec![];
+        for _i in 0..n {
+            let mut record = Record::new(&schema.name);
+            for (id, values) in &mut cols {
+                let col_sch = schema
+                    .get_column_by_id(id)
+                    .ok_or(Error(format!("Invalid nested column '{id}'")))?;
+                let value = values.next().ok_or(Error::new("Missing nested value"))?;
+                record.add_field(id, col_sch.primary, col_sch.ty.clone(), value);
+            }
+            records.push(T::from_ch_record(record)?);
+        }
+
+        Ok(records)
+    }
+}
+
 // // -- TEST --
 
 // use prelude::*;
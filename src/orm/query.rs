@@ -77,13 +77,18 @@ where
     #[tracing::instrument(skip(self))]
     pub async fn select(&self, where_cond: Option<Where>) -> Result<Vec<U>, Error> {
         let schema = U::ch_schema();
+        let mapping = schema
+            .columns
+            .iter()
+            .map(|c| (c.id.as_str(), c.ty.clone()))
+            .collect::<Vec<_>>();
         let table = self
             .client
             .crud()
             .format(Format::RowBinaryWithNamesAndTypes)
             .select(&schema.name, vec![], where_cond)
             .await?
-            .into_table(None)?;
+            .into_table(Some(&mapping))?;
         U::from_query_data(table)
     }
 
@@ -4,9 +4,9 @@ use time::Date;
 use tokio::{time::sleep, time::Duration};
 use uuid::Uuid;
 
-use std::str::FromStr;
+use std::{collections::BTreeMap, str::FromStr};
 
-use crate::query::Where;
+use crate::query::{Format, QueryData, Where};
 
 use super::{prelude::*, ChRecord};
 
@@ -70,3 +70,237 @@ async fn orm_crud() {
 
     client.orm().delete(vec![sample]).await.unwrap();
 }
+
+/// Test enum
+#[derive(Debug, Clone, Copy, PartialEq, ChEnum)]
+enum TestEnum {
+    /// Active
+    #[ch(name = "active")]
+    Active,
+    /// Inactive
+    Inactive,
+    /// Deleted
+    #[ch(value = -1)]
+    Deleted,
+}
+
+/// Test enum, with large discriminants
+#[derive(Debug, Clone, Copy, PartialEq, ChEnum)]
+enum TestEnum16 {
+    /// Low
+    Low = 1,
+    /// High
+    High = 1000,
+}
+
+#[test]
+fn orm_enum_derive() {
+    assert_eq!(
+        TestEnum::ch_type(),
+        Type::Enum8(BTreeMap::from([
+            ("active".to_string(), 0),
+            ("Inactive".to_string(), 1),
+            ("Deleted".to_string(), -1),
+        ]))
+    );
+    assert_eq!(
+        TestEnum::Deleted.into_ch_value(),
+        Value::Enum8(-1, Some("Deleted".to_string()))
+    );
+    assert_eq!(
+        TestEnum::from_ch_value(Value::Enum8(1, None)).unwrap(),
+        TestEnum::Inactive
+    );
+    assert!(TestEnum::from_ch_value(Value::Enum8(2, None)).is_err());
+
+    // nullable enums
+    assert_eq!(
+        Option::<TestEnum>::ch_type(),
+        TestEnum::ch_type().into_nullable().unwrap()
+    );
+    assert_eq!(
+        Some(TestEnum::Deleted).into_ch_value(),
+        Value::NullableEnum8(Some(-1), Some("Deleted".to_string()))
+    );
+    assert_eq!(
+        None::<TestEnum16>.into_ch_value(),
+        Value::NullableEnum16(None, None)
+    );
+    assert_eq!(
+        Option::<TestEnum>::from_ch_value(Value::NullableEnum8(Some(1), None)).unwrap(),
+        Some(TestEnum::Inactive)
+    );
+    assert_eq!(
+        Option::<TestEnum>::from_ch_value(Value::NullableEnum8(None, None)).unwrap(),
+        None
+    );
+    assert!(Option::<TestEnum>::from_ch_value(Value::NullableEnum8(Some(2), None)).is_err());
+
+    assert_eq!(
+        TestEnum16::ch_type(),
+        Type::Enum16(BTreeMap::from([
+            ("Low".to_string(), 1),
+            ("High".to_string(), 1000),
+        ]))
+    );
+    assert_eq!(
+        TestEnum16::High.into_ch_value(),
+        Value::Enum16(1000, Some("High".to_string()))
+    );
+
+    // TSV uses the variant names
+    let bytes = TestEnum::Active
+        .into_ch_value()
+        .to_bytes(Format::TabSep)
+        .unwrap();
+    assert_eq!(bytes, b"active");
+    let value_parsed = Value::from_bytes(&bytes, Format::TabSep, TestEnum::ch_type()).unwrap();
+    assert_eq!(
+        TestEnum::from_ch_value(value_parsed).unwrap(),
+        TestEnum::Active
+    );
+}
+
+/// Test named tuple
+#[derive(Debug, Clone, PartialEq, ChTuple)]
+struct TestPoint {
+    /// Latitude
+    lat: f64,
+    /// Longitude
+    #[ch(name = "lon")]
+    longitude: f64,
+    /// Label
+    #[ch(skip)]
+    label: String,
+}
+
+#[test]
+fn orm_tuple_derive() {
+    assert_eq!(
+        TestPoint::ch_type().to_string(),
+        "Tuple(lat Float64, lon Float64)"
+    );
+
+    let point = TestPoint {
+        lat: 48.85,
+        longitude: 2.35,
+        label: "Paris".to_string(),
+    };
+    let value = point.clone().into_ch_value();
+    assert_eq!(
+        value,
+        Value::NamedTuple(vec![
+            ("lat".to_string(), Value::Float64(48.85)),
+            ("lon".to_string(), Value::Float64(2.35)),
+        ])
+    );
+    assert_eq!(value.get_element("lon"), Some(&Value::Float64(2.35)));
+    assert_eq!(value.get_element("label"), None);
+
+    // elements are looked up by name, skipped fields are defaulted
+    let value = Value::NamedTuple(vec![
+        ("lon".to_string(), Value::Float64(2.35)),
+        ("lat".to_string(), Value::Float64(48.85)),
+    ]);
+    let expected = TestPoint {
+        label: String::new(),
+        ..point
+    };
+    assert_eq!(TestPoint::from_ch_value(value).unwrap(), expected);
+    assert!(TestPoint::from_ch_value(Value::NamedTuple(vec![(
+        "lat".to_string(),
+        Value::Float64(48.85)
+    )]))
+    .is_err());
+
+    // unnamed tuples are mapped by position
+    let value = Value::Tuple(vec![Value::Float64(48.85), Value::Float64(2.35)]);
+    assert_eq!(TestPoint::from_ch_value(value).unwrap(), expected);
+
+    // RowBinary round trip
+    let bytes = expected
+        .clone()
+        .into_ch_value()
+        .to_bytes(Format::RowBinary)
+        .unwrap();
+    let value_parsed = Value::from_bytes(&bytes, Format::RowBinary, TestPoint::ch_type()).unwrap();
+    assert_eq!(TestPoint::from_ch_value(value_parsed).unwrap(), expected);
+}
+
+/// Test nested record
+#[derive(Debug, Clone, PartialEq, AsChRecord)]
+#[ch(nested)]
+struct TestItem {
+    /// Name
+    name: String,
+    /// Count
+    count: u8,
+}
+
+/// Test record with a nested column
+#[derive(Debug, Clone, PartialEq, AsChRecord)]
+#[ch(table = "test_orm_nested")]
+struct TestNestedRecord {
+    /// ID
+    #[ch(primary_key)]
+    id: u8,
+    /// Items
+    #[ch(nested)]
+    items: Vec<TestItem>,
+}
+
+#[test]
+fn orm_nested_derive() {
+    let nested_ty = Type::Nested(vec![
+        ("name".to_string(), Type::String),
+        ("count".to_string(), Type::UInt8),
+    ]);
+    assert_eq!(
+        TestNestedRecord::ch_schema()
+            .get_column_by_id("items")
+            .unwrap()
+            .ty,
+        nested_ty
+    );
+
+    let record = TestNestedRecord {
+        id: 1,
+        items: vec![
+            TestItem {
+                name: "a".to_string(),
+                count: 1,
+            },
+            TestItem {
+                name: "b".to_string(),
+                count: 2,
+            },
+        ],
+    };
+    let value = record
+        .clone()
+        .into_ch_record()
+        .get_field("items")
+        .unwrap()
+        .value
+        .clone();
+    assert_eq!(
+        value,
+        Value::Nested(vec![
+            (
+                "name".to_string(),
+                vec!["a".to_string().into(), "b".to_string().into()]
+            ),
+            ("count".to_string(), vec![Value::UInt8(1), Value::UInt8(2)]),
+        ])
+    );
+
+    // round trip through flattened columns
+    let format = Format::TabSepWithNamesAndTypes;
+    let bytes = TestNestedRecord::to_query_data(vec![record.clone()])
+        .to_bytes(format)
+        .unwrap();
+    let mapping = vec![("id", Type::UInt8), ("items", nested_ty)];
+    let data = QueryData::from_bytes(&bytes, format, Some(&mapping)).unwrap();
+    let records = TestNestedRecord::from_query_data(data).unwrap();
+    assert_eq!(records, vec![record]);
+}
@@ -0,0 +1,123 @@
+//! Columns
+
+use crate::{
+    error::Error,
+    value::{Type, Value},
+};
+
+use super::QueryData;
+
+/// Columnar query data
+///
+/// The values are stored per column, as in the columnar formats (eg. `Native`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryColumns {
+    /// Columns
+    pub columns: Vec<QueryColumn>,
+}
+
+/// Column of a [QueryColumns]
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryColumn {
+    /// Name
+    pub name: String,
+    /// Type
+    pub ty: Type,
+    /// Values
+    pub values: Vec<Value>,
+}
+
+impl QueryColumns {
+    /// Creates empty columns
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a column
+    pub fn column(mut self, name: &str, ty: Type, values: Vec<Value>) -> Self {
+        self.add_column(name, ty, values);
+        self
+    }
+
+    /// Adds a column
+    pub fn add_column(&mut self, name: &str, ty: Type, values: Vec<Value>) -> &mut Self {
+        self.columns.push(QueryColumn {
+            name: name.to_string(),
+            ty,
+            values,
+        });
+        self
+    }
+
+    /// Returns a column
+    pub fn get_column(&self, name: &str) -> Option<&QueryColumn> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    /// Returns the number of columns
+    pub fn n_cols(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Returns the number of rows
+    ///
+    /// Returns an error if the columns have different lengths.
+    pub fn n_rows(&self) -> Result<usize, Error> {
+        let n = self.columns.first().map(|c| c.values.len()).unwrap_or(0);
+        if self.columns.iter().any(|c| c.values.len() != n) {
+            return Err(Error::new("Columns have different lengths"));
+        }
+        Ok(n)
+    }
+}
+
+impl QueryData {
+    /// Creates a [QueryData] from columns
+    pub fn from_columns(columns: QueryColumns) -> Result<Self, Error> {
+        let n_rows = columns.n_rows()?;
+        let mut rows = vec![Vec::with_capacity(columns.n_cols()); n_rows];
+        let mut names_and_types = vec![];
+        for column in columns.columns {
+            for (row, value) in rows.iter_mut().zip(column.values) {
+                row.push(value);
+            }
+            names_and_types.push((column.name, column.ty));
+        }
+        Ok(QueryData::WithNamesAndTypes {
+            names_and_types,
+            rows,
+        })
+    }
+
+    /// Converts into columns
+    ///
+    /// The data must have the column names and types.
+    pub fn into_columns(self) -> Result<QueryColumns, Error> {
+        let parts = self.into_parts();
+        let names = parts
+            .names
+            .ok_or(Error::new("Table is missing the column names"))?;
+        let types = parts
+            .types
+            .ok_or(Error::new("Table is missing the column types"))?;
+
+        let mut columns = names
+            .into_iter()
+            .zip(types)
+            .map(|(name, ty)| QueryColumn {
+                name,
+                ty,
+                values: Vec::with_capacity(parts.rows.len()),
+            })
+            .collect::<Vec<_>>();
+        for row in parts.rows {
+            if row.len() != columns.len() {
+                return Err(Error::new("Row has an invalid number of values"));
+            }
+            for (column, value) in columns.iter_mut().zip(row) {
+                column.values.push(value);
+            }
+        }
+        Ok(QueryColumns { columns })
+    }
+}
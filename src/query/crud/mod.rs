@@ -5,6 +5,9 @@ mod tests;
 
 use crate::{error::Error, intf::Interface, value::Value, Client};
 
+#[cfg(feature = "serde")]
+use crate::value::Type;
+
 use super::{Format, Query, QueryData, QueryResponse, Where};
 
 /// CRUD query
@@ -51,7 +54,12 @@ where
     pub async fn insert(self, table: &str, data: QueryData) -> Result<QueryResponse, Error> {
         // NB: To pass the data inside the HTTP body, a FORMAT clause must be passed to the
         // SQL statement explicitly.
-        let format = self.query.format.unwrap_or(Format::RowBinary);
+        // NB: default values are written with the RowBinaryWithDefaults format
+        let format = match (self.query.format, data.has_defaults()) {
+            (Some(format), _) => format,
+            (None, true) => Format::RowBinaryWithDefaults,
+            (None, false) => Format::RowBinary,
+        };
 
         let query = self
             .query
@@ -63,6 +71,86 @@ where
         self.client.send(query).await
     }
 
+    /// Inserts `serde` rows, in the RowBinary format
+    ///
+    /// The row fields are mapped to the columns by position, and checked against the column types.
+    #[cfg(feature = "serde")]
+    #[tracing::instrument(skip(self, columns, rows))]
+    pub async fn insert_rows<R: serde::Serialize>(
+        self,
+        table: &str,
+        columns: &[(&str, Type)],
+        rows: &[R],
+    ) -> Result<QueryResponse, Error> {
+        let names = columns
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let types = columns.iter().map(|(_, ty)| ty.clone()).collect::<Vec<_>>();
+        let bytes = super::to_rowbin(rows, &types)?;
+
+        let query = self
+            .query
+            .statement("INSERT INTO [??] ([??]) FORMAT RowBinary")
+            .bind_str(table)
+            .bind_str(&names)
+            .format(Format::RowBinary)
+            .raw_data(bytes);
+        self.client.send(query).await
+    }
+
+    /// Inserts Arrow record batches, in the ArrowStream format
+    ///
+    /// The batch fields are mapped to the columns by name. The batches must have the same schema.
+    #[cfg(feature = "arrow")]
+    #[tracing::instrument(skip(self, batches))]
+    pub async fn insert_batches(
+        self,
+        table: &str,
+        batches: &[arrow::record_batch::RecordBatch],
+    ) -> Result<QueryResponse, Error> {
+        let names = batches
+            .first()
+            .ok_or(Error::new("Missing Arrow record batch"))?
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let bytes = super::ArrowFormatter::stream().write_batches(batches)?;
+
+        let query = self
+            .query
+            .statement("INSERT INTO [??] ([??]) FORMAT ArrowStream")
+            .bind_str(table)
+            .bind_str(&names)
+            .format(Format::ArrowStream)
+            .raw_data(bytes);
+        self.client.send(query).await
+    }
+
+    /// Selects `serde` rows, in the RowBinary format
+    ///
+    /// The columns are mapped to the row fields by position.
+    #[cfg(feature = "serde")]
+    #[tracing::instrument(skip(self, columns))]
+    pub async fn select_rows<R: serde::de::DeserializeOwned>(
+        self,
+        table: &str,
+        columns: &[(&str, Type)],
+        where_cond: Option<Where>,
+    ) -> Result<Vec<R>, Error> {
+        let fields = columns.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        let types = columns.iter().map(|(_, ty)| ty.clone()).collect::<Vec<_>>();
+        let res = self
+            .format(Format::RowBinary)
+            .select(table, fields, where_cond)
+            .await?;
+        res.rows(&types)
+    }
+
     /// Select rows
     ///
     /// If the columns is empty, all columns are returned
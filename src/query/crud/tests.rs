@@ -26,7 +26,7 @@ pub fn sample_table() -> (TableSchema, QueryData) {
         .column("bool", Type::Bool, false)
         .column("string", Type::String, false)
         .column("date", Type::Date32, false)
-        .column("datetime", Type::DateTime64(9), false)
+        .column("datetime", Type::DateTime64(9, None), false)
         .column(
             "enum8",
             Type::Enum8(BTreeMap::from([
@@ -56,7 +56,7 @@ pub fn sample_table() -> (TableSchema, QueryData) {
         "hello world".into(),
         Date::from_unix_days(10).unwrap().into(),
         OffsetDateTime::now_utc().into(),
-        Value::Enum8(0),
+        Value::Enum8(0, Some("var1".to_string())),
         vec![1_u8, 2, 3].into(),
         (1_u8, "hey".to_string()).into(),
         HashMap::from([("key".to_string(), 1_u8)]).into(),
@@ -1,6 +1,7 @@
 //! Table
 
 use crate::{
+    error::Error,
     schema::TableSchema,
     value::{Type, Value},
 };
@@ -81,6 +82,21 @@ impl QueryData {
         Self::with_names_and_types(columns)
     }
 
+    /// Creates a new [QueryTable] from its parts
+    pub fn from_parts(parts: QueryDataParts) -> Self {
+        match (parts.names, parts.types) {
+            (Some(names), Some(types)) => Self::WithNamesAndTypes {
+                names_and_types: names.into_iter().zip(types).collect(),
+                rows: parts.rows,
+            },
+            (Some(names), None) => Self::WithNames {
+                names,
+                rows: parts.rows,
+            },
+            _ => Self::NoHeaders { rows: parts.rows },
+        }
+    }
+
     /// Adds a row
     pub fn row(mut self, row: Vec<Value>) -> Self {
         self.add_row(row);
@@ -132,7 +148,8 @@ impl QueryData {
             if let Some(columns) = self.get_columns() {
                 for (i, value) in row.iter().enumerate() {
                     if let Some((n, Some(ty))) = columns.get(i) {
-                        if !value.is_same_type_as(ty) {
+                        // NB: any column can be the column default
+                        if !matches!(value, Value::Default) && !value.is_same_type_as(ty) {
                             panic!("Field '{n}' should have the type '{ty}'");
                         };
                     }
@@ -155,6 +172,13 @@ impl QueryData {
         }
     }
 
+    /// Returns `true` if a cell is the column default ([Value::Default])
+    pub fn has_defaults(&self) -> bool {
+        self.get_rows()
+            .iter()
+            .any(|row| row.iter().any(|v| matches!(v, Value::Default)))
+    }
+
     /// Returns a mut reference to the rows
     pub fn get_rows_mut(&mut self) -> &mut Vec<Vec<Value>> {
         match self {
@@ -261,6 +285,213 @@ impl QueryData {
     //     }
     // }
 
+    /// Flattens the `Nested` columns into 1 array column per nested column
+    ///
+    /// This is the layout of nested columns with the Clickhouse setting `flatten_nested=1`,
+    /// eg. `n Nested(a T, b U)` becomes `n.a Array(T), n.b Array(U)`.
+    ///
+    /// Nested columns are detected from the column types, or from the first row if there are
+    /// no types.
+    pub fn flatten_nested(self) -> Result<Self, Error> {
+        let parts = self.into_parts();
+
+        // nested fields of each column
+        let n_cols = match (&parts.names, parts.rows.first()) {
+            (Some(names), _) => names.len(),
+            (None, Some(row)) => row.len(),
+            (None, None) => 0,
+        };
+        let nested = (0..n_cols)
+            .map(
+                |i| match parts.types.as_ref().and_then(|types| types.get(i)) {
+                    Some(Type::Nested(fields)) => Some(
+                        fields
+                            .iter()
+                            .map(|(name, _)| name.clone())
+                            .collect::<Vec<_>>(),
+                    ),
+                    Some(_) => None,
+                    None => match parts.rows.first().and_then(|row| row.get(i)) {
+                        Some(Value::Nested(cols)) => {
+                            Some(cols.iter().map(|(name, _)| name.clone()).collect())
+                        }
+                        _ => None,
+                    },
+                },
+            )
+            .collect::<Vec<_>>();
+        if nested.iter().all(Option::is_none) {
+            return Ok(Self::from_parts(parts));
+        }
+
+        // headers
+        let names = parts.names.map(|names| {
+            let mut flat_names = vec![];
+            for (name, fields) in names.into_iter().zip(&nested) {
+                match fields {
+                    Some(fields) => {
+                        for field in fields {
+                            flat_names.push(format!("{name}.{field}"));
+                        }
+                    }
+                    None => flat_names.push(name),
+                }
+            }
+            flat_names
+        });
+        let types = parts.types.map(|types| {
+            let mut flat_types = vec![];
+            for ty in types {
+                match ty {
+                    Type::Nested(fields) => {
+                        for (_, field_ty) in fields {
+                            flat_types.push(Type::Array(Box::new(field_ty)));
+                        }
+                    }
+                    ty => flat_types.push(ty),
+                }
+            }
+            flat_types
+        });
+
+        // rows
+        let mut rows = vec![];
+        for row in parts.rows {
+            let mut flat_row = vec![];
+            for (value, fields) in row.into_iter().zip(&nested) {
+                match (value, fields) {
+                    (value, None) => flat_row.push(value),
+                    (Value::Nested(cols), Some(fields)) if cols.len() == fields.len() => {
+                        Value::nested_len(&cols)?;
+                        for (_, col) in cols {
+                            flat_row.push(Value::Array(col));
+                        }
+                    }
+                    _ => return Err(Error::new("Invalid Nested value")),
+                }
+            }
+            rows.push(flat_row);
+        }
+
+        Ok(Self::from_parts(QueryDataParts { names, types, rows }))
+    }
+
+    /// Groups flattened array columns (eg. `n.a`, `n.b`) into the `Nested` columns of a mapping
+    ///
+    /// This is the reverse of [QueryData::flatten_nested]. If there are no column names, the
+    /// columns are expected in the order of the flattened mapping.
+    pub fn unflatten_nested(self, mapping: &[(&str, Type)]) -> Result<Self, Error> {
+        let nested = mapping
+            .iter()
+            .filter_map(|(name, ty)| match ty {
+                Type::Nested(fields) => Some((*name, fields)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if nested.is_empty() {
+            return Ok(self);
+        }
+
+        let parts = self.into_parts();
+        let has_names = parts.names.is_some();
+        let names = match parts.names {
+            Some(names) => names,
+            None => flatten_nested_mapping(mapping)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect(),
+        };
+
+        /// Column of the unflattened data
+        enum Column<'a> {
+            /// Column index
+            Column(usize),
+            /// Nested column (name, fields and column index of each field)
+            Nested(&'a str, &'a [(String, Type)], Vec<Option<usize>>),
+        }
+
+        let mut columns: Vec<Column> = vec![];
+        for (i, name) in names.iter().enumerate() {
+            let field = name.split_once('.').and_then(|(prefix, field)| {
+                let (prefix, fields) = nested.iter().find(|(n, _)| *n == prefix)?;
+                let j = fields.iter().position(|(f, _)| f == field)?;
+                Some((*prefix, fields.as_slice(), j))
+            });
+            match field {
+                Some((prefix, fields, j)) => {
+                    let group = columns.iter_mut().find_map(|c| match c {
+                        Column::Nested(n, _, idxs) if *n == prefix => Some(idxs),
+                        _ => None,
+                    });
+                    match group {
+                        Some(idxs) => idxs[j] = Some(i),
+                        None => {
+                            let mut idxs = vec![None; fields.len()];
+                            idxs[j] = Some(i);
+                            columns.push(Column::Nested(prefix, fields, idxs));
+                        }
+                    }
+                }
+                None => columns.push(Column::Column(i)),
+            }
+        }
+
+        // headers
+        let new_names = columns
+            .iter()
+            .map(|c| match c {
+                Column::Column(i) => names[*i].clone(),
+                Column::Nested(name, _, _) => name.to_string(),
+            })
+            .collect::<Vec<_>>();
+        let new_types = parts.types.map(|types| {
+            columns
+                .iter()
+                .map(|c| match c {
+                    Column::Column(i) => types[*i].clone(),
+                    Column::Nested(_, fields, _) => Type::Nested(fields.to_vec()),
+                })
+                .collect::<Vec<_>>()
+        });
+
+        // rows
+        let mut rows = vec![];
+        for row in parts.rows {
+            let mut row = row.into_iter().map(Some).collect::<Vec<_>>();
+            let mut new_row = vec![];
+            for c in &columns {
+                match c {
+                    Column::Column(i) => {
+                        let value = row.get_mut(*i).and_then(Option::take);
+                        new_row.push(value.ok_or(Error::new("Missing column value"))?);
+                    }
+                    Column::Nested(name, fields, idxs) => {
+                        let mut cols = vec![];
+                        for ((field, _), idx) in fields.iter().zip(idxs) {
+                            let value = idx.and_then(|i| row.get_mut(i).and_then(Option::take));
+                            match value {
+                                Some(Value::Array(values)) => cols.push((field.clone(), values)),
+                                _ => {
+                                    return Err(Error::new(
+                                        format!("Missing nested column '{name}.{field}'").as_str(),
+                                    ))
+                                }
+                            }
+                        }
+                        new_row.push(Value::Nested(cols));
+                    }
+                }
+            }
+            rows.push(new_row);
+        }
+
+        Ok(Self::from_parts(QueryDataParts {
+            names: if has_names { Some(new_names) } else { None },
+            types: new_types,
+            rows,
+        }))
+    }
+
     /// Extracts the parts
     pub fn into_parts(self) -> QueryDataParts {
         match self {
@@ -294,6 +525,23 @@ impl QueryData {
     }
 }
 
+/// Flattens the `Nested` columns of a mapping (eg. `n Nested(a T)` becomes `n.a Array(T)`)
+pub(crate) fn flatten_nested_mapping(mapping: &[(&str, Type)]) -> Vec<(String, Type)> {
+    let mut flat_mapping = vec![];
+    for (name, ty) in mapping {
+        match ty {
+            Type::Nested(fields) => {
+                for (field, field_ty) in fields {
+                    let field_ty = Type::Array(Box::new(field_ty.clone()));
+                    flat_mapping.push((format!("{name}.{field}"), field_ty));
+                }
+            }
+            _ => flat_mapping.push((name.to_string(), ty.clone())),
+        }
+    }
+    flat_mapping
+}
+
 impl std::fmt::Display for QueryData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use prettytable::{Cell, Row, Table};
@@ -10,7 +10,7 @@ use crate::{
     Client,
 };
 
-use super::{Format, Query, QueryData, QueryResponse, SqlStatement};
+use super::{Format, Formatter, Query, QueryData, QueryResponse, SqlStatement};
 
 /// Query executor
 #[derive(Debug)]
@@ -94,6 +94,24 @@ where
         self
     }
 
+    /// Assigns a formatter (with its settings)
+    pub fn formatter(mut self, formatter: impl Formatter + 'static) -> Self {
+        self.query = self.query.formatter(formatter);
+        self
+    }
+
+    /// Assigns a custom format, registered on the client
+    pub fn custom_format(mut self, name: &str) -> Self {
+        self.query = self.query.custom_format(name);
+        self
+    }
+
+    /// Adds a setting
+    pub fn setting(mut self, name: &str, value: &str) -> Self {
+        self.query = self.query.setting(name, value);
+        self
+    }
+
     /// Executes the query
     #[tracing::instrument(skip(self))]
     pub async fn exec(self) -> Result<QueryResponse, Error> {
@@ -0,0 +1,1213 @@
+//! Arrow formats
+//!
+//! The data is converted to Arrow record batches (1 Arrow array per column), which are written
+//! as an IPC file (`Arrow`), an IPC stream (`ArrowStream`) or a Parquet file (`Parquet`).
+//!
+//! The Clickhouse type of each column is kept in the field metadata (`clickhouse_type`), so that
+//! the types are restored exactly. Without it (eg. for the data written by Clickhouse), the type
+//! is the type of the mapping, or is inferred from the Arrow type.
+
+#[cfg(test)]
+mod tests;
+
+use std::{collections::HashMap, io::Cursor, str::FromStr, sync::Arc};
+
+use arrow::{
+    array::{
+        Array, ArrayRef, AsArray, BooleanArray, Date32Array, Decimal128Array, Decimal256Array,
+        DictionaryArray, FixedSizeBinaryArray, Float32Array, Float64Array, Int16Array, Int32Array,
+        Int64Array, Int8Array, ListArray, MapArray, NullArray, StringArray, StructArray,
+        TimestampMicrosecondArray, TimestampMillisecondArray, TimestampNanosecondArray,
+        TimestampSecondArray, UInt16Array, UInt32Array, UInt64Array, UInt8Array,
+    },
+    buffer::OffsetBuffer,
+    datatypes::{
+        i256, DataType, Date32Type, Decimal128Type, Decimal256Type, Field, Fields, Float32Type,
+        Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, Schema, TimeUnit,
+        TimestampMicrosecondType, TimestampMillisecondType, TimestampNanosecondType,
+        TimestampSecondType, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+    },
+    ipc::{
+        reader::{FileReader, StreamReader},
+        writer::{FileWriter, StreamWriter},
+    },
+    record_batch::{RecordBatch, RecordBatchOptions},
+};
+
+use crate::{
+    error::Error,
+    query::{flatten_nested_mapping, QueryColumns, QueryData},
+    value::{time::rescale_ticks, Type, Value},
+};
+
+use super::{
+    native::{infer_type, transpose},
+    Formatter, RowBinFormatter,
+};
+
+/// Key of the field metadata with the Clickhouse type
+const TYPE_METADATA_KEY: &str = "clickhouse_type";
+
+/// Arrow formatter
+///
+/// The record batches are written as an IPC file (`Arrow`) or an IPC stream (`ArrowStream`).
+#[derive(Debug, Clone)]
+pub struct ArrowFormatter {
+    /// IPC stream (instead of an IPC file)
+    stream: bool,
+    /// Nested columns are flattened
+    flatten_nested: bool,
+}
+
+impl Default for ArrowFormatter {
+    fn default() -> Self {
+        Self {
+            stream: false,
+            flatten_nested: true,
+        }
+    }
+}
+
+impl ArrowFormatter {
+    /// Creates a new [ArrowFormatter] (`Arrow` format)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new [ArrowFormatter] for the `ArrowStream` format
+    pub fn stream() -> Self {
+        Self {
+            stream: true,
+            ..Default::default()
+        }
+    }
+
+    /// Sets if `Nested` columns are flattened (`true` by default)
+    ///
+    /// This must match the Clickhouse setting `flatten_nested`.
+    pub fn flatten_nested(mut self, flatten: bool) -> Self {
+        self.flatten_nested = flatten;
+        self
+    }
+}
+
+impl Formatter for ArrowFormatter {
+    fn serialize_value(&self, value: Value) -> Result<Vec<u8>, Error> {
+        self.write_batches(&[value_to_batch(value)?])
+    }
+
+    fn serialize_query_data(&self, data: QueryData) -> Result<Vec<u8>, Error> {
+        self.format_data(data)
+    }
+
+    fn deserialize_value(&self, bytes: &[u8], ty: Type) -> Result<Value, Error> {
+        value_from_batches(&self.read_batches(bytes)?, &ty)
+    }
+
+    fn deserialize_query_data(
+        &self,
+        bytes: &[u8],
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        self.parse_data(bytes, mapping)
+    }
+
+    fn settings(&self) -> Vec<(String, String)> {
+        vec![
+            (
+                "output_format_arrow_string_as_string".to_string(),
+                "1".to_string(),
+            ),
+            (
+                "output_format_arrow_low_cardinality_as_dictionary".to_string(),
+                "1".to_string(),
+            ),
+        ]
+    }
+}
+
+impl ArrowFormatter {
+    /// Formats a [QueryData] (as 1 record batch)
+    pub fn format_data(&self, data: QueryData) -> Result<Vec<u8>, Error> {
+        self.write_batches(&[data_to_batch(data, self.flatten_nested)?])
+    }
+
+    /// Parses a [QueryData]
+    pub fn parse_data(
+        &self,
+        bytes: &[u8],
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        data_from_batches(&self.read_batches(bytes)?, mapping, self.flatten_nested)
+    }
+
+    /// Writes record batches
+    ///
+    /// The batches must have the same schema.
+    pub fn write_batches(&self, batches: &[RecordBatch]) -> Result<Vec<u8>, Error> {
+        let schema = batches
+            .first()
+            .map(|batch| batch.schema())
+            .ok_or(Error::new("Missing Arrow record batch"))?;
+
+        let mut buf = vec![];
+        if self.stream {
+            let mut writer = StreamWriter::try_new(&mut buf, &schema)?;
+            for batch in batches {
+                writer.write(batch)?;
+            }
+            writer.finish()?;
+        } else {
+            let mut writer = FileWriter::try_new(&mut buf, &schema)?;
+            for batch in batches {
+                writer.write(batch)?;
+            }
+            writer.finish()?;
+        }
+        Ok(buf)
+    }
+
+    /// Reads record batches
+    ///
+    /// NB: if there is no batch, an empty batch is returned to keep the schema.
+    pub fn read_batches(&self, bytes: &[u8]) -> Result<Vec<RecordBatch>, Error> {
+        let (schema, batches) = if self.stream {
+            let reader = StreamReader::try_new(bytes, None)?;
+            (reader.schema(), reader.collect::<Result<Vec<_>, _>>()?)
+        } else {
+            let reader = FileReader::try_new(Cursor::new(bytes), None)?;
+            (reader.schema(), reader.collect::<Result<Vec<_>, _>>()?)
+        };
+        match batches.is_empty() {
+            true => Ok(vec![RecordBatch::new_empty(schema)]),
+            false => Ok(batches),
+        }
+    }
+}
+
+/// Parquet formatter
+#[cfg(feature = "parquet")]
+#[derive(Debug, Clone)]
+pub struct ParquetFormatter {
+    /// Nested columns are flattened
+    flatten_nested: bool,
+}
+
+#[cfg(feature = "parquet")]
+impl Default for ParquetFormatter {
+    fn default() -> Self {
+        Self {
+            flatten_nested: true,
+        }
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl ParquetFormatter {
+    /// Creates a new [ParquetFormatter]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets if `Nested` columns are flattened (`true` by default)
+    ///
+    /// This must match the Clickhouse setting `flatten_nested`.
+    pub fn flatten_nested(mut self, flatten: bool) -> Self {
+        self.flatten_nested = flatten;
+        self
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl Formatter for ParquetFormatter {
+    fn serialize_value(&self, value: Value) -> Result<Vec<u8>, Error> {
+        self.write_batches(&[value_to_batch(value)?])
+    }
+
+    fn serialize_query_data(&self, data: QueryData) -> Result<Vec<u8>, Error> {
+        self.format_data(data)
+    }
+
+    fn deserialize_value(&self, bytes: &[u8], ty: Type) -> Result<Value, Error> {
+        value_from_batches(&self.read_batches(bytes)?, &ty)
+    }
+
+    fn deserialize_query_data(
+        &self,
+        bytes: &[u8],
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        self.parse_data(bytes, mapping)
+    }
+
+    fn settings(&self) -> Vec<(String, String)> {
+        vec![(
+            "output_format_parquet_string_as_string".to_string(),
+            "1".to_string(),
+        )]
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl ParquetFormatter {
+    /// Formats a [QueryData] (as 1 record batch)
+    pub fn format_data(&self, data: QueryData) -> Result<Vec<u8>, Error> {
+        self.write_batches(&[data_to_batch(data, self.flatten_nested)?])
+    }
+
+    /// Parses a [QueryData]
+    pub fn parse_data(
+        &self,
+        bytes: &[u8],
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        data_from_batches(&self.read_batches(bytes)?, mapping, self.flatten_nested)
+    }
+
+    /// Writes record batches
+    ///
+    /// The batches must have the same schema.
+    pub fn write_batches(&self, batches: &[RecordBatch]) -> Result<Vec<u8>, Error> {
+        let schema = batches
+            .first()
+            .map(|batch| batch.schema())
+            .ok_or(Error::new("Missing Arrow record batch"))?;
+
+        let mut buf = vec![];
+        let mut writer = parquet::arrow::ArrowWriter::try_new(&mut buf, schema, None)?;
+        for batch in batches {
+            writer.write(batch)?;
+        }
+        writer.close()?;
+        Ok(buf)
+    }
+
+    /// Reads record batches
+    ///
+    /// NB: if there is no batch, an empty batch is returned to keep the schema.
+    pub fn read_batches(&self, bytes: &[u8]) -> Result<Vec<RecordBatch>, Error> {
+        let builder = parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder::try_new(
+            bytes::Bytes::copy_from_slice(bytes),
+        )?;
+        let schema = builder.schema().clone();
+        let batches = builder.build()?.collect::<Result<Vec<_>, _>>()?;
+        match batches.is_empty() {
+            true => Ok(vec![RecordBatch::new_empty(schema)]),
+            false => Ok(batches),
+        }
+    }
+}
+
+impl QueryColumns {
+    /// Converts to an Arrow [RecordBatch]
+    ///
+    /// The Clickhouse type of each column is kept in the field metadata (`clickhouse_type`).
+    pub fn into_record_batch(self) -> Result<RecordBatch, Error> {
+        let n_rows = self.n_rows()?;
+        let mut fields = Vec::with_capacity(self.n_cols());
+        let mut arrays = Vec::with_capacity(self.n_cols());
+        for column in self.columns {
+            let metadata = HashMap::from([(TYPE_METADATA_KEY.to_string(), column.ty.to_string())]);
+            fields.push(field_of(&column.name, &column.ty)?.with_metadata(metadata));
+            let values = column
+                .values
+                .into_iter()
+                .map(|value| value.conform_to(&column.ty).map(Some))
+                .collect::<Result<Vec<_>, Error>>()?;
+            arrays.push(to_array(&column.ty, values)?);
+        }
+
+        let options = RecordBatchOptions::new().with_row_count(Some(n_rows));
+        Ok(RecordBatch::try_new_with_options(
+            Arc::new(Schema::new(fields)),
+            arrays,
+            &options,
+        )?)
+    }
+
+    /// Creates columns from an Arrow [RecordBatch]
+    ///
+    /// The column types are read from the field metadata, or inferred from the Arrow types.
+    /// Dictionary arrays are decoded.
+    pub fn from_record_batch(batch: &RecordBatch) -> Result<Self, Error> {
+        columns_from_batch(batch, None)
+    }
+
+    /// Creates columns from Arrow record batches (the batches are concatenated)
+    pub fn from_record_batches(batches: &[RecordBatch]) -> Result<Self, Error> {
+        columns_from_batches(batches, None)
+    }
+}
+
+/// Converts a record batch to columns
+///
+/// NB: the types of the mapping take precedence over the field types, because Clickhouse
+/// writes some types as their storage type (eg `Date` as `UInt16`, `DateTime` as `UInt32`).
+fn columns_from_batch(
+    batch: &RecordBatch,
+    mapping: Option<&[(String, Type)]>,
+) -> Result<QueryColumns, Error> {
+    let mut columns = QueryColumns::new();
+    for (field, array) in batch.schema().fields().iter().zip(batch.columns()) {
+        let mapping_ty = mapping
+            .and_then(|mapping| mapping.iter().find(|(name, _)| name == field.name()))
+            .map(|(_, ty)| ty.clone());
+        let ty = match mapping_ty {
+            Some(ty) => ty,
+            None => type_of_field(field)?,
+        };
+        let values = from_array(&ty, array.as_ref())?;
+        columns.add_column(field.name(), ty, values);
+    }
+    Ok(columns)
+}
+
+/// Converts record batches to columns (the batches are concatenated)
+fn columns_from_batches(
+    batches: &[RecordBatch],
+    mapping: Option<&[(String, Type)]>,
+) -> Result<QueryColumns, Error> {
+    let mut batches = batches.iter();
+    let mut columns = match batches.next() {
+        Some(batch) => columns_from_batch(batch, mapping)?,
+        None => return Ok(QueryColumns::new()),
+    };
+    for batch in batches {
+        let batch_columns = columns_from_batch(batch, mapping)?;
+        if batch_columns.n_cols() != columns.n_cols() {
+            return Err(Error::new("Arrow batch has an invalid number of columns"));
+        }
+        for (column, batch_column) in columns.columns.iter_mut().zip(batch_columns.columns) {
+            if column.name != batch_column.name || column.ty != batch_column.ty {
+                return Err(Error::new("Arrow batch has an invalid column"));
+            }
+            column.values.extend(batch_column.values);
+        }
+    }
+    Ok(columns)
+}
+
+/// Converts a [QueryData] to a record batch
+fn data_to_batch(data: QueryData, flatten_nested: bool) -> Result<RecordBatch, Error> {
+    let data = match flatten_nested {
+        true => data.flatten_nested()?,
+        false => data,
+    };
+    data.into_columns()?.into_record_batch()
+}
+
+/// Converts record batches to a [QueryData]
+fn data_from_batches(
+    batches: &[RecordBatch],
+    mapping: Option<&[(&str, Type)]>,
+    flatten_nested: bool,
+) -> Result<QueryData, Error> {
+    // nested columns are received as 1 array column per nested column
+    let flat_mapping = mapping.map(|mapping| match flatten_nested {
+        true => flatten_nested_mapping(mapping),
+        false => mapping
+            .iter()
+            .map(|(n, t)| (n.to_string(), t.clone()))
+            .collect(),
+    });
+    let data = QueryData::from_columns(columns_from_batches(batches, flat_mapping.as_deref())?)?;
+    match mapping {
+        Some(mapping) if flatten_nested => data.unflatten_nested(mapping),
+        _ => Ok(data),
+    }
+}
+
+/// Converts a value to a record batch
+///
+/// NB: a value is a batch of 1 column (`value`) with 1 row.
+fn value_to_batch(value: Value) -> Result<RecordBatch, Error> {
+    let ty = infer_type(&value);
+    QueryColumns::new()
+        .column("value", ty, vec![value])
+        .into_record_batch()
+}
+
+/// Converts record batches to a value
+fn value_from_batches(batches: &[RecordBatch], ty: &Type) -> Result<Value, Error> {
+    let array = batches
+        .first()
+        .and_then(|batch| batch.columns().first())
+        .ok_or(Error::new("Missing Arrow value"))?;
+    let mut values = from_array(ty, array.as_ref())?;
+    if batches.len() != 1 || values.len() != 1 {
+        return Err(Error::new("Arrow value must be 1 row"));
+    }
+    Ok(values.remove(0))
+}
+
+/// Returns the Arrow field of a type
+fn field_of(name: &str, ty: &Type) -> Result<Field, Error> {
+    let (data_type, nullable) = arrow_type(ty)?;
+    Ok(Field::new(name, data_type, nullable))
+}
+
+/// Returns the Arrow type of a type, and if it is nullable
+fn arrow_type(ty: &Type) -> Result<(DataType, bool), Error> {
+    if let Some(inner_ty) = ty.clone().into_non_nullable() {
+        return Ok((arrow_type(&inner_ty)?.0, true));
+    }
+
+    let data_type = match ty {
+        Type::UInt8 => DataType::UInt8,
+        Type::UInt16 => DataType::UInt16,
+        Type::UInt32 => DataType::UInt32,
+        Type::UInt64 => DataType::UInt64,
+        Type::Int8 | Type::Enum8(_) => DataType::Int8,
+        Type::Int16 | Type::Enum16(_) => DataType::Int16,
+        Type::Int32 | Type::Time => DataType::Int32,
+        Type::Int64 | Type::Time64(_) | Type::Interval(_) => DataType::Int64,
+        // NB: large integers are written as little endian bytes
+        Type::UInt128 | Type::Int128 | Type::UUID => DataType::FixedSizeBinary(16),
+        Type::UInt256 | Type::Int256 => DataType::FixedSizeBinary(32),
+        Type::Float32 | Type::BFloat16 => DataType::Float32,
+        Type::Float64 => DataType::Float64,
+        Type::Decimal(..)
+        | Type::Decimal32(_)
+        | Type::Decimal64(_)
+        | Type::Decimal128(_)
+        | Type::Decimal256(_) => {
+            let (p, s) = decimal_precision_scale(ty);
+            match p {
+                0..=38 => DataType::Decimal128(p, s.try_into()?),
+                _ => DataType::Decimal256(p, s.try_into()?),
+            }
+        }
+        Type::Bool => DataType::Boolean,
+        Type::String => DataType::Utf8,
+        Type::FixedString(n) => DataType::FixedSizeBinary((*n).try_into()?),
+        Type::Date | Type::Date32 => DataType::Date32,
+        Type::DateTime(tz) => DataType::Timestamp(TimeUnit::Second, tz.as_deref().map(Arc::from)),
+        Type::DateTime64(p, tz) => {
+            DataType::Timestamp(time_unit(*p).0, tz.as_deref().map(Arc::from))
+        }
+        Type::Array(ty) => DataType::List(Arc::new(field_of("item", ty)?)),
+        Type::Tuple(types) => {
+            let elements = types
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| ((i + 1).to_string(), ty.clone()))
+                .collect::<Vec<_>>();
+            DataType::Struct(struct_fields(&elements)?)
+        }
+        Type::NamedTuple(elements) => DataType::Struct(struct_fields(elements)?),
+        Type::Map(ty_key, ty_val) => {
+            let entries = struct_fields(&[
+                ("keys".to_string(), *ty_key.clone()),
+                ("values".to_string(), *ty_val.clone()),
+            ])?;
+            let entries = Field::new("entries", DataType::Struct(entries), false);
+            DataType::Map(Arc::new(entries), false)
+        }
+        // NB: a nested column is an array of named tuples
+        Type::Nested(fields) => {
+            return arrow_type(&Type::Array(Box::new(Type::NamedTuple(fields.clone()))))
+        }
+        // NB: the dictionary keys are i32, the values are not nullable
+        Type::LowCardinality(ty) => {
+            let (data_type, nullable) = arrow_type(ty)?;
+            let data_type = DataType::Dictionary(Box::new(DataType::Int32), Box::new(data_type));
+            return Ok((data_type, nullable));
+        }
+        Type::SimpleAggregateFunction(_, ty) => return arrow_type(ty),
+        Type::Nothing => return Ok((DataType::Null, true)),
+        _ => return Err(Error(format!("Unsupported Arrow type: {ty}"))),
+    };
+    Ok((data_type, false))
+}
+
+/// Returns the Arrow fields of a struct
+fn struct_fields(elements: &[(String, Type)]) -> Result<Fields, Error> {
+    let fields = elements
+        .iter()
+        .map(|(name, ty)| field_of(name, ty))
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(Fields::from(fields))
+}
+
+/// Returns the precision and the scale of a decimal type
+fn decimal_precision_scale(ty: &Type) -> (u8, u8) {
+    match ty {
+        Type::Decimal(p, s) => (*p, *s),
+        Type::Decimal32(s) => (9, *s),
+        Type::Decimal64(s) => (18, *s),
+        Type::Decimal128(s) => (38, *s),
+        Type::Decimal256(s) => (76, *s),
+        _ => (0, 0),
+    }
+}
+
+/// Returns the Arrow time unit of a `DateTime64` precision, with the precision of the unit
+fn time_unit(precision: u8) -> (TimeUnit, u8) {
+    match precision {
+        0 => (TimeUnit::Second, 0),
+        1..=3 => (TimeUnit::Millisecond, 3),
+        4..=6 => (TimeUnit::Microsecond, 6),
+        _ => (TimeUnit::Nanosecond, 9),
+    }
+}
+
+/// Returns the precision of an Arrow time unit
+fn unit_precision(unit: &TimeUnit) -> u8 {
+    match unit {
+        TimeUnit::Second => 0,
+        TimeUnit::Millisecond => 3,
+        TimeUnit::Microsecond => 6,
+        TimeUnit::Nanosecond => 9,
+    }
+}
+
+/// Returns the type of an Arrow field
+fn type_of_field(field: &Field) -> Result<Type, Error> {
+    match field.metadata().get(TYPE_METADATA_KEY) {
+        Some(ty) => Type::from_str(ty),
+        None => infer_arrow_type(field.data_type(), field.is_nullable()),
+    }
+}
+
+/// Infers the type of an Arrow type
+fn infer_arrow_type(data_type: &DataType, nullable: bool) -> Result<Type, Error> {
+    let ty = match data_type {
+        DataType::Null => return Ok(Type::NullableNothing),
+        DataType::UInt8 => Type::UInt8,
+        DataType::UInt16 => Type::UInt16,
+        DataType::UInt32 => Type::UInt32,
+        DataType::UInt64 => Type::UInt64,
+        DataType::Int8 => Type::Int8,
+        DataType::Int16 => Type::Int16,
+        DataType::Int32 => Type::Int32,
+        DataType::Int64 => Type::Int64,
+        DataType::Float32 => Type::Float32,
+        DataType::Float64 => Type::Float64,
+        DataType::Boolean => Type::Bool,
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Binary | DataType::LargeBinary => {
+            Type::String
+        }
+        DataType::FixedSizeBinary(n) => Type::FixedString((*n).try_into()?),
+        DataType::Date32 => Type::Date32,
+        DataType::Timestamp(TimeUnit::Second, tz) => {
+            Type::DateTime(tz.as_ref().map(|tz| tz.to_string()))
+        }
+        DataType::Timestamp(unit, tz) => {
+            Type::DateTime64(unit_precision(unit), tz.as_ref().map(|tz| tz.to_string()))
+        }
+        DataType::Decimal128(p, s) | DataType::Decimal256(p, s) => {
+            Type::Decimal(*p, (*s).try_into()?)
+        }
+        DataType::List(field) | DataType::LargeList(field) => {
+            return Ok(Type::Array(Box::new(type_of_field(field)?)))
+        }
+        DataType::Struct(fields) => {
+            let elements = fields
+                .iter()
+                .map(|field| Ok((field.name().clone(), type_of_field(field)?)))
+                .collect::<Result<Vec<_>, Error>>()?;
+            return Ok(Type::NamedTuple(elements));
+        }
+        DataType::Map(field, _) => match field.data_type() {
+            DataType::Struct(fields) if fields.len() == 2 => {
+                return Ok(Type::Map(
+                    Box::new(type_of_field(&fields[0])?),
+                    Box::new(type_of_field(&fields[1])?),
+                ))
+            }
+            _ => return Err(Error::new("Invalid Arrow map type")),
+        },
+        DataType::Dictionary(_, data_type) => {
+            return Ok(Type::LowCardinality(Box::new(infer_arrow_type(
+                data_type, nullable,
+            )?)))
+        }
+        _ => return Err(Error(format!("Unsupported Arrow type: {data_type}"))),
+    };
+    Ok(match nullable {
+        true => ty.clone().into_nullable().unwrap_or(ty),
+        false => ty,
+    })
+}
+
+/// Returns the error of an invalid value
+fn invalid_value(ty: &Type, value: Option<Value>) -> Error {
+    match value {
+        Some(value) => Error(format!("Invalid Arrow value for {ty}: {value:?}")),
+        None => Error(format!("Invalid Arrow NULL value for {ty}")),
+    }
+}
+
+/// Converts values to an Arrow array (NULL values are `None`)
+fn to_array(ty: &Type, values: Vec<Option<Value>>) -> Result<ArrayRef, Error> {
+    /// Collects the values of an array
+    macro_rules! collect {
+        ($ARR:ty, $PAT:pat => $EXPR:expr) => {
+            values
+                .into_iter()
+                .map(|value| match value {
+                    Some($PAT) => Ok(Some($EXPR)),
+                    None => Ok(None),
+                    value => Err(invalid_value(ty, value)),
+                })
+                .collect::<Result<$ARR, Error>>()?
+        };
+    }
+
+    /// Collects the values of a fixed size binary array
+    macro_rules! collect_fixed {
+        ($SIZE:expr, $PAT:pat => $EXPR:expr) => {{
+            let values = collect!(Vec<Option<Vec<u8>>>, $PAT => $EXPR);
+            let array = FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+                values.into_iter(),
+                i32::try_from($SIZE)?,
+            )?;
+            Arc::new(array)
+        }};
+    }
+
+    if let Some(inner_ty) = ty.clone().into_non_nullable() {
+        let values = values
+            .into_iter()
+            .map(|value| value.and_then(Value::into_non_nullable))
+            .collect();
+        return to_array(&inner_ty, values);
+    }
+
+    Ok(match ty {
+        Type::UInt8 => Arc::new(collect!(UInt8Array, Value::UInt8(v) => v)),
+        Type::UInt16 => Arc::new(collect!(UInt16Array, Value::UInt16(v) => v)),
+        Type::UInt32 => Arc::new(collect!(UInt32Array, Value::UInt32(v) => v)),
+        Type::UInt64 => Arc::new(collect!(UInt64Array, Value::UInt64(v) => v)),
+        Type::Int8 => Arc::new(collect!(Int8Array, Value::Int8(v) => v)),
+        Type::Int16 => Arc::new(collect!(Int16Array, Value::Int16(v) => v)),
+        Type::Int32 => Arc::new(collect!(Int32Array, Value::Int32(v) => v)),
+        Type::Int64 => Arc::new(collect!(Int64Array, Value::Int64(v) => v)),
+        Type::UInt128 => collect_fixed!(16_usize, Value::UInt128(v) => v.to_le_bytes().to_vec()),
+        Type::Int128 => collect_fixed!(16_usize, Value::Int128(v) => v.to_le_bytes().to_vec()),
+        Type::UInt256 => {
+            let formatter = RowBinFormatter::new();
+            collect_fixed!(32_usize, v @ Value::UInt256(_) => formatter.format_value(v)?)
+        }
+        Type::Int256 => {
+            let formatter = RowBinFormatter::new();
+            collect_fixed!(32_usize, v @ Value::Int256(_) => formatter.format_value(v)?)
+        }
+        Type::Float32 => Arc::new(collect!(Float32Array, Value::Float32(v) => v)),
+        Type::BFloat16 => Arc::new(collect!(Float32Array, Value::BFloat16(v) => v)),
+        Type::Float64 => Arc::new(collect!(Float64Array, Value::Float64(v) => v)),
+        // NB: decimals are held as f64
+        Type::Decimal(..)
+        | Type::Decimal32(_)
+        | Type::Decimal64(_)
+        | Type::Decimal128(_)
+        | Type::Decimal256(_) => {
+            let (p, s) = decimal_precision_scale(ty);
+            let scale = 10_f64.powi(s.into());
+            match p {
+                0..=38 => Arc::new(
+                    collect!(Decimal128Array, Value::Float64(v) => (v * scale).round() as i128)
+                        .with_precision_and_scale(p, s.try_into()?)?,
+                ),
+                _ => Arc::new(
+                    collect!(Decimal256Array, Value::Float64(v) => {
+                        i256::from_f64((v * scale).round())
+                            .ok_or_else(|| invalid_value(ty, Some(Value::Float64(v))))?
+                    })
+                    .with_precision_and_scale(p, s.try_into()?)?,
+                ),
+            }
+        }
+        Type::Bool => Arc::new(collect!(BooleanArray, Value::Bool(v) => v)),
+        // NB: Arrow strings are UTF-8, invalid sequences are replaced
+        Type::String => Arc::new(
+            values
+                .into_iter()
+                .map(|value| match value {
+                    Some(Value::String(v)) => Ok(Some(v)),
+                    Some(Value::Bytes(v)) => Ok(Some(String::from_utf8_lossy(&v).into_owned())),
+                    None => Ok(None),
+                    value => Err(invalid_value(ty, value)),
+                })
+                .collect::<Result<StringArray, Error>>()?,
+        ),
+        Type::FixedString(n) => collect_fixed!(*n, Value::FixedString(v) => v),
+        Type::UUID => collect_fixed!(16_usize, Value::UUID(v) => v.to_vec()),
+        Type::Date => Arc::new(collect!(Date32Array, Value::Date(v) => v.into())),
+        Type::Date32 => Arc::new(collect!(Date32Array, Value::Date32(v) => v)),
+        Type::DateTime(tz) => Arc::new(
+            collect!(TimestampSecondArray, Value::DateTime(v, _) => v.into())
+                .with_timezone_opt(tz.clone()),
+        ),
+        Type::DateTime64(p, tz) => {
+            let (unit, unit_p) = time_unit(*p);
+            let p = (*p).min(9);
+            match unit {
+                TimeUnit::Second => Arc::new(
+                    collect!(TimestampSecondArray, Value::DateTime64(v, ..) => v)
+                        .with_timezone_opt(tz.clone()),
+                ),
+                TimeUnit::Millisecond => Arc::new(
+                    collect!(TimestampMillisecondArray, Value::DateTime64(v, ..) => {
+                        rescale_ticks(v, p, unit_p)
+                    })
+                    .with_timezone_opt(tz.clone()),
+                ),
+                TimeUnit::Microsecond => Arc::new(
+                    collect!(TimestampMicrosecondArray, Value::DateTime64(v, ..) => {
+                        rescale_ticks(v, p, unit_p)
+                    })
+                    .with_timezone_opt(tz.clone()),
+                ),
+                TimeUnit::Nanosecond => Arc::new(
+                    collect!(TimestampNanosecondArray, Value::DateTime64(v, ..) => {
+                        rescale_ticks(v, p, unit_p)
+                    })
+                    .with_timezone_opt(tz.clone()),
+                ),
+            }
+        }
+        Type::Time => Arc::new(collect!(Int32Array, Value::Time(v) => v)),
+        Type::Time64(_) => Arc::new(collect!(Int64Array, Value::Time64(v, _) => v)),
+        Type::Interval(_) => Arc::new(collect!(Int64Array, Value::Interval(v, _) => v)),
+        Type::Enum8(_) => Arc::new(collect!(Int8Array, Value::Enum8(v, _) => v)),
+        Type::Enum16(_) => Arc::new(collect!(Int16Array, Value::Enum16(v, _) => v)),
+        // NB: a list is the offsets of the arrays, and the values of all the arrays
+        Type::Array(inner_ty) => {
+            let mut offsets = vec![0_i32];
+            let mut inner_values = vec![];
+            for value in values {
+                match value {
+                    Some(Value::Array(array)) => {
+                        inner_values.extend(array.into_iter().map(Some));
+                        offsets.push(inner_values.len().try_into()?);
+                    }
+                    value => return Err(invalid_value(ty, value)),
+                }
+            }
+            Arc::new(ListArray::try_new(
+                Arc::new(field_of("item", inner_ty)?),
+                OffsetBuffer::new(offsets.into()),
+                to_array(inner_ty, inner_values)?,
+                None,
+            )?)
+        }
+        Type::Tuple(types) => {
+            let elements = types
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| ((i + 1).to_string(), ty.clone()))
+                .collect::<Vec<_>>();
+            to_struct_array(ty, &elements, values)?
+        }
+        Type::NamedTuple(elements) => to_struct_array(ty, elements, values)?,
+        Type::Map(ty_key, ty_val) => {
+            let mut offsets = vec![0_i32];
+            let mut keys = vec![];
+            let mut vals = vec![];
+            for value in values {
+                match value {
+                    Some(Value::Map(map)) => {
+                        for (key, val) in map {
+                            keys.push(Some(key));
+                            vals.push(Some(val));
+                        }
+                        offsets.push(keys.len().try_into()?);
+                    }
+                    value => return Err(invalid_value(ty, value)),
+                }
+            }
+            let entries = StructArray::try_new(
+                struct_fields(&[
+                    ("keys".to_string(), *ty_key.clone()),
+                    ("values".to_string(), *ty_val.clone()),
+                ])?,
+                vec![to_array(ty_key, keys)?, to_array(ty_val, vals)?],
+                None,
+            )?;
+            Arc::new(MapArray::try_new(
+                Arc::new(Field::new("entries", entries.data_type().clone(), false)),
+                OffsetBuffer::new(offsets.into()),
+                entries,
+                None,
+                false,
+            )?)
+        }
+        Type::Nested(fields) => {
+            let values = values
+                .into_iter()
+                .map(|value| match value {
+                    Some(Value::Nested(fields)) => Ok(Some(Value::nested_into_tuples(fields)?)),
+                    value => Err(invalid_value(ty, value)),
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            to_array(
+                &Type::Array(Box::new(Type::NamedTuple(fields.clone()))),
+                values,
+            )?
+        }
+        // NB: the values are indexed by their binary representation
+        Type::LowCardinality(inner_ty) => {
+            let formatter = RowBinFormatter::new();
+            let mut index = HashMap::new();
+            let mut dict = vec![];
+            let mut keys = Vec::with_capacity(values.len());
+            for value in values {
+                let key = match value.and_then(Value::into_non_nullable) {
+                    Some(value) => {
+                        let key = *index
+                            .entry(formatter.format_value(value.clone())?)
+                            .or_insert_with(|| {
+                                dict.push(Some(value));
+                                dict.len() - 1
+                            });
+                        Some(i32::try_from(key)?)
+                    }
+                    None => None,
+                };
+                keys.push(key);
+            }
+            let dict_ty = inner_ty
+                .clone()
+                .into_non_nullable()
+                .unwrap_or(*inner_ty.clone());
+            Arc::new(DictionaryArray::try_new(
+                Int32Array::from(keys),
+                to_array(&dict_ty, dict)?,
+            )?)
+        }
+        Type::SimpleAggregateFunction(_, ty) => to_array(ty, values)?,
+        Type::Nothing => Arc::new(NullArray::new(values.len())),
+        _ => return Err(Error(format!("Unsupported Arrow type: {ty}"))),
+    })
+}
+
+/// Converts tuple values to an Arrow struct array
+fn to_struct_array(
+    ty: &Type,
+    elements: &[(String, Type)],
+    values: Vec<Option<Value>>,
+) -> Result<ArrayRef, Error> {
+    let mut columns = vec![Vec::with_capacity(values.len()); elements.len()];
+    for value in values {
+        let tuple = match value {
+            Some(Value::Tuple(tuple)) => tuple,
+            Some(Value::NamedTuple(tuple)) => tuple.into_iter().map(|(_, v)| v).collect(),
+            value => return Err(invalid_value(ty, value)),
+        };
+        if tuple.len() != elements.len() {
+            return Err(invalid_value(ty, Some(Value::Tuple(tuple))));
+        }
+        for (column, value) in columns.iter_mut().zip(tuple) {
+            column.push(Some(value));
+        }
+    }
+    let arrays = elements
+        .iter()
+        .zip(columns)
+        .map(|((_, ty), column)| to_array(ty, column))
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(Arc::new(StructArray::try_new(
+        struct_fields(elements)?,
+        arrays,
+        None,
+    )?))
+}
+
+/// Converts an Arrow array to values
+///
+/// NB: NULL values of non nullable types are read as the default values.
+fn from_array(ty: &Type, array: &dyn Array) -> Result<Vec<Value>, Error> {
+    let invalid = || {
+        Error(format!(
+            "Invalid Arrow array for {ty}: {}",
+            array.data_type()
+        ))
+    };
+
+    /// Collects the values of a primitive array
+    macro_rules! collect {
+        ($T:ty, $V:ident => $EXPR:expr) => {
+            array
+                .as_primitive_opt::<$T>()
+                .ok_or_else(invalid)?
+                .values()
+                .iter()
+                .map(|&$V| $EXPR)
+                .collect()
+        };
+    }
+
+    // NB: dictionaries are decoded whatever the type
+    if let Some(dict) = array.as_any_dictionary_opt() {
+        let value_ty = match ty {
+            Type::LowCardinality(ty) => ty.as_ref(),
+            ty => ty,
+        };
+        let dict_ty = value_ty.clone().into_non_nullable();
+        let is_nullable = dict_ty.is_some();
+        let dict_values = from_array(dict_ty.as_ref().unwrap_or(value_ty), dict.values().as_ref())?;
+        let nulls = array.logical_nulls();
+        return dict
+            .normalized_keys()
+            .into_iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let is_null = nulls.as_ref().is_some_and(|nulls| nulls.is_null(i));
+                let value = match (is_null, is_nullable) {
+                    (true, true) => Value::null_of(value_ty),
+                    (_, true) => dict_values.get(key).and_then(|v| v.clone().into_nullable()),
+                    (_, false) => dict_values.get(key).cloned(),
+                };
+                value.ok_or(Error::new("Invalid Arrow dictionary key"))
+            })
+            .collect();
+    }
+
+    if let Type::LowCardinality(ty) | Type::SimpleAggregateFunction(_, ty) = ty {
+        return from_array(ty, array);
+    }
+
+    if let Some(inner_ty) = ty.clone().into_non_nullable() {
+        let nulls = array.logical_nulls();
+        return from_array(&inner_ty, array)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let value = match nulls.as_ref().is_some_and(|nulls| nulls.is_null(i)) {
+                    true => Value::null_of(ty),
+                    false => value.into_nullable(),
+                };
+                value.ok_or_else(invalid)
+            })
+            .collect();
+    }
+
+    Ok(match ty {
+        Type::UInt8 => collect!(UInt8Type, v => Value::UInt8(v)),
+        Type::UInt16 => collect!(UInt16Type, v => Value::UInt16(v)),
+        Type::UInt32 => collect!(UInt32Type, v => Value::UInt32(v)),
+        Type::UInt64 => collect!(UInt64Type, v => Value::UInt64(v)),
+        Type::Int8 => collect!(Int8Type, v => Value::Int8(v)),
+        Type::Int16 => collect!(Int16Type, v => Value::Int16(v)),
+        Type::Int32 => collect!(Int32Type, v => Value::Int32(v)),
+        Type::Int64 => collect!(Int64Type, v => Value::Int64(v)),
+        Type::UInt128
+        | Type::Int128
+        | Type::UInt256
+        | Type::Int256
+        | Type::UUID
+        | Type::FixedString(_) => {
+            let array = array.as_fixed_size_binary_opt().ok_or_else(invalid)?;
+            let formatter = RowBinFormatter::new();
+            (0..array.len())
+                .map(|i| {
+                    let bytes = array.value(i);
+                    match ty {
+                        Type::UUID => Ok(Value::UUID(bytes.try_into()?)),
+                        Type::FixedString(_) => Ok(Value::FixedString(bytes.to_vec())),
+                        ty => formatter.parse_value(&mut &bytes[..], ty.clone()),
+                    }
+                })
+                .collect::<Result<_, Error>>()?
+        }
+        Type::Float32 => collect!(Float32Type, v => Value::Float32(v)),
+        Type::BFloat16 => collect!(Float32Type, v => Value::BFloat16(v)),
+        Type::Float64 => collect!(Float64Type, v => Value::Float64(v)),
+        Type::Decimal(..)
+        | Type::Decimal32(_)
+        | Type::Decimal64(_)
+        | Type::Decimal128(_)
+        | Type::Decimal256(_) => match array.data_type() {
+            DataType::Decimal128(_, s) => {
+                let scale = 10_f64.powi((*s).into());
+                collect!(Decimal128Type, v => Value::Float64(v as f64 / scale))
+            }
+            DataType::Decimal256(_, s) => {
+                let scale = 10_f64.powi((*s).into());
+                array
+                    .as_primitive::<Decimal256Type>()
+                    .values()
+                    .iter()
+                    .map(|v| Ok(Value::Float64(v.to_string().parse::<f64>()? / scale)))
+                    .collect::<Result<_, Error>>()?
+            }
+            _ => return Err(invalid()),
+        },
+        Type::Bool => {
+            let array = array.as_boolean_opt().ok_or_else(invalid)?;
+            array.values().iter().map(Value::Bool).collect()
+        }
+        Type::String => {
+            if let Some(array) = array.as_string_opt::<i32>() {
+                array
+                    .iter()
+                    .map(|v| Value::String(v.unwrap_or_default().to_string()))
+                    .collect()
+            } else if let Some(array) = array.as_string_opt::<i64>() {
+                array
+                    .iter()
+                    .map(|v| Value::String(v.unwrap_or_default().to_string()))
+                    .collect()
+            } else if let Some(array) = array.as_binary_opt::<i32>() {
+                array
+                    .iter()
+                    .map(|v| Value::from_string_bytes(v.unwrap_or_default().to_vec(), false))
+                    .collect()
+            } else if let Some(array) = array.as_binary_opt::<i64>() {
+                array
+                    .iter()
+                    .map(|v| Value::from_string_bytes(v.unwrap_or_default().to_vec(), false))
+                    .collect()
+            } else {
+                return Err(invalid());
+            }
+        }
+        // NB: Clickhouse writes dates as UInt16
+        Type::Date => match array.as_primitive_opt::<UInt16Type>() {
+            Some(array) => array.values().iter().map(|&v| Value::Date(v)).collect(),
+            None => array
+                .as_primitive_opt::<Date32Type>()
+                .ok_or_else(invalid)?
+                .values()
+                .iter()
+                .map(|&v| {
+                    let days =
+                        u16::try_from(v).map_err(|_| Error(format!("Date out of range: {v}")))?;
+                    Ok(Value::Date(days))
+                })
+                .collect::<Result<_, Error>>()?,
+        },
+        Type::Date32 => collect!(Date32Type, v => Value::Date32(v)),
+        // NB: Clickhouse writes datetimes as UInt32
+        Type::DateTime(tz) if array.data_type() == &DataType::UInt32 => {
+            collect!(UInt32Type, v => Value::DateTime(v, tz.clone()))
+        }
+        Type::DateTime(tz) => {
+            let (ticks, p) = timestamp_ticks(array).ok_or_else(invalid)?;
+            ticks
+                .into_iter()
+                .map(|v| {
+                    let secs = u32::try_from(rescale_ticks(v, p, 0))
+                        .map_err(|_| Error(format!("DateTime out of range: {v} ({p} digits)")))?;
+                    Ok(Value::DateTime(secs, tz.clone()))
+                })
+                .collect::<Result<_, Error>>()?
+        }
+        Type::DateTime64(precision, tz) => {
+            let (ticks, p) = timestamp_ticks(array).ok_or_else(invalid)?;
+            ticks
+                .into_iter()
+                .map(|v| Value::DateTime64(rescale_ticks(v, p, *precision), *precision, tz.clone()))
+                .collect()
+        }
+        Type::Time => collect!(Int32Type, v => Value::Time(v)),
+        Type::Time64(p) => collect!(Int64Type, v => Value::Time64(v, *p)),
+        Type::Interval(kind) => collect!(Int64Type, v => Value::Interval(v, *kind)),
+        Type::Enum8(_) => {
+            collect!(Int8Type, v => Value::Enum8(v, ty.enum_variant_name(v.into()).map(String::from)))
+        }
+        Type::Enum16(_) => {
+            collect!(Int16Type, v => Value::Enum16(v, ty.enum_variant_name(v).map(String::from)))
+        }
+        Type::Array(inner_ty) => {
+            let (offsets, inner_array) = list_parts(array).ok_or_else(invalid)?;
+            let inner_values = from_array(inner_ty, inner_array.as_ref())?;
+            offsets
+                .windows(2)
+                .map(|w| Value::Array(inner_values[w[0]..w[1]].to_vec()))
+                .collect()
+        }
+        Type::Tuple(types) => {
+            let types = types.iter().collect::<Vec<_>>();
+            transpose(struct_columns(array, &types)?, array.len())
+                .into_iter()
+                .map(Value::Tuple)
+                .collect()
+        }
+        Type::NamedTuple(elements) => {
+            let types = elements.iter().map(|(_, ty)| ty).collect::<Vec<_>>();
+            transpose(struct_columns(array, &types)?, array.len())
+                .into_iter()
+                .map(|values| {
+                    let names = elements.iter().map(|(name, _)| name.clone());
+                    Value::NamedTuple(names.zip(values).collect())
+                })
+                .collect()
+        }
+        Type::Map(ty_key, ty_val) => {
+            let array = array.as_map_opt().ok_or_else(invalid)?;
+            let keys = from_array(ty_key, array.keys().as_ref())?;
+            let vals = from_array(ty_val, array.values().as_ref())?;
+            let pairs = keys.into_iter().zip(vals).collect::<Vec<_>>();
+            array
+                .value_offsets()
+                .windows(2)
+                .map(|w| Value::Map(pairs[w[0] as usize..w[1] as usize].to_vec()))
+                .collect()
+        }
+        Type::Nested(fields) => {
+            let (names, types): (Vec<_>, Vec<_>) = fields.iter().cloned().unzip();
+            from_array(&Type::Array(Box::new(Type::Tuple(types))), array)?
+                .into_iter()
+                .map(|value| Value::nested_from_tuples(names.clone(), value))
+                .collect::<Result<_, Error>>()?
+        }
+        Type::Nothing => vec![Value::Null; array.len()],
+        _ => return Err(Error(format!("Unsupported Arrow type: {ty}"))),
+    })
+}
+
+/// Returns the ticks of an Arrow timestamp array, with the precision of its unit
+fn timestamp_ticks(array: &dyn Array) -> Option<(Vec<i64>, u8)> {
+    let unit = match array.data_type() {
+        DataType::Timestamp(unit, _) => unit,
+        _ => return None,
+    };
+    let ticks = match unit {
+        TimeUnit::Second => array
+            .as_primitive_opt::<TimestampSecondType>()?
+            .values()
+            .to_vec(),
+        TimeUnit::Millisecond => array
+            .as_primitive_opt::<TimestampMillisecondType>()?
+            .values()
+            .to_vec(),
+        TimeUnit::Microsecond => array
+            .as_primitive_opt::<TimestampMicrosecondType>()?
+            .values()
+            .to_vec(),
+        TimeUnit::Nanosecond => array
+            .as_primitive_opt::<TimestampNanosecondType>()?
+            .values()
+            .to_vec(),
+    };
+    Some((ticks, unit_precision(unit)))
+}
+
+/// Returns the offsets and the values of an Arrow list array
+fn list_parts(array: &dyn Array) -> Option<(Vec<usize>, &ArrayRef)> {
+    if let Some(array) = array.as_list_opt::<i32>() {
+        let offsets = array.value_offsets().iter().map(|o| *o as usize).collect();
+        Some((offsets, array.values()))
+    } else {
+        let array = array.as_list_opt::<i64>()?;
+        let offsets = array.value_offsets().iter().map(|o| *o as usize).collect();
+        Some((offsets, array.values()))
+    }
+}
+
+/// Returns the values of the columns of an Arrow struct array
+fn struct_columns(array: &dyn Array, types: &[&Type]) -> Result<Vec<Vec<Value>>, Error> {
+    let array = array
+        .as_struct_opt()
+        .ok_or(Error::new("Invalid Arrow struct array"))?;
+    if array.num_columns() != types.len() {
+        return Err(Error::new("Invalid Arrow struct array"));
+    }
+    types
+        .iter()
+        .zip(array.columns())
+        .map(|(ty, column)| from_array(ty, column.as_ref()))
+        .collect()
+}
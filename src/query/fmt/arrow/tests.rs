@@ -0,0 +1,336 @@
+//! Tests
+
+use std::{str::FromStr, sync::Arc};
+
+use arrow::{
+    array::{
+        Array, ArrayRef, AsArray, Date32Array, DictionaryArray, Int32Array, Int8Array, StringArray,
+        TimestampMillisecondArray, UInt16Array, UInt32Array,
+    },
+    datatypes::{DataType, Field, Int32Type, Schema, TimeUnit, TimestampMillisecondType},
+    record_batch::RecordBatch,
+};
+
+use crate::{
+    query::{ArrowFormatter, Format, Formatter, QueryColumns, QueryData},
+    value::{Type, Value},
+};
+
+/// Creates sample columns
+fn sample_columns() -> QueryColumns {
+    QueryColumns::new()
+        .column(
+            "id",
+            Type::UInt128,
+            vec![Value::UInt128(1), Value::UInt128(u128::MAX)],
+        )
+        .column(
+            "name",
+            Type::NullableString,
+            vec![
+                Value::NullableString(Some("a".to_string())),
+                Value::NullableString(None),
+            ],
+        )
+        .column(
+            "tag",
+            Type::from_str("LowCardinality(Nullable(String))").unwrap(),
+            vec![
+                Value::NullableString(None),
+                Value::NullableString(Some("x".to_string())),
+            ],
+        )
+        .column(
+            "price",
+            Type::Decimal(10, 2),
+            vec![Value::Float64(12.34), Value::Float64(-0.5)],
+        )
+        .column(
+            "ts",
+            Type::DateTime64(6, Some("Europe/Paris".to_string())),
+            vec![
+                Value::DateTime64(1_700_000_000_123_456, 6, Some("Europe/Paris".to_string())),
+                Value::DateTime64(0, 6, Some("Europe/Paris".to_string())),
+            ],
+        )
+        .column(
+            "attrs",
+            Type::Map(Box::new(Type::String), Box::new(Type::NullableInt64)),
+            vec![
+                Value::Map(vec![(
+                    "k".to_string().into(),
+                    Value::NullableInt64(Some(1)),
+                )]),
+                Value::Map(vec![]),
+            ],
+        )
+        .column(
+            "point",
+            Type::Tuple(vec![Type::Float64, Type::Array(Box::new(Type::UInt8))]),
+            vec![
+                Value::Tuple(vec![
+                    Value::Float64(1.5),
+                    Value::Array(vec![Value::UInt8(1)]),
+                ]),
+                Value::Tuple(vec![Value::Float64(0.0), Value::Array(vec![])]),
+            ],
+        )
+}
+
+#[test]
+fn fmt_arrow_types() {
+    let batch = sample_columns().into_record_batch().unwrap();
+    let schema = batch.schema();
+
+    let field = schema.field_with_name("id").unwrap();
+    assert_eq!(field.data_type(), &DataType::FixedSizeBinary(16));
+    assert!(!field.is_nullable());
+
+    let field = schema.field_with_name("name").unwrap();
+    assert_eq!(field.data_type(), &DataType::Utf8);
+    assert!(field.is_nullable());
+
+    // NB: LowCardinality columns are dictionaries
+    let field = schema.field_with_name("tag").unwrap();
+    assert_eq!(
+        field.data_type(),
+        &DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8))
+    );
+    assert!(field.is_nullable());
+    assert_eq!(
+        field.metadata().get("clickhouse_type").map(String::as_str),
+        Some("LowCardinality(Nullable(String))")
+    );
+
+    let field = schema.field_with_name("price").unwrap();
+    assert_eq!(field.data_type(), &DataType::Decimal128(10, 2));
+    let prices = batch
+        .column(3)
+        .as_primitive::<arrow::datatypes::Decimal128Type>();
+    assert_eq!(prices.values().to_vec(), vec![1234, -50]);
+
+    let field = schema.field_with_name("ts").unwrap();
+    assert_eq!(
+        field.data_type(),
+        &DataType::Timestamp(TimeUnit::Microsecond, Some("Europe/Paris".into()))
+    );
+}
+
+#[test]
+fn fmt_arrow_record_batch() {
+    let columns = sample_columns();
+    let batch = columns.clone().into_record_batch().unwrap();
+    assert_eq!(batch.num_rows(), 2);
+    assert_eq!(QueryColumns::from_record_batch(&batch).unwrap(), columns);
+}
+
+#[test]
+fn fmt_arrow_infer_types() {
+    // NB: without the field metadata, the types are inferred
+    let keys = Int32Array::from(vec![Some(0), None, Some(1), Some(0)]);
+    let values: ArrayRef = Arc::new(StringArray::from(vec!["a", "b"]));
+    let dict = DictionaryArray::<Int32Type>::try_new(keys, values).unwrap();
+    let schema = Schema::new(vec![Field::new("tag", dict.data_type().clone(), true)]);
+    let batch = RecordBatch::try_new(Arc::new(schema), vec![Arc::new(dict)]).unwrap();
+
+    let columns = QueryColumns::from_record_batch(&batch).unwrap();
+    assert_eq!(
+        columns.columns[0].ty,
+        Type::LowCardinality(Box::new(Type::NullableString))
+    );
+    assert_eq!(
+        columns.columns[0].values,
+        vec![
+            Value::NullableString(Some("a".to_string())),
+            Value::NullableString(None),
+            Value::NullableString(Some("b".to_string())),
+            Value::NullableString(Some("a".to_string())),
+        ]
+    );
+
+    let batch = columns.into_record_batch().unwrap();
+    let dict = batch.column(0).as_dictionary::<Int32Type>();
+    assert_eq!(dict.values().len(), 2);
+    assert_eq!(dict.null_count(), 1);
+}
+
+#[test]
+fn fmt_arrow_table() {
+    let mapping = vec![
+        ("id", Type::UInt32),
+        (
+            "n",
+            Type::Nested(vec![
+                ("a".to_string(), Type::UInt8),
+                ("b".to_string(), Type::String),
+            ]),
+        ),
+    ];
+    let table = QueryData::with_names_and_types(mapping.clone())
+        .row(vec![
+            Value::UInt32(1),
+            Value::Nested(vec![
+                ("a".to_string(), vec![Value::UInt8(1)]),
+                ("b".to_string(), vec!["x".to_string().into()]),
+            ]),
+        ])
+        .row(vec![
+            Value::UInt32(2),
+            Value::Nested(vec![("a".to_string(), vec![]), ("b".to_string(), vec![])]),
+        ]);
+
+    for format in [Format::Arrow, Format::ArrowStream] {
+        let bytes = table.clone().to_bytes(format).unwrap();
+        let table_parsed = QueryData::from_bytes(&bytes, format, Some(&mapping)).unwrap();
+        assert_eq!(table_parsed, table);
+    }
+
+    // NB: multiple batches are concatenated
+    let batch = sample_columns().into_record_batch().unwrap();
+    let formatter = ArrowFormatter::stream();
+    let bytes = formatter.write_batches(&[batch.clone(), batch]).unwrap();
+    let table = formatter.parse_data(&bytes, None).unwrap();
+    assert_eq!(table.get_rows().len(), 4);
+}
+
+#[test]
+fn fmt_arrow_clickhouse_types() {
+    // NB: Clickhouse writes no field metadata, dates as UInt16 and datetimes as UInt32
+    let keys = Int32Array::from(vec![0, 0]);
+    let values: ArrayRef = Arc::new(StringArray::from(vec!["x"]));
+    let dict = DictionaryArray::<Int32Type>::try_new(keys, values).unwrap();
+    let arrays: Vec<ArrayRef> = vec![
+        Arc::new(UInt16Array::from(vec![1, 19_000])),
+        Arc::new(UInt16Array::from(vec![Some(2), None])),
+        Arc::new(UInt32Array::from(vec![0, 1_700_000_000])),
+        Arc::new(StringArray::from(vec!["a", "b"])),
+        Arc::new(dict),
+        Arc::new(Int8Array::from(vec![1, 2])),
+    ];
+    let fields = ["d", "nd", "dt", "s", "tag", "e"]
+        .into_iter()
+        .zip(&arrays)
+        .map(|(name, array)| Field::new(name, array.data_type().clone(), name == "nd"))
+        .collect::<Vec<_>>();
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays).unwrap();
+    let formatter = ArrowFormatter::stream();
+    let bytes = formatter.write_batches(&[batch]).unwrap();
+
+    let mapping = vec![
+        ("d", Type::Date),
+        ("nd", Type::NullableDate),
+        ("dt", Type::DateTime(None)),
+        ("s", Type::String),
+        ("tag", Type::LowCardinality(Box::new(Type::String))),
+        ("e", Type::from_str("Enum8('a' = 1, 'b' = 2)").unwrap()),
+    ];
+    let table = formatter.parse_data(&bytes, Some(&mapping)).unwrap();
+    assert_eq!(
+        table.get_rows()[1],
+        vec![
+            Value::Date(19_000),
+            Value::NullableDate(None),
+            Value::DateTime(1_700_000_000, None),
+            Value::String("b".to_string()),
+            Value::String("x".to_string()),
+            Value::Enum8(2, Some("b".to_string())),
+        ]
+    );
+
+    let settings = formatter.settings();
+    assert!(settings.contains(&(
+        "output_format_arrow_low_cardinality_as_dictionary".to_string(),
+        "1".to_string()
+    )));
+}
+
+#[test]
+fn fmt_arrow_out_of_range() {
+    // NB: a Date32 outside of the Date range is an error
+    let array = Date32Array::from(vec![-1]);
+    let schema = Schema::new(vec![Field::new("value", array.data_type().clone(), false)]);
+    let batch = RecordBatch::try_new(Arc::new(schema), vec![Arc::new(array)]).unwrap();
+    let bytes = ArrowFormatter::new().write_batches(&[batch]).unwrap();
+    assert!(Value::from_bytes(&bytes, Format::Arrow, Type::Date).is_err());
+    assert_eq!(
+        Value::from_bytes(&bytes, Format::Arrow, Type::Date32).unwrap(),
+        Value::Date32(-1)
+    );
+
+    // NB: a decimal which overflows a Decimal256 is an error
+    let columns = QueryColumns::new().column("v", Type::Decimal256(0), vec![Value::Float64(1e80)]);
+    assert!(columns.into_record_batch().is_err());
+}
+
+#[test]
+fn fmt_arrow_timestamps() {
+    let timestamp_bytes = |ms: i64| {
+        let array = TimestampMillisecondArray::from(vec![ms]);
+        let schema = Schema::new(vec![Field::new("value", array.data_type().clone(), false)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![Arc::new(array)]).unwrap();
+        ArrowFormatter::new().write_batches(&[batch]).unwrap()
+    };
+
+    // NB: ticks before 1970 are rounded down
+    let ty = Type::DateTime64(0, None);
+    let bytes = timestamp_bytes(-1_501);
+    assert_eq!(
+        Value::from_bytes(&bytes, Format::Arrow, ty.clone()).unwrap(),
+        Value::DateTime64(-2, 0, None)
+    );
+    let bytes = timestamp_bytes(1_501);
+    assert_eq!(
+        Value::from_bytes(&bytes, Format::Arrow, ty).unwrap(),
+        Value::DateTime64(1, 0, None)
+    );
+
+    // NB: a DateTime cannot be before 1970
+    let bytes = timestamp_bytes(-1_000);
+    assert!(Value::from_bytes(&bytes, Format::Arrow, Type::DateTime(None)).is_err());
+
+    // NB: ticks which overflow the Arrow time unit are saturated
+    let columns = QueryColumns::new().column(
+        "ts",
+        Type::DateTime64(1, None),
+        vec![Value::DateTime64(i64::MAX, 1, None)],
+    );
+    let batch = columns.into_record_batch().unwrap();
+    let array = batch.column(0).as_primitive::<TimestampMillisecondType>();
+    assert_eq!(array.value(0), i64::MAX);
+}
+
+#[test]
+fn fmt_arrow_value() {
+    let value = Value::Array(vec![
+        Value::NullableUInt8(Some(1)),
+        Value::NullableUInt8(None),
+    ]);
+    let ty = Type::Array(Box::new(Type::NullableUInt8));
+    let bytes = value.clone().to_bytes(Format::ArrowStream).unwrap();
+    assert_eq!(
+        Value::from_bytes(&bytes, Format::ArrowStream, ty).unwrap(),
+        value
+    );
+
+    let err = QueryColumns::new()
+        .column("v", Type::Dynamic(None), vec![Value::Dynamic(None)])
+        .into_record_batch()
+        .unwrap_err();
+    assert_eq!(err.0, "Unsupported Arrow type: Dynamic");
+
+    // NB: values without an Arrow type are an error, not a panic
+    let value = Value::Variant(Some((0, Box::new(Value::UInt8(1)))));
+    assert!(value.clone().to_bytes(Format::Arrow).is_err());
+    #[cfg(feature = "parquet")]
+    assert!(value.to_bytes(Format::Parquet).is_err());
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn fmt_arrow_parquet() {
+    let columns = sample_columns();
+    let table = QueryData::from_columns(columns.clone()).unwrap();
+    let bytes = table.clone().to_bytes(Format::Parquet).unwrap();
+    let table_parsed = QueryData::from_bytes(&bytes, Format::Parquet, None).unwrap();
+    assert_eq!(table_parsed, table);
+}
@@ -0,0 +1,501 @@
+//! CSV format
+
+#[cfg(test)]
+mod tests;
+
+use std::str::FromStr;
+
+use crate::{
+    error::Error,
+    query::{flatten_nested_mapping, QueryData},
+    value::{Type, Value},
+};
+
+use super::{Formatter, TsvFormatter};
+
+/// CSV formatter
+///
+/// Strings, dates and composite values are enclosed in double quotes. Arrays and maps are
+/// written with their TSV representation, and tuples are written as separate fields.
+#[derive(Debug)]
+pub struct CsvFormatter {
+    /// With column names
+    with_names: bool,
+    /// With column types
+    with_types: bool,
+    /// Field delimiter
+    delimiter: char,
+    /// NULL representation
+    null: String,
+    /// Rows end with `\r\n`
+    crlf: bool,
+    /// Nested columns are flattened
+    flatten_nested: bool,
+}
+
+impl Default for CsvFormatter {
+    fn default() -> Self {
+        Self {
+            with_names: false,
+            with_types: false,
+            delimiter: ',',
+            null: r"\N".to_string(),
+            crlf: false,
+            flatten_nested: true,
+        }
+    }
+}
+
+impl CsvFormatter {
+    /// Use the default variant
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the variant with names
+    pub fn with_names() -> Self {
+        Self {
+            with_names: true,
+            ..Self::default()
+        }
+    }
+
+    /// Use the variant with names and types
+    pub fn with_names_and_types() -> Self {
+        Self {
+            with_names: true,
+            with_types: true,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the field delimiter (`,` by default)
+    ///
+    /// This must match the Clickhouse setting `format_csv_delimiter`.
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets the NULL representation (`\N` by default)
+    ///
+    /// This must match the Clickhouse setting `format_csv_null_representation`.
+    pub fn null_representation(mut self, null: &str) -> Self {
+        self.null = null.to_string();
+        self
+    }
+
+    /// Sets if rows end with `\r\n` instead of `\n` (`false` by default)
+    ///
+    /// This must match the Clickhouse setting `output_format_csv_crlf_end_of_line`. Both are
+    /// accepted when parsing.
+    pub fn crlf_end_of_line(mut self, crlf: bool) -> Self {
+        self.crlf = crlf;
+        self
+    }
+
+    /// Sets if `Nested` columns are flattened (`true` by default)
+    ///
+    /// This must match the Clickhouse setting `flatten_nested`.
+    pub fn flatten_nested(mut self, flatten: bool) -> Self {
+        self.flatten_nested = flatten;
+        self
+    }
+}
+
+impl Formatter for CsvFormatter {
+    fn serialize_value(&self, value: Value) -> Result<Vec<u8>, Error> {
+        self.format_value(value).map(String::into_bytes)
+    }
+
+    fn serialize_query_data(&self, data: QueryData) -> Result<Vec<u8>, Error> {
+        self.format_data(data).map(|s| s.into_bytes())
+    }
+
+    fn deserialize_value(&self, bytes: &[u8], ty: Type) -> Result<Value, Error> {
+        let value = String::from_utf8_lossy(bytes);
+        self.parse_value(&value, ty)
+    }
+
+    fn deserialize_query_data(
+        &self,
+        bytes: &[u8],
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        // NB: invalid UTF-8 sequences are replaced
+        let value = String::from_utf8_lossy(bytes);
+        self.parse_data(&value, mapping)
+    }
+
+    fn settings(&self) -> Vec<(String, String)> {
+        vec![
+            (
+                "format_csv_delimiter".to_string(),
+                self.delimiter.to_string(),
+            ),
+            (
+                "format_csv_null_representation".to_string(),
+                self.null.clone(),
+            ),
+            (
+                "output_format_csv_crlf_end_of_line".to_string(),
+                if self.crlf { "1" } else { "0" }.to_string(),
+            ),
+        ]
+    }
+}
+
+impl CsvFormatter {
+    /// Formats a [Value]
+    ///
+    /// A tuple is formatted as several fields.
+    pub fn format_value(&self, value: Value) -> Result<String, Error> {
+        Ok(self.format_fields(value)?.join(&self.delimiter.to_string()))
+    }
+
+    /// Formats a [Value] to its CSV fields
+    fn format_fields(&self, value: Value) -> Result<Vec<String>, Error> {
+        // NB: a nullable value is written as its inner value, or the NULL representation
+        let value = match value.into_non_nullable() {
+            Some(value) => value,
+            None => return Ok(vec![self.null.clone()]),
+        };
+
+        let field = match value {
+            Value::UInt8(_)
+            | Value::UInt16(_)
+            | Value::UInt32(_)
+            | Value::UInt64(_)
+            | Value::UInt128(_)
+            | Value::UInt256(_)
+            | Value::Int8(_)
+            | Value::Int16(_)
+            | Value::Int32(_)
+            | Value::Int64(_)
+            | Value::Int128(_)
+            | Value::Int256(_)
+            | Value::Float32(_)
+            | Value::Float64(_)
+            | Value::BFloat16(_)
+            | Value::Bool(_)
+            | Value::Interval(..)
+            | Value::Enum8(_, None)
+            | Value::Enum16(_, None) => TsvFormatter::raw().format_value(value)?,
+            Value::String(v) => self.quote(&v),
+            Value::Bytes(v) | Value::FixedString(v) | Value::AggregateFunction(v) => {
+                self.quote(&String::from_utf8_lossy(&v))
+            }
+            Value::Enum8(_, Some(name)) | Value::Enum16(_, Some(name)) => self.quote(&name),
+            Value::Tuple(values) => {
+                return Ok(values
+                    .into_iter()
+                    .map(|v| self.format_fields(v))
+                    .collect::<Result<Vec<_>, Error>>()?
+                    .concat());
+            }
+            Value::NamedTuple(values) => {
+                return Ok(values
+                    .into_iter()
+                    .map(|(_, v)| self.format_fields(v))
+                    .collect::<Result<Vec<_>, Error>>()?
+                    .concat());
+            }
+            Value::Variant(Some((_, v))) | Value::Dynamic(Some((_, v))) => {
+                return self.format_fields(*v)
+            }
+            Value::Variant(None) | Value::Dynamic(None) => self.null.clone(),
+            Value::Json(_) => self.quote(&value.to_json_string()),
+            // NB: dates, arrays, maps, etc.. have their TSV representation
+            value => self.quote(&TsvFormatter::new().format_value(value)?),
+        };
+        Ok(vec![field])
+    }
+
+    /// Encloses a field in double quotes
+    fn quote(&self, s: &str) -> String {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    }
+
+    /// Formats a [QueryData]
+    pub fn format_data(&self, data: QueryData) -> Result<String, Error> {
+        let mut buf = String::new();
+        let data = match self.flatten_nested {
+            true => data.flatten_nested()?,
+            false => data,
+        };
+        let parts = data.into_parts();
+        if self.with_names {
+            if let Some(names) = &parts.names {
+                let names = names.iter().map(|n| self.quote(n)).collect();
+                buf.push_str(&self.format_record(names));
+            } else {
+                return Err(Error::new("Table is missing the column names"));
+            }
+        }
+
+        if self.with_types {
+            if let Some(types) = &parts.types {
+                let types = types.iter().map(|t| self.quote(&t.to_string())).collect();
+                buf.push_str(&self.format_record(types));
+            } else {
+                return Err(Error::new("Table is missing the column types"));
+            }
+        }
+
+        for row in parts.rows {
+            let mut fields = vec![];
+            for (i, value) in row.into_iter().enumerate() {
+                // NB: values are conformed to the column types (eg DateTime64 precision)
+                let value = match parts.types.as_ref().and_then(|types| types.get(i)) {
+                    Some(ty) => value.conform_to(ty)?,
+                    None => value,
+                };
+                fields.extend(self.format_fields(value)?);
+            }
+            buf.push_str(&self.format_record(fields));
+        }
+
+        Ok(buf)
+    }
+
+    /// Formats a record (fields separated by the delimiter, followed by a newline)
+    fn format_record(&self, fields: Vec<String>) -> String {
+        let mut buf = fields.join(&self.delimiter.to_string());
+        buf.push_str(if self.crlf { "\r\n" } else { "\n" });
+        buf
+    }
+
+    /// Parses a [Value]
+    ///
+    /// A tuple is parsed from several fields.
+    pub(super) fn parse_value(&self, value: &str, ty: Type) -> Result<Value, Error> {
+        let mut reader = CsvReader::new(value, self.delimiter);
+        let fields = reader.read_record()?.unwrap_or_default();
+        let mut fields = fields.into_iter();
+        let value = self.parse_fields(&mut fields, ty)?;
+        match fields.next() {
+            Some(_) => Err(Error::new("Too many CSV fields")),
+            None => Ok(value),
+        }
+    }
+
+    /// Parses a [Value] from the next CSV fields
+    fn parse_fields(
+        &self,
+        fields: &mut impl Iterator<Item = CsvField>,
+        ty: Type,
+    ) -> Result<Value, Error> {
+        match ty {
+            Type::Tuple(types) => {
+                let mut values = vec![];
+                for ty in types {
+                    values.push(self.parse_fields(fields, ty)?);
+                }
+                return Ok(Value::Tuple(values));
+            }
+            Type::NamedTuple(elements) => {
+                let mut values = vec![];
+                for (name, ty) in elements {
+                    values.push((name, self.parse_fields(fields, ty)?));
+                }
+                return Ok(Value::NamedTuple(values));
+            }
+            _ => {}
+        }
+
+        let field = fields.next().ok_or(Error::new("Missing CSV field"))?;
+        self.parse_field(field, ty)
+    }
+
+    /// Parses a [Value] from a CSV field
+    fn parse_field(&self, field: CsvField, ty: Type) -> Result<Value, Error> {
+        if !field.quoted && field.text == self.null {
+            match ty {
+                Type::Variant(_) => return Ok(Value::Variant(None)),
+                Type::Dynamic(_) => return Ok(Value::Dynamic(None)),
+                ref ty => {
+                    if let Some(value) = Value::null_of(ty) {
+                        return Ok(value);
+                    }
+                }
+            }
+        }
+
+        if let Some(inner_ty) = ty.clone().into_non_nullable() {
+            let value = self.parse_field(field, inner_ty)?;
+            return Ok(value.into_nullable().unwrap());
+        }
+
+        match ty {
+            Type::String => Ok(Value::String(field.text)),
+            Type::FixedString(_) => Ok(Value::FixedString(field.text.into_bytes())),
+            Type::AggregateFunction(..) => Ok(Value::AggregateFunction(field.text.into_bytes())),
+            Type::LowCardinality(ty) | Type::SimpleAggregateFunction(_, ty) => {
+                self.parse_field(field, *ty)
+            }
+            Type::Tuple(_) | Type::NamedTuple(_) => {
+                let mut fields = std::iter::once(field);
+                self.parse_fields(&mut fields, ty)
+            }
+            // NB: other values have their TSV representation
+            ty => TsvFormatter::new().parse_value(&field.text, ty),
+        }
+    }
+
+    /// Parses a [QueryData]
+    pub fn parse_data(
+        &self,
+        value: &str,
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        let mut reader = CsvReader::new(value, self.delimiter);
+
+        // parse names and types from the buffer
+        let mut data = if self.with_names {
+            let names = match reader.read_record()? {
+                Some(fields) => fields.into_iter().map(|f| f.text).collect::<Vec<_>>(),
+                None => return Err(Error::new("Table is missing the row with names")),
+            };
+
+            if self.with_types {
+                let types = match reader.read_record()? {
+                    Some(fields) => fields
+                        .iter()
+                        .map(|f| Type::from_str(&f.text))
+                        .collect::<Result<Vec<_>, Error>>()?,
+                    None => return Err(Error::new("Table is missing the row with types")),
+                };
+                QueryData::with_names_and_types(
+                    names.iter().map(|n| n.as_str()).zip(types).collect(),
+                )
+            } else {
+                QueryData::with_names(names.iter().map(|n| n.as_str()).collect())
+            }
+        } else {
+            QueryData::no_headers()
+        };
+
+        // nested columns are received as 1 array column per nested column
+        let flat_mapping = mapping.map(|mapping| match self.flatten_nested {
+            true => flatten_nested_mapping(mapping),
+            false => mapping
+                .iter()
+                .map(|(n, t)| (n.to_string(), t.clone()))
+                .collect(),
+        });
+
+        // parse rows from the buffer
+        let types = if let Some(types) = data.get_types() {
+            types
+        } else if let Some(mapping) = &flat_mapping {
+            mapping.iter().map(|(_, t)| t.clone()).collect()
+        } else {
+            return Err(Error::new("Deserializing data requires a mapping table"));
+        };
+
+        while let Some(fields) = reader.read_record()? {
+            let mut fields = fields.into_iter();
+            let mut row = vec![];
+            for ty in &types {
+                row.push(self.parse_fields(&mut fields, ty.clone())?);
+            }
+            if fields.next().is_some() {
+                return Err(Error::new("Too many CSV fields"));
+            }
+            data.add_row(row);
+        }
+
+        match mapping {
+            Some(mapping) if self.flatten_nested => data.unflatten_nested(mapping),
+            _ => Ok(data),
+        }
+    }
+}
+
+/// CSV field
+#[derive(Debug, Default)]
+struct CsvField {
+    /// Text (unquoted)
+    text: String,
+    /// The field is enclosed in double quotes
+    quoted: bool,
+}
+
+/// CSV reader (RFC 4180)
+///
+/// Quoted fields can contain delimiters, newlines and escaped quotes (`""`). Unquoted fields are
+/// trimmed.
+struct CsvReader<'a> {
+    /// Characters
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    /// Field delimiter
+    delimiter: char,
+}
+
+impl<'a> CsvReader<'a> {
+    /// Creates a new reader
+    fn new(s: &'a str, delimiter: char) -> Self {
+        Self {
+            chars: s.chars().peekable(),
+            delimiter,
+        }
+    }
+
+    /// Reads the fields of the next record (`None` if there are no remaining records)
+    fn read_record(&mut self) -> Result<Option<Vec<CsvField>>, Error> {
+        if self.chars.peek().is_none() {
+            return Ok(None);
+        }
+
+        let mut fields = vec![];
+        loop {
+            let field = self.read_field()?;
+            fields.push(field);
+            match self.chars.next() {
+                Some(c) if c == self.delimiter => {}
+                Some('\r') => {
+                    if self.chars.next_if_eq(&'\n').is_none() {
+                        return Err(Error::new("Invalid CSV end of line"));
+                    }
+                    return Ok(Some(fields));
+                }
+                Some('\n') | None => return Ok(Some(fields)),
+                Some(c) => return Err(Error(format!("Unexpected character '{c}' in CSV"))),
+            }
+        }
+    }
+
+    /// Reads a field (up to the next delimiter or newline)
+    fn read_field(&mut self) -> Result<CsvField, Error> {
+        // NB: the spaces before and after a field are ignored
+        let delimiter = self.delimiter;
+        let is_space = |c: &char| (*c == ' ' || *c == '\t') && *c != delimiter;
+        while self.chars.next_if(is_space).is_some() {}
+
+        let mut field = CsvField::default();
+        if self.chars.next_if_eq(&'"').is_some() {
+            field.quoted = true;
+            loop {
+                match self.chars.next() {
+                    Some('"') => match self.chars.next_if_eq(&'"') {
+                        Some(_) => field.text.push('"'),
+                        None => break,
+                    },
+                    Some(c) => field.text.push(c),
+                    None => return Err(Error::new("Unterminated CSV quoted field")),
+                }
+            }
+            while self.chars.next_if(is_space).is_some() {}
+        } else {
+            while let Some(c) = self
+                .chars
+                .next_if(|c| *c != delimiter && *c != '\n' && *c != '\r')
+            {
+                field.text.push(c);
+            }
+            let n = field.text.trim_end_matches([' ', '\t']).len();
+            field.text.truncate(n);
+        }
+        Ok(field)
+    }
+}
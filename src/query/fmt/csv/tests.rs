@@ -0,0 +1,189 @@
+//! Tests
+
+use std::collections::HashMap;
+
+use time::Date;
+
+use crate::{
+    query::{CsvFormatter, Format, Formatter, QueryData},
+    value::{time::DateExt, ChValue, Type, Value},
+};
+
+/// Sets a test
+macro_rules! set_test {
+    ($ID:ident, $TY:ty, $VAL:expr, $STR:literal) => {
+        #[test]
+        fn $ID() {
+            let x: $TY = $VAL;
+            let ty = <$TY as ChValue>::ch_type();
+            let value = x.into_ch_value();
+            let formatter = CsvFormatter::default();
+            let value_str = formatter.format_value(value.clone()).unwrap();
+            assert_eq!(value_str, $STR);
+
+            let value_parsed = formatter.parse_value(&value_str, ty).unwrap();
+            assert_eq!(value_parsed, value);
+        }
+    };
+}
+
+set_test!(fmt_csv_uint64, u64, u64::MAX, "18446744073709551615");
+set_test!(fmt_csv_f64, f64, -1.5, "-1.5");
+set_test!(fmt_csv_bool, bool, true, "true");
+set_test!(
+    fmt_csv_string,
+    String,
+    "a \"b\", c\nd\\e".to_string(),
+    "\"a \"\"b\"\", c\nd\\e\""
+);
+set_test!(
+    fmt_csv_date,
+    Date,
+    Date::from_unix_days(1).unwrap(),
+    "\"1970-01-02\""
+);
+set_test!(
+    fmt_csv_array,
+    Vec<String>,
+    vec!["a'b".to_string(), "c\"".to_string()],
+    r#""['a\'b', 'c""']""#
+);
+set_test!(
+    fmt_csv_map,
+    HashMap<String, u8>,
+    HashMap::from([("a".to_string(), 1)]),
+    r#""{'a': 1}""#
+);
+set_test!(fmt_csv_tuple, (u8, String), (1, "a".to_string()), "1,\"a\"");
+set_test!(fmt_csv_null_some, Option<u8>, Some(1), "1");
+set_test!(fmt_csv_null_none, Option<u8>, None, r"\N");
+
+/// Creates a sample table
+fn sample_table() -> (Vec<(&'static str, Type)>, QueryData) {
+    let mapping = vec![
+        ("id", Type::UInt32),
+        ("name", Type::NullableString),
+        ("point", Type::Tuple(vec![Type::Float64, Type::Float64])),
+        ("tags", Type::Array(Box::new(Type::String))),
+    ];
+    let table = QueryData::with_names_and_types(mapping.clone())
+        .row(vec![
+            Value::UInt32(1),
+            Value::NullableString(Some("multi\nline, \"quoted\"".to_string())),
+            Value::Tuple(vec![Value::Float64(1.5), Value::Float64(-2.0)]),
+            vec!["x".to_string(), "y".to_string()].into(),
+        ])
+        .row(vec![
+            Value::UInt32(2),
+            Value::NullableString(None),
+            Value::Tuple(vec![Value::Float64(0.0), Value::Float64(0.0)]),
+            Value::Array(vec![]),
+        ]);
+    (mapping, table)
+}
+
+#[test]
+fn fmt_csv_table() {
+    let (mapping, table) = sample_table();
+
+    let format = Format::CSVWithNamesAndTypes;
+    let bytes = table.clone().to_bytes(format).unwrap();
+    assert_eq!(
+        String::from_utf8(bytes.clone()).unwrap(),
+        concat!(
+            "\"id\",\"name\",\"point\",\"tags\"\n",
+            "\"UInt32\",\"Nullable(String)\",\"Tuple(Float64, Float64)\",\"Array(String)\"\n",
+            "1,\"multi\nline, \"\"quoted\"\"\",1.5,-2,\"['x', 'y']\"\n",
+            "2,\\N,0,0,\"[]\"\n"
+        )
+    );
+    let table_parsed = QueryData::from_bytes(&bytes, format, None).unwrap();
+    assert_eq!(table_parsed, table);
+
+    let format = Format::CSV;
+    let bytes = table.clone().to_bytes(format).unwrap();
+    let table_parsed = QueryData::from_bytes(&bytes, format, Some(&mapping)).unwrap();
+    assert_eq!(table_parsed.get_rows(), table.get_rows());
+}
+
+#[test]
+fn fmt_csv_dialect() {
+    let (mapping, table) = sample_table();
+    let formatter = CsvFormatter::with_names()
+        .delimiter(';')
+        .null_representation("NULL")
+        .crlf_end_of_line(true);
+
+    let value = formatter.format_data(table.clone()).unwrap();
+    assert_eq!(
+        value,
+        concat!(
+            "\"id\";\"name\";\"point\";\"tags\"\r\n",
+            "1;\"multi\nline, \"\"quoted\"\"\";1.5;-2;\"['x', 'y']\"\r\n",
+            "2;NULL;0;0;\"[]\"\r\n"
+        )
+    );
+    let table_parsed = formatter.parse_data(&value, Some(&mapping)).unwrap();
+    assert_eq!(table_parsed.get_rows(), table.get_rows());
+
+    // NB: a quoted NULL representation is a string
+    let value = formatter
+        .parse_value("\"NULL\"", Type::NullableString)
+        .unwrap();
+    assert_eq!(value, Value::NullableString(Some("NULL".to_string())));
+}
+
+#[test]
+fn fmt_csv_settings() {
+    let settings = CsvFormatter::new()
+        .delimiter(';')
+        .null_representation("NULL")
+        .crlf_end_of_line(true)
+        .settings();
+    assert_eq!(
+        settings,
+        vec![
+            ("format_csv_delimiter".to_string(), ";".to_string()),
+            (
+                "format_csv_null_representation".to_string(),
+                "NULL".to_string()
+            ),
+            (
+                "output_format_csv_crlf_end_of_line".to_string(),
+                "1".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn fmt_csv_parse_file() {
+    let mapping = vec![("a", Type::UInt8), ("b", Type::String), ("c", Type::Date)];
+    let file = "1, hello ,\"2024-01-31\"\r\n  2 ,\"a,b\",2024-02-01\n";
+    let table = QueryData::from_bytes(file.as_bytes(), Format::CSV, Some(&mapping)).unwrap();
+    assert_eq!(
+        table.get_rows(),
+        &vec![
+            vec![
+                Value::UInt8(1),
+                "hello".to_string().into(),
+                Date::from_calendar_date(2024, time::Month::January, 31)
+                    .unwrap()
+                    .into()
+            ],
+            vec![
+                Value::UInt8(2),
+                "a,b".to_string().into(),
+                Date::from_calendar_date(2024, time::Month::February, 1)
+                    .unwrap()
+                    .into()
+            ],
+        ]
+    );
+
+    let err = QueryData::from_bytes(b"1,\"a", Format::CSV, Some(&mapping)).unwrap_err();
+    assert_eq!(err.0, "Unterminated CSV quoted field");
+    let err =
+        QueryData::from_bytes(b"1,a,2024-01-01,0\n", Format::CSV, Some(&mapping)).unwrap_err();
+    assert_eq!(err.0, "Too many CSV fields");
+}
@@ -0,0 +1,641 @@
+//! CustomSeparated and Template formats
+
+#[cfg(test)]
+mod tests;
+
+use std::str::FromStr;
+
+use crate::{
+    error::Error,
+    query::{flatten_nested_mapping, QueryData},
+    value::{Type, Value},
+};
+
+use super::{CsvFormatter, Formatter, JsonFormatter, TsvFormatter};
+
+/// Escaping rule of the values (`format_custom_escaping_rule`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EscapingRule {
+    /// As in `TSV`
+    #[default]
+    Escaped,
+    /// As in `Values` (eg strings enclosed in single quotes)
+    Quoted,
+    /// As in `CSV`
+    CSV,
+    /// As in `JSONEachRow`
+    JSON,
+    /// As in `TSVRaw` (without escaping)
+    Raw,
+}
+
+// NB: the string is the Clickhouse escaping rule name
+impl std::fmt::Display for EscapingRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rule = match self {
+            EscapingRule::Escaped => "Escaped",
+            EscapingRule::Quoted => "Quoted",
+            EscapingRule::CSV => "CSV",
+            EscapingRule::JSON => "JSON",
+            EscapingRule::Raw => "Raw",
+        };
+        write!(f, "{rule}")
+    }
+}
+
+/// CustomSeparated formatter
+///
+/// Rows and values are separated by user-defined delimiters, and values are escaped with an
+/// [EscapingRule]. The `Template` format is supported with the same options, for templates
+/// which have 1 escaping rule for all the columns.
+#[derive(Debug)]
+pub struct CustomFormatter {
+    /// With column names
+    with_names: bool,
+    /// With column types
+    with_types: bool,
+    /// Escaping rule
+    escaping_rule: EscapingRule,
+    /// Delimiter between fields
+    field_delimiter: String,
+    /// Delimiter before a row
+    row_before_delimiter: String,
+    /// Delimiter after a row
+    row_after_delimiter: String,
+    /// Delimiter between rows
+    row_between_delimiter: String,
+    /// Delimiter before the result set
+    result_before_delimiter: String,
+    /// Delimiter after the result set
+    result_after_delimiter: String,
+    /// Column names of the `Template` format
+    template: Option<Vec<String>>,
+    /// Whitespaces around delimiters and values are ignored (parsing only)
+    ignore_spaces: bool,
+    /// Nested columns are flattened
+    flatten_nested: bool,
+}
+
+impl Default for CustomFormatter {
+    fn default() -> Self {
+        Self {
+            with_names: false,
+            with_types: false,
+            escaping_rule: EscapingRule::Escaped,
+            field_delimiter: "\t".to_string(),
+            row_before_delimiter: String::new(),
+            row_after_delimiter: "\n".to_string(),
+            row_between_delimiter: String::new(),
+            result_before_delimiter: String::new(),
+            result_after_delimiter: String::new(),
+            template: None,
+            ignore_spaces: false,
+            flatten_nested: true,
+        }
+    }
+}
+
+impl CustomFormatter {
+    /// Use the default variant
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the variant with names
+    pub fn with_names() -> Self {
+        Self {
+            with_names: true,
+            ..Self::default()
+        }
+    }
+
+    /// Use the variant with names and types
+    pub fn with_names_and_types() -> Self {
+        Self {
+            with_names: true,
+            with_types: true,
+            ..Self::default()
+        }
+    }
+
+    /// Use the `Template` variant, with the column names of the row template
+    ///
+    /// The row template is `<row_before>${col_1:<rule>}<field>${col_2:<rule>}..<row_after>`.
+    /// Without column names, the templates are not sent to the server, and must be configured
+    /// with the server settings.
+    ///
+    /// NB: the `Template` formats have no built-in formatter, it must be registered on the client
+    /// (see [Client::formatter](crate::Client::formatter)).
+    pub fn template(columns: &[&str]) -> Self {
+        Self {
+            template: Some(columns.iter().map(|c| c.to_string()).collect()),
+            ..Self::default()
+        }
+    }
+
+    /// Use the `TemplateIgnoreSpaces` variant, with the column names of the row template
+    ///
+    /// Whitespaces around delimiters and values are ignored when parsing.
+    pub fn template_ignore_spaces(columns: &[&str]) -> Self {
+        Self {
+            ignore_spaces: true,
+            ..Self::template(columns)
+        }
+    }
+
+    /// Sets the escaping rule (`Escaped` by default)
+    pub fn escaping_rule(mut self, rule: EscapingRule) -> Self {
+        self.escaping_rule = rule;
+        self
+    }
+
+    /// Sets the delimiter between fields (`\t` by default)
+    pub fn field_delimiter(mut self, delimiter: &str) -> Self {
+        self.field_delimiter = delimiter.to_string();
+        self
+    }
+
+    /// Sets the delimiter before a row (empty by default)
+    pub fn row_before_delimiter(mut self, delimiter: &str) -> Self {
+        self.row_before_delimiter = delimiter.to_string();
+        self
+    }
+
+    /// Sets the delimiter after a row (`\n` by default)
+    pub fn row_after_delimiter(mut self, delimiter: &str) -> Self {
+        self.row_after_delimiter = delimiter.to_string();
+        self
+    }
+
+    /// Sets the delimiter between rows (empty by default)
+    pub fn row_between_delimiter(mut self, delimiter: &str) -> Self {
+        self.row_between_delimiter = delimiter.to_string();
+        self
+    }
+
+    /// Sets the delimiter before the result set (empty by default)
+    pub fn result_before_delimiter(mut self, delimiter: &str) -> Self {
+        self.result_before_delimiter = delimiter.to_string();
+        self
+    }
+
+    /// Sets the delimiter after the result set (empty by default)
+    pub fn result_after_delimiter(mut self, delimiter: &str) -> Self {
+        self.result_after_delimiter = delimiter.to_string();
+        self
+    }
+
+    /// Sets if `Nested` columns are flattened (`true` by default)
+    ///
+    /// This must match the Clickhouse setting `flatten_nested`.
+    pub fn flatten_nested(mut self, flatten: bool) -> Self {
+        self.flatten_nested = flatten;
+        self
+    }
+
+    /// Sets if whitespaces around delimiters and values are ignored when parsing
+    pub(super) fn ignore_spaces(mut self, ignore: bool) -> Self {
+        self.ignore_spaces = ignore;
+        self
+    }
+}
+
+impl Formatter for CustomFormatter {
+    fn serialize_value(&self, value: Value) -> Result<Vec<u8>, Error> {
+        self.format_value(value).map(String::into_bytes)
+    }
+
+    fn serialize_query_data(&self, data: QueryData) -> Result<Vec<u8>, Error> {
+        self.format_data(data).map(|s| s.into_bytes())
+    }
+
+    fn deserialize_value(&self, bytes: &[u8], ty: Type) -> Result<Value, Error> {
+        let value = String::from_utf8_lossy(bytes);
+        self.parse_value(&value, ty)
+    }
+
+    fn deserialize_query_data(
+        &self,
+        bytes: &[u8],
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        // NB: invalid UTF-8 sequences are replaced
+        let value = String::from_utf8_lossy(bytes);
+        self.parse_data(&value, mapping)
+    }
+
+    fn settings(&self) -> Vec<(String, String)> {
+        match &self.template {
+            None => vec![
+                (
+                    "format_custom_escaping_rule",
+                    self.escaping_rule.to_string(),
+                ),
+                (
+                    "format_custom_field_delimiter",
+                    self.field_delimiter.clone(),
+                ),
+                (
+                    "format_custom_row_before_delimiter",
+                    self.row_before_delimiter.clone(),
+                ),
+                (
+                    "format_custom_row_after_delimiter",
+                    self.row_after_delimiter.clone(),
+                ),
+                (
+                    "format_custom_row_between_delimiter",
+                    self.row_between_delimiter.clone(),
+                ),
+                (
+                    "format_custom_result_before_delimiter",
+                    self.result_before_delimiter.clone(),
+                ),
+                (
+                    "format_custom_result_after_delimiter",
+                    self.result_after_delimiter.clone(),
+                ),
+            ],
+            Some(columns) if columns.is_empty() => vec![],
+            Some(columns) => {
+                // NB: `$` is escaped as `$$` in templates
+                let escape = |s: &str| s.replace('$', "$$");
+                let fields = columns
+                    .iter()
+                    .map(|c| format!("${{{c}:{}}}", self.escaping_rule))
+                    .collect::<Vec<_>>();
+                let row_format = format!(
+                    "{}{}{}",
+                    escape(&self.row_before_delimiter),
+                    fields.join(&escape(&self.field_delimiter)),
+                    escape(&self.row_after_delimiter)
+                );
+                let resultset_format = format!(
+                    "{}${{data}}{}",
+                    escape(&self.result_before_delimiter),
+                    escape(&self.result_after_delimiter)
+                );
+                vec![
+                    ("format_template_row_format", row_format),
+                    ("format_template_resultset_format", resultset_format),
+                    (
+                        "format_template_rows_between_delimiter",
+                        self.row_between_delimiter.clone(),
+                    ),
+                ]
+            }
+        }
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect()
+    }
+}
+
+impl CustomFormatter {
+    /// Formats a [Value] with the escaping rule
+    pub fn format_value(&self, value: Value) -> Result<String, Error> {
+        match self.escaping_rule {
+            EscapingRule::Escaped => TsvFormatter::new().format_value(value),
+            EscapingRule::Raw => TsvFormatter::raw().format_value(value),
+            EscapingRule::CSV => CsvFormatter::new().format_value(value),
+            EscapingRule::JSON => JsonFormatter::new().format_value(value),
+            // NB: the quoted values are the TSV values within arrays
+            EscapingRule::Quoted => match value.into_non_nullable() {
+                Some(value) => TsvFormatter::new().format_value_iter(value, true),
+                None => Ok("NULL".to_string()),
+            },
+        }
+    }
+
+    /// Formats a [QueryData]
+    pub fn format_data(&self, data: QueryData) -> Result<String, Error> {
+        let data = match self.flatten_nested {
+            true => data.flatten_nested()?,
+            false => data,
+        };
+        let parts = data.into_parts();
+
+        let mut rows = vec![];
+        if self.with_names {
+            if let Some(names) = &parts.names {
+                let names = names
+                    .iter()
+                    .map(|n| self.format_value(n.as_str().into()))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                rows.push(self.format_row(names));
+            } else {
+                return Err(Error::new("Table is missing the column names"));
+            }
+        }
+
+        if self.with_types {
+            if let Some(types) = &parts.types {
+                let types = types
+                    .iter()
+                    .map(|t| self.format_value(t.to_string().into()))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                rows.push(self.format_row(types));
+            } else {
+                return Err(Error::new("Table is missing the column types"));
+            }
+        }
+
+        for row in parts.rows {
+            let values = row
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| {
+                    // NB: values are conformed to the column types (eg DateTime64 precision)
+                    match parts.types.as_ref().and_then(|types| types.get(i)) {
+                        Some(ty) => self.format_value(value.conform_to(ty)?),
+                        None => self.format_value(value),
+                    }
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            rows.push(self.format_row(values));
+        }
+
+        Ok(format!(
+            "{}{}{}",
+            self.result_before_delimiter,
+            rows.join(&self.row_between_delimiter),
+            self.result_after_delimiter
+        ))
+    }
+
+    /// Formats a row, with its delimiters
+    fn format_row(&self, values: Vec<String>) -> String {
+        format!(
+            "{}{}{}",
+            self.row_before_delimiter,
+            values.join(&self.field_delimiter),
+            self.row_after_delimiter
+        )
+    }
+
+    /// Parses a [Value] with the escaping rule
+    pub(super) fn parse_value(&self, value: &str, ty: Type) -> Result<Value, Error> {
+        let value = match self.ignore_spaces {
+            true => value.trim(),
+            false => value,
+        };
+        match self.escaping_rule {
+            EscapingRule::Escaped => TsvFormatter::new().parse_value(value, ty),
+            EscapingRule::Raw => match ty {
+                Type::String => Ok(Value::String(value.to_string())),
+                ty => TsvFormatter::raw().parse_value(value, ty),
+            },
+            EscapingRule::CSV => CsvFormatter::new().parse_value(value, ty),
+            EscapingRule::JSON => JsonFormatter::new().parse_value(value, ty),
+            EscapingRule::Quoted => match (value, Value::null_of(&ty)) {
+                ("NULL", Some(null)) => Ok(null),
+                // NB: nullable values are parsed as the non nullable values within arrays
+                _ => match ty.clone().into_non_nullable() {
+                    Some(ty) => Ok(TsvFormatter::new()
+                        .parse_value_iter(value, ty, true)?
+                        .into_nullable()
+                        .unwrap()),
+                    None => TsvFormatter::new().parse_value_iter(value, ty, true),
+                },
+            },
+        }
+    }
+
+    /// Parses the fields of a header row (names or types)
+    fn parse_header(&self, fields: Vec<&str>) -> Result<Vec<String>, Error> {
+        fields
+            .into_iter()
+            .map(|field| match self.parse_value(field, Type::String)? {
+                Value::String(s) => Ok(s),
+                _ => Err(Error::new("Invalid CustomSeparated header")),
+            })
+            .collect()
+    }
+
+    /// Parses a [QueryData]
+    pub fn parse_data(
+        &self,
+        value: &str,
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        let mut reader = CustomReader {
+            s: value,
+            formatter: self,
+            rows: 0,
+        };
+        reader.expect(&self.result_before_delimiter)?;
+
+        // parse names and types from the buffer
+        let mut data = if self.with_names {
+            if reader.is_end() {
+                return Err(Error::new("Table is missing the row with names"));
+            }
+            let names = self.parse_header(reader.read_row(None)?)?;
+
+            if self.with_types {
+                if reader.is_end() {
+                    return Err(Error::new("Table is missing the row with types"));
+                }
+                let types = self
+                    .parse_header(reader.read_row(Some(names.len()))?)?
+                    .iter()
+                    .map(|t| Type::from_str(t))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                QueryData::with_names_and_types(
+                    names.iter().map(|n| n.as_str()).zip(types).collect(),
+                )
+            } else {
+                QueryData::with_names(names.iter().map(|n| n.as_str()).collect())
+            }
+        } else {
+            QueryData::no_headers()
+        };
+
+        // nested columns are received as 1 array column per nested column
+        let flat_mapping = mapping.map(|mapping| match self.flatten_nested {
+            true => flatten_nested_mapping(mapping),
+            false => mapping
+                .iter()
+                .map(|(n, t)| (n.to_string(), t.clone()))
+                .collect(),
+        });
+
+        // parse rows from the buffer
+        let types = if let Some(types) = data.get_types() {
+            types
+        } else if let Some(mapping) = &flat_mapping {
+            mapping.iter().map(|(_, t)| t.clone()).collect()
+        } else {
+            return Err(Error::new("Deserializing data requires a mapping table"));
+        };
+
+        while !reader.is_end() {
+            let fields = reader.read_row(Some(types.len()))?;
+            let row = fields
+                .iter()
+                .zip(&types)
+                .map(|(field, ty)| self.parse_value(field, ty.clone()))
+                .collect::<Result<Vec<_>, Error>>()?;
+            data.add_row(row);
+        }
+
+        match mapping {
+            Some(mapping) if self.flatten_nested => data.unflatten_nested(mapping),
+            _ => Ok(data),
+        }
+    }
+}
+
+/// Reader of the rows of a CustomSeparated buffer
+struct CustomReader<'a> {
+    /// Remaining text
+    s: &'a str,
+    /// Formatter (with the delimiters)
+    formatter: &'a CustomFormatter,
+    /// Number of rows read
+    rows: usize,
+}
+
+impl<'a> CustomReader<'a> {
+    /// Returns the delimiter which is matched
+    ///
+    /// NB: if whitespaces are ignored, the whitespaces around a delimiter are not matched,
+    /// unless the delimiter is only made of whitespaces.
+    fn delimiter<'b>(&self, delimiter: &'b str) -> &'b str {
+        match self.formatter.ignore_spaces && !delimiter.trim().is_empty() {
+            true => delimiter.trim(),
+            false => delimiter,
+        }
+    }
+
+    /// Returns `true` if the remaining text is the end of the result set
+    fn is_end(&self) -> bool {
+        let f = self.formatter;
+        let result_after = self.delimiter(&f.result_after_delimiter);
+        if f.ignore_spaces {
+            let rest = self.s.trim();
+            rest.is_empty() || rest == result_after.trim()
+        } else {
+            self.s == result_after
+        }
+    }
+
+    /// Consumes a delimiter
+    fn expect(&mut self, delimiter: &str) -> Result<(), Error> {
+        let delimiter = self.delimiter(delimiter);
+        if self.formatter.ignore_spaces {
+            self.s = self.s.trim_start();
+            if delimiter.trim().is_empty() {
+                return Ok(());
+            }
+        }
+        match self.s.strip_prefix(delimiter) {
+            Some(rest) => {
+                self.s = rest;
+                Ok(())
+            }
+            None => Err(Error(format!(
+                "Expected delimiter '{}'",
+                delimiter.escape_debug()
+            ))),
+        }
+    }
+
+    /// Returns the position of the next delimiter which is not within a value
+    ///
+    /// Without a delimiter, the value ends with the text.
+    fn scan(&self, delimiter: Option<&str>) -> Option<usize> {
+        let delimiter = match delimiter {
+            Some(d) => d,
+            None => return Some(self.s.len()),
+        };
+        // quote, escaping with a backslash, nesting with brackets
+        let (quote, backslash, nested) = match self.formatter.escaping_rule {
+            EscapingRule::Escaped => (None, true, false),
+            EscapingRule::Raw => (None, false, false),
+            EscapingRule::Quoted => (Some('\''), true, true),
+            EscapingRule::CSV => (Some('"'), false, false),
+            EscapingRule::JSON => (Some('"'), true, true),
+        };
+
+        let mut chars = self.s.char_indices();
+        let mut in_quotes = false;
+        let mut depth = 0_usize;
+        while let Some((i, c)) = chars.next() {
+            if !in_quotes && depth == 0 && self.s[i..].starts_with(delimiter) {
+                return Some(i);
+            }
+            match c {
+                '\\' if backslash && (in_quotes || quote.is_none()) => {
+                    chars.next();
+                }
+                c if Some(c) == quote => in_quotes = !in_quotes,
+                '[' | '(' | '{' if nested && !in_quotes => depth += 1,
+                ']' | ')' | '}' if nested && !in_quotes => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Reads the fields of a row (with the expected number of fields)
+    ///
+    /// Without an expected number, the row has all the fields up to the row delimiter.
+    fn read_row(&mut self, n: Option<usize>) -> Result<Vec<&'a str>, Error> {
+        let f = self.formatter;
+        if self.rows > 0 {
+            self.expect(&f.row_between_delimiter)?;
+        }
+        self.expect(&f.row_before_delimiter)?;
+
+        // NB: the delimiter after the last field is the 1st non empty delimiter after a row
+        let row_end = [
+            &f.row_after_delimiter,
+            &f.row_between_delimiter,
+            &f.result_after_delimiter,
+        ]
+        .into_iter()
+        .map(|d| self.delimiter(d))
+        .find(|d| !d.is_empty());
+        let field_delimiter = Some(self.delimiter(&f.field_delimiter)).filter(|d| !d.is_empty());
+
+        let mut fields = vec![];
+        loop {
+            if f.ignore_spaces {
+                self.s = self.s.trim_start();
+            }
+            let is_last = n == Some(fields.len() + 1);
+            let to_field = match is_last {
+                true => None,
+                false => field_delimiter.and_then(|d| self.scan(Some(d))),
+            };
+            // NB: the last row is not followed by the delimiter between rows
+            let to_row =
+                self.scan(row_end)
+                    .or_else(|| match self.delimiter(&f.result_after_delimiter) {
+                        "" => Some(self.s.len()),
+                        d => self.scan(Some(d)),
+                    });
+            let (pos, is_last) = match (to_field, to_row) {
+                (Some(i), Some(j)) if n.is_none() && j < i => (j, true),
+                (Some(i), _) => (i, false),
+                (None, Some(j)) => (j, true),
+                (None, None) => return Err(Error::new("Missing CustomSeparated delimiter")),
+            };
+            fields.push(&self.s[..pos]);
+            self.s = &self.s[pos..];
+            if is_last {
+                break;
+            }
+            self.expect(&f.field_delimiter)?;
+        }
+        if let Some(n) = n {
+            if fields.len() != n {
+                return Err(Error::new("Missing CustomSeparated field"));
+            }
+        }
+        self.expect(&f.row_after_delimiter)?;
+        self.rows += 1;
+        Ok(fields)
+    }
+}
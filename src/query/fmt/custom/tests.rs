@@ -0,0 +1,228 @@
+//! Tests
+
+use crate::{
+    query::{CustomFormatter, EscapingRule, Format, Formatter, QueryData},
+    value::{ChValue, Type, Value},
+};
+
+/// Sets a test
+macro_rules! set_test {
+    ($ID:ident, $RULE:expr, $TY:ty, $VAL:expr, $STR:literal) => {
+        #[test]
+        fn $ID() {
+            let x: $TY = $VAL;
+            let ty = <$TY as ChValue>::ch_type();
+            let value = x.into_ch_value();
+            let formatter = CustomFormatter::new().escaping_rule($RULE);
+            let value_str = formatter.format_value(value.clone()).unwrap();
+            assert_eq!(value_str, $STR);
+
+            let value_parsed = formatter.parse_value(&value_str, ty).unwrap();
+            assert_eq!(value_parsed, value);
+        }
+    };
+}
+
+set_test!(
+    fmt_custom_escaped,
+    EscapingRule::Escaped,
+    String,
+    "a\tb'c".to_string(),
+    r"a\tb\'c"
+);
+set_test!(
+    fmt_custom_quoted,
+    EscapingRule::Quoted,
+    String,
+    "a\tb'c".to_string(),
+    r"'a\tb\'c'"
+);
+set_test!(
+    fmt_custom_quoted_null,
+    EscapingRule::Quoted,
+    Option<u8>,
+    None,
+    "NULL"
+);
+set_test!(
+    fmt_custom_csv,
+    EscapingRule::CSV,
+    String,
+    "a,\"b\"".to_string(),
+    r#""a,""b""""#
+);
+set_test!(
+    fmt_custom_json,
+    EscapingRule::JSON,
+    Vec<String>,
+    vec!["a\"".to_string()],
+    r#"["a\""]"#
+);
+set_test!(
+    fmt_custom_raw,
+    EscapingRule::Raw,
+    String,
+    "a\\b".to_string(),
+    r"a\b"
+);
+
+/// Creates a sample table
+fn sample_table() -> (Vec<(&'static str, Type)>, QueryData) {
+    let mapping = vec![
+        ("id", Type::UInt32),
+        ("name", Type::NullableString),
+        ("tags", Type::Array(Box::new(Type::String))),
+    ];
+    let table = QueryData::with_names_and_types(mapping.clone())
+        .row(vec![
+            Value::UInt32(1),
+            Value::NullableString(Some("a; b".to_string())),
+            vec!["x;".to_string(), "y".to_string()].into(),
+        ])
+        .row(vec![
+            Value::UInt32(2),
+            Value::NullableString(None),
+            Value::Array(vec![]),
+        ]);
+    (mapping, table)
+}
+
+#[test]
+fn fmt_custom_table() {
+    let (mapping, table) = sample_table();
+
+    let format = Format::CustomSeparatedWithNamesAndTypes;
+    let bytes = table.clone().to_bytes(format).unwrap();
+    assert_eq!(
+        String::from_utf8(bytes.clone()).unwrap(),
+        concat!(
+            "id\tname\ttags\n",
+            "UInt32\tNullable(String)\tArray(String)\n",
+            "1\ta; b\t['x;', 'y']\n",
+            "2\t\\N\t[]\n"
+        )
+    );
+    let table_parsed = QueryData::from_bytes(&bytes, format, None).unwrap();
+    assert_eq!(table_parsed, table);
+
+    let format = Format::CustomSeparated;
+    let bytes = table.clone().to_bytes(format).unwrap();
+    let table_parsed = QueryData::from_bytes(&bytes, format, Some(&mapping)).unwrap();
+    assert_eq!(table_parsed.get_rows(), table.get_rows());
+}
+
+#[test]
+fn fmt_custom_delimiters() {
+    let (mapping, table) = sample_table();
+    let formatter = CustomFormatter::with_names()
+        .escaping_rule(EscapingRule::Quoted)
+        .field_delimiter("; ")
+        .row_before_delimiter("(")
+        .row_after_delimiter(")")
+        .row_between_delimiter(",\n")
+        .result_before_delimiter("[\n")
+        .result_after_delimiter("\n]");
+
+    let value = formatter.format_data(table.clone()).unwrap();
+    assert_eq!(
+        value,
+        concat!(
+            "[\n",
+            "('id'; 'name'; 'tags'),\n",
+            "(1; 'a; b'; ['x;', 'y']),\n",
+            "(2; NULL; [])\n",
+            "]"
+        )
+    );
+    let table_parsed = formatter.parse_data(&value, Some(&mapping)).unwrap();
+    assert_eq!(table_parsed.get_rows(), table.get_rows());
+
+    let err = formatter
+        .parse_data("[\n('id'; 'name'; 'tags'),\n(1; 'a')\n]", Some(&mapping))
+        .unwrap_err();
+    assert_eq!(err.0, "Missing CustomSeparated field");
+}
+
+#[test]
+fn fmt_custom_row_between_only() {
+    let mapping = vec![("id", Type::UInt8), ("name", Type::String)];
+    let mut table = QueryData::no_headers();
+    table.add_row(vec![Value::UInt8(1), Value::String("a".to_string())]);
+    table.add_row(vec![Value::UInt8(2), Value::String("b".to_string())]);
+
+    // NB: the last row is followed by the end of the result set
+    let formatter = CustomFormatter::new()
+        .row_after_delimiter("")
+        .row_between_delimiter("\n");
+    let value = formatter.format_data(table.clone()).unwrap();
+    assert_eq!(value, "1\ta\n2\tb");
+    let table_parsed = formatter.parse_data(&value, Some(&mapping)).unwrap();
+    assert_eq!(table_parsed.get_rows(), table.get_rows());
+
+    let formatter = formatter.result_after_delimiter(";");
+    let value = formatter.format_data(table.clone()).unwrap();
+    assert_eq!(value, "1\ta\n2\tb;");
+    let table_parsed = formatter.parse_data(&value, Some(&mapping)).unwrap();
+    assert_eq!(table_parsed.get_rows(), table.get_rows());
+}
+
+#[test]
+fn fmt_custom_settings() {
+    let formatter = CustomFormatter::new()
+        .escaping_rule(EscapingRule::CSV)
+        .field_delimiter(";");
+    let settings = formatter.settings();
+    assert!(settings.contains(&("format_custom_escaping_rule".to_string(), "CSV".to_string())));
+    assert!(settings.contains(&("format_custom_field_delimiter".to_string(), ";".to_string())));
+    assert!(settings.contains(&(
+        "format_custom_row_after_delimiter".to_string(),
+        "\n".to_string()
+    )));
+
+    let formatter = CustomFormatter::template(&["id", "name"])
+        .escaping_rule(EscapingRule::Quoted)
+        .field_delimiter(", ")
+        .row_before_delimiter("$(")
+        .row_after_delimiter(")")
+        .row_between_delimiter(",\n")
+        .result_after_delimiter(";");
+    assert_eq!(
+        formatter.settings(),
+        vec![
+            (
+                "format_template_row_format".to_string(),
+                "$$(${id:Quoted}, ${name:Quoted})".to_string()
+            ),
+            (
+                "format_template_resultset_format".to_string(),
+                "${data};".to_string()
+            ),
+            (
+                "format_template_rows_between_delimiter".to_string(),
+                ",\n".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn fmt_custom_template_ignore_spaces() {
+    let mapping = vec![("id", Type::UInt8), ("name", Type::String)];
+    let formatter = CustomFormatter::template_ignore_spaces(&["id", "name"])
+        .escaping_rule(EscapingRule::JSON)
+        .field_delimiter(",")
+        .row_before_delimiter("{")
+        .row_after_delimiter("}")
+        .row_between_delimiter(";");
+
+    let table = formatter
+        .parse_data("{ 1 , \"a, b\" } ;\n{2,\"c\"}\n", Some(&mapping))
+        .unwrap();
+    assert_eq!(
+        table.get_rows(),
+        &vec![
+            vec![Value::UInt8(1), "a, b".to_string().into()],
+            vec![Value::UInt8(2), "c".to_string().into()],
+        ]
+    );
+}
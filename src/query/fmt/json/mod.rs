@@ -0,0 +1,1397 @@
+//! JSON formats
+
+#[cfg(test)]
+mod tests;
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use crate::{
+    error::Error,
+    query::{flatten_nested_mapping, json::quote_json, QueryData, QueryDataParts},
+    value::{Type, Value},
+};
+
+use super::{Formatter, TsvFormatter};
+
+/// Layout of the rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    /// 1 object per row (`JSONEachRow`)
+    Object,
+    /// 1 object per row, with 1 line per column (`PrettyJSONEachRow`)
+    Pretty,
+    /// 1 `{"row":{..}}` object per row, with progress objects (`JSONEachRowWithProgress`)
+    Progress,
+    /// 1 array per row (`JSONCompactEachRow`)
+    Compact,
+    /// 1 object with the rows as `row_N` fields (`JSONObjectEachRow`)
+    ObjectEachRow,
+    /// 1 document with the metadata and 1 object per row (`JSON`)
+    Document,
+    /// 1 document with the metadata and 1 array per row (`JSONCompact`)
+    CompactDocument,
+    /// 1 document with the metadata and 1 array per column (`JSONColumnsWithMetadata`)
+    ColumnsDocument,
+}
+
+impl Layout {
+    /// Returns `true` if the rows are within a document with metadata
+    fn is_document(&self) -> bool {
+        matches!(
+            self,
+            Layout::Document | Layout::CompactDocument | Layout::ColumnsDocument
+        )
+    }
+}
+
+/// Metadata of a JSON document (`JSON`, `JSONCompact`, `JSONColumnsWithMetadata` formats)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JsonMetadata {
+    /// Column names and types (`meta`)
+    pub columns: Vec<(String, Type)>,
+    /// Number of rows (`rows`)
+    pub rows: Option<u64>,
+    /// Number of rows without the `LIMIT` clause (`rows_before_limit_at_least`)
+    pub rows_before_limit_at_least: Option<u64>,
+    /// Query statistics (`statistics`)
+    pub statistics: Option<JsonStatistics>,
+}
+
+/// Query statistics of a JSON document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JsonStatistics {
+    /// Elapsed time (in seconds)
+    pub elapsed: f64,
+    /// Number of rows read
+    pub rows_read: u64,
+    /// Number of bytes read
+    pub bytes_read: u64,
+}
+
+/// JSON formatter
+///
+/// It covers the `EachRow` variants of the JSON formats, and the JSON documents with metadata
+/// (`JSON`, `JSONCompact`, `JSONColumnsWithMetadata`). Values are written with the Clickhouse
+/// JSON encoding, which is configured with the `output_format_json_*` settings.
+#[derive(Debug)]
+pub struct JsonFormatter {
+    /// Layout
+    layout: Layout,
+    /// Values are written as JSON strings
+    strings: bool,
+    /// With column names (compact layout)
+    with_names: bool,
+    /// With column types (compact layout)
+    with_types: bool,
+    /// Nested columns are flattened
+    flatten_nested: bool,
+    /// 64-bit integers are quoted
+    quote_64bit_integers: bool,
+    /// 64-bit floats are quoted
+    quote_64bit_floats: bool,
+    /// NaN and infinite floats are quoted
+    quote_denormals: bool,
+    /// Named tuples are written as objects
+    named_tuples_as_objects: bool,
+}
+
+impl Default for JsonFormatter {
+    fn default() -> Self {
+        Self {
+            layout: Layout::Object,
+            strings: false,
+            with_names: false,
+            with_types: false,
+            flatten_nested: true,
+            quote_64bit_integers: true,
+            quote_64bit_floats: false,
+            quote_denormals: false,
+            named_tuples_as_objects: true,
+        }
+    }
+}
+
+impl JsonFormatter {
+    /// Use the `JSONEachRow` variant
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the `PrettyJSONEachRow` variant
+    pub fn pretty() -> Self {
+        Self {
+            layout: Layout::Pretty,
+            ..Self::default()
+        }
+    }
+
+    /// Use the `JSONEachRowWithProgress` variant
+    pub fn with_progress() -> Self {
+        Self {
+            layout: Layout::Progress,
+            ..Self::default()
+        }
+    }
+
+    /// Use the `JSONCompactEachRow` variant
+    pub fn compact() -> Self {
+        Self {
+            layout: Layout::Compact,
+            ..Self::default()
+        }
+    }
+
+    /// Use the `JSONCompactEachRowWithNames` variant
+    pub fn compact_with_names() -> Self {
+        Self {
+            layout: Layout::Compact,
+            with_names: true,
+            ..Self::default()
+        }
+    }
+
+    /// Use the `JSONCompactEachRowWithNamesAndTypes` variant
+    pub fn compact_with_names_and_types() -> Self {
+        Self {
+            layout: Layout::Compact,
+            with_names: true,
+            with_types: true,
+            ..Self::default()
+        }
+    }
+
+    /// Use the `JSONObjectEachRow` variant
+    pub fn object_each_row() -> Self {
+        Self {
+            layout: Layout::ObjectEachRow,
+            ..Self::default()
+        }
+    }
+
+    /// Use the `JSON` variant
+    pub fn document() -> Self {
+        Self {
+            layout: Layout::Document,
+            ..Self::default()
+        }
+    }
+
+    /// Use the `JSONCompact` variant
+    pub fn compact_document() -> Self {
+        Self {
+            layout: Layout::CompactDocument,
+            ..Self::default()
+        }
+    }
+
+    /// Use the `JSONColumnsWithMetadata` variant
+    pub fn columns_document() -> Self {
+        Self {
+            layout: Layout::ColumnsDocument,
+            ..Self::default()
+        }
+    }
+
+    /// Sets if values are written as JSON strings (`false` by default)
+    ///
+    /// This is the `Strings` variant of a format (eg `JSONStringsEachRow`), where each value is
+    /// written as a string with its text representation.
+    pub fn strings(mut self, strings: bool) -> Self {
+        self.strings = strings;
+        self
+    }
+
+    /// Sets if `Nested` columns are flattened (`true` by default)
+    ///
+    /// This must match the Clickhouse setting `flatten_nested`.
+    pub fn flatten_nested(mut self, flatten: bool) -> Self {
+        self.flatten_nested = flatten;
+        self
+    }
+
+    /// Sets if 64-bit (and larger) integers are quoted (`true` by default)
+    ///
+    /// This must match the Clickhouse setting `output_format_json_quote_64bit_integers`.
+    pub fn quote_64bit_integers(mut self, quote: bool) -> Self {
+        self.quote_64bit_integers = quote;
+        self
+    }
+
+    /// Sets if 64-bit floats are quoted (`false` by default)
+    ///
+    /// This must match the Clickhouse setting `output_format_json_quote_64bit_floats`.
+    pub fn quote_64bit_floats(mut self, quote: bool) -> Self {
+        self.quote_64bit_floats = quote;
+        self
+    }
+
+    /// Sets if NaN and infinite floats are quoted (`false` by default)
+    ///
+    /// They are written as `"nan"`, `"inf"` and `"-inf"`, otherwise as `null`. This must match
+    /// the Clickhouse setting `output_format_json_quote_denormals`.
+    pub fn quote_denormals(mut self, quote: bool) -> Self {
+        self.quote_denormals = quote;
+        self
+    }
+
+    /// Sets if named tuples are written as objects (`true` by default)
+    ///
+    /// This must match the Clickhouse setting `output_format_json_named_tuples_as_objects`.
+    pub fn named_tuples_as_objects(mut self, as_objects: bool) -> Self {
+        self.named_tuples_as_objects = as_objects;
+        self
+    }
+}
+
+impl Formatter for JsonFormatter {
+    fn serialize_value(&self, value: Value) -> Result<Vec<u8>, Error> {
+        self.format_value(value).map(String::into_bytes)
+    }
+
+    fn serialize_query_data(&self, data: QueryData) -> Result<Vec<u8>, Error> {
+        self.format_data(data).map(|s| s.into_bytes())
+    }
+
+    fn deserialize_value(&self, bytes: &[u8], ty: Type) -> Result<Value, Error> {
+        let value = String::from_utf8_lossy(bytes);
+        self.parse_value(&value, ty)
+    }
+
+    fn deserialize_query_data(
+        &self,
+        bytes: &[u8],
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        // NB: JSON strings are UTF-8, invalid sequences are replaced
+        let value = String::from_utf8_lossy(bytes);
+        self.parse_data(&value, mapping)
+    }
+
+    fn settings(&self) -> Vec<(String, String)> {
+        let flag = |b: bool| if b { "1" } else { "0" }.to_string();
+        vec![
+            (
+                "output_format_json_quote_64bit_integers".to_string(),
+                flag(self.quote_64bit_integers),
+            ),
+            (
+                "output_format_json_quote_64bit_floats".to_string(),
+                flag(self.quote_64bit_floats),
+            ),
+            (
+                "output_format_json_quote_denormals".to_string(),
+                flag(self.quote_denormals),
+            ),
+            (
+                "output_format_json_named_tuples_as_objects".to_string(),
+                flag(self.named_tuples_as_objects),
+            ),
+        ]
+    }
+}
+
+impl JsonFormatter {
+    /// Formats a [Value]
+    pub fn format_value(&self, value: Value) -> Result<String, Error> {
+        // NB: a nullable value is written as its inner value, or null
+        let value = match value.into_non_nullable() {
+            Some(value) => value,
+            None => return Ok("null".to_string()),
+        };
+
+        if self.strings {
+            return Ok(match value {
+                Value::Variant(None) | Value::Dynamic(None) => "null".to_string(),
+                Value::String(v) => quote_json(&v),
+                Value::Json(_) => quote_json(&value.to_json_string()),
+                value => quote_json(&TsvFormatter::raw().format_value(value)?),
+            });
+        }
+
+        Ok(match value {
+            Value::UInt64(_)
+            | Value::UInt128(_)
+            | Value::UInt256(_)
+            | Value::Int64(_)
+            | Value::Int128(_)
+            | Value::Int256(_)
+            | Value::Interval(..)
+                if self.quote_64bit_integers =>
+            {
+                quote_json(&value.to_json_string())
+            }
+            Value::Float32(v) | Value::BFloat16(v) if !v.is_finite() => {
+                self.format_denormal(v.into())
+            }
+            Value::Float64(v) if !v.is_finite() => self.format_denormal(v),
+            Value::Float64(v) if self.quote_64bit_floats => quote_json(&v.to_string()),
+            Value::Array(values) | Value::Tuple(values) => format!(
+                "[{}]",
+                values
+                    .into_iter()
+                    .map(|v| self.format_value(v))
+                    .collect::<Result<Vec<_>, Error>>()?
+                    .join(",")
+            ),
+            Value::NamedTuple(values) if self.named_tuples_as_objects => format!(
+                "{{{}}}",
+                values
+                    .into_iter()
+                    .map(|(name, v)| Ok(format!("{}:{}", quote_json(&name), self.format_value(v)?)))
+                    .collect::<Result<Vec<_>, Error>>()?
+                    .join(",")
+            ),
+            Value::NamedTuple(values) => {
+                self.format_value(Value::Tuple(values.into_iter().map(|(_, v)| v).collect()))?
+            }
+            Value::Map(map) => format!(
+                "{{{}}}",
+                map.into_iter()
+                    .map(|(k, v)| {
+                        // NB: keys are written as strings
+                        let key = match k.into_non_nullable() {
+                            Some(Value::String(k)) => k,
+                            Some(k) => TsvFormatter::raw().format_value(k)?,
+                            None => String::new(),
+                        };
+                        Ok(format!("{}:{}", quote_json(&key), self.format_value(v)?))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?
+                    .join(",")
+            ),
+            // NB: a single nested value is written as an array of named tuples
+            Value::Nested(fields) => {
+                let names = fields.iter().map(|(n, _)| n.clone()).collect::<Vec<_>>();
+                let rows = match Value::nested_into_tuples(fields)? {
+                    Value::Array(rows) => rows,
+                    _ => unreachable!(),
+                };
+                let rows = rows
+                    .into_iter()
+                    .map(|row| match row {
+                        Value::Tuple(values) => {
+                            Value::NamedTuple(names.iter().cloned().zip(values).collect())
+                        }
+                        row => row,
+                    })
+                    .collect();
+                self.format_value(Value::Array(rows))?
+            }
+            Value::Variant(Some((_, v))) | Value::Dynamic(Some((_, v))) => self.format_value(*v)?,
+            value => value.to_json_string(),
+        })
+    }
+
+    /// Formats a NaN or infinite float
+    fn format_denormal(&self, v: f64) -> String {
+        if !self.quote_denormals {
+            return "null".to_string();
+        }
+        let s = if v.is_nan() {
+            "nan"
+        } else if v.is_sign_positive() {
+            "inf"
+        } else {
+            "-inf"
+        };
+        quote_json(s)
+    }
+
+    /// Formats a [QueryData]
+    pub fn format_data(&self, data: QueryData) -> Result<String, Error> {
+        // each row is followed by a newline (inc. last row)
+        let mut buf = String::new();
+        let data = match self.flatten_nested {
+            true => data.flatten_nested()?,
+            false => data,
+        };
+        let parts = data.into_parts();
+        if self.layout.is_document() {
+            return self.format_document(parts);
+        }
+
+        let names = match (&parts.names, self.layout) {
+            (Some(names), _) => names.iter().map(|n| quote_json(n)).collect::<Vec<_>>(),
+            (None, Layout::Compact) if !self.with_names => vec![],
+            (None, _) => return Err(Error::new("Table is missing the column names")),
+        };
+
+        if self.with_names {
+            buf.push_str(&format!("[{}]\n", names.join(",")));
+        }
+
+        if self.with_types {
+            if let Some(types) = &parts.types {
+                let types = types
+                    .iter()
+                    .map(|t| quote_json(&t.to_string()))
+                    .collect::<Vec<_>>();
+                buf.push_str(&format!("[{}]\n", types.join(",")));
+            } else {
+                return Err(Error::new("Table is missing the column types"));
+            }
+        }
+
+        let mut objects = vec![];
+        for row in parts.rows {
+            let values = self.format_row(row, parts.types.as_deref())?;
+
+            let fields = |sep: &str| {
+                names
+                    .iter()
+                    .zip(&values)
+                    .map(|(name, value)| format!("{name}{sep}{value}"))
+                    .collect::<Vec<_>>()
+            };
+            match self.layout {
+                Layout::Compact => buf.push_str(&format!("[{}]\n", values.join(","))),
+                Layout::Object => buf.push_str(&format!("{{{}}}\n", fields(":").join(","))),
+                Layout::Pretty => {
+                    let fields = fields(": ")
+                        .into_iter()
+                        .map(|field| format!("    {field}"))
+                        .collect::<Vec<_>>();
+                    buf.push_str(&format!("{{\n{}\n}}\n", fields.join(",\n")))
+                }
+                Layout::Progress => {
+                    buf.push_str(&format!("{{\"row\":{{{}}}}}\n", fields(":").join(",")))
+                }
+                Layout::ObjectEachRow => objects.push(format!(
+                    "\"row_{}\":{{{}}}",
+                    objects.len() + 1,
+                    fields(":").join(",")
+                )),
+                Layout::Document | Layout::CompactDocument | Layout::ColumnsDocument => {
+                    unreachable!()
+                }
+            }
+        }
+
+        if self.layout == Layout::ObjectEachRow {
+            buf.push_str(&format!("{{\n{}\n}}\n", objects.join(",\n")));
+        }
+
+        Ok(buf)
+    }
+
+    /// Formats the values of a row
+    fn format_row(&self, row: Vec<Value>, types: Option<&[Type]>) -> Result<Vec<String>, Error> {
+        row.into_iter()
+            .enumerate()
+            .map(|(i, value)| {
+                // NB: values are conformed to the column types (eg DateTime64 precision)
+                match types.and_then(|types| types.get(i)) {
+                    Some(ty) => self.format_value(value.conform_to(ty)?),
+                    None => self.format_value(value),
+                }
+            })
+            .collect()
+    }
+
+    /// Formats a JSON document, with the metadata and the rows
+    fn format_document(&self, parts: QueryDataParts) -> Result<String, Error> {
+        let (names, types) = match (parts.names, parts.types) {
+            (Some(names), Some(types)) => (names, types),
+            _ => return Err(Error::new("Table is missing the column names and types")),
+        };
+        let meta = names
+            .iter()
+            .zip(&types)
+            .map(|(name, ty)| {
+                format!(
+                    "{{\"name\":{},\"type\":{}}}",
+                    quote_json(name),
+                    quote_json(&ty.to_string())
+                )
+            })
+            .collect::<Vec<_>>();
+        let names = names.iter().map(|n| quote_json(n)).collect::<Vec<_>>();
+
+        let n_rows = parts.rows.len();
+        let mut rows = vec![];
+        for row in parts.rows {
+            rows.push(self.format_row(row, Some(&types))?);
+        }
+        let data = match self.layout {
+            Layout::CompactDocument => {
+                let rows = rows
+                    .into_iter()
+                    .map(|values| format!("[{}]", values.join(",")))
+                    .collect::<Vec<_>>();
+                format!("[\n{}\n]", rows.join(",\n"))
+            }
+            Layout::ColumnsDocument => {
+                let mut columns = vec![vec![]; names.len()];
+                for values in rows {
+                    for (col, value) in columns.iter_mut().zip(values) {
+                        col.push(value);
+                    }
+                }
+                let columns = names
+                    .iter()
+                    .zip(columns)
+                    .map(|(name, col)| format!("{name}:[{}]", col.join(",")))
+                    .collect::<Vec<_>>();
+                format!("{{\n{}\n}}", columns.join(",\n"))
+            }
+            _ => {
+                let rows = rows
+                    .into_iter()
+                    .map(|values| {
+                        let fields = names
+                            .iter()
+                            .zip(values)
+                            .map(|(name, value)| format!("{name}:{value}"))
+                            .collect::<Vec<_>>();
+                        format!("{{{}}}", fields.join(","))
+                    })
+                    .collect::<Vec<_>>();
+                format!("[\n{}\n]", rows.join(",\n"))
+            }
+        };
+
+        Ok(format!(
+            "{{\n\"meta\":[{}],\n\"data\":{data},\n\"rows\":{n_rows}\n}}\n",
+            meta.join(",")
+        ))
+    }
+
+    /// Parses a [Value]
+    pub(super) fn parse_value(&self, value: &str, ty: Type) -> Result<Value, Error> {
+        let mut reader = JsonReader::new(value);
+        let node = reader.read_node()?;
+        reader.expect_end()?;
+        self.parse_node(node, ty)
+    }
+
+    /// Parses a [Value] from a JSON node
+    fn parse_node(&self, node: JsonNode, ty: Type) -> Result<Value, Error> {
+        if node == JsonNode::Null {
+            return match ty {
+                Type::LowCardinality(ty) | Type::SimpleAggregateFunction(_, ty) => {
+                    self.parse_node(node, *ty)
+                }
+                Type::Variant(_) => Ok(Value::Variant(None)),
+                Type::Dynamic(_) => Ok(Value::Dynamic(None)),
+                // NB: NaN and infinite floats are written as null (unless they are quoted)
+                Type::Float32 => Ok(Value::Float32(f32::NAN)),
+                Type::Float64 => Ok(Value::Float64(f64::NAN)),
+                Type::BFloat16 => Ok(Value::BFloat16(f32::NAN)),
+                ty => Value::null_of(&ty).ok_or(Error(format!("Invalid null value for {ty}"))),
+            };
+        }
+
+        if let Some(inner_ty) = ty.clone().into_non_nullable() {
+            let value = self.parse_node(node, inner_ty)?;
+            return Ok(value.into_nullable().unwrap());
+        }
+
+        // NB: with the Strings variants, composite values have their text representation
+        if self.strings && !is_scalar(&ty) {
+            return match node {
+                JsonNode::String(s) => match ty {
+                    Type::Json(..) => self.parse_node(JsonReader::new(&s).read_node()?, ty),
+                    ty => TsvFormatter::raw().parse_value(&s, ty),
+                },
+                _ => Err(Error(format!("Expected a JSON string for {ty}"))),
+            };
+        }
+
+        match ty {
+            Type::String => Ok(Value::String(node.into_text(&ty)?)),
+            Type::FixedString(_) => Ok(Value::FixedString(node.into_text(&ty)?.into_bytes())),
+            Type::AggregateFunction(..) => {
+                Ok(Value::AggregateFunction(node.into_text(&ty)?.into_bytes()))
+            }
+            Type::Enum8(ref variants) => {
+                let v = node.into_text(&ty)?;
+                match parse_enum(&v, variants) {
+                    Some((name, i)) => Ok(Value::Enum8(i, Some(name))),
+                    None => Err(Error::new(format!("Invalid enum variant: {v}").as_str())),
+                }
+            }
+            Type::Enum16(ref variants) => {
+                let v = node.into_text(&ty)?;
+                match parse_enum(&v, variants) {
+                    Some((name, i)) => Ok(Value::Enum16(i, Some(name))),
+                    None => Err(Error::new(format!("Invalid enum variant: {v}").as_str())),
+                }
+            }
+            Type::Array(ty) => match node {
+                JsonNode::Array(nodes) => Ok(Value::Array(
+                    nodes
+                        .into_iter()
+                        .map(|node| self.parse_node(node, *ty.clone()))
+                        .collect::<Result<_, _>>()?,
+                )),
+                _ => Err(Error::new("Invalid array")),
+            },
+            Type::Tuple(types) => {
+                // NB: an object is read by position
+                let nodes = match node {
+                    JsonNode::Array(nodes) => nodes,
+                    JsonNode::Object(fields) => fields.into_iter().map(|(_, n)| n).collect(),
+                    _ => return Err(Error::new("Invalid tuple")),
+                };
+                if nodes.len() != types.len() {
+                    return Err(Error::new("Invalid tuple"));
+                }
+                Ok(Value::Tuple(
+                    nodes
+                        .into_iter()
+                        .zip(types)
+                        .map(|(node, ty)| self.parse_node(node, ty))
+                        .collect::<Result<_, _>>()?,
+                ))
+            }
+            Type::NamedTuple(elements) => match node {
+                JsonNode::Object(mut fields) => {
+                    let mut values = vec![];
+                    for (name, ty) in elements {
+                        let i = fields
+                            .iter()
+                            .position(|(n, _)| *n == name)
+                            .ok_or(Error(format!("Missing tuple element '{name}'")))?;
+                        let (_, node) = fields.swap_remove(i);
+                        values.push((name, self.parse_node(node, ty)?));
+                    }
+                    Ok(Value::NamedTuple(values))
+                }
+                node => {
+                    let (names, types): (Vec<_>, Vec<_>) = elements.into_iter().unzip();
+                    match self.parse_node(node, Type::Tuple(types))? {
+                        Value::Tuple(values) => {
+                            Ok(Value::NamedTuple(names.into_iter().zip(values).collect()))
+                        }
+                        _ => unreachable!(),
+                    }
+                }
+            },
+            Type::Map(ty_key, ty_val) => match node {
+                JsonNode::Object(fields) => {
+                    let mut map = vec![];
+                    for (key, node) in fields {
+                        let key = self.parse_node(JsonNode::String(key), *ty_key.clone())?;
+                        map.push((key, self.parse_node(node, *ty_val.clone())?));
+                    }
+                    Ok(Value::Map(map))
+                }
+                _ => Err(Error::new("Invalid map")),
+            },
+            Type::LowCardinality(ty) | Type::SimpleAggregateFunction(_, ty) => {
+                self.parse_node(node, *ty)
+            }
+            Type::Nested(fields) => {
+                let names = fields.iter().map(|(n, _)| n.clone()).collect();
+                let ty = Type::Array(Box::new(Type::NamedTuple(fields)));
+                let rows = match self.parse_node(node, ty)? {
+                    Value::Array(rows) => rows,
+                    _ => unreachable!(),
+                };
+                let rows = rows
+                    .into_iter()
+                    .map(|row| match row {
+                        Value::NamedTuple(values) => {
+                            Value::Tuple(values.into_iter().map(|(_, v)| v).collect())
+                        }
+                        row => row,
+                    })
+                    .collect();
+                Value::nested_from_tuples(names, Value::Array(rows))
+            }
+            Type::Variant(_) => {
+                // NB: the value is parsed with the first matching type, strings are tried last
+                let (strings, others): (Vec<_>, Vec<_>) = ty
+                    .variant_types()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .enumerate()
+                    .partition(|(_, ty)| matches!(ty, Type::String | Type::FixedString(_)));
+                for (i, var_ty) in others.into_iter().chain(strings) {
+                    if let Ok(v) = self.parse_node(node.clone(), var_ty.clone()) {
+                        return Ok(Value::Variant(Some((i.try_into()?, Box::new(v)))));
+                    }
+                }
+                Err(Error::new("Invalid variant value"))
+            }
+            Type::Dynamic(_) => Ok(node.into_dynamic()),
+            Type::Json(paths, _) => match node {
+                JsonNode::Object(fields) => {
+                    let mut values = vec![];
+                    for (path, leaf) in flatten_object(fields) {
+                        let value = match paths.iter().find(|(p, _)| *p == path) {
+                            Some((_, ty)) => self.parse_node(leaf, ty.clone())?,
+                            None => leaf.into_dynamic(),
+                        };
+                        values.push((path, value));
+                    }
+                    Ok(Value::Json(values))
+                }
+                _ => Err(Error::new("Invalid JSON object")),
+            },
+            // NB: other types are scalars, which have the same text as TSV
+            ty => {
+                let v = node.into_text(&ty)?;
+                TsvFormatter::new().parse_value(&v, ty)
+            }
+        }
+    }
+
+    /// Parses a [QueryData]
+    pub fn parse_data(
+        &self,
+        value: &str,
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        let mut reader = JsonReader::new(value);
+        let mut nodes = vec![];
+        while !reader.is_end() {
+            nodes.push(reader.read_node()?);
+        }
+        let mut nodes = nodes.into_iter();
+
+        // nested columns are received as 1 array column per nested column
+        let flat_mapping = mapping.map(|mapping| match self.flatten_nested {
+            true => flatten_nested_mapping(mapping),
+            false => mapping
+                .iter()
+                .map(|(n, t)| (n.to_string(), t.clone()))
+                .collect(),
+        });
+
+        let data = if self.layout.is_document() {
+            let node = nodes.next().ok_or(Error::new("Missing JSON document"))?;
+            self.parse_document(node, flat_mapping)?.0
+        } else if self.layout == Layout::Compact {
+            self.parse_compact_rows(nodes, flat_mapping)?
+        } else {
+            let columns = match flat_mapping {
+                Some(columns) => columns,
+                None => return Err(Error::new("Deserializing data requires a mapping table")),
+            };
+
+            let rows = match self.layout {
+                Layout::ObjectEachRow => match nodes.next() {
+                    Some(JsonNode::Object(fields)) => fields.into_iter().map(|(_, n)| n).collect(),
+                    Some(_) => return Err(Error::new("Invalid JSON object of rows")),
+                    None => vec![],
+                },
+                Layout::Progress => {
+                    let mut rows = vec![];
+                    for node in nodes {
+                        let (key, node) = match node {
+                            JsonNode::Object(mut fields) if fields.len() == 1 => fields.remove(0),
+                            _ => return Err(Error::new("Invalid JSON row")),
+                        };
+                        match key.as_str() {
+                            "row" => rows.push(node),
+                            "exception" => return Err(Error(node.into_text(&Type::String)?)),
+                            // NB: progress, totals, etc.. are ignored
+                            _ => {}
+                        }
+                    }
+                    rows
+                }
+                _ => nodes.collect(),
+            };
+
+            let mut data = QueryData::with_names_and_types(
+                columns
+                    .iter()
+                    .map(|(n, t)| (n.as_str(), t.clone()))
+                    .collect(),
+            );
+            for node in rows {
+                data.add_row(self.parse_object_row(node, &columns)?);
+            }
+            data
+        };
+
+        match mapping {
+            Some(mapping) if self.flatten_nested => data.unflatten_nested(mapping),
+            _ => Ok(data),
+        }
+    }
+
+    /// Parses the rows of the compact layout (1 array per row)
+    fn parse_compact_rows(
+        &self,
+        mut nodes: impl Iterator<Item = JsonNode>,
+        flat_mapping: Option<Vec<(String, Type)>>,
+    ) -> Result<QueryData, Error> {
+        // parse names and types from the buffer
+        let mut data = if self.with_names {
+            let names = match nodes.next() {
+                Some(node) => node.into_strings()?,
+                None => return Err(Error::new("Table is missing the row with names")),
+            };
+
+            if self.with_types {
+                let types = match nodes.next() {
+                    Some(node) => node
+                        .into_strings()?
+                        .iter()
+                        .map(|t| Type::from_str(t))
+                        .collect::<Result<Vec<_>, Error>>()?,
+                    None => return Err(Error::new("Table is missing the row with types")),
+                };
+                QueryData::with_names_and_types(
+                    names.iter().map(|n| n.as_str()).zip(types).collect(),
+                )
+            } else {
+                QueryData::with_names(names.iter().map(|n| n.as_str()).collect())
+            }
+        } else {
+            QueryData::no_headers()
+        };
+
+        let types = if let Some(types) = data.get_types() {
+            types
+        } else if let Some(mapping) = &flat_mapping {
+            mapping.iter().map(|(_, t)| t.clone()).collect()
+        } else {
+            return Err(Error::new("Deserializing data requires a mapping table"));
+        };
+
+        for node in nodes {
+            data.add_row(self.parse_array_row(node, &types)?);
+        }
+        Ok(data)
+    }
+
+    /// Parses a row object, with the values of the columns
+    fn parse_object_row(
+        &self,
+        node: JsonNode,
+        columns: &[(String, Type)],
+    ) -> Result<Vec<Value>, Error> {
+        let mut fields = match node {
+            JsonNode::Object(fields) => fields,
+            _ => return Err(Error::new("Invalid JSON row")),
+        };
+        let mut row = vec![];
+        for (name, ty) in columns {
+            let i = fields
+                .iter()
+                .position(|(n, _)| n == name)
+                .ok_or(Error(format!("Missing column '{name}'")))?;
+            let (_, node) = fields.swap_remove(i);
+            row.push(self.parse_node(node, ty.clone())?);
+        }
+        Ok(row)
+    }
+
+    /// Parses a row array, with the values in the order of the column types
+    fn parse_array_row(&self, node: JsonNode, types: &[Type]) -> Result<Vec<Value>, Error> {
+        let values = match node {
+            JsonNode::Array(values) => values,
+            _ => return Err(Error::new("Invalid JSON row")),
+        };
+        if values.len() != types.len() {
+            return Err(Error(format!(
+                "JSON row has {} values, expected {}",
+                values.len(),
+                types.len()
+            )));
+        }
+        values
+            .into_iter()
+            .zip(types)
+            .map(|(value, ty)| self.parse_node(value, ty.clone()))
+            .collect()
+    }
+
+    /// Parses the metadata of a JSON document
+    ///
+    /// The rows are not parsed.
+    pub fn parse_metadata(&self, value: &str) -> Result<JsonMetadata, Error> {
+        let mut reader = JsonReader::new(value);
+        let node = reader.read_node()?;
+        self.parse_document_metadata(node)
+            .map(|(metadata, _)| metadata)
+    }
+
+    /// Parses a JSON document, with its metadata
+    ///
+    /// The column types are read from the metadata, and from the mapping otherwise.
+    fn parse_document(
+        &self,
+        node: JsonNode,
+        flat_mapping: Option<Vec<(String, Type)>>,
+    ) -> Result<(QueryData, JsonMetadata), Error> {
+        let (metadata, data) = self.parse_document_metadata(node)?;
+        let columns = match flat_mapping {
+            _ if !metadata.columns.is_empty() => metadata.columns.clone(),
+            Some(columns) => columns,
+            None => return Err(Error::new("Deserializing data requires a mapping table")),
+        };
+        let types = columns.iter().map(|(_, t)| t.clone()).collect::<Vec<_>>();
+
+        let mut table = QueryData::with_names_and_types(
+            columns
+                .iter()
+                .map(|(n, t)| (n.as_str(), t.clone()))
+                .collect(),
+        );
+        match (self.layout, data) {
+            (_, None) => {}
+            (Layout::ColumnsDocument, Some(JsonNode::Object(mut fields))) => {
+                let mut cols = vec![];
+                for (name, ty) in &columns {
+                    let i = fields
+                        .iter()
+                        .position(|(n, _)| n == name)
+                        .ok_or(Error(format!("Missing column '{name}'")))?;
+                    let (_, node) = fields.swap_remove(i);
+                    let ty = Type::Array(Box::new(ty.clone()));
+                    match self.parse_node(node, ty)? {
+                        Value::Array(values) => cols.push(values),
+                        _ => unreachable!(),
+                    }
+                }
+                let n_rows = cols.iter().map(|col| col.len()).max().unwrap_or(0);
+                if cols.iter().any(|col| col.len() != n_rows) {
+                    return Err(Error::new("Columns have different lengths"));
+                }
+                let mut rows = vec![Vec::with_capacity(cols.len()); n_rows];
+                for col in cols {
+                    for (row, value) in rows.iter_mut().zip(col) {
+                        row.push(value);
+                    }
+                }
+                table.add_rows(rows);
+            }
+            (Layout::CompactDocument, Some(JsonNode::Array(nodes))) => {
+                for node in nodes {
+                    table.add_row(self.parse_array_row(node, &types)?);
+                }
+            }
+            (Layout::Document, Some(JsonNode::Array(nodes))) => {
+                for node in nodes {
+                    table.add_row(self.parse_object_row(node, &columns)?);
+                }
+            }
+            _ => return Err(Error::new("Invalid JSON data")),
+        }
+        Ok((table, metadata))
+    }
+
+    /// Parses the metadata of a JSON document, and returns its data node
+    fn parse_document_metadata(
+        &self,
+        node: JsonNode,
+    ) -> Result<(JsonMetadata, Option<JsonNode>), Error> {
+        let fields = match node {
+            JsonNode::Object(fields) => fields,
+            _ => return Err(Error::new("Invalid JSON document")),
+        };
+
+        let mut metadata = JsonMetadata::default();
+        let mut data = None;
+        for (key, node) in fields {
+            match key.as_str() {
+                "meta" => {
+                    let columns = match node {
+                        JsonNode::Array(columns) => columns,
+                        _ => return Err(Error::new("Invalid JSON metadata")),
+                    };
+                    for column in columns {
+                        let mut name = None;
+                        let mut ty = None;
+                        if let JsonNode::Object(fields) = column {
+                            for (key, node) in fields {
+                                match key.as_str() {
+                                    "name" => name = Some(node.into_text(&Type::String)?),
+                                    "type" => ty = Some(node.into_text(&Type::String)?),
+                                    _ => {}
+                                }
+                            }
+                        }
+                        match (name, ty) {
+                            (Some(name), Some(ty)) => {
+                                metadata.columns.push((name, Type::from_str(&ty)?))
+                            }
+                            _ => return Err(Error::new("Invalid JSON metadata")),
+                        }
+                    }
+                }
+                "data" => data = Some(node),
+                "rows" => metadata.rows = Some(node.into_number()?),
+                "rows_before_limit_at_least" => {
+                    metadata.rows_before_limit_at_least = Some(node.into_number()?)
+                }
+                "statistics" => {
+                    let mut statistics = JsonStatistics::default();
+                    if let JsonNode::Object(fields) = node {
+                        for (key, node) in fields {
+                            match key.as_str() {
+                                "elapsed" => statistics.elapsed = node.into_number()?,
+                                "rows_read" => statistics.rows_read = node.into_number()?,
+                                "bytes_read" => statistics.bytes_read = node.into_number()?,
+                                _ => {}
+                            }
+                        }
+                    }
+                    metadata.statistics = Some(statistics);
+                }
+                "exception" => return Err(Error(node.into_text(&Type::String)?)),
+                // NB: totals and extremes are ignored
+                _ => {}
+            }
+        }
+        Ok((metadata, data))
+    }
+}
+
+/// Returns `true` if a type has a scalar text representation
+fn is_scalar(ty: &Type) -> bool {
+    match ty {
+        Type::Array(_)
+        | Type::Tuple(_)
+        | Type::NamedTuple(_)
+        | Type::Map(..)
+        | Type::Nested(_)
+        | Type::Variant(_)
+        | Type::Dynamic(_)
+        | Type::Json(..) => false,
+        Type::LowCardinality(ty) | Type::SimpleAggregateFunction(_, ty) => is_scalar(ty),
+        _ => true,
+    }
+}
+
+/// Finds an enum variant from its name or its value
+fn parse_enum<T>(value: &str, variants: &BTreeMap<String, T>) -> Option<(String, T)>
+where
+    T: FromStr + PartialEq + Copy,
+{
+    match variants.get_key_value(value) {
+        Some((name, i)) => Some((name.clone(), *i)),
+        None => {
+            let i = value.parse::<T>().ok()?;
+            variants
+                .iter()
+                .find(|(_, vi)| **vi == i)
+                .map(|(name, vi)| (name.clone(), *vi))
+        }
+    }
+}
+
+/// Flattens a JSON object to its (path, value) leaves
+///
+/// Empty nested objects have no leaves, and null values are skipped.
+fn flatten_object(fields: Vec<(String, JsonNode)>) -> Vec<(String, JsonNode)> {
+    let mut leaves = vec![];
+    for (key, node) in fields {
+        match node {
+            JsonNode::Null => {}
+            JsonNode::Object(fields) => {
+                for (path, node) in flatten_object(fields) {
+                    leaves.push((format!("{key}.{path}"), node));
+                }
+            }
+            node => leaves.push((key, node)),
+        }
+    }
+    leaves
+}
+
+/// JSON node
+///
+/// NB: numbers are kept as text, so that 64-bit (and larger) integers are not truncated.
+#[derive(Debug, Clone, PartialEq)]
+enum JsonNode {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<JsonNode>),
+    Object(Vec<(String, JsonNode)>),
+}
+
+impl JsonNode {
+    /// Returns the text of a scalar node (quoted or not)
+    fn into_text(self, ty: &Type) -> Result<String, Error> {
+        match self {
+            JsonNode::Bool(v) => Ok(v.to_string()),
+            JsonNode::Number(v) | JsonNode::String(v) => Ok(v),
+            _ => Err(Error(format!("Invalid JSON value for {ty}"))),
+        }
+    }
+
+    /// Returns the number of a scalar node (quoted or not)
+    fn into_number<T: FromStr>(self) -> Result<T, Error> {
+        self.into_text(&Type::Float64)?
+            .parse::<T>()
+            .map_err(|_| Error::new("Invalid JSON number"))
+    }
+
+    /// Returns the strings of an array node (eg column names)
+    fn into_strings(self) -> Result<Vec<String>, Error> {
+        match self {
+            JsonNode::Array(nodes) => nodes
+                .into_iter()
+                .map(|node| match node {
+                    JsonNode::String(s) => Ok(s),
+                    _ => Err(Error::new("Invalid JSON header")),
+                })
+                .collect(),
+            _ => Err(Error::new("Invalid JSON header")),
+        }
+    }
+
+    /// Converts a node to a `Dynamic` value
+    ///
+    /// Integers are `Int64` (or `UInt64` if they are too large), other numbers are `Float64`,
+    /// arrays are `Array(Dynamic)`, and objects are `JSON`.
+    fn into_dynamic(self) -> Value {
+        match self {
+            JsonNode::Null => Value::Dynamic(None),
+            JsonNode::Bool(v) => Value::dynamic(v),
+            JsonNode::Number(v) => {
+                if let Ok(v) = v.parse::<i64>() {
+                    Value::dynamic(v)
+                } else if let Ok(v) = v.parse::<u64>() {
+                    Value::dynamic(v)
+                } else {
+                    Value::dynamic(v.parse::<f64>().unwrap_or(f64::NAN))
+                }
+            }
+            JsonNode::String(v) => Value::dynamic(v),
+            JsonNode::Array(nodes) => {
+                let ty = Type::Array(Box::new(Type::Dynamic(None)));
+                let values = nodes.into_iter().map(JsonNode::into_dynamic).collect();
+                Value::Dynamic(Some((ty, Box::new(Value::Array(values)))))
+            }
+            JsonNode::Object(fields) => {
+                let ty = Type::Json(vec![], vec![]);
+                Value::Dynamic(Some((ty, Box::new(JsonNode::object_into_json(fields)))))
+            }
+        }
+    }
+
+    /// Converts the fields of an object node to a `JSON` value, with `Dynamic` values
+    fn object_into_json(fields: Vec<(String, JsonNode)>) -> Value {
+        let values = flatten_object(fields)
+            .into_iter()
+            .map(|(path, node)| (path, node.into_dynamic()))
+            .collect();
+        Value::Json(values)
+    }
+}
+
+// NB: numbers are kept as text, as when they are parsed
+#[cfg(feature = "json")]
+impl From<serde_json::Value> for JsonNode {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => JsonNode::Null,
+            serde_json::Value::Bool(v) => JsonNode::Bool(v),
+            serde_json::Value::Number(v) => JsonNode::Number(v.to_string()),
+            serde_json::Value::String(v) => JsonNode::String(v),
+            serde_json::Value::Array(values) => {
+                JsonNode::Array(values.into_iter().map(JsonNode::from).collect())
+            }
+            serde_json::Value::Object(obj) => {
+                JsonNode::Object(obj.into_iter().map(|(k, v)| (k, v.into())).collect())
+            }
+        }
+    }
+}
+
+/// Converts a `serde_json` object to a `JSON` value
+///
+/// The values are converted as when a JSON object is parsed.
+#[cfg(feature = "json")]
+pub(crate) fn json_object_into_value(obj: serde_json::Map<String, serde_json::Value>) -> Value {
+    JsonNode::object_into_json(obj.into_iter().map(|(k, v)| (k, v.into())).collect())
+}
+
+/// JSON reader
+///
+/// It reads a sequence of JSON values, separated by whitespaces.
+struct JsonReader<'a> {
+    /// Text
+    s: &'a str,
+    /// Position
+    pos: usize,
+}
+
+impl<'a> JsonReader<'a> {
+    /// Creates a new reader
+    fn new(s: &'a str) -> Self {
+        Self { s, pos: 0 }
+    }
+
+    /// Returns an error at the current position
+    fn error(&self) -> Error {
+        Error(format!("Invalid JSON at position {}", self.pos))
+    }
+
+    /// Returns the next byte (without consuming it)
+    fn peek(&self) -> Option<u8> {
+        self.s.as_bytes().get(self.pos).copied()
+    }
+
+    /// Skips the whitespaces
+    fn skip_whitespaces(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    /// Returns `true` if there are no other values
+    fn is_end(&mut self) -> bool {
+        self.skip_whitespaces();
+        self.pos >= self.s.len()
+    }
+
+    /// Checks that there are no other values
+    fn expect_end(&mut self) -> Result<(), Error> {
+        match self.is_end() {
+            true => Ok(()),
+            false => Err(self.error()),
+        }
+    }
+
+    /// Consumes an expected byte
+    fn expect(&mut self, b: u8) -> Result<(), Error> {
+        self.skip_whitespaces();
+        match self.peek() {
+            Some(c) if c == b => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    /// Consumes a keyword (eg `null`)
+    fn read_keyword(&mut self, keyword: &str, node: JsonNode) -> Result<JsonNode, Error> {
+        match self.s[self.pos..].starts_with(keyword) {
+            true => {
+                self.pos += keyword.len();
+                Ok(node)
+            }
+            false => Err(self.error()),
+        }
+    }
+
+    /// Reads a JSON value
+    fn read_node(&mut self) -> Result<JsonNode, Error> {
+        self.skip_whitespaces();
+        match self.peek() {
+            Some(b'n') => self.read_keyword("null", JsonNode::Null),
+            Some(b't') => self.read_keyword("true", JsonNode::Bool(true)),
+            Some(b'f') => self.read_keyword("false", JsonNode::Bool(false)),
+            Some(b'"') => Ok(JsonNode::String(self.read_string()?)),
+            Some(b'[') => {
+                self.pos += 1;
+                let mut nodes = vec![];
+                self.skip_whitespaces();
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                    return Ok(JsonNode::Array(nodes));
+                }
+                loop {
+                    nodes.push(self.read_node()?);
+                    self.skip_whitespaces();
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => {
+                            self.pos += 1;
+                            return Ok(JsonNode::Array(nodes));
+                        }
+                        _ => return Err(self.error()),
+                    }
+                }
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                let mut fields = vec![];
+                self.skip_whitespaces();
+                if self.peek() == Some(b'}') {
+                    self.pos += 1;
+                    return Ok(JsonNode::Object(fields));
+                }
+                loop {
+                    self.skip_whitespaces();
+                    let key = self.read_string()?;
+                    self.expect(b':')?;
+                    fields.push((key, self.read_node()?));
+                    self.skip_whitespaces();
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b'}') => {
+                            self.pos += 1;
+                            return Ok(JsonNode::Object(fields));
+                        }
+                        _ => return Err(self.error()),
+                    }
+                }
+            }
+            Some(b'-' | b'0'..=b'9') => {
+                let start = self.pos;
+                while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.peek() {
+                    self.pos += 1;
+                }
+                Ok(JsonNode::Number(self.s[start..self.pos].to_string()))
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    /// Reads a JSON string
+    fn read_string(&mut self) -> Result<String, Error> {
+        if self.peek() != Some(b'"') {
+            return Err(self.error());
+        }
+        self.pos += 1;
+        let mut buf = String::new();
+        loop {
+            let rest = &self.s[self.pos..];
+            let end = rest.find(['"', '\\']).ok_or(self.error())?;
+            buf.push_str(&rest[..end]);
+            self.pos += end + 1;
+            if rest.as_bytes()[end] == b'"' {
+                return Ok(buf);
+            }
+            let c = match self.peek().ok_or(self.error())? {
+                b'"' => '"',
+                b'\\' => '\\',
+                b'/' => '/',
+                b'b' => '\u{08}',
+                b'f' => '\u{0C}',
+                b'n' => '\n',
+                b'r' => '\r',
+                b't' => '\t',
+                b'u' => {
+                    let hi = self.read_hex4()?;
+                    let code = if (0xD800..0xDC00).contains(&hi) {
+                        // NB: a surrogate pair is written as 2 escapes
+                        self.pos += 1;
+                        if !self.s[self.pos..].starts_with("\\u") {
+                            return Err(self.error());
+                        }
+                        self.pos += 1;
+                        let lo = self.read_hex4()?;
+                        0x10000 + ((hi - 0xD800) << 10) + (lo.wrapping_sub(0xDC00) & 0x3FF)
+                    } else {
+                        hi
+                    };
+                    char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                }
+                _ => return Err(self.error()),
+            };
+            buf.push(c);
+            self.pos += 1;
+        }
+    }
+
+    /// Reads the 4 hex digits of a `\uXXXX` escape (the position is on the `u`)
+    fn read_hex4(&mut self) -> Result<u32, Error> {
+        let hex = self.s.get(self.pos + 1..self.pos + 5).ok_or(self.error())?;
+        let code = u32::from_str_radix(hex, 16).map_err(|_| self.error())?;
+        self.pos += 4;
+        Ok(code)
+    }
+}
@@ -0,0 +1,453 @@
+//! Tests
+
+use std::collections::HashMap;
+
+use ethnum::U256;
+use time::Date;
+
+use crate::{
+    query::{
+        Format, Formatter, JsonFormatter, JsonMetadata, JsonStatistics, QueryData, QueryResponse,
+    },
+    value::{time::DateExt, ChValue, Type, Value},
+};
+
+/// Sets a test
+macro_rules! set_test {
+    ($ID:ident, $TY:ty, $VAL:expr, $STR:literal) => {
+        #[test]
+        fn $ID() {
+            let x: $TY = $VAL;
+            let ty = <$TY as ChValue>::ch_type();
+            let value = x.into_ch_value();
+            let formatter = JsonFormatter::default();
+            let value_str = formatter.format_value(value.clone()).unwrap();
+            assert_eq!(value_str, $STR);
+
+            let value_parsed = formatter.parse_value(&value_str, ty).unwrap();
+            assert_eq!(value_parsed, value);
+        }
+    };
+}
+
+set_test!(fmt_json_uint8, u8, 1, "1");
+set_test!(fmt_json_uint64, u64, u64::MAX, r#""18446744073709551615""#);
+set_test!(fmt_json_int64, i64, -1, r#""-1""#);
+set_test!(
+    fmt_json_uint256,
+    U256,
+    U256::from_words(1, 1),
+    r#""340282366920938463463374607431768211457""#
+);
+set_test!(fmt_json_f64, f64, 1.5, "1.5");
+set_test!(fmt_json_bool, bool, true, "true");
+set_test!(
+    fmt_json_string,
+    String,
+    "a\"b\\c\n\u{1}é".to_string(),
+    r#""a\"b\\c\n\u0001é""#
+);
+set_test!(
+    fmt_json_date,
+    Date,
+    Date::from_unix_days(1).unwrap(),
+    r#""1970-01-02""#
+);
+set_test!(
+    fmt_json_array,
+    Vec<Option<u32>>,
+    vec![Some(1), None],
+    "[1,null]"
+);
+set_test!(
+    fmt_json_map,
+    HashMap<u8, String>,
+    HashMap::from([(1, "a".to_string())]),
+    r#"{"1":"a"}"#
+);
+set_test!(fmt_json_null_none, Option<i64>, None, "null");
+
+#[test]
+fn fmt_json_settings() {
+    let formatter = JsonFormatter::new()
+        .quote_64bit_integers(false)
+        .quote_64bit_floats(true)
+        .quote_denormals(true);
+    assert_eq!(formatter.format_value(Value::UInt64(1)).unwrap(), "1");
+    assert_eq!(
+        formatter.format_value(Value::Float64(1.5)).unwrap(),
+        r#""1.5""#
+    );
+    assert_eq!(
+        formatter.format_value(Value::Float32(f32::NAN)).unwrap(),
+        r#""nan""#
+    );
+    assert_eq!(
+        formatter
+            .format_value(Value::Float64(f64::NEG_INFINITY))
+            .unwrap(),
+        r#""-inf""#
+    );
+    let value = formatter.parse_value(r#""inf""#, Type::Float64).unwrap();
+    assert_eq!(value, Value::Float64(f64::INFINITY));
+
+    // NaN is null by default
+    let formatter = JsonFormatter::new();
+    assert_eq!(
+        formatter.format_value(Value::Float64(f64::NAN)).unwrap(),
+        "null"
+    );
+    let value = formatter.parse_value("null", Type::Float64).unwrap();
+    assert!(matches!(value, Value::Float64(v) if v.is_nan()));
+
+    // 64-bit integers can be read quoted or not
+    let value = formatter.parse_value("1", Type::UInt64).unwrap();
+    assert_eq!(value, Value::UInt64(1));
+    let value = formatter.parse_value(r#""1""#, Type::UInt64).unwrap();
+    assert_eq!(value, Value::UInt64(1));
+
+    // the settings are sent with the query
+    let settings = JsonFormatter::new()
+        .quote_64bit_integers(false)
+        .named_tuples_as_objects(false)
+        .settings();
+    assert!(settings.contains(&(
+        "output_format_json_quote_64bit_integers".to_string(),
+        "0".to_string()
+    )));
+    assert!(settings.contains(&(
+        "output_format_json_quote_denormals".to_string(),
+        "0".to_string()
+    )));
+    assert!(settings.contains(&(
+        "output_format_json_named_tuples_as_objects".to_string(),
+        "0".to_string()
+    )));
+}
+
+#[test]
+fn fmt_json_named_tuple() {
+    let ty = Type::NamedTuple(vec![
+        ("a".to_string(), Type::UInt8),
+        ("b".to_string(), Type::String),
+    ]);
+    let value = Value::NamedTuple(vec![
+        ("a".to_string(), Value::UInt8(1)),
+        ("b".to_string(), "x".to_string().into()),
+    ]);
+
+    let formatter = JsonFormatter::new();
+    assert_eq!(
+        formatter.format_value(value.clone()).unwrap(),
+        r#"{"a":1,"b":"x"}"#
+    );
+    let value_parsed = formatter.parse_value(r#"{"b":"x","a":1}"#, ty.clone());
+    assert_eq!(value_parsed.unwrap(), value);
+
+    let formatter = JsonFormatter::new().named_tuples_as_objects(false);
+    assert_eq!(formatter.format_value(value.clone()).unwrap(), r#"[1,"x"]"#);
+    let value_parsed = formatter.parse_value(r#"[1,"x"]"#, ty);
+    assert_eq!(value_parsed.unwrap(), value);
+}
+
+#[test]
+fn fmt_json_unicode_escapes() {
+    let formatter = JsonFormatter::new();
+    let value = formatter.parse_value(r#""é😀\/""#, Type::String).unwrap();
+    assert_eq!(value, Value::String("é😀/".to_string()));
+    assert!(formatter.parse_value(r#""abc"#, Type::String).is_err());
+    assert!(formatter.parse_value("1 2", Type::UInt8).is_err());
+}
+
+/// Creates a sample table
+fn sample_table() -> (Vec<(&'static str, Type)>, QueryData) {
+    let mapping = vec![
+        ("id", Type::UInt64),
+        ("name", Type::NullableString),
+        ("tags", Type::Array(Box::new(Type::String))),
+    ];
+    let table = QueryData::with_names_and_types(mapping.clone())
+        .row(vec![
+            Value::UInt64(1),
+            Value::NullableString(Some("a".to_string())),
+            vec!["x".to_string()].into(),
+        ])
+        .row(vec![
+            Value::UInt64(2),
+            Value::NullableString(None),
+            Value::Array(vec![]),
+        ]);
+    (mapping, table)
+}
+
+#[test]
+fn fmt_json_each_row_table() {
+    let (mapping, table) = sample_table();
+    let cases = [
+        (
+            Format::JSONEachRow,
+            "{\"id\":\"1\",\"name\":\"a\",\"tags\":[\"x\"]}\n\
+             {\"id\":\"2\",\"name\":null,\"tags\":[]}\n",
+        ),
+        (
+            Format::PrettyJSONEachRow,
+            "{\n    \"id\": \"1\",\n    \"name\": \"a\",\n    \"tags\": [\"x\"]\n}\n\
+             {\n    \"id\": \"2\",\n    \"name\": null,\n    \"tags\": []\n}\n",
+        ),
+        (
+            Format::JSONEachRowWithProgress,
+            "{\"row\":{\"id\":\"1\",\"name\":\"a\",\"tags\":[\"x\"]}}\n\
+             {\"row\":{\"id\":\"2\",\"name\":null,\"tags\":[]}}\n",
+        ),
+        (
+            Format::JSONStringsEachRow,
+            "{\"id\":\"1\",\"name\":\"a\",\"tags\":\"['x']\"}\n\
+             {\"id\":\"2\",\"name\":null,\"tags\":\"[]\"}\n",
+        ),
+        (
+            Format::JSONCompactEachRowWithNamesAndTypes,
+            "[\"id\",\"name\",\"tags\"]\n\
+             [\"UInt64\",\"Nullable(String)\",\"Array(String)\"]\n\
+             [\"1\",\"a\",[\"x\"]]\n\
+             [\"2\",null,[]]\n",
+        ),
+        (
+            Format::JSONCompactStringsEachRow,
+            "[\"1\",\"a\",\"['x']\"]\n[\"2\",null,\"[]\"]\n",
+        ),
+        (
+            Format::JSONObjectEachRow,
+            "{\n\"row_1\":{\"id\":\"1\",\"name\":\"a\",\"tags\":[\"x\"]},\n\
+             \"row_2\":{\"id\":\"2\",\"name\":null,\"tags\":[]}\n}\n",
+        ),
+    ];
+
+    for (format, expected) in cases {
+        let bytes = table.clone().to_bytes(format).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes.clone()).unwrap(),
+            expected,
+            "{format}"
+        );
+
+        let table_parsed = QueryData::from_bytes(&bytes, format, Some(&mapping)).unwrap();
+        assert_eq!(table_parsed.get_rows(), table.get_rows(), "{format}");
+    }
+
+    // NB: a row must have a value per column
+    let formatter = JsonFormatter::compact_with_names();
+    let mapping = vec![("a", Type::UInt8), ("b", Type::UInt8)];
+    let err = formatter
+        .parse_data("[\"a\",\"b\"]\n[1]\n", Some(&mapping))
+        .unwrap_err();
+    assert_eq!(err.0, "JSON row has 1 values, expected 2");
+}
+
+#[test]
+fn fmt_json_each_row_response() {
+    let mapping = vec![("a", Type::UInt8), ("b", Type::String)];
+    let response = concat!(
+        "{\"progress\":{\"read_rows\":\"2\"}}\n",
+        "{\"row\":{\"b\":\"x\",\"a\":1}}\n",
+        "{\"row\":{\"a\":2,\"b\":\"y\",\"c\":0}}\n"
+    );
+    let table = QueryData::from_bytes(
+        response.as_bytes(),
+        Format::JSONEachRowWithProgress,
+        Some(&mapping),
+    )
+    .unwrap();
+    assert_eq!(
+        table.get_rows(),
+        &vec![
+            vec![Value::UInt8(1), "x".to_string().into()],
+            vec![Value::UInt8(2), "y".to_string().into()],
+        ]
+    );
+
+    let response = "{\"exception\":\"Code: 395. DB::Exception: boom\"}\n";
+    let err = QueryData::from_bytes(
+        response.as_bytes(),
+        Format::JSONEachRowWithProgress,
+        Some(&mapping),
+    )
+    .unwrap_err();
+    assert_eq!(err.0, "Code: 395. DB::Exception: boom");
+
+    let err = QueryData::from_bytes(b"{\"a\":1}", Format::JSONEachRow, Some(&mapping)).unwrap_err();
+    assert_eq!(err.0, "Missing column 'b'");
+}
+
+#[test]
+fn fmt_json_nested() {
+    let ty = Type::Nested(vec![
+        ("a".to_string(), Type::UInt8),
+        ("b".to_string(), Type::String),
+    ]);
+    let value = Value::Nested(vec![
+        ("a".to_string(), vec![Value::UInt8(1), Value::UInt8(2)]),
+        (
+            "b".to_string(),
+            vec!["x".to_string().into(), "y".to_string().into()],
+        ),
+    ]);
+    let mapping = vec![("id", Type::UInt8), ("n", ty.clone())];
+    let table =
+        QueryData::with_names_and_types(mapping.clone()).row(vec![Value::UInt8(0), value.clone()]);
+
+    // flattened columns
+    let format = Format::JSONEachRow;
+    let bytes = table.clone().to_bytes(format).unwrap();
+    assert_eq!(
+        String::from_utf8(bytes.clone()).unwrap(),
+        "{\"id\":0,\"n.a\":[1,2],\"n.b\":[\"x\",\"y\"]}\n"
+    );
+    let table_parsed = QueryData::from_bytes(&bytes, format, Some(&mapping)).unwrap();
+    assert_eq!(table_parsed, table);
+
+    // array of objects
+    let formatter = JsonFormatter::new().flatten_nested(false);
+    let value_str = formatter.format_value(value.clone()).unwrap();
+    assert_eq!(value_str, r#"[{"a":1,"b":"x"},{"a":2,"b":"y"}]"#);
+    let value_parsed = formatter.parse_value(&value_str, ty).unwrap();
+    assert_eq!(value_parsed, value);
+}
+
+#[test]
+fn fmt_json_dynamic() {
+    let ty = Type::Json(vec![("a.b".to_string(), Type::UInt64)], vec![]);
+    let formatter = JsonFormatter::new();
+    let value = formatter
+        .parse_value(r#"{"a":{"b":"1","c":[1,"x"]},"d":null}"#, ty)
+        .unwrap();
+    assert_eq!(
+        value,
+        Value::Json(vec![
+            ("a.b".to_string(), Value::UInt64(1)),
+            (
+                "a.c".to_string(),
+                Value::Dynamic(Some((
+                    Type::Array(Box::new(Type::Dynamic(None))),
+                    Box::new(Value::Array(vec![
+                        Value::dynamic(1_i64),
+                        Value::dynamic("x")
+                    ]))
+                )))
+            ),
+        ])
+    );
+}
+
+#[test]
+fn fmt_json_document_table() {
+    let (mapping, table) = sample_table();
+    let meta = concat!(
+        "\"meta\":[{\"name\":\"id\",\"type\":\"UInt64\"},",
+        "{\"name\":\"name\",\"type\":\"Nullable(String)\"},",
+        "{\"name\":\"tags\",\"type\":\"Array(String)\"}]"
+    );
+    let cases = [
+        (
+            Format::JSON,
+            "[\n{\"id\":\"1\",\"name\":\"a\",\"tags\":[\"x\"]},\n\
+             {\"id\":\"2\",\"name\":null,\"tags\":[]}\n]",
+        ),
+        (
+            Format::JSONCompact,
+            "[\n[\"1\",\"a\",[\"x\"]],\n[\"2\",null,[]]\n]",
+        ),
+        (
+            Format::JSONColumnsWithMetadata,
+            "{\n\"id\":[\"1\",\"2\"],\n\"name\":[\"a\",null],\n\"tags\":[[\"x\"],[]]\n}",
+        ),
+    ];
+
+    for (format, data) in cases {
+        let bytes = table.clone().to_bytes(format).unwrap();
+        let expected = format!("{{\n{meta},\n\"data\":{data},\n\"rows\":2\n}}\n");
+        assert_eq!(
+            String::from_utf8(bytes.clone()).unwrap(),
+            expected,
+            "{format}"
+        );
+
+        // NB: the mapping is not required, the types are read from the metadata
+        let table_parsed = QueryData::from_bytes(&bytes, format, None).unwrap();
+        assert_eq!(table_parsed, table, "{format}");
+        let table_parsed = QueryData::from_bytes(&bytes, format, Some(&mapping)).unwrap();
+        assert_eq!(table_parsed, table, "{format}");
+    }
+}
+
+#[test]
+fn fmt_json_document_response() {
+    let data = r#"{
+	"meta":
+	[
+		{
+			"name": "n",
+			"type": "UInt64"
+		},
+		{
+			"name": "s",
+			"type": "String"
+		}
+	],
+
+	"data":
+	[
+		{
+			"n": "0",
+			"s": "a"
+		},
+		{
+			"n": "1",
+			"s": "b"
+		}
+	],
+
+	"rows": 2,
+
+	"rows_before_limit_at_least": 10,
+
+	"statistics":
+	{
+		"elapsed": 0.000512,
+		"rows_read": 10,
+		"bytes_read": 80
+	}
+}
+"#;
+    let res = QueryResponse::new(Format::JSON, data.as_bytes().to_vec())
+        .with_metadata()
+        .unwrap();
+    assert_eq!(
+        res.metadata.clone().unwrap(),
+        JsonMetadata {
+            columns: vec![
+                ("n".to_string(), Type::UInt64),
+                ("s".to_string(), Type::String)
+            ],
+            rows: Some(2),
+            rows_before_limit_at_least: Some(10),
+            statistics: Some(JsonStatistics {
+                elapsed: 0.000512,
+                rows_read: 10,
+                bytes_read: 80,
+            }),
+        }
+    );
+
+    let table = res.into_table(None).unwrap();
+    assert_eq!(
+        table,
+        QueryData::with_names_and_types(vec![("n", Type::UInt64), ("s", Type::String)])
+            .row(vec![Value::UInt64(0), "a".to_string().into()])
+            .row(vec![Value::UInt64(1), "b".to_string().into()])
+    );
+
+    let res = QueryResponse::new(Format::JSONEachRow, vec![])
+        .with_metadata()
+        .unwrap();
+    assert_eq!(res.metadata, None);
+}
@@ -1,13 +1,33 @@
 //! Formats
 
+#[cfg(feature = "arrow")]
+mod arrow;
+mod csv;
+mod custom;
+mod json;
+mod native;
+mod pretty;
+mod registry;
 mod rowbin;
 mod tab;
+mod tskv;
+mod values;
 
+#[cfg(feature = "arrow")]
+pub use self::arrow::*;
+pub use csv::*;
+pub use custom::*;
+pub use json::*;
+pub use native::*;
+pub use pretty::*;
+pub use registry::*;
 pub use rowbin::*;
 pub use tab::*;
+pub use tskv::*;
+pub use values::*;
 
 use crate::{
-    error::Error,
+    error::{Error, UnsupportedFormat},
     value::{Type, Value},
 };
 
@@ -16,9 +36,9 @@ use super::QueryData;
 /// Clickhouse formatter
 ///
 /// A formatter serializes and deserializes
-pub trait Formatter {
+pub trait Formatter: std::fmt::Debug + Send + Sync {
     /// Serializes a [Value]
-    fn serialize_value(&self, value: Value) -> Vec<u8>;
+    fn serialize_value(&self, value: Value) -> Result<Vec<u8>, Error>;
 
     /// Serializes a [QueryData]
     fn serialize_query_data(&self, data: QueryData) -> Result<Vec<u8>, Error>;
@@ -32,18 +52,25 @@ pub trait Formatter {
         bytes: &[u8],
         mapping: Option<&[(&str, Type)]>,
     ) -> Result<QueryData, Error>;
+
+    /// Returns the server settings matching the formatter options
+    ///
+    /// NB: the settings are sent with the queries which use the formatter.
+    fn settings(&self) -> Vec<(String, String)> {
+        vec![]
+    }
 }
 
 impl Value {
     /// Serializes a [Value] to bytes
-    pub fn to_bytes(self, format: Format) -> Vec<u8> {
-        let formatter = format.formatter();
+    pub fn to_bytes(self, format: Format) -> Result<Vec<u8>, Error> {
+        let formatter = format.try_formatter()?;
         formatter.serialize_value(self)
     }
 
     /// Deserializes a buffer to a [Value]
     pub fn from_bytes(bytes: &[u8], format: Format, ty: Type) -> Result<Value, Error> {
-        let formatter = format.formatter();
+        let formatter = format.try_formatter()?;
         let value = formatter.deserialize_value(bytes, ty)?;
         Ok(value)
     }
@@ -52,7 +79,7 @@ impl Value {
 impl QueryData {
     /// Converts to bytes
     pub fn to_bytes(self, format: Format) -> Result<Vec<u8>, Error> {
-        let formatter = format.formatter();
+        let formatter = format.try_formatter()?;
         formatter.serialize_query_data(self)
     }
 
@@ -62,7 +89,7 @@ impl QueryData {
         format: Format,
         mapping: Option<&[(&str, Type)]>,
     ) -> Result<Self, Error> {
-        let formatter = format.formatter();
+        let formatter = format.try_formatter()?;
         let table = formatter.deserialize_query_data(bytes, mapping)?;
         Ok(table)
     }
@@ -149,9 +176,12 @@ pub enum Format {
 }
 
 impl Format {
-    /// Returns the formatter
-    pub fn formatter(&self) -> Box<dyn Formatter> {
-        match self {
+    /// Returns the built-in formatter, or an error if the format is not supported
+    ///
+    /// NB: formatters for the other formats can be registered on the client
+    /// (see [FormatterRegistry]).
+    pub fn try_formatter(&self) -> Result<Box<dyn Formatter>, UnsupportedFormat> {
+        let formatter: Box<dyn Formatter> = match self {
             Self::TabSep => Box::new(TsvFormatter::new()),
             Self::TabSepWithNames => Box::new(TsvFormatter::with_names()),
             Self::TabSepWithNamesAndTypes => Box::new(TsvFormatter::with_names_and_types()),
@@ -161,8 +191,57 @@ impl Format {
             Self::RowBinary => Box::new(RowBinFormatter::new()),
             Self::RowBinaryWithNames => Box::new(RowBinFormatter::with_names()),
             Self::RowBinaryWithNamesAndTypes => Box::new(RowBinFormatter::with_names_and_types()),
-            _ => unimplemented!(),
-        }
+            Self::RowBinaryWithDefaults => Box::new(RowBinFormatter::with_defaults()),
+            Self::TSKV => Box::new(TskvFormatter::new()),
+            Self::Native => Box::new(NativeFormatter::new()),
+            #[cfg(feature = "arrow")]
+            Self::Arrow => Box::new(ArrowFormatter::new()),
+            #[cfg(feature = "arrow")]
+            Self::ArrowStream => Box::new(ArrowFormatter::stream()),
+            #[cfg(feature = "parquet")]
+            Self::Parquet => Box::new(ParquetFormatter::new()),
+            Self::CSV => Box::new(CsvFormatter::new()),
+            Self::CSVWithNames => Box::new(CsvFormatter::with_names()),
+            Self::CSVWithNamesAndTypes => Box::new(CsvFormatter::with_names_and_types()),
+            Self::CustomSeparated => Box::new(CustomFormatter::new()),
+            Self::CustomSeparatedWithNames => Box::new(CustomFormatter::with_names()),
+            Self::CustomSeparatedWithNamesAndTypes => {
+                Box::new(CustomFormatter::with_names_and_types())
+            }
+            Self::PrettyCompact => Box::new(PrettyFormatter::new()),
+            Self::Vertical => Box::new(PrettyFormatter::vertical()),
+            Self::Markdown => Box::new(PrettyFormatter::markdown()),
+            Self::Values => Box::new(ValuesFormatter::new()),
+            Self::SQLInsert => Box::new(ValuesFormatter::sql_insert()),
+            Self::JSONEachRow => Box::new(JsonFormatter::new()),
+            Self::PrettyJSONEachRow => Box::new(JsonFormatter::pretty()),
+            Self::JSONEachRowWithProgress => Box::new(JsonFormatter::with_progress()),
+            Self::JSONStringsEachRow => Box::new(JsonFormatter::new().strings(true)),
+            Self::JSONStringsEachRowWithProgress => {
+                Box::new(JsonFormatter::with_progress().strings(true))
+            }
+            Self::JSONCompactEachRow => Box::new(JsonFormatter::compact()),
+            Self::JSONCompactEachRowWithNames => Box::new(JsonFormatter::compact_with_names()),
+            Self::JSONCompactEachRowWithNamesAndTypes => {
+                Box::new(JsonFormatter::compact_with_names_and_types())
+            }
+            Self::JSONCompactStringsEachRow => Box::new(JsonFormatter::compact().strings(true)),
+            Self::JSONCompactStringsEachRowWithNames => {
+                Box::new(JsonFormatter::compact_with_names().strings(true))
+            }
+            Self::JSONCompactStringsEachRowWithNamesAndTypes => {
+                Box::new(JsonFormatter::compact_with_names_and_types().strings(true))
+            }
+            Self::JSONObjectEachRow => Box::new(JsonFormatter::object_each_row()),
+            Self::JSON => Box::new(JsonFormatter::document()),
+            Self::JSONStrings => Box::new(JsonFormatter::document().strings(true)),
+            Self::JSONCompact => Box::new(JsonFormatter::compact_document()),
+            Self::JSONCompactStrings => Box::new(JsonFormatter::compact_document().strings(true)),
+            Self::JSONColumnsWithMetadata => Box::new(JsonFormatter::columns_document()),
+            // NB: a template needs its columns (see [CustomFormatter::template])
+            _ => return Err(UnsupportedFormat(self.to_string())),
+        };
+        Ok(formatter)
     }
 }
 
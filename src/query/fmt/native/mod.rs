@@ -0,0 +1,773 @@
+//! Native format
+//!
+//! The data is sent as blocks of columns. Each column has its name, its type and its values,
+//! serialized one after the other.
+
+#[cfg(test)]
+mod tests;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Read,
+    str::FromStr,
+};
+
+use crate::{
+    error::Error,
+    query::{QueryColumn, QueryColumns, QueryData},
+    value::{Type, Value},
+};
+
+use super::{Formatter, RowBinFormatter};
+
+/// Version of the `LowCardinality` keys (`SharedDictionariesWithAdditionalKeys`)
+const LOW_CARDINALITY_KEYS_VERSION: u64 = 1;
+
+/// Flag of a `LowCardinality` column which needs a global dictionary
+const NEED_GLOBAL_DICTIONARY_BIT: u64 = 1 << 8;
+
+/// Flag of a `LowCardinality` column which has additional keys (the block dictionary)
+const HAS_ADDITIONAL_KEYS_BIT: u64 = 1 << 9;
+
+/// Protocol version with the custom serialization flag of the columns
+const PROTOCOL_VERSION_WITH_CUSTOM_SERIALIZATION: u64 = 54454;
+
+/// Native formatter
+#[derive(Debug, Clone)]
+pub struct NativeFormatter {
+    /// Protocol version (0 for the HTTP default)
+    protocol_version: u64,
+    /// Nested columns are flattened
+    flatten_nested: bool,
+}
+
+impl Default for NativeFormatter {
+    fn default() -> Self {
+        Self {
+            protocol_version: 0,
+            flatten_nested: true,
+        }
+    }
+}
+
+impl NativeFormatter {
+    /// Creates a new [NativeFormatter]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the protocol version (`0` by default)
+    ///
+    /// With a protocol version, the blocks start with the block info, and the server
+    /// is sent the setting `client_protocol_version`.
+    pub fn protocol_version(mut self, version: u64) -> Self {
+        self.protocol_version = version;
+        self
+    }
+
+    /// Sets if `Nested` columns are flattened (`true` by default)
+    ///
+    /// This must match the Clickhouse setting `flatten_nested`.
+    pub fn flatten_nested(mut self, flatten: bool) -> Self {
+        self.flatten_nested = flatten;
+        self
+    }
+}
+
+impl Formatter for NativeFormatter {
+    fn serialize_value(&self, value: Value) -> Result<Vec<u8>, Error> {
+        // NB: a value is serialized as a column of 1 value
+        let ty = infer_type(&value);
+        let mut buf = vec![];
+        format_prefix(&mut buf, &ty);
+        format_column(&mut buf, &ty, vec![value])?;
+        Ok(buf)
+    }
+
+    fn serialize_query_data(&self, data: QueryData) -> Result<Vec<u8>, Error> {
+        self.format_data(data)
+    }
+
+    fn deserialize_value(&self, bytes: &[u8], ty: Type) -> Result<Value, Error> {
+        let mut bytes = bytes;
+        parse_prefix(&mut bytes, &ty)?;
+        let value = parse_column(&mut bytes, &ty, 1)?
+            .pop()
+            .ok_or(Error::new("Missing Native value"))?;
+        if !bytes.is_empty() {
+            return Err(Error::new("Value bytes has remaining bytes"));
+        }
+        Ok(value)
+    }
+
+    fn deserialize_query_data(
+        &self,
+        bytes: &[u8],
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        self.parse_data(bytes, mapping)
+    }
+
+    fn settings(&self) -> Vec<(String, String)> {
+        match self.protocol_version {
+            0 => vec![],
+            v => vec![("client_protocol_version".to_string(), v.to_string())],
+        }
+    }
+}
+
+impl NativeFormatter {
+    /// Formats a [QueryData]
+    pub fn format_data(&self, data: QueryData) -> Result<Vec<u8>, Error> {
+        let data = match self.flatten_nested {
+            true => data.flatten_nested()?,
+            false => data,
+        };
+        self.format_columns(data.into_columns()?)
+    }
+
+    /// Formats [QueryColumns] as 1 block
+    pub fn format_columns(&self, columns: QueryColumns) -> Result<Vec<u8>, Error> {
+        let mut buf = vec![];
+        let n_rows = columns.n_rows()?;
+
+        // NB: the block info has the fields `is_overflows` (1) and `bucket_num` (2)
+        if self.protocol_version > 0 {
+            leb128::write::unsigned(&mut buf, 1).unwrap();
+            buf.push(0x00);
+            leb128::write::unsigned(&mut buf, 2).unwrap();
+            buf.extend_from_slice(&(-1_i32).to_le_bytes());
+            leb128::write::unsigned(&mut buf, 0).unwrap();
+        }
+
+        leb128::write::unsigned(&mut buf, columns.n_cols() as u64).unwrap();
+        leb128::write::unsigned(&mut buf, n_rows as u64).unwrap();
+        for column in columns.columns {
+            format_string(&mut buf, &column.name);
+            format_string(&mut buf, &column.ty.to_string());
+            if self.protocol_version >= PROTOCOL_VERSION_WITH_CUSTOM_SERIALIZATION {
+                buf.push(0x00);
+            }
+
+            // NB: the values of an empty block are not written
+            if n_rows > 0 {
+                let values = column
+                    .values
+                    .into_iter()
+                    .map(|value| value.conform_to(&column.ty))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                format_prefix(&mut buf, &column.ty);
+                format_column(&mut buf, &column.ty, values)?;
+            }
+        }
+
+        Ok(buf)
+    }
+
+    /// Parses a [QueryData]
+    pub fn parse_data(
+        &self,
+        bytes: &[u8],
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        let data = QueryData::from_columns(self.parse_columns(bytes)?)?;
+        match mapping {
+            Some(mapping) if self.flatten_nested => data.unflatten_nested(mapping),
+            _ => Ok(data),
+        }
+    }
+
+    /// Parses the blocks to [QueryColumns]
+    ///
+    /// The columns of all the blocks are concatenated.
+    pub fn parse_columns(&self, bytes: &[u8]) -> Result<QueryColumns, Error> {
+        let mut bytes = bytes;
+        let mut columns = QueryColumns::new();
+        let mut is_first_block = true;
+
+        while !bytes.is_empty() {
+            if self.protocol_version > 0 {
+                parse_block_info(&mut bytes)?;
+            }
+
+            let n_cols: usize = leb128::read::unsigned(&mut bytes)?.try_into()?;
+            let n_rows: usize = leb128::read::unsigned(&mut bytes)?.try_into()?;
+            if !is_first_block && n_cols != columns.n_cols() {
+                return Err(Error::new("Native block has an invalid number of columns"));
+            }
+
+            for i in 0..n_cols {
+                let name = parse_string(&mut bytes)?;
+                let ty = Type::from_str(&parse_string(&mut bytes)?)?;
+                if self.protocol_version >= PROTOCOL_VERSION_WITH_CUSTOM_SERIALIZATION {
+                    let mut buf = [0x00_u8; 1];
+                    bytes.read_exact(&mut buf)?;
+                    if buf[0] != 0x00 {
+                        return Err(Error::new("Unsupported Native custom serialization"));
+                    }
+                }
+
+                let values = match n_rows {
+                    0 => vec![],
+                    n => {
+                        parse_prefix(&mut bytes, &ty)?;
+                        parse_column(&mut bytes, &ty, n)?
+                    }
+                };
+
+                if is_first_block {
+                    columns.columns.push(QueryColumn { name, ty, values });
+                } else {
+                    let column = &mut columns.columns[i];
+                    if column.name != name || column.ty != ty {
+                        return Err(Error::new("Native block has an invalid column"));
+                    }
+                    column.values.extend(values);
+                }
+            }
+            is_first_block = false;
+        }
+
+        Ok(columns)
+    }
+}
+
+/// Formats a string (prefixed with its length)
+fn format_string(buf: &mut Vec<u8>, s: &str) {
+    leb128::write::unsigned(buf, s.len() as u64).unwrap();
+    buf.extend_from_slice(s.as_bytes());
+}
+
+/// Formats the prefix of a column
+///
+/// NB: the prefixes of the nested columns are written before the values.
+fn format_prefix(buf: &mut Vec<u8>, ty: &Type) {
+    match ty {
+        Type::LowCardinality(_) => {
+            buf.extend_from_slice(&LOW_CARDINALITY_KEYS_VERSION.to_le_bytes());
+        }
+        Type::Array(ty) | Type::SimpleAggregateFunction(_, ty) => format_prefix(buf, ty),
+        Type::Map(ty_key, ty_val) => {
+            format_prefix(buf, ty_key);
+            format_prefix(buf, ty_val);
+        }
+        Type::Tuple(types) => types.iter().for_each(|ty| format_prefix(buf, ty)),
+        Type::NamedTuple(elements) | Type::Nested(elements) => {
+            elements.iter().for_each(|(_, ty)| format_prefix(buf, ty))
+        }
+        _ => {}
+    }
+}
+
+/// Formats the values of a column
+fn format_column(buf: &mut Vec<u8>, ty: &Type, values: Vec<Value>) -> Result<(), Error> {
+    match ty {
+        // NB: arrays are written as the offsets of their ends, then the values of all the arrays
+        Type::Array(ty) => {
+            let mut offset = 0_u64;
+            let mut inner_values = vec![];
+            for value in values {
+                match value {
+                    Value::Array(values) => {
+                        offset += values.len() as u64;
+                        buf.extend_from_slice(&offset.to_le_bytes());
+                        inner_values.extend(values);
+                    }
+                    _ => return Err(Error::new("Invalid Native array value")),
+                }
+            }
+            format_column(buf, ty, inner_values)
+        }
+        // NB: maps are written as arrays of keys and values
+        Type::Map(ty_key, ty_val) => {
+            let mut offset = 0_u64;
+            let mut keys = vec![];
+            let mut vals = vec![];
+            for value in values {
+                match value {
+                    Value::Map(map) => {
+                        offset += map.len() as u64;
+                        buf.extend_from_slice(&offset.to_le_bytes());
+                        for (key, val) in map {
+                            keys.push(key);
+                            vals.push(val);
+                        }
+                    }
+                    _ => return Err(Error::new("Invalid Native map value")),
+                }
+            }
+            format_column(buf, ty_key, keys)?;
+            format_column(buf, ty_val, vals)
+        }
+        Type::Tuple(types) => {
+            let elements = types.iter().collect::<Vec<_>>();
+            format_tuple_columns(buf, &elements, values)
+        }
+        Type::NamedTuple(elements) => {
+            let elements = elements.iter().map(|(_, ty)| ty).collect::<Vec<_>>();
+            format_tuple_columns(buf, &elements, values)
+        }
+        Type::Nested(fields) => {
+            let types = fields.iter().map(|(_, ty)| ty.clone()).collect();
+            let values = values
+                .into_iter()
+                .map(|value| match value {
+                    Value::Nested(fields) => Value::nested_into_tuples(fields),
+                    _ => Err(Error::new("Invalid Native nested value")),
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            format_column(buf, &Type::Array(Box::new(Type::Tuple(types))), values)
+        }
+        Type::LowCardinality(ty) => format_low_cardinality(buf, ty, values),
+        Type::SimpleAggregateFunction(_, ty) => format_column(buf, ty, values),
+        // NB: a Nothing value is written as the character '0'
+        Type::Nothing => {
+            buf.extend(values.iter().map(|_| b'0'));
+            Ok(())
+        }
+        Type::AggregateFunction(..) | Type::Variant(_) | Type::Dynamic(_) | Type::Json(..) => {
+            Err(Error(format!("Unsupported Native type: {ty}")))
+        }
+        // NB: nullable values are written as the NULL map, then the values (default if NULL)
+        _ => match ty.clone().into_non_nullable() {
+            Some(inner_ty) => {
+                let mut inner_values = vec![];
+                for value in values {
+                    match value.into_non_nullable() {
+                        Some(value) => {
+                            buf.push(0x00);
+                            inner_values.push(value);
+                        }
+                        None => {
+                            buf.push(0x01);
+                            inner_values.push(default_of(&inner_ty)?);
+                        }
+                    }
+                }
+                format_column(buf, &inner_ty, inner_values)
+            }
+            None => {
+                let formatter = RowBinFormatter::new();
+                for value in values {
+                    buf.extend(formatter.format_value(value)?);
+                }
+                Ok(())
+            }
+        },
+    }
+}
+
+/// Formats the values of a tuple column (1 column per element)
+fn format_tuple_columns(
+    buf: &mut Vec<u8>,
+    types: &[&Type],
+    values: Vec<Value>,
+) -> Result<(), Error> {
+    let mut columns = vec![Vec::with_capacity(values.len()); types.len()];
+    for value in values {
+        let elements = match value {
+            Value::Tuple(elements) => elements,
+            Value::NamedTuple(elements) => elements.into_iter().map(|(_, v)| v).collect(),
+            _ => return Err(Error::new("Invalid Native tuple value")),
+        };
+        if elements.len() != types.len() {
+            return Err(Error::new("Invalid Native tuple value"));
+        }
+        for (column, element) in columns.iter_mut().zip(elements) {
+            column.push(element);
+        }
+    }
+    for (ty, column) in types.iter().zip(columns) {
+        format_column(buf, ty, column)?;
+    }
+    Ok(())
+}
+
+/// Formats the values of a `LowCardinality` column
+///
+/// The column is written as a dictionary and the keys of the values. The dictionary
+/// starts with the default value (and the NULL value for a nullable type).
+fn format_low_cardinality(buf: &mut Vec<u8>, ty: &Type, values: Vec<Value>) -> Result<(), Error> {
+    // NB: nothing is written for an empty column (eg. within empty arrays)
+    if values.is_empty() {
+        return Ok(());
+    }
+
+    let (dict_ty, n_special) = match ty.clone().into_non_nullable() {
+        Some(inner_ty) => (inner_ty, 2),
+        None => (ty.clone(), 1),
+    };
+    let mut dict = vec![default_of(&dict_ty)?; n_special];
+
+    // NB: the values are indexed by their binary representation
+    let formatter = RowBinFormatter::new();
+    let mut index = HashMap::from([(formatter.format_value(dict[0].clone())?, n_special - 1)]);
+    let mut keys = vec![];
+    for value in values {
+        let key = match value.into_non_nullable() {
+            Some(value) => *index
+                .entry(formatter.format_value(value.clone())?)
+                .or_insert_with(|| {
+                    dict.push(value);
+                    dict.len() - 1
+                }),
+            None => 0,
+        };
+        keys.push(key as u64);
+    }
+
+    let index_type: u64 = match dict.len() {
+        n if n <= 1 << 8 => 0,
+        n if n <= 1 << 16 => 1,
+        n if n <= 1 << 32 => 2,
+        _ => 3,
+    };
+    buf.extend_from_slice(&(index_type | HAS_ADDITIONAL_KEYS_BIT).to_le_bytes());
+    buf.extend_from_slice(&(dict.len() as u64).to_le_bytes());
+    format_column(buf, &dict_ty, dict)?;
+    buf.extend_from_slice(&(keys.len() as u64).to_le_bytes());
+    for key in keys {
+        match index_type {
+            0 => buf.push(key as u8),
+            1 => buf.extend_from_slice(&(key as u16).to_le_bytes()),
+            2 => buf.extend_from_slice(&(key as u32).to_le_bytes()),
+            _ => buf.extend_from_slice(&key.to_le_bytes()),
+        }
+    }
+    Ok(())
+}
+
+/// Parses a string (prefixed with its length)
+fn parse_string(bytes: &mut &[u8]) -> Result<String, Error> {
+    let n: usize = leb128::read::unsigned(bytes)?.try_into()?;
+    let mut buf = vec![0x00_u8; n];
+    bytes.read_exact(&mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+/// Parses a u64 (little endian)
+fn parse_u64(bytes: &mut &[u8]) -> Result<u64, Error> {
+    let mut buf = [0x00_u8; 8];
+    bytes.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Parses the block info
+fn parse_block_info(bytes: &mut &[u8]) -> Result<(), Error> {
+    loop {
+        match leb128::read::unsigned(bytes)? {
+            0 => return Ok(()),
+            1 => {
+                let mut buf = [0x00_u8; 1];
+                bytes.read_exact(&mut buf)?;
+            }
+            2 => {
+                let mut buf = [0x00_u8; 4];
+                bytes.read_exact(&mut buf)?;
+            }
+            _ => return Err(Error::new("Invalid Native block info")),
+        }
+    }
+}
+
+/// Parses the prefix of a column
+fn parse_prefix(bytes: &mut &[u8], ty: &Type) -> Result<(), Error> {
+    match ty {
+        Type::LowCardinality(_) => match parse_u64(bytes)? {
+            LOW_CARDINALITY_KEYS_VERSION => Ok(()),
+            v => Err(Error(format!("Invalid LowCardinality keys version: {v}"))),
+        },
+        Type::Array(ty) | Type::SimpleAggregateFunction(_, ty) => parse_prefix(bytes, ty),
+        Type::Map(ty_key, ty_val) => {
+            parse_prefix(bytes, ty_key)?;
+            parse_prefix(bytes, ty_val)
+        }
+        Type::Tuple(types) => types.iter().try_for_each(|ty| parse_prefix(bytes, ty)),
+        Type::NamedTuple(elements) | Type::Nested(elements) => elements
+            .iter()
+            .try_for_each(|(_, ty)| parse_prefix(bytes, ty)),
+        _ => Ok(()),
+    }
+}
+
+/// Parses the offsets of `n` arrays
+fn parse_offsets(bytes: &mut &[u8], n: usize) -> Result<Vec<usize>, Error> {
+    let mut offsets = Vec::with_capacity(n);
+    let mut last = 0;
+    for _ in 0..n {
+        let offset: usize = parse_u64(bytes)?.try_into()?;
+        if offset < last {
+            return Err(Error::new("Invalid Native array offsets"));
+        }
+        offsets.push(offset);
+        last = offset;
+    }
+    Ok(offsets)
+}
+
+/// Splits values at the array offsets
+fn split_at_offsets<T>(values: Vec<T>, offsets: &[usize]) -> Vec<Vec<T>> {
+    let mut values = values.into_iter();
+    let mut start = 0;
+    offsets
+        .iter()
+        .map(|offset| {
+            let chunk = values.by_ref().take(offset - start).collect();
+            start = *offset;
+            chunk
+        })
+        .collect()
+}
+
+/// Parses `n` values of a column
+fn parse_column(bytes: &mut &[u8], ty: &Type, n: usize) -> Result<Vec<Value>, Error> {
+    match ty {
+        Type::Array(inner_ty) => {
+            let offsets = parse_offsets(bytes, n)?;
+            let values = parse_column(bytes, inner_ty, offsets.last().copied().unwrap_or(0))?;
+            Ok(split_at_offsets(values, &offsets)
+                .into_iter()
+                .map(Value::Array)
+                .collect())
+        }
+        Type::Map(ty_key, ty_val) => {
+            let offsets = parse_offsets(bytes, n)?;
+            let total = offsets.last().copied().unwrap_or(0);
+            let keys = parse_column(bytes, ty_key, total)?;
+            let vals = parse_column(bytes, ty_val, total)?;
+            let pairs = keys.into_iter().zip(vals).collect();
+            Ok(split_at_offsets(pairs, &offsets)
+                .into_iter()
+                .map(Value::Map)
+                .collect())
+        }
+        Type::Tuple(types) => {
+            let columns = types
+                .iter()
+                .map(|ty| parse_column(bytes, ty, n))
+                .collect::<Result<Vec<_>, Error>>()?;
+            Ok(transpose(columns, n)
+                .into_iter()
+                .map(Value::Tuple)
+                .collect())
+        }
+        Type::NamedTuple(elements) => {
+            let columns = elements
+                .iter()
+                .map(|(_, ty)| parse_column(bytes, ty, n))
+                .collect::<Result<Vec<_>, Error>>()?;
+            Ok(transpose(columns, n)
+                .into_iter()
+                .map(|values| {
+                    let names = elements.iter().map(|(name, _)| name.clone());
+                    Value::NamedTuple(names.zip(values).collect())
+                })
+                .collect())
+        }
+        Type::Nested(fields) => {
+            let (names, types): (Vec<_>, Vec<_>) = fields.iter().cloned().unzip();
+            parse_column(bytes, &Type::Array(Box::new(Type::Tuple(types))), n)?
+                .into_iter()
+                .map(|value| Value::nested_from_tuples(names.clone(), value))
+                .collect()
+        }
+        Type::LowCardinality(ty) => parse_low_cardinality(bytes, ty, n),
+        Type::SimpleAggregateFunction(_, ty) => parse_column(bytes, ty, n),
+        Type::Nothing => {
+            let mut buf = vec![0x00_u8; n];
+            bytes.read_exact(&mut buf)?;
+            Ok(vec![Value::Null; n])
+        }
+        Type::AggregateFunction(..) | Type::Variant(_) | Type::Dynamic(_) | Type::Json(..) => {
+            Err(Error(format!("Unsupported Native type: {ty}")))
+        }
+        _ => match ty.clone().into_non_nullable() {
+            Some(inner_ty) => {
+                let mut nulls = vec![0x00_u8; n];
+                bytes.read_exact(&mut nulls)?;
+                let values = parse_column(bytes, &inner_ty, n)?;
+                nulls
+                    .into_iter()
+                    .zip(values)
+                    .map(|(is_null, value)| match is_null {
+                        0x00 => value
+                            .into_nullable()
+                            .ok_or(Error::new("Invalid Native nullable value")),
+                        _ => Value::null_of(ty).ok_or(Error::new("Invalid Native nullable value")),
+                    })
+                    .collect()
+            }
+            None => {
+                let formatter = RowBinFormatter::new();
+                (0..n)
+                    .map(|_| formatter.parse_value(bytes, ty.clone()))
+                    .collect()
+            }
+        },
+    }
+}
+
+/// Transposes the columns of a tuple to the elements of the tuples
+pub(super) fn transpose(columns: Vec<Vec<Value>>, n: usize) -> Vec<Vec<Value>> {
+    let mut rows = vec![Vec::with_capacity(columns.len()); n];
+    for column in columns {
+        for (row, value) in rows.iter_mut().zip(column) {
+            row.push(value);
+        }
+    }
+    rows
+}
+
+/// Parses `n` values of a `LowCardinality` column
+fn parse_low_cardinality(bytes: &mut &[u8], ty: &Type, n: usize) -> Result<Vec<Value>, Error> {
+    if n == 0 {
+        return Ok(vec![]);
+    }
+
+    let flags = parse_u64(bytes)?;
+    if flags & NEED_GLOBAL_DICTIONARY_BIT != 0 {
+        return Err(Error::new("Unsupported LowCardinality global dictionary"));
+    }
+    let (dict_ty, is_nullable) = match ty.clone().into_non_nullable() {
+        Some(inner_ty) => (inner_ty, true),
+        None => (ty.clone(), false),
+    };
+    let dict = match flags & HAS_ADDITIONAL_KEYS_BIT {
+        0 => vec![],
+        _ => {
+            let n_dict: usize = parse_u64(bytes)?.try_into()?;
+            parse_column(bytes, &dict_ty, n_dict)?
+        }
+    };
+
+    let n_keys: usize = parse_u64(bytes)?.try_into()?;
+    if n_keys != n {
+        return Err(Error::new("Invalid LowCardinality number of keys"));
+    }
+    let key_size = 1 << (flags & 0xFF);
+    let mut values = Vec::with_capacity(n);
+    for _ in 0..n {
+        let mut buf = [0x00_u8; 8];
+        bytes.read_exact(&mut buf[..key_size])?;
+        let key: usize = u64::from_le_bytes(buf).try_into()?;
+        let value = match (key, is_nullable) {
+            (0, true) => Value::null_of(ty),
+            (key, true) => dict.get(key).and_then(|v| v.clone().into_nullable()),
+            (key, false) => dict.get(key).cloned(),
+        };
+        values.push(value.ok_or(Error::new("Invalid LowCardinality key"))?);
+    }
+    Ok(values)
+}
+
+/// Returns the default value of a type
+///
+/// NB: the default value of a scalar type is made of zero bytes.
+fn default_of(ty: &Type) -> Result<Value, Error> {
+    if *ty == Type::Nothing {
+        return Ok(Value::Null);
+    }
+    let zeros = vec![
+        0x00_u8;
+        match ty {
+            Type::FixedString(n) => *n,
+            _ => 32,
+        }
+    ];
+    RowBinFormatter::new().parse_value(&mut zeros.as_slice(), ty.clone())
+}
+
+/// Infers the type of a value (for a single value)
+///
+/// NB: the type of an empty array or map is `Nothing`, and the enum variants are unknown.
+pub(super) fn infer_type(value: &Value) -> Type {
+    /// Infers the type of the 1st value
+    fn first_type<'a>(mut values: impl Iterator<Item = &'a Value>) -> Box<Type> {
+        Box::new(values.next().map(infer_type).unwrap_or(Type::Nothing))
+    }
+
+    match value {
+        Value::UInt8(_) => Type::UInt8,
+        Value::UInt16(_) => Type::UInt16,
+        Value::UInt32(_) => Type::UInt32,
+        Value::UInt64(_) => Type::UInt64,
+        Value::UInt128(_) => Type::UInt128,
+        Value::UInt256(_) => Type::UInt256,
+        Value::Int8(_) => Type::Int8,
+        Value::Int16(_) => Type::Int16,
+        Value::Int32(_) => Type::Int32,
+        Value::Int64(_) => Type::Int64,
+        Value::Int128(_) => Type::Int128,
+        Value::Int256(_) => Type::Int256,
+        Value::Float32(_) => Type::Float32,
+        Value::Float64(_) => Type::Float64,
+        Value::BFloat16(_) => Type::BFloat16,
+        Value::Bool(_) => Type::Bool,
+        Value::String(_) | Value::Bytes(_) => Type::String,
+        Value::FixedString(v) => Type::FixedString(v.len()),
+        Value::UUID(_) => Type::UUID,
+        Value::Date(_) => Type::Date,
+        Value::Date32(_) => Type::Date32,
+        Value::DateTime(_, tz) => Type::DateTime(tz.clone()),
+        Value::DateTime64(_, p, tz) => Type::DateTime64(*p, tz.clone()),
+        Value::Time(_) => Type::Time,
+        Value::Time64(_, p) => Type::Time64(*p),
+        Value::Interval(_, kind) => Type::Interval(*kind),
+        Value::Enum8(..) => Type::Enum8(BTreeMap::new()),
+        Value::Enum16(..) => Type::Enum16(BTreeMap::new()),
+        Value::Array(values) => Type::Array(first_type(values.iter())),
+        Value::Tuple(values) => Type::Tuple(values.iter().map(infer_type).collect()),
+        Value::NamedTuple(values) => Type::NamedTuple(
+            values
+                .iter()
+                .map(|(name, v)| (name.clone(), infer_type(v)))
+                .collect(),
+        ),
+        Value::Map(map) => Type::Map(
+            first_type(map.iter().map(|(k, _)| k)),
+            first_type(map.iter().map(|(_, v)| v)),
+        ),
+        Value::Nested(fields) => Type::Nested(
+            fields
+                .iter()
+                .map(|(name, values)| (name.clone(), *first_type(values.iter())))
+                .collect(),
+        ),
+        Value::Variant(_) => Type::Variant(vec![]),
+        Value::Dynamic(_) => Type::Dynamic(None),
+        Value::AggregateFunction(_) => Type::AggregateFunction(String::new(), vec![]),
+        Value::Json(_) => Type::Json(vec![], vec![]),
+        Value::NullableUInt8(_) => Type::NullableUInt8,
+        Value::NullableUInt16(_) => Type::NullableUInt16,
+        Value::NullableUInt32(_) => Type::NullableUInt32,
+        Value::NullableUInt64(_) => Type::NullableUInt64,
+        Value::NullableUInt128(_) => Type::NullableUInt128,
+        Value::NullableUInt256(_) => Type::NullableUInt256,
+        Value::NullableInt8(_) => Type::NullableInt8,
+        Value::NullableInt16(_) => Type::NullableInt16,
+        Value::NullableInt32(_) => Type::NullableInt32,
+        Value::NullableInt64(_) => Type::NullableInt64,
+        Value::NullableInt128(_) => Type::NullableInt128,
+        Value::NullableInt256(_) => Type::NullableInt256,
+        Value::NullableFloat32(_) => Type::NullableFloat32,
+        Value::NullableFloat64(_) => Type::NullableFloat64,
+        Value::NullableBFloat16(_) => Type::NullableBFloat16,
+        Value::NullableBool(_) => Type::NullableBool,
+        Value::NullableString(_) | Value::NullableBytes(_) => Type::NullableString,
+        Value::NullableFixedString(v) => {
+            Type::NullableFixedString(v.as_ref().map(|v| v.len()).unwrap_or(0))
+        }
+        Value::NullableUUID(_) => Type::NullableUUID,
+        Value::NullableDate(_) => Type::NullableDate,
+        Value::NullableDate32(_) => Type::NullableDate32,
+        Value::NullableDateTime(_, tz) => Type::NullableDateTime(tz.clone()),
+        Value::NullableDateTime64(_, p, tz) => Type::NullableDateTime64(*p, tz.clone()),
+        Value::NullableTime(_) => Type::NullableTime,
+        Value::NullableTime64(_, p) => Type::NullableTime64(*p),
+        Value::NullableInterval(_, kind) => Type::NullableInterval(*kind),
+        Value::NullableEnum8(..) => Type::NullableEnum8(BTreeMap::new()),
+        Value::NullableEnum16(..) => Type::NullableEnum16(BTreeMap::new()),
+        Value::Null => Type::NullableNothing,
+        Value::Default => Type::Nothing,
+    }
+}
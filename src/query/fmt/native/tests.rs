@@ -0,0 +1,251 @@
+//! Tests
+
+use std::str::FromStr;
+
+use crate::{
+    query::{Format, NativeFormatter, QueryColumns, QueryData},
+    value::{Type, Value},
+};
+
+/// u64 (little endian) of a fixture
+macro_rules! u64_le {
+    ($N:expr) => {
+        &($N as u64).to_le_bytes()
+    };
+}
+
+#[test]
+fn fmt_native_golden_simple() {
+    // SELECT number AS n, toString(number) AS s FROM numbers(2) FORMAT Native
+    let golden = [
+        &[0x02, 0x02][..],
+        b"\x01n\x06UInt64",
+        u64_le!(0),
+        u64_le!(1),
+        b"\x01s\x06String",
+        b"\x010\x011",
+    ]
+    .concat();
+    let columns = QueryColumns::new()
+        .column("n", Type::UInt64, vec![Value::UInt64(0), Value::UInt64(1)])
+        .column(
+            "s",
+            Type::String,
+            vec!["0".to_string().into(), "1".to_string().into()],
+        );
+
+    let formatter = NativeFormatter::new();
+    assert_eq!(formatter.format_columns(columns.clone()).unwrap(), golden);
+    assert_eq!(formatter.parse_columns(&golden).unwrap(), columns);
+}
+
+#[test]
+fn fmt_native_golden_nullable_array() {
+    let golden = [
+        &[0x02, 0x02][..],
+        // NB: the NULL map, then the values (default for NULL)
+        b"\x01n\x0FNullable(Int32)",
+        &[0x00, 0x01],
+        &1_i32.to_le_bytes(),
+        &0_i32.to_le_bytes(),
+        // NB: the offsets of the array ends, then all the values
+        b"\x01a\x0DArray(String)",
+        u64_le!(2),
+        u64_le!(2),
+        b"\x01a\x01b",
+    ]
+    .concat();
+    let columns = QueryColumns::new()
+        .column(
+            "n",
+            Type::NullableInt32,
+            vec![Value::NullableInt32(Some(1)), Value::NullableInt32(None)],
+        )
+        .column(
+            "a",
+            Type::Array(Box::new(Type::String)),
+            vec![
+                vec!["a".to_string(), "b".to_string()].into(),
+                Value::Array(vec![]),
+            ],
+        );
+
+    let formatter = NativeFormatter::new();
+    assert_eq!(formatter.format_columns(columns.clone()).unwrap(), golden);
+    assert_eq!(formatter.parse_columns(&golden).unwrap(), columns);
+}
+
+#[test]
+fn fmt_native_golden_low_cardinality() {
+    let golden = [
+        &[0x03, 0x03][..],
+        // NB: the keys version, the flags (UInt8 keys, additional keys), the dictionary
+        // (starting with the default value) and the keys
+        b"\x02lc\x16LowCardinality(String)",
+        u64_le!(1),
+        u64_le!(0x0200),
+        u64_le!(3),
+        b"\x00\x01a\x01b",
+        u64_le!(3),
+        &[0x01, 0x02, 0x01],
+        // NB: the dictionary of a nullable type starts with the NULL and the default values
+        b"\x03lcn\x20LowCardinality(Nullable(String))",
+        u64_le!(1),
+        u64_le!(0x0200),
+        u64_le!(3),
+        b"\x00\x00\x01a",
+        u64_le!(3),
+        &[0x00, 0x02, 0x01],
+        // NB: the prefixes are written before the array offsets
+        b"\x03arr\x1DArray(LowCardinality(String))",
+        u64_le!(1),
+        u64_le!(1),
+        u64_le!(1),
+        u64_le!(1),
+        u64_le!(0x0200),
+        u64_le!(2),
+        b"\x00\x01a",
+        u64_le!(1),
+        &[0x01],
+    ]
+    .concat();
+    let columns = QueryColumns::new()
+        .column(
+            "lc",
+            Type::from_str("LowCardinality(String)").unwrap(),
+            vec![
+                "a".to_string().into(),
+                "b".to_string().into(),
+                "a".to_string().into(),
+            ],
+        )
+        .column(
+            "lcn",
+            Type::from_str("LowCardinality(Nullable(String))").unwrap(),
+            vec![
+                Value::NullableString(None),
+                Value::NullableString(Some("a".to_string())),
+                Value::NullableString(Some("".to_string())),
+            ],
+        )
+        .column(
+            "arr",
+            Type::from_str("Array(LowCardinality(String))").unwrap(),
+            vec![
+                vec!["a".to_string()].into(),
+                Value::Array(vec![]),
+                Value::Array(vec![]),
+            ],
+        );
+
+    let formatter = NativeFormatter::new();
+    assert_eq!(formatter.format_columns(columns.clone()).unwrap(), golden);
+    assert_eq!(formatter.parse_columns(&golden).unwrap(), columns);
+}
+
+#[test]
+fn fmt_native_golden_block_info() {
+    let block = |n: u8| {
+        [
+            // NB: is_overflows (1) and bucket_num (2)
+            &[0x01, 0x00, 0x02][..],
+            &(-1_i32).to_le_bytes(),
+            &[0x00, 0x01, 0x01],
+            // NB: the custom serialization flag
+            b"\x01n\x05UInt8\x00",
+            &[n],
+        ]
+        .concat()
+    };
+    let golden = [block(1), block(2)].concat();
+
+    let formatter = NativeFormatter::new().protocol_version(54454);
+    let columns = formatter.parse_columns(&golden).unwrap();
+    assert_eq!(
+        columns,
+        QueryColumns::new().column("n", Type::UInt8, vec![Value::UInt8(1), Value::UInt8(2)])
+    );
+
+    let columns = QueryColumns::new().column("n", Type::UInt8, vec![Value::UInt8(1)]);
+    assert_eq!(formatter.format_columns(columns).unwrap(), block(1));
+}
+
+#[test]
+fn fmt_native_table() {
+    let mapping = vec![
+        ("id", Type::UInt32),
+        (
+            "point",
+            Type::Tuple(vec![Type::Float64, Type::NullableString]),
+        ),
+        (
+            "attrs",
+            Type::Map(Box::new(Type::String), Box::new(Type::UInt8)),
+        ),
+        (
+            "n",
+            Type::Nested(vec![
+                ("a".to_string(), Type::UInt8),
+                ("b".to_string(), Type::String),
+            ]),
+        ),
+    ];
+    let table = QueryData::with_names_and_types(mapping.clone())
+        .row(vec![
+            Value::UInt32(1),
+            Value::Tuple(vec![Value::Float64(1.5), Value::NullableString(None)]),
+            Value::Map(vec![("x".to_string().into(), Value::UInt8(1))]),
+            Value::Nested(vec![
+                ("a".to_string(), vec![Value::UInt8(1), Value::UInt8(2)]),
+                (
+                    "b".to_string(),
+                    vec!["x".to_string().into(), "y".to_string().into()],
+                ),
+            ]),
+        ])
+        .row(vec![
+            Value::UInt32(2),
+            Value::Tuple(vec![
+                Value::Float64(0.0),
+                Value::NullableString(Some("a".to_string())),
+            ]),
+            Value::Map(vec![]),
+            Value::Nested(vec![("a".to_string(), vec![]), ("b".to_string(), vec![])]),
+        ]);
+
+    let format = Format::Native;
+    let bytes = table.clone().to_bytes(format).unwrap();
+    let table_parsed = QueryData::from_bytes(&bytes, format, Some(&mapping)).unwrap();
+    assert_eq!(table_parsed, table);
+
+    // NB: nested columns are flattened
+    let columns = QueryData::from_bytes(&bytes, format, None)
+        .unwrap()
+        .into_columns()
+        .unwrap();
+    assert_eq!(
+        columns.get_column("n.a").map(|c| c.ty.clone()),
+        Some(Type::Array(Box::new(Type::UInt8)))
+    );
+
+    let err = QueryData::with_names(vec!["id"])
+        .row(vec![Value::UInt32(1)])
+        .to_bytes(format)
+        .unwrap_err();
+    assert_eq!(err.0, "Table is missing the column types");
+}
+
+#[test]
+fn fmt_native_value() {
+    let value = Value::Array(vec![
+        Value::NullableUInt8(Some(1)),
+        Value::NullableUInt8(None),
+    ]);
+    let bytes = value.clone().to_bytes(Format::Native).unwrap();
+    assert_eq!(bytes, [u64_le!(2), &[0x00, 0x01, 0x01, 0x00][..]].concat());
+    let ty = Type::Array(Box::new(Type::NullableUInt8));
+    assert_eq!(
+        Value::from_bytes(&bytes, Format::Native, ty).unwrap(),
+        value
+    );
+}
@@ -0,0 +1,328 @@
+//! Pretty formats (PrettyCompact, Vertical and Markdown)
+//!
+//! The tables are rendered client-side, eg. for a terminal or a log. They are output formats only.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    error::Error,
+    query::QueryData,
+    value::{Type, Value},
+};
+
+use super::{Formatter, TsvFormatter};
+
+/// NULL value
+const NULL: &str = "ᴺᵁᴸᴸ";
+
+/// Layout of a pretty table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    /// Table with box-drawing borders (`PrettyCompact` format)
+    Compact,
+    /// 1 block per row, 1 line per column (`Vertical` format)
+    Vertical,
+    /// Markdown table (`Markdown` format)
+    Markdown,
+}
+
+/// Pretty formatter
+///
+/// The values are written as with the `TSVRaw` format, and NULL values as `ᴺᵁᴸᴸ`.
+#[derive(Debug, Clone)]
+pub struct PrettyFormatter {
+    /// Layout
+    layout: Layout,
+    /// Nested columns are flattened
+    flatten_nested: bool,
+}
+
+impl Default for PrettyFormatter {
+    fn default() -> Self {
+        Self {
+            layout: Layout::Compact,
+            flatten_nested: true,
+        }
+    }
+}
+
+impl PrettyFormatter {
+    /// Creates a new [PrettyFormatter] (`PrettyCompact` format)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new [PrettyFormatter] for the `Vertical` format
+    pub fn vertical() -> Self {
+        Self {
+            layout: Layout::Vertical,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a new [PrettyFormatter] for the `Markdown` format
+    pub fn markdown() -> Self {
+        Self {
+            layout: Layout::Markdown,
+            ..Default::default()
+        }
+    }
+
+    /// Sets if `Nested` columns are flattened (`true` by default)
+    ///
+    /// This must match the Clickhouse setting `flatten_nested`.
+    pub fn flatten_nested(mut self, flatten: bool) -> Self {
+        self.flatten_nested = flatten;
+        self
+    }
+}
+
+impl Formatter for PrettyFormatter {
+    fn serialize_value(&self, value: Value) -> Result<Vec<u8>, Error> {
+        self.format_value(value).map(String::into_bytes)
+    }
+
+    fn serialize_query_data(&self, data: QueryData) -> Result<Vec<u8>, Error> {
+        self.format_data(data).map(|s| s.into_bytes())
+    }
+
+    fn deserialize_value(&self, _bytes: &[u8], _ty: Type) -> Result<Value, Error> {
+        Err(self.output_only_error())
+    }
+
+    fn deserialize_query_data(
+        &self,
+        _bytes: &[u8],
+        _mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        Err(self.output_only_error())
+    }
+}
+
+impl PrettyFormatter {
+    /// Returns the error of the parsing methods
+    fn output_only_error(&self) -> Error {
+        let format = match self.layout {
+            Layout::Compact => "PrettyCompact",
+            Layout::Vertical => "Vertical",
+            Layout::Markdown => "Markdown",
+        };
+        Error(format!("The {format} format cannot be parsed"))
+    }
+
+    /// Formats a [Value]
+    pub fn format_value(&self, value: Value) -> Result<String, Error> {
+        Ok(match value.into_non_nullable() {
+            Some(value) => {
+                let value = TsvFormatter::raw().format_value(value)?;
+                match self.layout {
+                    // NB: a `|` would end the cell
+                    Layout::Markdown => value.replace('|', r"\|").replace('\n', " "),
+                    _ => value,
+                }
+            }
+            None => NULL.to_string(),
+        })
+    }
+
+    /// Formats a [QueryData]
+    pub fn format_data(&self, data: QueryData) -> Result<String, Error> {
+        let data = match self.flatten_nested {
+            true => data.flatten_nested()?,
+            false => data,
+        };
+        let parts = data.into_parts();
+
+        let mut columns: Vec<Column> = match &parts.names {
+            Some(names) => names.iter().map(|name| Column::new(name)).collect(),
+            None if self.layout == Layout::Compact => {
+                let n = parts.rows.first().map(Vec::len).unwrap_or_default();
+                (0..n).map(|_| Column::new("")).collect()
+            }
+            None => return Err(Error::new("Table is missing the column names")),
+        };
+        let mut rows = vec![];
+        for row in parts.rows {
+            let mut cells = vec![];
+            for (i, value) in row.into_iter().enumerate() {
+                let column = columns
+                    .get_mut(i)
+                    .ok_or(Error(format!("No column for value at index {i}")))?;
+                // NB: values are conformed to the column types (eg DateTime64 precision)
+                let value = match parts.types.as_ref().and_then(|types| types.get(i)) {
+                    Some(ty) => value.conform_to(ty)?,
+                    None => value,
+                };
+                column.is_number &= is_number(&value);
+                let cell = self.format_value(value)?;
+                column.width = column.width.max(cell.chars().count());
+                cells.push(cell);
+            }
+            rows.push(cells);
+        }
+        if rows.is_empty() {
+            columns.iter_mut().for_each(|c| c.is_number = false);
+        }
+
+        Ok(match self.layout {
+            Layout::Compact => format_compact(&columns, &rows),
+            Layout::Vertical => format_vertical(&columns, &rows),
+            Layout::Markdown => format_markdown(&columns, &rows),
+        })
+    }
+}
+
+/// Column of a pretty table
+struct Column {
+    /// Name
+    name: String,
+    /// Width (in characters)
+    width: usize,
+    /// All the values are numbers (aligned to the right)
+    is_number: bool,
+}
+
+impl Column {
+    /// Creates a new column
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            width: name.chars().count(),
+            is_number: true,
+        }
+    }
+
+    /// Pads a text to the column width
+    fn pad(&self, text: &str, fill: char) -> String {
+        let fill = fill
+            .to_string()
+            .repeat(self.width.saturating_sub(text.chars().count()));
+        match self.is_number {
+            true => format!("{fill}{text}"),
+            false => format!("{text}{fill}"),
+        }
+    }
+}
+
+/// Checks if a value is a number (or a NULL number)
+fn is_number(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::UInt8(_)
+            | Value::UInt16(_)
+            | Value::UInt32(_)
+            | Value::UInt64(_)
+            | Value::UInt128(_)
+            | Value::UInt256(_)
+            | Value::Int8(_)
+            | Value::Int16(_)
+            | Value::Int32(_)
+            | Value::Int64(_)
+            | Value::Int128(_)
+            | Value::Int256(_)
+            | Value::Float32(_)
+            | Value::Float64(_)
+            | Value::BFloat16(_)
+            | Value::NullableUInt8(_)
+            | Value::NullableUInt16(_)
+            | Value::NullableUInt32(_)
+            | Value::NullableUInt64(_)
+            | Value::NullableUInt128(_)
+            | Value::NullableUInt256(_)
+            | Value::NullableInt8(_)
+            | Value::NullableInt16(_)
+            | Value::NullableInt32(_)
+            | Value::NullableInt64(_)
+            | Value::NullableInt128(_)
+            | Value::NullableInt256(_)
+            | Value::NullableFloat32(_)
+            | Value::NullableFloat64(_)
+            | Value::NullableBFloat16(_)
+    )
+}
+
+/// Formats a table with box-drawing borders
+///
+/// ```text
+/// ┌─id─┬─name─┐
+/// │  1 │ a    │
+/// └────┴──────┘
+/// ```
+fn format_compact(columns: &[Column], rows: &[Vec<String>]) -> String {
+    let border = |left: &str, sep: &str, right: &str, header: bool| {
+        let cells = columns
+            .iter()
+            .map(|c| match header {
+                true => format!("─{}─", c.pad(&c.name, '─')),
+                false => "─".repeat(c.width + 2),
+            })
+            .collect::<Vec<_>>();
+        format!("{left}{}{right}\n", cells.join(sep))
+    };
+
+    let mut buf = border("┌", "┬", "┐", true);
+    for row in rows {
+        let cells = columns
+            .iter()
+            .zip(row)
+            .map(|(c, cell)| c.pad(cell, ' '))
+            .collect::<Vec<_>>();
+        buf.push_str(&format!("│ {} │\n", cells.join(" │ ")));
+    }
+    buf.push_str(&border("└", "┴", "┘", false));
+    buf
+}
+
+/// Formats a table with 1 block per row
+///
+/// ```text
+/// Row 1:
+/// ──────
+/// id:   1
+/// name: a
+/// ```
+fn format_vertical(columns: &[Column], rows: &[Vec<String>]) -> String {
+    let name_width = columns
+        .iter()
+        .map(|c| c.name.chars().count() + 1)
+        .max()
+        .unwrap_or_default();
+
+    let mut blocks = vec![];
+    for (i, row) in rows.iter().enumerate() {
+        let title = format!("Row {}:", i + 1);
+        let mut block = format!("{title}\n{}\n", "─".repeat(title.chars().count()));
+        for (c, cell) in columns.iter().zip(row) {
+            let name = format!("{}:", c.name);
+            let fill = " ".repeat(name_width - name.chars().count());
+            block.push_str(&format!("{name}{fill} {cell}\n"));
+        }
+        blocks.push(block);
+    }
+    blocks.join("\n")
+}
+
+/// Formats a Markdown table
+///
+/// ```text
+/// | id | name |
+/// |-:|:-|
+/// | 1 | a |
+/// ```
+fn format_markdown(columns: &[Column], rows: &[Vec<String>]) -> String {
+    let names = columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+    let aligns = columns
+        .iter()
+        .map(|c| match c.is_number {
+            true => "-:",
+            false => ":-",
+        })
+        .collect::<Vec<_>>();
+
+    let mut buf = format!("| {} |\n|{}|\n", names.join(" | "), aligns.join("|"));
+    for row in rows {
+        buf.push_str(&format!("| {} |\n", row.join(" | ")));
+    }
+    buf
+}
@@ -0,0 +1,76 @@
+//! Tests
+
+use crate::{
+    query::{Format, PrettyFormatter, QueryData},
+    value::{Type, Value},
+};
+
+/// Creates a sample table
+fn sample_table() -> QueryData {
+    QueryData::with_names_and_types(vec![
+        ("id", Type::UInt32),
+        ("name", Type::NullableString),
+        ("tags", Type::Array(Box::new(Type::String))),
+    ])
+    .row(vec![
+        Value::UInt32(1),
+        Value::NullableString(Some("a|b".to_string())),
+        vec!["x".to_string()].into(),
+    ])
+    .row(vec![
+        Value::UInt32(100),
+        Value::NullableString(None),
+        Value::Array(vec![]),
+    ])
+}
+
+#[test]
+fn fmt_pretty_compact() {
+    let bytes = sample_table().to_bytes(Format::PrettyCompact).unwrap();
+    assert_eq!(
+        String::from_utf8(bytes).unwrap(),
+        "┌──id─┬─name─┬─tags──┐\n\
+         │   1 │ a|b  │ ['x'] │\n\
+         │ 100 │ ᴺᵁᴸᴸ │ []    │\n\
+         └─────┴──────┴───────┘\n"
+    );
+
+    let err = QueryData::from_bytes(b"", Format::PrettyCompact, None).unwrap_err();
+    assert_eq!(err.0, "The PrettyCompact format cannot be parsed");
+}
+
+#[test]
+fn fmt_pretty_vertical() {
+    let bytes = sample_table().to_bytes(Format::Vertical).unwrap();
+    assert_eq!(
+        String::from_utf8(bytes).unwrap(),
+        "Row 1:\n\
+         ──────\n\
+         id:   1\n\
+         name: a|b\n\
+         tags: ['x']\n\
+         \n\
+         Row 2:\n\
+         ──────\n\
+         id:   100\n\
+         name: ᴺᵁᴸᴸ\n\
+         tags: []\n"
+    );
+
+    let err = PrettyFormatter::vertical()
+        .format_data(QueryData::no_headers().row(vec![Value::UInt8(1)]))
+        .unwrap_err();
+    assert_eq!(err.0, "Table is missing the column names");
+}
+
+#[test]
+fn fmt_pretty_markdown() {
+    let bytes = sample_table().to_bytes(Format::Markdown).unwrap();
+    assert_eq!(
+        String::from_utf8(bytes).unwrap(),
+        "| id | name | tags |\n\
+         |-:|:-|:-|\n\
+         | 1 | a\\|b | ['x'] |\n\
+         | 100 | ᴺᵁᴸᴸ | [] |\n"
+    );
+}
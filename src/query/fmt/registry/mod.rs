@@ -0,0 +1,98 @@
+//! Formatter registry
+//!
+//! Applications register their own formatters, for a [Format] (overriding the built-in
+//! formatter) or for a custom format name.
+
+#[cfg(test)]
+mod tests;
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{error::UnsupportedFormat, query::Query};
+
+use super::{Format, Formatter};
+
+/// Custom format
+///
+/// A custom format is sent to the server as a Clickhouse format, and serialized and
+/// deserialized by its formatter.
+#[derive(Debug, Clone)]
+pub struct CustomFormat {
+    /// Clickhouse format
+    pub format: Format,
+    /// Formatter
+    pub formatter: Arc<dyn Formatter>,
+}
+
+/// Formatter registry
+#[derive(Debug, Clone, Default)]
+pub struct FormatterRegistry {
+    /// Formatters of the formats
+    formats: HashMap<Format, Arc<dyn Formatter>>,
+    /// Custom formats, by name
+    custom: HashMap<String, CustomFormat>,
+}
+
+impl FormatterRegistry {
+    /// Creates a new [FormatterRegistry]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the formatter of a format
+    ///
+    /// NB: the formatter takes precedence over the built-in formatter.
+    pub fn register(&mut self, format: Format, formatter: impl Formatter + 'static) {
+        self.formats.insert(format, Arc::new(formatter));
+    }
+
+    /// Registers a custom format
+    ///
+    /// The data is sent to the server with the Clickhouse `format`.
+    pub fn register_custom(
+        &mut self,
+        name: &str,
+        format: Format,
+        formatter: impl Formatter + 'static,
+    ) {
+        self.custom.insert(
+            name.to_string(),
+            CustomFormat {
+                format,
+                formatter: Arc::new(formatter),
+            },
+        );
+    }
+
+    /// Returns the formatter of a format
+    ///
+    /// The registered formatter takes precedence over the built-in formatter.
+    pub fn get(&self, format: Format) -> Result<Arc<dyn Formatter>, UnsupportedFormat> {
+        match self.formats.get(&format) {
+            Some(formatter) => Ok(formatter.clone()),
+            None => format.try_formatter().map(Arc::from),
+        }
+    }
+
+    /// Returns a custom format
+    pub fn get_custom(&self, name: &str) -> Result<CustomFormat, UnsupportedFormat> {
+        self.custom
+            .get(name)
+            .cloned()
+            .ok_or_else(|| UnsupportedFormat(name.to_string()))
+    }
+
+    /// Assigns the registered formatter to a query
+    ///
+    /// NB: the formatter of the query takes precedence over the registered formatters.
+    pub(crate) fn resolve(&self, query: &mut Query) -> Result<(), UnsupportedFormat> {
+        if let Some(name) = query.custom_format.take() {
+            let custom = self.get_custom(&name)?;
+            query.format = Some(custom.format);
+            query.formatter.get_or_insert(custom.formatter);
+        } else if let (None, Some(format)) = (&query.formatter, query.format) {
+            query.formatter = self.formats.get(&format).cloned();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+//! Tests
+
+use crate::{
+    error::UnsupportedFormat,
+    query::{CsvFormatter, Format, FormatterRegistry, Query, QueryData, TsvFormatter},
+    value::{Type, Value},
+};
+
+/// Creates a sample table
+fn sample_table() -> QueryData {
+    QueryData::with_names_and_types(vec![("id", Type::UInt32), ("name", Type::String)])
+        .row(vec![Value::UInt32(1), "a".to_string().into()])
+}
+
+#[test]
+fn fmt_registry_unsupported() {
+    let err = Format::MsgPack.try_formatter().unwrap_err();
+    assert_eq!(err, UnsupportedFormat("MsgPack".to_string()));
+
+    let err = sample_table().to_bytes(Format::MsgPack).unwrap_err();
+    assert_eq!(err.0, "Unsupported format: MsgPack");
+    let err = QueryData::from_bytes(b"", Format::MsgPack, None).unwrap_err();
+    assert_eq!(err.0, "Unsupported format: MsgPack");
+    let err = Value::UInt8(1).to_bytes(Format::MsgPack).unwrap_err();
+    assert_eq!(err.0, "Unsupported format: MsgPack");
+    let err = Value::from_bytes(b"", Format::MsgPack, Type::UInt8).unwrap_err();
+    assert_eq!(err.0, "Unsupported format: MsgPack");
+
+    let registry = FormatterRegistry::new();
+    assert!(registry.get(Format::MsgPack).is_err());
+    assert!(registry.get(Format::CSV).is_ok());
+    assert_eq!(
+        registry.get_custom("csv_semicolon").unwrap_err(),
+        UnsupportedFormat("csv_semicolon".to_string())
+    );
+}
+
+#[test]
+fn fmt_registry_lookup() {
+    let mut registry = FormatterRegistry::new();
+    registry.register(Format::MsgPack, TsvFormatter::new());
+    registry.register_custom(
+        "csv_semicolon",
+        Format::CSV,
+        CsvFormatter::new().delimiter(';'),
+    );
+
+    let table = sample_table();
+    let bytes = registry
+        .get(Format::MsgPack)
+        .unwrap()
+        .serialize_query_data(table.clone())
+        .unwrap();
+    assert_eq!(bytes, b"1\ta\n");
+
+    let custom = registry.get_custom("csv_semicolon").unwrap();
+    assert_eq!(custom.format, Format::CSV);
+    let bytes = custom.formatter.serialize_query_data(table).unwrap();
+    assert_eq!(bytes, b"1;\"a\"\n");
+
+    // NB: the custom format sets the format and the formatter of the query
+    let mut query = Query::new("SELECT 1").custom_format("csv_semicolon");
+    registry.resolve(&mut query).unwrap();
+    assert_eq!(query.format, Some(Format::CSV));
+    assert!(query.formatter.is_some());
+    assert!(query.custom_format.is_none());
+
+    let mut query = Query::new("SELECT 1").format(Format::MsgPack);
+    registry.resolve(&mut query).unwrap();
+    assert!(query.formatter.is_some());
+
+    // NB: the built-in formatters are not assigned
+    let mut query = Query::new("SELECT 1").format(Format::TabSep);
+    registry.resolve(&mut query).unwrap();
+    assert!(query.formatter.is_none());
+
+    let mut query = Query::new("SELECT 1").custom_format("unknown");
+    assert!(registry.resolve(&mut query).is_err());
+}
@@ -0,0 +1,116 @@
+//! RowBinary row cursor
+
+use std::{marker::PhantomData, str::FromStr};
+
+use serde::Deserialize;
+
+use crate::{error::Error, value::Type};
+
+use super::RowBinDeserializer;
+
+/// Cursor over the rows of a RowBinary buffer
+///
+/// Rows are decoded lazily, and can borrow strings and bytes (`&'a str`, `&'a [u8]`) from the
+/// buffer instead of allocating them.
+#[derive(Debug)]
+pub struct RowCursor<'a, T> {
+    /// Deserializer
+    de: RowBinDeserializer<'a>,
+    /// Column names (if the buffer has a header)
+    names: Option<Vec<&'a str>>,
+    /// An error has been returned (no more rows are decoded)
+    done: bool,
+    /// Row type
+    _row: PhantomData<T>,
+}
+
+impl<'a, T: Deserialize<'a>> RowCursor<'a, T> {
+    /// Creates a new [RowCursor] over RowBinary rows with the column types
+    pub fn new(bytes: &'a [u8], types: &[Type]) -> Self {
+        Self {
+            de: RowBinDeserializer::new(bytes, types),
+            names: None,
+            done: false,
+            _row: PhantomData,
+        }
+    }
+
+    /// Creates a new [RowCursor] over RowBinary rows, with a header
+    ///
+    /// The header has the column names (`RowBinaryWithNames`), and the column types
+    /// (`RowBinaryWithNamesAndTypes`). If the types are provided, they take precedence over the
+    /// header types.
+    pub fn with_header(
+        mut bytes: &'a [u8],
+        with_types: bool,
+        types: Option<&[Type]>,
+    ) -> Result<Self, Error> {
+        let n: usize = leb128::read::unsigned(&mut bytes)?.try_into()?;
+        let mut names = vec![];
+        for _ in 0..n {
+            names.push(read_str(&mut bytes)?);
+        }
+        let mut header_types = vec![];
+        if with_types {
+            for _ in 0..n {
+                header_types.push(Type::from_str(read_str(&mut bytes)?)?);
+            }
+        }
+
+        let types = match types {
+            Some(types) => types,
+            None if with_types => &header_types,
+            None => return Err(Error::new("Missing column types to parse rows")),
+        };
+        if types.len() != n {
+            return Err(Error(format!(
+                "Expected {} column types, got {}",
+                n,
+                types.len()
+            )));
+        }
+
+        Ok(Self {
+            de: RowBinDeserializer::new(bytes, types),
+            names: Some(names),
+            done: false,
+            _row: PhantomData,
+        })
+    }
+
+    /// Returns the column names (if the buffer has a header)
+    pub fn names(&self) -> Option<&[&'a str]> {
+        self.names.as_deref()
+    }
+
+    /// Returns the next row (or `None` if there are no remaining rows)
+    ///
+    /// NB: after an error, the position in the buffer is unknown, so no more rows are returned.
+    pub fn next_row(&mut self) -> Result<Option<T>, Error> {
+        if self.done {
+            return Ok(None);
+        }
+        let row = self.de.deserialize_row();
+        self.done = row.is_err();
+        row
+    }
+}
+
+impl<'a, T: Deserialize<'a>> Iterator for RowCursor<'a, T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_row().transpose()
+    }
+}
+
+/// Reads a string of the header
+fn read_str<'a>(bytes: &mut &'a [u8]) -> Result<&'a str, Error> {
+    let n: usize = leb128::read::unsigned(bytes)?.try_into()?;
+    if bytes.len() < n {
+        return Err(Error::new("Unexpected end of RowBinary data"));
+    }
+    let (buf, rest) = bytes.split_at(n);
+    *bytes = rest;
+    std::str::from_utf8(buf).map_err(|err| Error(err.to_string()))
+}
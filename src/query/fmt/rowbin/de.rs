@@ -0,0 +1,443 @@
+//! RowBinary serde deserializer
+
+use serde::{
+    de::{self, DeserializeSeed, IntoDeserializer, Visitor},
+    forward_to_deserialize_any, Deserialize,
+};
+use uuid::Uuid;
+
+use crate::{error::Error, value::Type};
+
+use super::ser::{is_nullable, unwrap_type};
+
+/// RowBinary deserializer of serde rows
+///
+/// Each row (a struct or a tuple) is read from the bytes directly, without going through
+/// [Value](crate::value::Value). The columns are mapped to the fields by position, and strings
+/// can be borrowed from the bytes.
+#[derive(Debug)]
+pub struct RowBinDeserializer<'de> {
+    /// Row type (a tuple of the column types)
+    row_ty: Type,
+    /// Remaining bytes
+    bytes: &'de [u8],
+}
+
+impl<'de> RowBinDeserializer<'de> {
+    /// Creates a new [RowBinDeserializer] for the column types
+    pub fn new(bytes: &'de [u8], types: &[Type]) -> Self {
+        Self {
+            row_ty: Type::Tuple(types.to_vec()),
+            bytes,
+        }
+    }
+
+    /// Deserializes the next row (or `None` if there are no remaining bytes)
+    pub fn deserialize_row<T: Deserialize<'de>>(&mut self) -> Result<Option<T>, Error> {
+        if self.bytes.is_empty() {
+            return Ok(None);
+        }
+        let row = T::deserialize(ValueDeserializer::new(&mut self.bytes, &self.row_ty))?;
+        Ok(Some(row))
+    }
+}
+
+/// Deserializes rows from RowBinary
+pub fn from_rowbin<'de, T: Deserialize<'de>>(
+    bytes: &'de [u8],
+    types: &[Type],
+) -> Result<Vec<T>, Error> {
+    let mut deserializer = RowBinDeserializer::new(bytes, types);
+    let mut rows = vec![];
+    while let Some(row) = deserializer.deserialize_row()? {
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Deserializer of a single value, read with its column type
+struct ValueDeserializer<'a, 't, 'de> {
+    /// Remaining bytes
+    bytes: &'a mut &'de [u8],
+    /// Column type
+    ty: &'t Type,
+    /// The value is the inner value of a nullable (the NULL flag is read)
+    is_some: bool,
+}
+
+impl<'a, 't, 'de> ValueDeserializer<'a, 't, 'de> {
+    /// Creates a new value deserializer
+    fn new(bytes: &'a mut &'de [u8], ty: &'t Type) -> Self {
+        Self {
+            bytes,
+            ty: unwrap_type(ty),
+            is_some: false,
+        }
+    }
+
+    /// Reads N bytes
+    fn read(&mut self, n: usize) -> Result<&'de [u8], Error> {
+        if self.bytes.len() < n {
+            return Err(Error::new("Unexpected end of RowBinary data"));
+        }
+        let (buf, rest) = self.bytes.split_at(n);
+        *self.bytes = rest;
+        Ok(buf)
+    }
+
+    /// Reads a fixed number of bytes
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        Ok(self.read(N)?.try_into()?)
+    }
+
+    /// Reads a length
+    fn read_len(&mut self) -> Result<usize, Error> {
+        Ok(leb128::read::unsigned(self.bytes)?.try_into()?)
+    }
+
+    /// Reads the NULL flag of a nullable column (`true` if NULL)
+    fn read_null_flag(&mut self) -> Result<bool, Error> {
+        match self.read(1)? {
+            [0x00] => Ok(false),
+            [0x01] => Ok(true),
+            _ => Err(Error::new("Invalid NULL flag")),
+        }
+    }
+
+    /// Reads a UUID
+    fn read_uuid(&mut self) -> Result<Uuid, Error> {
+        // NB: in RowBinary, the UUID is represented as 2 u64 in little endian
+        let w1 = u64::from_le_bytes(self.read_array()?);
+        let w2 = u64::from_le_bytes(self.read_array()?);
+        Ok(Uuid::from_u64_pair(w1, w2))
+    }
+
+    /// Reads the value of an enum
+    fn read_enum_name(&mut self) -> Result<&'t str, Error> {
+        let ty = self.ty;
+        let value = match ty {
+            Type::Enum8(_) | Type::NullableEnum8(_) => {
+                i16::from(i8::from_le_bytes(self.read_array()?))
+            }
+            Type::Enum16(_) | Type::NullableEnum16(_) => i16::from_le_bytes(self.read_array()?),
+            _ => {
+                return Err(Error(format!(
+                    "Cannot deserialize a {ty} column as an enum"
+                )))
+            }
+        };
+        ty.enum_variant_name(value)
+            .ok_or_else(|| Error(format!("Invalid enum value '{value}'")))
+    }
+
+    /// Visits the elements of a compound value
+    fn visit_elements<V: Visitor<'de>>(
+        self,
+        visitor: V,
+        elements: Elements<'t>,
+    ) -> Result<V::Value, Error> {
+        let mut access = ElementsAccess {
+            bytes: self.bytes,
+            elements,
+        };
+        let value = visitor.visit_seq(&mut access)?;
+        let remaining = access.elements.len();
+        if remaining > 0 {
+            return Err(Error(format!(
+                "{remaining} element(s) were not deserialized"
+            )));
+        }
+        Ok(value)
+    }
+}
+
+impl<'a, 't, 'de> de::Deserializer<'de> for ValueDeserializer<'a, 't, 'de> {
+    type Error = Error;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        if !self.is_some && is_nullable(self.ty) {
+            return self.deserialize_option(visitor);
+        }
+
+        match self.ty {
+            Type::UInt8 | Type::NullableUInt8 => visitor.visit_u8(self.read(1)?[0]),
+            Type::UInt16 | Type::NullableUInt16 | Type::Date | Type::NullableDate => {
+                visitor.visit_u16(u16::from_le_bytes(self.read_array()?))
+            }
+            Type::UInt32 | Type::NullableUInt32 | Type::DateTime(_) | Type::NullableDateTime(_) => {
+                visitor.visit_u32(u32::from_le_bytes(self.read_array()?))
+            }
+            Type::UInt64 | Type::NullableUInt64 => {
+                visitor.visit_u64(u64::from_le_bytes(self.read_array()?))
+            }
+            Type::UInt128 | Type::NullableUInt128 => {
+                visitor.visit_u128(u128::from_le_bytes(self.read_array()?))
+            }
+            Type::Int8 | Type::NullableInt8 | Type::Enum8(_) | Type::NullableEnum8(_) => {
+                visitor.visit_i8(i8::from_le_bytes(self.read_array()?))
+            }
+            Type::Int16 | Type::NullableInt16 | Type::Enum16(_) | Type::NullableEnum16(_) => {
+                visitor.visit_i16(i16::from_le_bytes(self.read_array()?))
+            }
+            Type::Int32
+            | Type::NullableInt32
+            | Type::Date32
+            | Type::NullableDate32
+            | Type::Time
+            | Type::NullableTime => visitor.visit_i32(i32::from_le_bytes(self.read_array()?)),
+            Type::Int64
+            | Type::NullableInt64
+            | Type::DateTime64(..)
+            | Type::NullableDateTime64(..)
+            | Type::Time64(_)
+            | Type::NullableTime64(_)
+            | Type::Interval(_)
+            | Type::NullableInterval(_) => {
+                visitor.visit_i64(i64::from_le_bytes(self.read_array()?))
+            }
+            Type::Int128 | Type::NullableInt128 => {
+                visitor.visit_i128(i128::from_le_bytes(self.read_array()?))
+            }
+            // NB: 256-bit integers are passed as their 32 bytes (little endian)
+            Type::UInt256 | Type::NullableUInt256 | Type::Int256 | Type::NullableInt256 => {
+                visitor.visit_borrowed_bytes(self.read(32)?)
+            }
+            Type::Float32 | Type::NullableFloat32 => {
+                visitor.visit_f32(f32::from_le_bytes(self.read_array()?))
+            }
+            Type::Float64 | Type::NullableFloat64 => {
+                visitor.visit_f64(f64::from_le_bytes(self.read_array()?))
+            }
+            Type::BFloat16 | Type::NullableBFloat16 => {
+                let bits = u16::from_le_bytes(self.read_array()?);
+                visitor.visit_f32(f32::from_bits(u32::from(bits) << 16))
+            }
+            Type::Bool | Type::NullableBool => match self.read(1)? {
+                [0x00] => visitor.visit_bool(false),
+                [0x01] => visitor.visit_bool(true),
+                _ => Err(Error::new("Invalid bool value")),
+            },
+            Type::String | Type::NullableString => {
+                let len = self.read_len()?;
+                let buf = self.read(len)?;
+                match std::str::from_utf8(buf) {
+                    Ok(s) => visitor.visit_borrowed_str(s),
+                    Err(_) => visitor.visit_borrowed_bytes(buf),
+                }
+            }
+            Type::FixedString(n) | Type::NullableFixedString(n) => {
+                visitor.visit_borrowed_bytes(self.read(*n)?)
+            }
+            Type::UUID | Type::NullableUUID => {
+                let uuid = self.read_uuid()?;
+                visitor.visit_str(uuid.hyphenated().encode_lower(&mut Uuid::encode_buffer()))
+            }
+            Type::Array(ty) => {
+                let len = self.read_len()?;
+                self.visit_elements(visitor, Elements::Repeat(ty, len))
+            }
+            Type::Tuple(types) => self.visit_elements(visitor, Elements::Types(types.iter())),
+            Type::NamedTuple(elements) => {
+                self.visit_elements(visitor, Elements::Named(elements.iter()))
+            }
+            Type::Map(key_ty, val_ty) => {
+                let len = self.read_len()?;
+                let mut access = MapAccess {
+                    bytes: self.bytes,
+                    key_ty,
+                    val_ty,
+                    remaining: len,
+                };
+                let value = visitor.visit_map(&mut access)?;
+                if access.remaining > 0 {
+                    return Err(Error(format!(
+                        "{} map entries were not deserialized",
+                        access.remaining
+                    )));
+                }
+                Ok(value)
+            }
+            ty => Err(Error(format!("Cannot deserialize a {ty} column"))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        if self.is_some || !is_nullable(self.ty) {
+            // NB: a non-nullable column is read as an option with a value
+            return visitor.visit_some(self);
+        }
+        if self.read_null_flag()? {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(ValueDeserializer {
+                bytes: self.bytes,
+                ty: self.ty,
+                is_some: true,
+            })
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        match self.ty {
+            // NB: an enum value is read as its variant name
+            Type::Enum8(_) | Type::NullableEnum8(_) | Type::Enum16(_) | Type::NullableEnum16(_)
+                if self.is_some || !is_nullable(self.ty) =>
+            {
+                visitor.visit_str(self.read_enum_name()?)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        match self.ty {
+            // NB: a UUID is read as its 16 bytes (a string otherwise)
+            Type::UUID | Type::NullableUUID if self.is_some || !is_nullable(self.ty) => {
+                visitor.visit_bytes(self.read_uuid()?.as_bytes())
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        mut self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if !self.is_some && is_nullable(self.ty) {
+            return Err(Error(format!(
+                "Cannot deserialize a {} column as an enum (use an Option)",
+                self.ty
+            )));
+        }
+        // NB: an enum value is mapped to the unit variant with the same name
+        let name = self.read_enum_name()?;
+        visitor.visit_enum(name.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char unit
+        unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Types of the elements of a compound value
+enum Elements<'t> {
+    /// Array elements (type and number of remaining elements)
+    Repeat(&'t Type, usize),
+    /// Tuple elements
+    Types(std::slice::Iter<'t, Type>),
+    /// Named tuple elements
+    Named(std::slice::Iter<'t, (String, Type)>),
+}
+
+impl<'t> Elements<'t> {
+    /// Returns the type of the next element
+    fn next(&mut self) -> Option<&'t Type> {
+        match self {
+            Elements::Repeat(ty, remaining) => {
+                if *remaining == 0 {
+                    return None;
+                }
+                *remaining -= 1;
+                Some(*ty)
+            }
+            Elements::Types(types) => types.next(),
+            Elements::Named(elements) => elements.next().map(|(_, ty)| ty),
+        }
+    }
+
+    /// Returns the number of remaining elements
+    fn len(&self) -> usize {
+        match self {
+            Elements::Repeat(_, remaining) => *remaining,
+            Elements::Types(types) => types.len(),
+            Elements::Named(elements) => elements.len(),
+        }
+    }
+}
+
+/// Access to the elements of an array or a tuple
+struct ElementsAccess<'a, 't, 'de> {
+    /// Remaining bytes
+    bytes: &'a mut &'de [u8],
+    /// Element types
+    elements: Elements<'t>,
+}
+
+impl<'a, 't, 'de> de::SeqAccess<'de> for ElementsAccess<'a, 't, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.elements.next() {
+            Some(ty) => seed
+                .deserialize(ValueDeserializer::new(self.bytes, ty))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
+
+/// Access to the entries of a map
+struct MapAccess<'a, 't, 'de> {
+    /// Remaining bytes
+    bytes: &'a mut &'de [u8],
+    /// Key type
+    key_ty: &'t Type,
+    /// Value type
+    val_ty: &'t Type,
+    /// Number of remaining entries
+    remaining: usize,
+}
+
+impl<'a, 't, 'de> de::MapAccess<'de> for MapAccess<'a, 't, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(ValueDeserializer::new(self.bytes, self.key_ty))
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        seed.deserialize(ValueDeserializer::new(self.bytes, self.val_ty))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
@@ -1,7 +1,6 @@
 //! RowBin format
 
 use std::{
-    collections::HashMap,
     io::{Read, Write},
     str::FromStr,
 };
@@ -11,22 +10,65 @@ use uuid::Uuid;
 
 use crate::{
     error::Error,
-    query::QueryData,
+    query::{flatten_nested_mapping, QueryData},
     value::{Type, Value},
 };
 
 use super::Formatter;
 
+#[cfg(feature = "serde")]
+mod cursor;
+#[cfg(feature = "serde")]
+mod de;
+#[cfg(feature = "serde")]
+mod ser;
 #[cfg(test)]
 mod tests;
 
+#[cfg(feature = "serde")]
+pub use cursor::*;
+#[cfg(feature = "serde")]
+pub use de::*;
+#[cfg(feature = "serde")]
+pub use ser::*;
+
+/// Discriminator of a NULL `Variant` value
+const NULL_DISCRIMINATOR: u8 = 0xFF;
+
+/// Binary type of a NULL `Dynamic` value (`Nothing`)
+const NULL_DYNAMIC_TYPE: u8 = 0x00;
+
+/// Prefix of a column default value (`RowBinaryWithDefaults`)
+const DEFAULT_PREFIX: u8 = 0x01;
+
+/// Prefix of a value (`RowBinaryWithDefaults`)
+const VALUE_PREFIX: u8 = 0x00;
+
 /// RowBinary formatter
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct RowBinFormatter {
     /// Has column names
     with_names: bool,
     /// With column types
     with_types: bool,
+    /// Values are prefixed with a default flag
+    with_defaults: bool,
+    /// Nested columns are flattened
+    flatten_nested: bool,
+    /// Invalid UTF-8 strings are converted lossily
+    utf8_lossy: bool,
+}
+
+impl Default for RowBinFormatter {
+    fn default() -> Self {
+        Self {
+            with_names: false,
+            with_types: false,
+            with_defaults: false,
+            flatten_nested: true,
+            utf8_lossy: false,
+        }
+    }
 }
 
 impl RowBinFormatter {
@@ -39,7 +81,7 @@ impl RowBinFormatter {
     pub fn with_names() -> Self {
         Self {
             with_names: true,
-            with_types: false,
+            ..Self::default()
         }
     }
 
@@ -48,12 +90,41 @@ impl RowBinFormatter {
         Self {
             with_names: true,
             with_types: true,
+            ..Self::default()
+        }
+    }
+
+    /// Creates a new [RowBinaryFormatter] with defaults (`RowBinaryWithDefaults`)
+    ///
+    /// Each value is prefixed with a flag, and [Value::Default] values are filled by the server
+    /// with the column default (eg. `DEFAULT now()`).
+    pub fn with_defaults() -> Self {
+        Self {
+            with_defaults: true,
+            ..Self::default()
         }
     }
+
+    /// Sets if `Nested` columns are flattened (`true` by default)
+    ///
+    /// This must match the Clickhouse setting `flatten_nested`.
+    pub fn flatten_nested(mut self, flatten: bool) -> Self {
+        self.flatten_nested = flatten;
+        self
+    }
+
+    /// Sets if strings which are not valid UTF-8 are parsed lossily (`false` by default)
+    ///
+    /// By default, they are parsed as [Value::Bytes]. Otherwise, they are parsed as
+    /// [Value::String], with the invalid sequences replaced with `U+FFFD`.
+    pub fn utf8_lossy(mut self, lossy: bool) -> Self {
+        self.utf8_lossy = lossy;
+        self
+    }
 }
 
 impl Formatter for RowBinFormatter {
-    fn serialize_value(&self, value: Value) -> Vec<u8> {
+    fn serialize_value(&self, value: Value) -> Result<Vec<u8>, Error> {
         self.format_value(value)
     }
 
@@ -83,14 +154,14 @@ impl Formatter for RowBinFormatter {
 impl RowBinFormatter {
     /// Formats a value
     #[allow(clippy::only_used_in_recursion)]
-    fn format_value(&self, value: Value) -> Vec<u8> {
+    pub(super) fn format_value(&self, value: Value) -> Result<Vec<u8>, Error> {
         /// Implements the nullable variant for formatting
         macro_rules! impl_nullable {
-            ($VAL:tt, $VAR:ident) => {
+            ($VAL:tt, $VAR:ident $(, $ARG:expr)*) => {
                 match $VAL {
                     Some(v) => {
                         let mut buf = vec![0x00];
-                        let mut bytes = self.format_value(Value::$VAR(v));
+                        let mut bytes = self.format_value(Value::$VAR(v $(, $ARG)*))?;
                         buf.append(&mut bytes);
                         buf
                     }
@@ -99,7 +170,7 @@ impl RowBinFormatter {
             };
         }
 
-        match value {
+        Ok(match value {
             Value::UInt8(v) => v.to_le_bytes().to_vec(),
             Value::UInt16(v) => v.to_le_bytes().to_vec(),
             Value::UInt32(v) => v.to_le_bytes().to_vec(),
@@ -120,6 +191,8 @@ impl RowBinFormatter {
             }
             Value::Float32(v) => v.to_le_bytes().to_vec(),
             Value::Float64(v) => v.to_le_bytes().to_vec(),
+            // NB: a bf16 is the upper half of a f32 (the lower bits are truncated)
+            Value::BFloat16(v) => ((v.to_bits() >> 16) as u16).to_le_bytes().to_vec(),
             Value::Bool(v) => {
                 if v {
                     vec![0x01]
@@ -133,6 +206,14 @@ impl RowBinFormatter {
                 buf.write_all(v.as_bytes()).unwrap();
                 buf
             }
+            Value::Bytes(v) => {
+                let mut buf = vec![];
+                leb128::write::unsigned(&mut buf, v.len() as u64).unwrap();
+                buf.write_all(&v).unwrap();
+                buf
+            }
+            // NB: the length is validated against the column type when the value is conformed
+            Value::FixedString(v) => v,
             Value::UUID(v) => {
                 // NB: in RowBinary, the UUID is represented as 2 u64 in little endian
                 let (w1, w2) = Uuid::from_bytes(v).as_u64_pair();
@@ -142,42 +223,65 @@ impl RowBinFormatter {
             }
             Value::Date(v) => v.to_le_bytes().to_vec(),
             Value::Date32(v) => v.to_le_bytes().to_vec(),
-            Value::DateTime(v) => v.to_le_bytes().to_vec(),
-            Value::DateTime64(v) => v.to_le_bytes().to_vec(),
-            Value::Enum8(v) => v.to_le_bytes().to_vec(),
-            Value::Enum16(v) => v.to_le_bytes().to_vec(),
+            Value::DateTime(v, _) => v.to_le_bytes().to_vec(),
+            Value::DateTime64(v, _, _) => v.to_le_bytes().to_vec(),
+            Value::Time(v) => v.to_le_bytes().to_vec(),
+            Value::Time64(v, _) => v.to_le_bytes().to_vec(),
+            Value::Interval(v, _) => v.to_le_bytes().to_vec(),
+            Value::Enum8(v, _) => v.to_le_bytes().to_vec(),
+            Value::Enum16(v, _) => v.to_le_bytes().to_vec(),
             Value::Array(v) => {
                 let mut buf = vec![];
                 leb128::write::unsigned(&mut buf, v.len() as u64).unwrap();
-                let values = v
-                    .into_iter()
-                    .flat_map(|value| self.format_value(value))
-                    .collect::<Vec<_>>();
-                buf.write_all(&values).unwrap();
+                for value in v {
+                    buf.append(&mut self.format_value(value)?);
+                }
                 buf
             }
             Value::Tuple(v) => v
                 .into_iter()
-                .flat_map(|value| self.format_value(value))
-                .collect::<Vec<_>>(),
+                .map(|value| self.format_value(value))
+                .collect::<Result<Vec<_>, Error>>()?
+                .concat(),
+            Value::NamedTuple(v) => v
+                .into_iter()
+                .map(|(_, value)| self.format_value(value))
+                .collect::<Result<Vec<_>, Error>>()?
+                .concat(),
             Value::Map(map) => {
                 let mut buf = vec![];
                 leb128::write::unsigned(&mut buf, map.len() as u64).unwrap();
                 for (k, v) in map {
-                    let mut key_bytes = self.format_value(Value::String(k));
-                    let mut val_bytes = self.format_value(v);
+                    let mut key_bytes = self.format_value(k)?;
+                    let mut val_bytes = self.format_value(v)?;
                     buf.append(&mut key_bytes);
                     buf.append(&mut val_bytes);
                 }
                 buf
             }
-            Value::Nested(fields) => {
+            // NB: a single nested value is written as Array(Tuple(..))
+            Value::Nested(fields) => self.format_value(Value::nested_into_tuples(fields)?)?,
+            Value::Variant(Some((i, v))) => {
+                let mut buf = vec![i];
+                buf.append(&mut self.format_value(*v)?);
+                buf
+            }
+            Value::Variant(None) => vec![NULL_DISCRIMINATOR],
+            // NB: a dynamic value is prefixed with its binary type
+            Value::Dynamic(Some((ty, v))) => {
+                let mut buf = ty.to_binary()?;
+                buf.append(&mut self.format_value(*v)?);
+                buf
+            }
+            Value::Dynamic(None) => vec![NULL_DYNAMIC_TYPE],
+            // NB: the state is written as is (it is not prefixed with its length)
+            Value::AggregateFunction(v) => v,
+            Value::Json(values) => {
                 let mut buf = vec![];
-                for (k, v) in fields {
-                    let mut key_bytes = self.format_value(Value::String(k));
-                    let mut val_bytes = self.format_value(v);
-                    buf.append(&mut key_bytes);
-                    buf.append(&mut val_bytes);
+                leb128::write::unsigned(&mut buf, values.len() as u64).unwrap();
+                for (path, v) in values {
+                    buf.append(&mut self.format_value(Value::String(path))?);
+                    buf.append(&mut self.format_value(v)?);
                 }
                 buf
             }
@@ -195,21 +299,38 @@ impl RowBinFormatter {
             Value::NullableInt256(v) => impl_nullable!(v, Int256),
             Value::NullableFloat32(v) => impl_nullable!(v, Float32),
             Value::NullableFloat64(v) => impl_nullable!(v, Float64),
+            Value::NullableBFloat16(v) => impl_nullable!(v, BFloat16),
             Value::NullableBool(v) => impl_nullable!(v, Bool),
             Value::NullableString(v) => impl_nullable!(v, String),
+            Value::NullableBytes(v) => impl_nullable!(v, Bytes),
+            Value::NullableFixedString(v) => impl_nullable!(v, FixedString),
             Value::NullableUUID(v) => impl_nullable!(v, UUID),
             Value::NullableDate(v) => impl_nullable!(v, Date),
             Value::NullableDate32(v) => impl_nullable!(v, Date32),
-            Value::NullableDateTime(v) => impl_nullable!(v, DateTime),
-            Value::NullableDateTime64(v) => impl_nullable!(v, DateTime64),
-            Value::NullableEnum8(v) => impl_nullable!(v, Enum8),
-            Value::NullableEnum16(v) => impl_nullable!(v, Enum16),
-        }
+            Value::NullableDateTime(v, tz) => impl_nullable!(v, DateTime, tz),
+            Value::NullableDateTime64(v, p, tz) => impl_nullable!(v, DateTime64, p, tz),
+            Value::NullableTime(v) => impl_nullable!(v, Time),
+            Value::NullableTime64(v, p) => impl_nullable!(v, Time64, p),
+            Value::NullableInterval(v, kind) => impl_nullable!(v, Interval, kind),
+            Value::NullableEnum8(v, name) => impl_nullable!(v, Enum8, name),
+            Value::NullableEnum16(v, name) => impl_nullable!(v, Enum16, name),
+            Value::Null => vec![0x01],
+            // NB: a default value is only written as a prefix of a top-level cell (see `format_data`)
+            Value::Default => {
+                return Err(Error::new(
+                    "Default values are only valid as top-level cells",
+                ))
+            }
+        })
     }
 
     /// Formats a table
     fn format_data(&self, data: QueryData) -> Result<Vec<u8>, Error> {
         let mut buf = vec![];
+        let data = match self.flatten_nested {
+            true => data.flatten_nested()?,
+            false => data,
+        };
         let parts = data.into_parts();
 
         // column names
@@ -217,7 +338,7 @@ impl RowBinFormatter {
             if let Some(names) = parts.names {
                 leb128::write::unsigned(&mut buf, names.len().try_into()?).unwrap();
                 for name in names {
-                    let bytes = self.format_value(Value::String(name));
+                    let bytes = self.format_value(Value::String(name))?;
                     buf.write_all(&bytes)?;
                 }
             } else {
@@ -227,11 +348,11 @@ impl RowBinFormatter {
 
         // column types
         if self.with_types {
-            if let Some(types) = parts.types {
-                let types = types.into_iter().map(|t| t.to_string()).collect::<Vec<_>>();
+            if let Some(types) = &parts.types {
+                let types = types.iter().map(|t| t.to_string()).collect::<Vec<_>>();
                 leb128::write::unsigned(&mut buf, types.len().try_into()?).unwrap();
                 for ty in types {
-                    let bytes = self.format_value(Value::String(ty));
+                    let bytes = self.format_value(Value::String(ty))?;
                     buf.write_all(&bytes)?;
                 }
             } else {
@@ -240,8 +361,25 @@ impl RowBinFormatter {
         }
 
         for row in parts.rows {
-            for value in row {
-                let bytes = self.format_value(value);
+            for (i, value) in row.into_iter().enumerate() {
+                if let Value::Default = value {
+                    if !self.with_defaults {
+                        return Err(Error::new(
+                            "Default values require the RowBinaryWithDefaults format",
+                        ));
+                    }
+                    buf.push(DEFAULT_PREFIX);
+                    continue;
+                }
+                if self.with_defaults {
+                    buf.push(VALUE_PREFIX);
+                }
+                // NB: values are conformed to the column types (eg DateTime64 precision)
+                let value = match parts.types.as_ref().and_then(|types| types.get(i)) {
+                    Some(ty) => value.conform_to(ty)?,
+                    None => value,
+                };
+                let bytes = self.format_value(value)?;
                 buf.write_all(&bytes)?;
             }
         }
@@ -250,14 +388,14 @@ impl RowBinFormatter {
     }
 
     /// Parses a value
-    fn parse_value(&self, bytes: &mut &[u8], ty: Type) -> Result<Value, Error> {
+    pub(super) fn parse_value(&self, bytes: &mut &[u8], ty: Type) -> Result<Value, Error> {
         /// Implements the nullable variant for parsing
         macro_rules! impl_nullable {
-            ($NULL_TY:tt, $TY:expr) => {{
+            ($NULL_TY:tt, $TY:expr $(, $ARG:expr)*) => {{
                 let mut buf = [0x00_u8; 1];
                 bytes.read_exact(&mut buf)?;
                 match buf {
-                    [0x01] => Ok(Value::$NULL_TY(None)),
+                    [0x01] => Ok(Value::$NULL_TY(None $(, $ARG)*)),
                     [0x00] => match self.parse_value(bytes, $TY)?.into_nullable() {
                         Some(v) => Ok(v),
                         None => Err(Error::new("Invalid nullable value")),
@@ -352,6 +490,12 @@ impl RowBinFormatter {
                 let v = f64::from_le_bytes(buf);
                 Ok(Value::Float64(v))
             }
+            Type::BFloat16 => {
+                let mut buf = [0x00_u8; 2];
+                bytes.read_exact(&mut buf)?;
+                let v = f32::from_bits(u32::from(u16::from_le_bytes(buf)) << 16);
+                Ok(Value::BFloat16(v))
+            }
             Type::Decimal(_, _) => {
                 unimplemented!("RowBinary format Decimal")
             }
@@ -380,14 +524,12 @@ impl RowBinFormatter {
                 let n: usize = leb128::read::unsigned(bytes)?.try_into()?;
                 let mut buf = vec![0x00_u8; n];
                 bytes.read_exact(&mut buf)?;
-                let s = String::from_utf8(buf)?;
-                Ok(Value::String(s))
+                Ok(Value::from_string_bytes(buf, self.utf8_lossy))
             }
             Type::FixedString(n) => {
-                let mut buf = vec![0x00_u8; n.into()];
+                let mut buf = vec![0x00_u8; n];
                 bytes.read_exact(&mut buf)?;
-                let s = String::from_utf8(buf)?;
-                Ok(Value::String(s))
+                Ok(Value::FixedString(buf))
             }
             Type::UUID => {
                 // NB: in RowBinary, the UUID is represented as 2 u64 in little endian
@@ -411,29 +553,50 @@ impl RowBinFormatter {
                 let v = i32::from_le_bytes(buf);
                 Ok(Value::Date32(v))
             }
-            Type::DateTime => {
+            Type::DateTime(tz) => {
                 let mut buf = [0x00_u8; 4];
                 bytes.read_exact(&mut buf)?;
                 let v = u32::from_le_bytes(buf);
-                Ok(Value::DateTime(v))
+                Ok(Value::DateTime(v, tz))
+            }
+            Type::DateTime64(p, tz) => {
+                let mut buf = [0x00_u8; 8];
+                bytes.read_exact(&mut buf)?;
+                let v = i64::from_le_bytes(buf);
+                Ok(Value::DateTime64(v, p, tz))
+            }
+            Type::Time => {
+                let mut buf = [0x00_u8; 4];
+                bytes.read_exact(&mut buf)?;
+                let v = i32::from_le_bytes(buf);
+                Ok(Value::Time(v))
             }
-            Type::DateTime64(_) => {
+            Type::Time64(p) => {
                 let mut buf = [0x00_u8; 8];
                 bytes.read_exact(&mut buf)?;
                 let v = i64::from_le_bytes(buf);
-                Ok(Value::DateTime64(v))
+                Ok(Value::Time64(v, p))
             }
+            Type::Interval(kind) => {
+                let mut buf = [0x00_u8; 8];
+                bytes.read_exact(&mut buf)?;
+                let v = i64::from_le_bytes(buf);
+                Ok(Value::Interval(v, kind))
+            }
+            Type::Nothing => Err(Error::new("Cannot parse a Nothing value")),
             Type::Enum8(_) => {
                 let mut buf = [0x00_u8; 1];
                 bytes.read_exact(&mut buf)?;
                 let v = i8::from_le_bytes(buf);
-                Ok(Value::Enum8(v))
+                let name = ty.enum_variant_name(v.into()).map(String::from);
+                Ok(Value::Enum8(v, name))
             }
             Type::Enum16(_) => {
                 let mut buf = [0x00_u8; 2];
                 bytes.read_exact(&mut buf)?;
                 let v = i16::from_le_bytes(buf);
-                Ok(Value::Enum16(v))
+                let name = ty.enum_variant_name(v).map(String::from);
+                Ok(Value::Enum16(v, name))
             }
             Type::Array(ty) => {
                 let mut values = vec![];
@@ -452,24 +615,80 @@ impl RowBinFormatter {
                 }
                 Ok(Value::Tuple(values))
             }
-            Type::Map(_ty_key, ty_val) => {
-                let mut map = HashMap::new();
+            Type::NamedTuple(elements) => {
+                let mut values = vec![];
+                for (name, ty) in elements {
+                    let value = self.parse_value(bytes, ty)?;
+                    values.push((name, value));
+                }
+                Ok(Value::NamedTuple(values))
+            }
+            Type::Map(ty_key, ty_val) => {
+                let mut map = vec![];
                 let n = leb128::read::unsigned(bytes)?;
                 for _ in 0..n {
-                    let key = self.parse_value_str(bytes)?;
+                    let key = self.parse_value(bytes, (*ty_key).clone())?;
                     let value = self.parse_value(bytes, (*ty_val).clone())?;
-                    map.insert(key, value);
+                    map.push((key, value));
                 }
                 Ok(Value::Map(map))
             }
+            Type::LowCardinality(ty) => self.parse_value(bytes, *ty),
+            Type::SimpleAggregateFunction(_, ty) => self.parse_value(bytes, *ty),
+            // NB: the length of a state depends on the function, it cannot be parsed as opaque bytes
+            Type::AggregateFunction(func, _) => Err(Error::new(
+                format!(
+                    "Cannot parse an AggregateFunction({func}) state in RowBinary \
+                    (use finalizeAggregation or the TabSeparated format)"
+                )
+                .as_str(),
+            )),
             Type::Nested(fields) => {
-                let mut map = HashMap::new();
-                for (_name, ty) in fields {
-                    let key = self.parse_value_str(bytes)?;
-                    let value = self.parse_value(bytes, ty)?;
-                    map.insert(key, value);
+                let (names, types) = fields.into_iter().unzip();
+                let value = self.parse_value(bytes, Type::Array(Box::new(Type::Tuple(types))))?;
+                Value::nested_from_tuples(names, value)
+            }
+            Type::Variant(_) => {
+                let mut buf = [0x00_u8; 1];
+                bytes.read_exact(&mut buf)?;
+                match buf[0] {
+                    NULL_DISCRIMINATOR => Ok(Value::Variant(None)),
+                    i => {
+                        let var_ty = ty
+                            .variant_types()
+                            .and_then(|types| types.get(i as usize).map(|ty| (*ty).clone()))
+                            .ok_or(Error::new("Invalid variant discriminator"))?;
+                        let value = self.parse_value(bytes, var_ty)?;
+                        Ok(Value::Variant(Some((i, Box::new(value)))))
+                    }
                 }
-                Ok(Value::Nested(map))
+            }
+            Type::Dynamic(_) => match bytes.first() {
+                Some(&NULL_DYNAMIC_TYPE) => {
+                    *bytes = &bytes[1..];
+                    Ok(Value::Dynamic(None))
+                }
+                _ => {
+                    let dyn_ty = Type::from_binary(bytes)?;
+                    let value = self.parse_value(bytes, dyn_ty.clone())?;
+                    Ok(Value::Dynamic(Some((dyn_ty, Box::new(value)))))
+                }
+            },
+            // NB: typed paths have their type, other paths are dynamic
+            Type::Json(paths, _) => {
+                let mut values = vec![];
+                let n = leb128::read::unsigned(bytes)?;
+                for _ in 0..n {
+                    let path = self.parse_value_str(bytes)?;
+                    let path_ty = paths
+                        .iter()
+                        .find(|(p, _)| *p == path)
+                        .map(|(_, ty)| ty.clone())
+                        .unwrap_or(Type::Dynamic(None));
+                    let value = self.parse_value(bytes, path_ty)?;
+                    values.push((path, value));
+                }
+                Ok(Value::Json(values))
             }
             Type::NullableUInt8 => impl_nullable!(NullableUInt8, Type::UInt8),
             Type::NullableUInt16 => impl_nullable!(NullableUInt16, Type::UInt16),
@@ -485,6 +704,7 @@ impl RowBinFormatter {
             Type::NullableInt256 => impl_nullable!(NullableInt256, Type::Int256),
             Type::NullableFloat32 => impl_nullable!(NullableFloat32, Type::Float32),
             Type::NullableFloat64 => impl_nullable!(NullableFloat64, Type::Float64),
+            Type::NullableBFloat16 => impl_nullable!(NullableBFloat16, Type::BFloat16),
             Type::NullableDecimal(_, _) => unimplemented!("RowBinary format Decimal"),
             Type::NullableDecimal32(_) => unimplemented!("RowBinary format Decimal32"),
             Type::NullableDecimal64(_) => unimplemented!("RowBinary format Decimal64"),
@@ -492,16 +712,49 @@ impl RowBinFormatter {
             Type::NullableDecimal256(_) => unimplemented!("RowBinary format Decimal256"),
             Type::NullableBool => impl_nullable!(NullableBool, Type::Bool),
             Type::NullableString => impl_nullable!(NullableString, Type::String),
-            Type::NullableFixedString(n) => impl_nullable!(NullableString, Type::FixedString(n)),
+            Type::NullableFixedString(n) => {
+                impl_nullable!(NullableFixedString, Type::FixedString(n))
+            }
             Type::NullableUUID => impl_nullable!(NullableUUID, Type::UUID),
             Type::NullableDate => impl_nullable!(NullableDate, Type::Date),
             Type::NullableDate32 => impl_nullable!(NullableDate32, Type::Date32),
-            Type::NullableDateTime => impl_nullable!(NullableDateTime, Type::DateTime),
-            Type::NullableDateTime64(p) => impl_nullable!(NullableDateTime64, Type::DateTime64(p)),
-            Type::NullableEnum8(variants) => impl_nullable!(NullableEnum8, Type::Enum8(variants)),
+            Type::NullableDateTime(tz) => {
+                impl_nullable!(NullableDateTime, Type::DateTime(tz), tz)
+            }
+            Type::NullableDateTime64(p, tz) => {
+                impl_nullable!(NullableDateTime64, Type::DateTime64(p, tz), p, tz)
+            }
+            Type::NullableTime => impl_nullable!(NullableTime, Type::Time),
+            Type::NullableTime64(p) => impl_nullable!(NullableTime64, Type::Time64(p), p),
+            Type::NullableInterval(kind) => {
+                impl_nullable!(NullableInterval, Type::Interval(kind), kind)
+            }
+            Type::NullableEnum8(variants) => {
+                impl_nullable!(NullableEnum8, Type::Enum8(variants), None)
+            }
             Type::NullableEnum16(variants) => {
-                impl_nullable!(NullableEnum16, Type::Enum16(variants))
+                impl_nullable!(NullableEnum16, Type::Enum16(variants), None)
             }
+            // NB: a Nullable(Nothing) value is always NULL
+            Type::NullableNothing => {
+                let mut buf = [0x00_u8; 1];
+                bytes.read_exact(&mut buf)?;
+                match buf {
+                    [0x01] => Ok(Value::Null),
+                    _ => Err(Error::new("Invalid nullable value")),
+                }
+            }
+        }
+    }
+
+    /// Parses a value prefixed with the default flag
+    fn parse_default_value(&self, bytes: &mut &[u8], ty: Type) -> Result<Value, Error> {
+        let mut buf = [0x00_u8; 1];
+        bytes.read_exact(&mut buf)?;
+        match buf[0] {
+            DEFAULT_PREFIX => Ok(Value::Default),
+            VALUE_PREFIX => self.parse_value(bytes, ty),
+            _ => Err(Error::new("Invalid default prefix")),
         }
     }
 
@@ -546,10 +799,19 @@ impl RowBinFormatter {
             QueryData::no_headers()
         };
 
+        // nested columns are received as 1 array column per nested column
+        let flat_mapping = mapping.map(|mapping| match self.flatten_nested {
+            true => flatten_nested_mapping(mapping),
+            false => mapping
+                .iter()
+                .map(|(n, t)| (n.to_string(), t.clone()))
+                .collect(),
+        });
+
         // parse rows from the buffer
         let types = if let Some(types) = data.get_types() {
             types
-        } else if let Some(mapping) = mapping {
+        } else if let Some(mapping) = &flat_mapping {
             mapping.iter().map(|(_, t)| t.clone()).collect()
         } else {
             return Err(Error::new("Deserializing data requires a mapping table"));
@@ -560,12 +822,18 @@ impl RowBinFormatter {
             // loop on each columns
             let mut row = vec![];
             for ty in &types {
-                let value = self.parse_value(bytes, ty.clone())?;
+                let value = match self.with_defaults {
+                    true => self.parse_default_value(bytes, ty.clone())?,
+                    false => self.parse_value(bytes, ty.clone())?,
+                };
                 row.push(value);
             }
             data.add_row(row);
         }
 
-        Ok(data)
+        match mapping {
+            Some(mapping) if self.flatten_nested => data.unflatten_nested(mapping),
+            _ => Ok(data),
+        }
     }
 }
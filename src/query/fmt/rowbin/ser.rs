@@ -0,0 +1,588 @@
+//! RowBinary serde serializer
+
+use serde::{
+    ser::{self, Impossible},
+    Serialize,
+};
+use uuid::Uuid;
+
+use crate::{error::Error, value::Type};
+
+/// RowBinary serializer of serde rows
+///
+/// Each row (a struct or a tuple) is written to the buffer directly, without going through
+/// [Value](crate::value::Value). The fields are mapped to the columns by position, and each
+/// field is checked against its column type.
+#[derive(Debug)]
+pub struct RowBinSerializer {
+    /// Row type (a tuple of the column types)
+    row_ty: Type,
+    /// Buffer
+    buf: Vec<u8>,
+}
+
+impl RowBinSerializer {
+    /// Creates a new [RowBinSerializer] for the column types
+    pub fn new(types: &[Type]) -> Self {
+        Self {
+            row_ty: Type::Tuple(types.to_vec()),
+            buf: vec![],
+        }
+    }
+
+    /// Serializes a row
+    ///
+    /// NB: if the row is invalid, nothing is written to the buffer
+    pub fn serialize_row<T: Serialize>(&mut self, row: &T) -> Result<(), Error> {
+        let len = self.buf.len();
+        let res = row.serialize(ValueSerializer::new(&mut self.buf, &self.row_ty));
+        if res.is_err() {
+            self.buf.truncate(len);
+        }
+        res
+    }
+
+    /// Returns the serialized rows
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Serializes rows to RowBinary
+pub fn to_rowbin<T: Serialize>(rows: &[T], types: &[Type]) -> Result<Vec<u8>, Error> {
+    let mut serializer = RowBinSerializer::new(types);
+    for row in rows {
+        serializer.serialize_row(row)?;
+    }
+    Ok(serializer.into_bytes())
+}
+
+/// Returns the error for a value which does not match the column type
+fn mismatch(what: &str, ty: &Type) -> Error {
+    Error(format!("Cannot serialize {what} to a {ty} column"))
+}
+
+/// Returns the type with the transparent wrappers removed
+pub(super) fn unwrap_type(mut ty: &Type) -> &Type {
+    while let Type::LowCardinality(inner) | Type::SimpleAggregateFunction(_, inner) = ty {
+        ty = inner;
+    }
+    ty
+}
+
+/// Checks if a type is nullable
+pub(super) fn is_nullable(ty: &Type) -> bool {
+    matches!(
+        ty,
+        Type::NullableUInt8
+            | Type::NullableUInt16
+            | Type::NullableUInt32
+            | Type::NullableUInt64
+            | Type::NullableUInt128
+            | Type::NullableUInt256
+            | Type::NullableInt8
+            | Type::NullableInt16
+            | Type::NullableInt32
+            | Type::NullableInt64
+            | Type::NullableInt128
+            | Type::NullableInt256
+            | Type::NullableFloat32
+            | Type::NullableFloat64
+            | Type::NullableBFloat16
+            | Type::NullableDecimal(..)
+            | Type::NullableDecimal32(_)
+            | Type::NullableDecimal64(_)
+            | Type::NullableDecimal128(_)
+            | Type::NullableDecimal256(_)
+            | Type::NullableBool
+            | Type::NullableString
+            | Type::NullableFixedString(_)
+            | Type::NullableUUID
+            | Type::NullableDate
+            | Type::NullableDate32
+            | Type::NullableDateTime(_)
+            | Type::NullableDateTime64(..)
+            | Type::NullableTime
+            | Type::NullableTime64(_)
+            | Type::NullableInterval(_)
+            | Type::NullableEnum8(_)
+            | Type::NullableEnum16(_)
+            | Type::NullableNothing
+    )
+}
+
+/// Serializer of a single value, checked against its column type
+struct ValueSerializer<'a> {
+    /// Buffer
+    buf: &'a mut Vec<u8>,
+    /// Column type
+    ty: &'a Type,
+    /// The value is the inner value of a nullable (the NULL flag is written)
+    is_some: bool,
+}
+
+impl<'a> ValueSerializer<'a> {
+    /// Creates a new value serializer
+    fn new(buf: &'a mut Vec<u8>, ty: &'a Type) -> Self {
+        Self {
+            buf,
+            ty: unwrap_type(ty),
+            is_some: false,
+        }
+    }
+
+    /// Writes the non-NULL flag of a nullable column, if not written already
+    fn write_flag(&mut self) {
+        if !self.is_some && is_nullable(self.ty) {
+            self.buf.push(0x00);
+        }
+    }
+
+    /// Writes the value bytes
+    fn write(mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.write_flag();
+        self.buf.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Writes a string or bytes
+    fn write_bytes(mut self, what: &str, v: &[u8]) -> Result<(), Error> {
+        match self.ty {
+            Type::String | Type::NullableString => {
+                self.write_flag();
+                leb128::write::unsigned(self.buf, v.len() as u64).unwrap();
+                self.buf.extend_from_slice(v);
+                Ok(())
+            }
+            // NB: the value must have exactly `n` bytes, as with Value::conform_to
+            Type::FixedString(n) | Type::NullableFixedString(n) => {
+                if v.len() != *n {
+                    return Err(Error(format!(
+                        "FixedString({n}) value has {} bytes",
+                        v.len()
+                    )));
+                }
+                self.write_flag();
+                self.buf.extend_from_slice(v);
+                Ok(())
+            }
+            Type::UUID | Type::NullableUUID => {
+                let uuid = match std::str::from_utf8(v) {
+                    Ok(s) if v.len() != 16 => Uuid::parse_str(s)?,
+                    _ => Uuid::from_slice(v)?,
+                };
+                // NB: in RowBinary, the UUID is represented as 2 u64 in little endian
+                let (w1, w2) = uuid.as_u64_pair();
+                self.write_flag();
+                self.buf.extend_from_slice(&w1.to_le_bytes());
+                self.buf.extend_from_slice(&w2.to_le_bytes());
+                Ok(())
+            }
+            Type::UInt256 | Type::NullableUInt256 | Type::Int256 | Type::NullableInt256
+                if v.len() == 32 =>
+            {
+                self.write(v)
+            }
+            Type::Enum8(variants) | Type::NullableEnum8(variants) => {
+                match std::str::from_utf8(v).ok().and_then(|s| variants.get(s)) {
+                    Some(i) => self.write(&i.to_le_bytes()),
+                    None => Err(mismatch(what, self.ty)),
+                }
+            }
+            Type::Enum16(variants) | Type::NullableEnum16(variants) => {
+                match std::str::from_utf8(v).ok().and_then(|s| variants.get(s)) {
+                    Some(i) => self.write(&i.to_le_bytes()),
+                    None => Err(mismatch(what, self.ty)),
+                }
+            }
+            _ => Err(mismatch(what, self.ty)),
+        }
+    }
+
+    /// Starts a compound value (sequence, tuple, struct or map)
+    fn compound(mut self, what: &str, len: Option<usize>) -> Result<Compound<'a>, Error> {
+        let elements = match self.ty {
+            Type::Array(ty) => {
+                let len =
+                    len.ok_or_else(|| Error::new("The length of a sequence must be known"))?;
+                leb128::write::unsigned(self.buf, len as u64).unwrap();
+                Elements::Repeat(ty)
+            }
+            Type::Tuple(types) => Elements::Types(types.iter()),
+            Type::NamedTuple(elements) => Elements::Named(elements.iter()),
+            Type::Map(key_ty, val_ty) => {
+                let len = len.ok_or_else(|| Error::new("The length of a map must be known"))?;
+                leb128::write::unsigned(self.buf, len as u64).unwrap();
+                Elements::Map(key_ty, val_ty)
+            }
+            _ => return Err(mismatch(what, self.ty)),
+        };
+        self.write_flag();
+        Ok(Compound {
+            buf: self.buf,
+            elements,
+        })
+    }
+}
+
+/// Implements the serialization of a number, for the matching column types
+macro_rules! impl_serialize_number {
+    ($FN:ident, $T:ty, $($TY:pat_param)|+) => {
+        fn $FN(self, v: $T) -> Result<(), Error> {
+            match self.ty {
+                $($TY)|+ => self.write(&v.to_le_bytes()),
+                _ => Err(mismatch(stringify!($T), self.ty)),
+            }
+        }
+    };
+}
+
+impl<'a> ser::Serializer for ValueSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        match self.ty {
+            Type::Bool | Type::NullableBool => self.write(&[v as u8]),
+            _ => Err(mismatch("bool", self.ty)),
+        }
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        match self.ty {
+            Type::Int8 | Type::NullableInt8 => self.write(&v.to_le_bytes()),
+            Type::Enum8(variants) | Type::NullableEnum8(variants)
+                if variants.values().any(|i| *i == v) =>
+            {
+                self.write(&v.to_le_bytes())
+            }
+            _ => Err(mismatch("i8", self.ty)),
+        }
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        match self.ty {
+            Type::Int16 | Type::NullableInt16 => self.write(&v.to_le_bytes()),
+            Type::Enum16(variants) | Type::NullableEnum16(variants)
+                if variants.values().any(|i| *i == v) =>
+            {
+                self.write(&v.to_le_bytes())
+            }
+            _ => Err(mismatch("i16", self.ty)),
+        }
+    }
+
+    impl_serialize_number!(
+        serialize_i32,
+        i32,
+        Type::Int32
+            | Type::NullableInt32
+            | Type::Date32
+            | Type::NullableDate32
+            | Type::Time
+            | Type::NullableTime
+    );
+    impl_serialize_number!(
+        serialize_i64,
+        i64,
+        Type::Int64
+            | Type::NullableInt64
+            | Type::DateTime64(..)
+            | Type::NullableDateTime64(..)
+            | Type::Time64(_)
+            | Type::NullableTime64(_)
+            | Type::Interval(_)
+            | Type::NullableInterval(_)
+    );
+    impl_serialize_number!(serialize_i128, i128, Type::Int128 | Type::NullableInt128);
+    impl_serialize_number!(serialize_u8, u8, Type::UInt8 | Type::NullableUInt8);
+    impl_serialize_number!(
+        serialize_u16,
+        u16,
+        Type::UInt16 | Type::NullableUInt16 | Type::Date | Type::NullableDate
+    );
+    impl_serialize_number!(
+        serialize_u32,
+        u32,
+        Type::UInt32 | Type::NullableUInt32 | Type::DateTime(_) | Type::NullableDateTime(_)
+    );
+    impl_serialize_number!(serialize_u64, u64, Type::UInt64 | Type::NullableUInt64);
+    impl_serialize_number!(serialize_u128, u128, Type::UInt128 | Type::NullableUInt128);
+    impl_serialize_number!(serialize_f64, f64, Type::Float64 | Type::NullableFloat64);
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        match self.ty {
+            Type::Float32 | Type::NullableFloat32 => self.write(&v.to_le_bytes()),
+            // NB: a bf16 is the upper half of a f32 (the lower bits are truncated)
+            Type::BFloat16 | Type::NullableBFloat16 => {
+                self.write(&((v.to_bits() >> 16) as u16).to_le_bytes())
+            }
+            _ => Err(mismatch("f32", self.ty)),
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.write_bytes("char", v.encode_utf8(&mut [0; 4]).as_bytes())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.write_bytes("str", v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.write_bytes("bytes", v)
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        if is_nullable(self.ty) {
+            self.buf.push(0x01);
+            Ok(())
+        } else {
+            Err(mismatch("None", self.ty))
+        }
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+        if is_nullable(self.ty) {
+            self.buf.push(0x00);
+            value.serialize(ValueSerializer {
+                buf: self.buf,
+                ty: self.ty,
+                is_some: true,
+            })
+        } else {
+            // NB: an option is written as its value to a non-nullable column
+            value.serialize(self)
+        }
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Err(mismatch("()", self.ty))
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<(), Error> {
+        Err(mismatch(name, self.ty))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        // NB: a unit variant is mapped to the enum variant with the same name
+        self.write_bytes(variant, variant.as_bytes())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), Error> {
+        Err(mismatch(name, self.ty))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Compound<'a>, Error> {
+        self.compound("sequence", len)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a>, Error> {
+        self.compound("tuple", Some(len))
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Compound<'a>, Error> {
+        self.compound(name, Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Impossible<(), Error>, Error> {
+        Err(mismatch(name, self.ty))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Compound<'a>, Error> {
+        self.compound("map", len)
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Compound<'a>, Error> {
+        self.compound(name, Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Impossible<(), Error>, Error> {
+        Err(mismatch(name, self.ty))
+    }
+}
+
+/// Types of the elements of a compound value
+enum Elements<'a> {
+    /// Array elements
+    Repeat(&'a Type),
+    /// Tuple elements
+    Types(std::slice::Iter<'a, Type>),
+    /// Named tuple elements
+    Named(std::slice::Iter<'a, (String, Type)>),
+    /// Map keys and values
+    Map(&'a Type, &'a Type),
+}
+
+/// Serializer of a compound value
+struct Compound<'a> {
+    /// Buffer
+    buf: &'a mut Vec<u8>,
+    /// Element types
+    elements: Elements<'a>,
+}
+
+impl<'a> Compound<'a> {
+    /// Serializes the next element
+    fn element<T: ?Sized + Serialize>(
+        &mut self,
+        name: Option<&str>,
+        value: &T,
+    ) -> Result<(), Error> {
+        let ty = match &mut self.elements {
+            Elements::Repeat(ty) => *ty,
+            Elements::Types(types) => types
+                .next()
+                .ok_or_else(|| Error::new("Too many tuple elements"))?,
+            Elements::Named(elements) => {
+                let (el_name, ty) = elements
+                    .next()
+                    .ok_or_else(|| Error::new("Too many tuple elements"))?;
+                if let Some(name) = name {
+                    if name != el_name {
+                        return Err(Error(format!(
+                            "Field '{name}' does not match the tuple element '{el_name}'"
+                        )));
+                    }
+                }
+                ty
+            }
+            Elements::Map(..) => return Err(Error::new("Map entries must be keys and values")),
+        };
+        value.serialize(ValueSerializer::new(self.buf, ty))
+    }
+
+    /// Ends the compound value
+    fn finish(self) -> Result<(), Error> {
+        let remaining = match self.elements {
+            Elements::Types(types) => types.len(),
+            Elements::Named(elements) => elements.len(),
+            Elements::Repeat(_) | Elements::Map(..) => 0,
+        };
+        if remaining > 0 {
+            return Err(Error(format!("Missing {remaining} tuple element(s)")));
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeSeq for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(None, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTuple for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(None, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(None, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeStruct for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.element(Some(key), value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeMap for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        match self.elements {
+            Elements::Map(key_ty, _) => key.serialize(ValueSerializer::new(self.buf, key_ty)),
+            _ => Err(Error::new("Only a Map column can be serialized from a map")),
+        }
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        match self.elements {
+            Elements::Map(_, val_ty) => value.serialize(ValueSerializer::new(self.buf, val_ty)),
+            _ => Err(Error::new("Only a Map column can be serialized from a map")),
+        }
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
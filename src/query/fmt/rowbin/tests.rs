@@ -1,9 +1,13 @@
 //! Tests
 
 use super::RowBinFormatter;
-use crate::value::ChValue;
+use crate::{
+    query::{Format, QueryData, QueryDataParts},
+    value::{ChValue, IntervalKind, Type, Value},
+};
 use assert_hex::assert_eq_hex;
-use std::str::FromStr;
+use bytes::Bytes;
+use std::{collections::BTreeMap, str::FromStr};
 use time::{Date, OffsetDateTime};
 use uuid::Uuid;
 
@@ -17,7 +21,7 @@ macro_rules! set_test {
             let ty = <$TY as ChValue>::ch_type();
             let value = x.into_ch_value();
             let formatter = RowBinFormatter::default();
-            let bytes = formatter.format_value(value.clone());
+            let bytes = formatter.format_value(value.clone()).unwrap();
             assert_eq_hex!(bytes, $TARGET);
 
             let value_parsed = formatter.parse_value(&mut bytes.as_slice(), ty).unwrap();
@@ -32,6 +36,18 @@ set_test!(fmt_rowbin_int8, i8, -1, (-1_i8).to_le_bytes());
 set_test!(fmt_rowbin_f64, f64, 1.123_f64, 1.123_f64.to_le_bytes());
 set_test!(fmt_rowbin_bool, bool, true, [0x01]);
 set_test!(fmt_rowbin_str, String, "ab".to_string(), [0x02, 0x61, 0x62]);
+set_test!(
+    fmt_rowbin_fixed_str,
+    [u8; 3],
+    [0xff, 0x00, 0x61],
+    [0xff, 0x00, 0x61]
+);
+set_test!(
+    fmt_rowbin_bytes,
+    Bytes,
+    Bytes::from_static(&[0xff, 0x61]),
+    [0x02, 0xff, 0x61]
+);
 set_test!(
     fmt_rowbin_uuid,
     Uuid,
@@ -58,3 +74,672 @@ set_test!(
     OffsetDateTime::from_unix_timestamp(0).unwrap(),
     0_i64.to_le_bytes()
 );
+set_test!(
+    fmt_rowbin_duration,
+    std::time::Duration,
+    std::time::Duration::from_secs(1),
+    1_000_000_000_i64.to_le_bytes()
+);
+
+/// Sample ticks at precisions 0, 3, 6 and 9, for `2023-11-14 22:13:20.123456789`
+const DATETIME64_TICKS: [(u8, i64); 4] = [
+    (0, 1_700_000_000),
+    (3, 1_700_000_000_123),
+    (6, 1_700_000_000_123_456),
+    (9, 1_700_000_000_123_456_789),
+];
+
+#[test]
+fn fmt_rowbin_datetime64_precision() {
+    let formatter = RowBinFormatter::default();
+    for (p, ticks) in DATETIME64_TICKS {
+        let value = Value::DateTime64(ticks, p, None);
+        let bytes = formatter.format_value(value.clone()).unwrap();
+        assert_eq_hex!(bytes, ticks.to_le_bytes());
+
+        let value_parsed = formatter
+            .parse_value(&mut bytes.as_slice(), Type::DateTime64(p, None))
+            .unwrap();
+        assert_eq!(value_parsed, value);
+
+        let dt = OffsetDateTime::from_ch_value(value_parsed).unwrap();
+        let nanos = 1_700_000_000_123_456_789_i128;
+        let unit = 10_i128.pow(9 - u32::from(p));
+        assert_eq!(dt.unix_timestamp_nanos(), nanos / unit * unit);
+    }
+}
+
+#[test]
+fn fmt_rowbin_datetime64_column_precision() {
+    let formatter = RowBinFormatter::default();
+    let dt = OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_123_456_789).unwrap();
+    for (p, ticks) in DATETIME64_TICKS {
+        let table = QueryData::with_names_and_types(vec![("dt", Type::DateTime64(p, None))])
+            .row(vec![dt.into_ch_value()]);
+        let bytes = formatter.format_data(table).unwrap();
+        assert_eq_hex!(bytes, ticks.to_le_bytes());
+    }
+}
+
+#[test]
+fn fmt_rowbin_enum_names() {
+    let formatter = RowBinFormatter::default();
+    let ty = Type::Enum16(BTreeMap::from([
+        ("a".to_string(), 1),
+        ("b".to_string(), 1000),
+    ]));
+
+    let value = Value::Enum16(1000, None);
+    let bytes = formatter.format_value(value).unwrap();
+    assert_eq_hex!(bytes, 1000_i16.to_le_bytes());
+
+    let value_parsed = formatter
+        .parse_value(&mut bytes.as_slice(), ty.clone())
+        .unwrap();
+    assert_eq!(value_parsed, Value::Enum16(1000, Some("b".to_string())));
+    assert_eq!(
+        Value::Enum16(1, None).with_enum_names(&ty),
+        Value::Enum16(1, Some("a".to_string()))
+    );
+}
+
+#[test]
+fn fmt_rowbin_map_typed_keys() {
+    let formatter = RowBinFormatter::default();
+    let ty = Type::Map(
+        Box::new(Type::LowCardinality(Box::new(Type::UInt64))),
+        Box::new(Type::String),
+    );
+    let value = Value::Map(vec![
+        (Value::UInt64(2), Value::String("b".to_string())),
+        (Value::UInt64(1), Value::String("a".to_string())),
+    ]);
+    let bytes = formatter.format_value(value.clone()).unwrap();
+    let mut target = vec![0x02];
+    target.extend(2_u64.to_le_bytes());
+    target.extend([0x01, b'b']);
+    target.extend(1_u64.to_le_bytes());
+    target.extend([0x01, b'a']);
+    assert_eq_hex!(bytes, target);
+
+    let value_parsed = formatter.parse_value(&mut bytes.as_slice(), ty).unwrap();
+    assert_eq!(value_parsed, value);
+}
+
+#[test]
+fn fmt_rowbin_map_from_entries() {
+    let formatter = RowBinFormatter::default();
+    let ty = Type::Map(Box::new(Type::UInt8), Box::new(Type::UInt8));
+    let entries = vec![(2_u8, 20_u8), (1, 10)];
+    let table = QueryData::with_names_and_types(vec![("map", ty.clone())])
+        .row(vec![entries.clone().into_ch_value()]);
+    let bytes = formatter.format_data(table).unwrap();
+    assert_eq_hex!(bytes, [0x02, 2, 20, 1, 10]);
+
+    let value = formatter.parse_value(&mut bytes.as_slice(), ty).unwrap();
+    assert_eq!(Vec::<(u8, u8)>::from_ch_value(value).unwrap(), entries);
+}
+
+/// Returns a sample `Nested(a UInt8, b String)` type and value
+fn sample_nested() -> (Type, Value) {
+    let ty = Type::Nested(vec![
+        ("a".to_string(), Type::UInt8),
+        ("b".to_string(), Type::String),
+    ]);
+    let value = Value::Nested(vec![
+        ("a".to_string(), vec![Value::UInt8(1), Value::UInt8(2)]),
+        (
+            "b".to_string(),
+            vec!["x".to_string().into(), "y".to_string().into()],
+        ),
+    ]);
+    (ty, value)
+}
+
+#[test]
+fn fmt_rowbin_nested_flatten() {
+    let formatter = RowBinFormatter::default();
+    let (ty, value) = sample_nested();
+    let mapping = vec![("id", Type::UInt8), ("n", ty)];
+    let table =
+        QueryData::with_names_and_types(mapping.clone()).row(vec![Value::UInt8(0), value.clone()]);
+    let bytes = formatter.format_data(table).unwrap();
+    // 1 array per nested column
+    assert_eq_hex!(
+        bytes,
+        [0x00, 0x02, 0x01, 0x02, 0x02, 0x01, b'x', 0x01, b'y']
+    );
+
+    let table_parsed = formatter
+        .parse_data(&mut bytes.as_slice(), Some(&mapping))
+        .unwrap();
+    assert_eq!(
+        table_parsed.into_parts().rows,
+        vec![vec![Value::UInt8(0), value]]
+    );
+}
+
+#[test]
+fn fmt_rowbin_nested_no_flatten() {
+    let formatter = RowBinFormatter::default().flatten_nested(false);
+    let (ty, value) = sample_nested();
+    let mapping = vec![("n", ty.clone())];
+    let table = QueryData::with_names_and_types(mapping.clone()).row(vec![value.clone()]);
+    let bytes = formatter.format_data(table).unwrap();
+    // array of tuples
+    assert_eq_hex!(bytes, [0x02, 0x01, 0x01, b'x', 0x02, 0x01, b'y']);
+
+    let value_parsed = formatter.parse_value(&mut bytes.as_slice(), ty).unwrap();
+    assert_eq!(value_parsed, value);
+    let table_parsed = formatter
+        .parse_data(&mut bytes.as_slice(), Some(&mapping))
+        .unwrap();
+    assert_eq!(table_parsed.into_parts().rows, vec![vec![value]]);
+}
+
+#[test]
+fn fmt_rowbin_nested_unequal() {
+    let (ty, _) = sample_nested();
+    let value = Value::Nested(vec![
+        ("a".to_string(), vec![Value::UInt8(1), Value::UInt8(2)]),
+        ("b".to_string(), vec!["x".to_string().into()]),
+    ]);
+    let err = Value::nested_into_tuples(match value.clone() {
+        Value::Nested(fields) => fields,
+        _ => unreachable!(),
+    })
+    .unwrap_err();
+    assert_eq!(err.0, "Nested column 'b' has 1 values, expected 2");
+
+    // NB: the rows are not type checked when the table is created from its parts
+    let table = QueryData::from_parts(QueryDataParts {
+        names: Some(vec!["n".to_string()]),
+        types: Some(vec![ty]),
+        rows: vec![vec![value.clone()]],
+    });
+    assert!(table.clone().flatten_nested().is_err());
+    let formatter = RowBinFormatter::default();
+    assert!(formatter.format_data(table.clone()).is_err());
+    let formatter = RowBinFormatter::default().flatten_nested(false);
+    assert!(formatter.format_data(table).is_err());
+    assert!(formatter.format_value(value.clone()).is_err());
+    assert!(value.to_bytes(Format::TabSep).is_err());
+}
+
+#[test]
+fn fmt_rowbin_fixed_string_length() {
+    let formatter = RowBinFormatter::default();
+    let table = QueryData::with_names_and_types(vec![
+        ("hash", Type::FixedString(4)),
+        ("code", Type::NullableFixedString(2)),
+    ])
+    .row(vec![
+        Value::FixedString(vec![0xde, 0xad, 0xbe, 0xef]),
+        Value::NullableString(Some("fr".to_string())),
+    ]);
+    let bytes = formatter.format_data(table).unwrap();
+    assert_eq_hex!(bytes, [0xde, 0xad, 0xbe, 0xef, 0x00, b'f', b'r']);
+
+    // NB: bytes can be written to a nullable fixed string column
+    let table = QueryData::with_names_and_types(vec![("code", Type::NullableFixedString(2))])
+        .row(vec![Some(Bytes::from_static(&[0xca, 0xfe])).into_ch_value()])
+        .row(vec![None::<Bytes>.into_ch_value()]);
+    let bytes = formatter.format_data(table).unwrap();
+    assert_eq_hex!(bytes, [0x00, 0xca, 0xfe, 0x01]);
+    assert!(!Value::NullableBytes(Some(vec![0x01])).is_same_type_as(&Type::NullableFixedString(2)));
+
+    // NB: the row is not type checked when the table is created from its parts
+    let table = QueryData::from_parts(QueryDataParts {
+        names: Some(vec!["hash".to_string()]),
+        types: Some(vec![Type::FixedString(4)]),
+        rows: vec![vec![Value::FixedString(vec![0x01])]],
+    });
+    assert!(formatter.format_data(table).is_err());
+}
+
+#[test]
+fn fmt_rowbin_bytes_lossy() {
+    let bytes = [0x02, 0x61, 0xff];
+    let formatter = RowBinFormatter::default();
+    let value = formatter
+        .parse_value(&mut bytes.as_slice(), Type::String)
+        .unwrap();
+    assert_eq!(value, Value::Bytes(vec![0x61, 0xff]));
+    assert!(value.try_into::<String>().is_err());
+
+    let formatter = RowBinFormatter::default().utf8_lossy(true);
+    let value = formatter
+        .parse_value(&mut bytes.as_slice(), Type::String)
+        .unwrap();
+    assert_eq!(value, Value::String("a\u{FFFD}".to_string()));
+
+    // a `Vec<u8>` is written as bytes to a string column
+    let table = QueryData::with_names_and_types(vec![("s", Type::String)])
+        .row(vec![vec![0x61_u8, 0xff].into_ch_value()]);
+    let bytes_table = formatter.format_data(table).unwrap();
+    assert_eq_hex!(bytes_table, bytes);
+}
+
+#[test]
+fn fmt_rowbin_variant() {
+    let formatter = RowBinFormatter::default();
+    let ty = Type::Variant(vec![Type::UInt64, Type::String]);
+    // NB: the discriminators are the indexes of the sorted types (String, UInt64)
+    let value = Value::variant(&ty, Value::UInt64(1)).unwrap();
+    assert_eq!(value, Value::Variant(Some((1, Box::new(Value::UInt64(1))))));
+    let bytes = formatter.format_value(value.clone()).unwrap();
+    let mut target = vec![0x01];
+    target.extend(1_u64.to_le_bytes());
+    assert_eq_hex!(bytes, target);
+    let value_parsed = formatter
+        .parse_value(&mut bytes.as_slice(), ty.clone())
+        .unwrap();
+    assert_eq!(value_parsed, value);
+
+    let bytes = formatter.format_value(Value::Variant(None)).unwrap();
+    assert_eq_hex!(bytes, [0xFF]);
+    let value_parsed = formatter
+        .parse_value(&mut bytes.as_slice(), ty.clone())
+        .unwrap();
+    assert_eq!(value_parsed, Value::Variant(None));
+
+    // plain values are written with the discriminator of their type
+    let table = QueryData::with_names_and_types(vec![("v", ty.clone())])
+        .row(vec![Value::String("a".to_string())])
+        .row(vec![Value::NullableUInt64(None)]);
+    let bytes = formatter.format_data(table).unwrap();
+    assert_eq_hex!(bytes, [0x00, 0x01, b'a', 0xFF]);
+    assert!(Value::variant(&ty, Value::Bool(true)).is_err());
+}
+
+#[test]
+fn fmt_rowbin_dynamic() {
+    let formatter = RowBinFormatter::default();
+    let value = Value::dynamic(Some(42_u32));
+    assert_eq!(
+        value,
+        Value::Dynamic(Some((Type::UInt32, Box::new(Value::UInt32(42)))))
+    );
+    let bytes = formatter.format_value(value.clone()).unwrap();
+    let mut target = vec![0x03];
+    target.extend(42_u32.to_le_bytes());
+    assert_eq_hex!(bytes, target);
+    let value_parsed = formatter
+        .parse_value(&mut bytes.as_slice(), Type::Dynamic(None))
+        .unwrap();
+    assert_eq!(value_parsed, value);
+
+    let value = Value::dynamic(vec!["a".to_string()]);
+    let bytes = formatter.format_value(value.clone()).unwrap();
+    assert_eq_hex!(bytes, [0x1E, 0x15, 0x01, 0x01, b'a']);
+    let value_parsed = formatter
+        .parse_value(&mut bytes.as_slice(), Type::Dynamic(None))
+        .unwrap();
+    assert_eq!(value_parsed, value);
+
+    let value = Value::dynamic(None::<u32>);
+    assert_eq!(value, Value::Dynamic(None));
+    let bytes = formatter.format_value(value.clone()).unwrap();
+    assert_eq_hex!(bytes, [0x00]);
+    let value_parsed = formatter
+        .parse_value(&mut bytes.as_slice(), Type::Dynamic(None))
+        .unwrap();
+    assert_eq!(value_parsed, value);
+}
+
+#[test]
+fn fmt_rowbin_json() {
+    let formatter = RowBinFormatter::default();
+    let ty = Type::from_str("JSON(a.b UInt8)").unwrap();
+    let value = Value::Json(vec![
+        ("a.b".to_string(), Value::UInt8(1)),
+        ("c".to_string(), Value::dynamic("x".to_string())),
+    ]);
+    assert!(value.is_same_type_as(&ty));
+    let bytes = formatter.format_value(value.clone()).unwrap();
+    assert_eq_hex!(
+        bytes,
+        [0x02, 0x03, b'a', b'.', b'b', 0x01, 0x01, b'c', 0x15, 0x01, b'x']
+    );
+    let value_parsed = formatter.parse_value(&mut bytes.as_slice(), ty).unwrap();
+    assert_eq!(value_parsed, value);
+    assert_eq!(value.to_json_string(), r#"{"a":{"b":1},"c":"x"}"#);
+}
+
+#[test]
+fn fmt_rowbin_aggregate_functions() {
+    let sum_ty = Type::SimpleAggregateFunction("sum".to_string(), Box::new(Type::UInt64));
+    let uniq_ty = Type::AggregateFunction("uniq".to_string(), vec![Type::UInt64]);
+
+    // NB: the state of an aggregate function is written as is
+    let formatter = RowBinFormatter::default();
+    let table =
+        QueryData::with_names_and_types(vec![("s", sum_ty.clone()), ("u", uniq_ty)]).row(vec![
+            Value::UInt64(10),
+            Value::AggregateFunction(vec![0xAA, 0xBB]),
+        ]);
+    let mut target = 10_u64.to_le_bytes().to_vec();
+    target.extend([0xAA, 0xBB]);
+    assert_eq_hex!(formatter.format_data(table).unwrap(), target);
+    let value = formatter.parse_value(&mut &target[..8], sum_ty).unwrap();
+    assert_eq!(value, Value::UInt64(10));
+
+    // the header types are parsed, but the state has no length and cannot be parsed
+    let mut bytes = vec![0x02, 0x01, b's', 0x01, b'u'];
+    for ty in [
+        "SimpleAggregateFunction(sum, UInt64)",
+        "AggregateFunction(uniq, UInt64)",
+    ] {
+        bytes.push(ty.len() as u8);
+        bytes.extend(ty.as_bytes());
+    }
+    bytes.extend(target);
+    let err = RowBinFormatter::with_names_and_types()
+        .parse_data(&mut bytes.as_slice(), None)
+        .unwrap_err();
+    assert!(err.message().contains("AggregateFunction(uniq)"));
+}
+
+#[test]
+fn fmt_rowbin_time_interval_bfloat16() {
+    let formatter = RowBinFormatter::default();
+    let columns = vec![
+        ("n", Type::NullableNothing),
+        ("t", Type::Time64(3)),
+        ("b", Type::BFloat16),
+        ("i", Type::Interval(IntervalKind::Day)),
+    ];
+    let table = QueryData::with_names_and_types(columns.clone()).row(vec![
+        Value::Null,
+        Value::Time64(-1, 0),
+        Value::BFloat16(1.5),
+        Value::Interval(2, IntervalKind::Day),
+    ]);
+
+    // NB: Time64 ticks are rescaled to the column precision
+    let mut target = vec![0x01];
+    target.extend((-1000_i64).to_le_bytes());
+    target.extend([0xC0, 0x3F]);
+    target.extend(2_i64.to_le_bytes());
+    assert_eq_hex!(formatter.format_data(table).unwrap(), target);
+
+    let mut bytes = target.as_slice();
+    let values = columns
+        .into_iter()
+        .map(|(_, ty)| formatter.parse_value(&mut bytes, ty).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        vec![
+            Value::Null,
+            Value::Time64(-1000, 3),
+            Value::BFloat16(1.5),
+            Value::Interval(2, IntervalKind::Day),
+        ]
+    );
+    assert_eq!(f32::from_ch_value(values[2].clone()).unwrap(), 1.5);
+    assert!(formatter
+        .parse_value(&mut [0x00].as_slice(), Type::NullableNothing)
+        .is_err());
+
+    // NB: months do not have a fixed duration
+    let value = Value::Interval(1, IntervalKind::Month);
+    assert!(std::time::Duration::from_ch_value(value).is_err());
+    let value = Value::Interval(-1, IntervalKind::Second);
+    assert!(std::time::Duration::from_ch_value(value).is_err());
+}
+
+/// Test row for the serde serialization
+#[cfg(feature = "serde")]
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct SerdeRow {
+    id: Uuid,
+    name: String,
+    score: Option<f32>,
+    tags: Vec<String>,
+    status: SerdeStatus,
+    point: (f64, f64),
+}
+
+/// Test enum for the serde serialization
+#[cfg(feature = "serde")]
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+enum SerdeStatus {
+    Active,
+    Inactive,
+}
+
+#[test]
+fn fmt_rowbin_defaults() {
+    use crate::{query::Format, value::OrDefault};
+
+    let mapping = vec![("id", Type::UInt32), ("ts", Type::DateTime(None))];
+    let table = QueryData::with_names_and_types(mapping.clone())
+        .row(vec![Value::UInt32(1), Value::Default])
+        .row(vec![Value::UInt32(2), Value::DateTime(10, None)]);
+    assert!(table.has_defaults());
+
+    // NB: each value is prefixed with 0x01 (default) or 0x00 (value)
+    let bytes = table
+        .clone()
+        .to_bytes(Format::RowBinaryWithDefaults)
+        .unwrap();
+    assert_eq_hex!(
+        bytes,
+        [
+            &[0x00][..],
+            &1_u32.to_le_bytes(),
+            &[0x01, 0x00],
+            &2_u32.to_le_bytes(),
+            &[0x00],
+            &10_u32.to_le_bytes(),
+        ]
+        .concat()
+    );
+    let table_parsed =
+        QueryData::from_bytes(&bytes, Format::RowBinaryWithDefaults, Some(&mapping)).unwrap();
+    assert_eq!(table_parsed.get_rows(), table.get_rows());
+
+    let err = table.to_bytes(Format::RowBinary).unwrap_err();
+    assert_eq!(
+        err.0,
+        "Default values require the RowBinaryWithDefaults format"
+    );
+
+    // NB: a nested value cannot be the column default
+    let ty = Type::Array(Box::new(Type::UInt32));
+    let value = Value::Array(vec![Value::UInt32(1), Value::Default]);
+    assert!(!value.is_same_type_as(&ty));
+    let table = QueryData::from_parts(QueryDataParts {
+        names: Some(vec!["ids".to_string()]),
+        types: Some(vec![ty]),
+        rows: vec![vec![value]],
+    });
+    let err = table.to_bytes(Format::RowBinaryWithDefaults).unwrap_err();
+    assert_eq!(err.0, "Default values are only valid as top-level cells");
+
+    // NB: a record field can be the column default
+    assert_eq!(OrDefault::<u32>::ch_type(), Type::UInt32);
+    assert_eq!(OrDefault::<u32>::Default.into_ch_value(), Value::Default);
+    assert_eq!(OrDefault::Value(1_u32).into_ch_value(), Value::UInt32(1));
+    assert_eq!(
+        OrDefault::<u32>::from_ch_value(Value::UInt32(1)).unwrap(),
+        OrDefault::Value(1)
+    );
+    assert_eq!(
+        OrDefault::<u32>::from_ch_value(Value::Default).unwrap(),
+        OrDefault::Default
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn fmt_rowbin_serde() {
+    use super::{from_rowbin, to_rowbin, RowBinDeserializer};
+
+    let types = [
+        Type::UUID,
+        Type::LowCardinality(Box::new(Type::String)),
+        Type::NullableFloat32,
+        Type::Array(Box::new(Type::String)),
+        Type::Enum8(BTreeMap::from([
+            ("Active".to_string(), 1),
+            ("Inactive".to_string(), 2),
+        ])),
+        Type::from_str("Tuple(lat Float64, lon Float64)").unwrap(),
+    ];
+    let rows = vec![
+        SerdeRow {
+            id: Uuid::from_str("936da01f-9abd-4d9d-80c7-02af85c822a8").unwrap(),
+            name: "a".to_string(),
+            score: Some(1.5),
+            tags: vec!["x".to_string(), "y".to_string()],
+            status: SerdeStatus::Inactive,
+            point: (48.85, 2.35),
+        },
+        SerdeRow {
+            id: Uuid::nil(),
+            name: "b".to_string(),
+            score: None,
+            tags: vec![],
+            status: SerdeStatus::Active,
+            point: (0.0, 0.0),
+        },
+    ];
+
+    // same bytes as the Value rows
+    let bytes = to_rowbin(&rows, &types).unwrap();
+    let formatter = RowBinFormatter::default();
+    let mut expected = vec![];
+    for row in &rows {
+        let values = [
+            row.id.into_ch_value(),
+            row.name.clone().into_ch_value(),
+            row.score.into_ch_value(),
+            row.tags.clone().into_ch_value(),
+            Value::Enum8(
+                match row.status {
+                    SerdeStatus::Active => 1,
+                    SerdeStatus::Inactive => 2,
+                },
+                None,
+            ),
+            row.point.into_ch_value(),
+        ];
+        for value in values {
+            expected.append(&mut formatter.format_value(value).unwrap());
+        }
+    }
+    assert_eq_hex!(bytes, expected);
+    assert_eq!(from_rowbin::<SerdeRow>(&bytes, &types).unwrap(), rows);
+
+    // strings are borrowed
+    let types = [Type::UInt8, Type::String];
+    let bytes = to_rowbin(&[(1_u8, "ab"), (2_u8, "cd")], &types).unwrap();
+    let mut deserializer = RowBinDeserializer::new(&bytes, &types);
+    let row: (u8, &str) = deserializer.deserialize_row().unwrap().unwrap();
+    assert_eq!(row, (1, "ab"));
+    let row: (u8, String) = deserializer.deserialize_row().unwrap().unwrap();
+    assert_eq!(row, (2, "cd".to_string()));
+    assert!(deserializer
+        .deserialize_row::<(u8, &str)>()
+        .unwrap()
+        .is_none());
+
+    // values are checked against the column types
+    let err = to_rowbin(&[(1_u16, "ab")], &types).unwrap_err();
+    assert_eq!(err.message(), "Cannot serialize u16 to a UInt8 column");
+    let err = to_rowbin(&[(1_u8,)], &types).unwrap_err();
+    assert_eq!(err.message(), "Missing 1 tuple element(s)");
+    let err = to_rowbin(&[(None::<u8>, "ab")], &types).unwrap_err();
+    assert_eq!(err.message(), "Cannot serialize None to a UInt8 column");
+
+    // NB: a FixedString must have exactly n bytes, as with Value::conform_to
+    let types = [Type::FixedString(3)];
+    let err = to_rowbin(&[("ab",)], &types).unwrap_err();
+    assert_eq!(err.message(), "FixedString(3) value has 2 bytes");
+    let err = Value::FixedString(b"ab".to_vec())
+        .conform_to(&types[0])
+        .unwrap_err();
+    assert_eq!(err.message(), "FixedString(3) value has 2 bytes");
+    assert!(to_rowbin(&[("abc",)], &types).is_ok());
+    let types = [Type::UInt8, Type::String];
+    assert!(from_rowbin::<(u8,)>(&bytes, &types).is_err());
+    assert!(from_rowbin::<(u8, &str)>(&bytes[..3], &types).is_err());
+}
+
+/// Test row borrowing from the buffer
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+struct BorrowedRow<'a> {
+    id: u32,
+    name: &'a str,
+    #[serde(borrow)]
+    data: Option<&'a [u8]>,
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn fmt_rowbin_cursor() {
+    use super::{to_rowbin, RowCursor};
+    use crate::query::{Format, QueryResponse};
+
+    let types = [Type::UInt32, Type::String, Type::NullableFixedString(2)];
+    let rows = to_rowbin(&[(1_u32, "a", Some("xy")), (2_u32, "bc", None)], &types).unwrap();
+    let expected = [
+        BorrowedRow {
+            id: 1,
+            name: "a",
+            data: Some(b"xy"),
+        },
+        BorrowedRow {
+            id: 2,
+            name: "bc",
+            data: None,
+        },
+    ];
+
+    // without header
+    let res = QueryResponse::new(Format::RowBinary, rows.clone());
+    let cursor = res.cursor::<BorrowedRow>(Some(&types)).unwrap();
+    assert_eq!(cursor.names(), None);
+    let decoded = cursor.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(decoded, expected);
+    // NB: the strings point to the response buffer
+    let range = res.data.as_ptr_range();
+    assert!(range.contains(&decoded[1].name.as_ptr()));
+    assert!(res.cursor::<BorrowedRow>(None).is_err());
+
+    // with names and types
+    let mut data = vec![0x03];
+    for s in [
+        "id",
+        "name",
+        "data",
+        "UInt32",
+        "String",
+        "Nullable(FixedString(2))",
+    ] {
+        data.push(s.len() as u8);
+        data.extend_from_slice(s.as_bytes());
+    }
+    data.extend_from_slice(&rows);
+    let res = QueryResponse::new(Format::RowBinaryWithNamesAndTypes, data);
+    let mut cursor = res.cursor::<BorrowedRow>(None).unwrap();
+    assert_eq!(cursor.names(), Some(["id", "name", "data"].as_slice()));
+    assert_eq!(cursor.next_row().unwrap(), Some(expected[0].clone()));
+    assert_eq!(cursor.next().unwrap().unwrap(), expected[1]);
+    assert!(cursor.next().is_none());
+
+    // with an invalid number of types
+    let res = QueryResponse::new(Format::RowBinaryWithNamesAndTypes, res.data.clone());
+    assert!(res.cursor::<BorrowedRow>(Some(&types[..2])).is_err());
+    assert!(RowCursor::<BorrowedRow>::with_header(&[0x01], false, None).is_err());
+
+    // the cursor stops after an error
+    let mut cursor = RowCursor::<BorrowedRow>::new(&rows[..rows.len() - 1], &types);
+    assert!(cursor.next().unwrap().is_ok());
+    assert!(cursor.next().unwrap().is_err());
+    assert!(cursor.next().is_none());
+    assert_eq!(cursor.next_row().unwrap(), None);
+}
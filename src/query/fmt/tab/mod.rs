@@ -3,25 +3,29 @@
 #[cfg(test)]
 mod tests;
 
-use std::{collections::HashMap, str::FromStr};
+use std::str::FromStr;
 
 use ethnum::{I256, U256};
-use time::{Date, OffsetDateTime};
+use time::Date;
 use uuid::Uuid;
 
 use crate::{
     error::Error,
-    query::QueryData,
+    query::{flatten_nested_mapping, QueryData},
     value::{
-        time::{DateExt, DateTimeExt},
+        escape_bytes,
+        time::{
+            format_ticks_tz, format_time_ticks, format_unix_seconds_tz, parse_ticks_tz,
+            parse_time_ticks, parse_unix_seconds_tz, DateExt,
+        },
         Type, Value,
     },
 };
 
-use super::Formatter;
+use super::{Formatter, JsonFormatter};
 
 /// TabSeparated formatter
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct TsvFormatter {
     /// Raw
     raw: bool,
@@ -29,6 +33,22 @@ pub struct TsvFormatter {
     with_names: bool,
     /// With column types
     with_types: bool,
+    /// Nested columns are flattened
+    flatten_nested: bool,
+    /// Invalid UTF-8 strings are converted lossily
+    utf8_lossy: bool,
+}
+
+impl Default for TsvFormatter {
+    fn default() -> Self {
+        Self {
+            raw: false,
+            with_names: false,
+            with_types: false,
+            flatten_nested: true,
+            utf8_lossy: false,
+        }
+    }
 }
 
 impl TsvFormatter {
@@ -40,18 +60,17 @@ impl TsvFormatter {
     /// Use the variant with names
     pub fn with_names() -> Self {
         Self {
-            raw: false,
             with_names: true,
-            with_types: false,
+            ..Self::default()
         }
     }
 
     /// Use the variant with names and types
     pub fn with_names_and_types() -> Self {
         Self {
-            raw: false,
             with_names: true,
             with_types: true,
+            ..Self::default()
         }
     }
 
@@ -59,8 +78,7 @@ impl TsvFormatter {
     pub fn raw() -> Self {
         Self {
             raw: true,
-            with_names: false,
-            with_types: false,
+            ..Self::default()
         }
     }
 
@@ -69,7 +87,7 @@ impl TsvFormatter {
         Self {
             raw: true,
             with_names: true,
-            with_types: false,
+            ..Self::default()
         }
     }
 
@@ -79,13 +97,31 @@ impl TsvFormatter {
             raw: true,
             with_names: true,
             with_types: true,
+            ..Self::default()
         }
     }
+
+    /// Sets if `Nested` columns are flattened (`true` by default)
+    ///
+    /// This must match the Clickhouse setting `flatten_nested`.
+    pub fn flatten_nested(mut self, flatten: bool) -> Self {
+        self.flatten_nested = flatten;
+        self
+    }
+
+    /// Sets if strings which are not valid UTF-8 are parsed lossily (`false` by default)
+    ///
+    /// By default, they are parsed as [Value::Bytes]. Otherwise, they are parsed as
+    /// [Value::String], with the invalid sequences replaced with `U+FFFD`.
+    pub fn utf8_lossy(mut self, lossy: bool) -> Self {
+        self.utf8_lossy = lossy;
+        self
+    }
 }
 
 impl Formatter for TsvFormatter {
-    fn serialize_value(&self, value: Value) -> Vec<u8> {
-        self.format_value(value).into_bytes()
+    fn serialize_value(&self, value: Value) -> Result<Vec<u8>, Error> {
+        self.format_value(value).map(String::into_bytes)
     }
 
     fn serialize_query_data(&self, data: QueryData) -> Result<Vec<u8>, Error> {
@@ -93,7 +129,7 @@ impl Formatter for TsvFormatter {
     }
 
     fn deserialize_value(&self, bytes: &[u8], ty: Type) -> Result<Value, Error> {
-        let value = String::from_utf8(bytes.to_vec())?;
+        let value = escape_bytes(bytes, str::to_string);
         self.parse_value(&value, ty)
     }
 
@@ -102,7 +138,8 @@ impl Formatter for TsvFormatter {
         bytes: &[u8],
         mapping: Option<&[(&str, Type)]>,
     ) -> Result<QueryData, Error> {
-        let value = String::from_utf8(bytes.to_vec())?;
+        // NB: the bytes which are not valid UTF-8 are escaped, and unescaped with the values
+        let value = escape_bytes(bytes, str::to_string);
         self.parse_data(&value, mapping)
     }
 }
@@ -112,23 +149,27 @@ const NULL: &str = r"\N";
 
 impl TsvFormatter {
     /// Formats a [Value]
-    pub fn format_value(&self, value: Value) -> String {
+    pub fn format_value(&self, value: Value) -> Result<String, Error> {
         self.format_value_iter(value, false)
     }
 
     /// Formats a [Value] recursively
-    fn format_value_iter(&self, value: Value, is_within_array: bool) -> String {
+    pub(super) fn format_value_iter(
+        &self,
+        value: Value,
+        is_within_array: bool,
+    ) -> Result<String, Error> {
         /// Implements the nullable variant for formatting
         macro_rules! impl_nullable {
-            ($VAL:tt, $VAR:ident) => {
+            ($VAL:tt, $VAR:ident $(, $ARG:expr)*) => {
                 match $VAL {
-                    Some(v) => self.format_value_iter(Value::$VAR(v), is_within_array),
+                    Some(v) => self.format_value_iter(Value::$VAR(v $(, $ARG)*), is_within_array)?,
                     None => NULL.to_string(),
                 }
             };
         }
 
-        match value {
+        Ok(match value {
             Value::UInt8(v) => v.to_string(),
             Value::UInt16(v) => v.to_string(),
             Value::UInt32(v) => v.to_string(),
@@ -149,6 +190,7 @@ impl TsvFormatter {
             }
             Value::Float32(v) => v.to_string(),
             Value::Float64(v) => v.to_string(),
+            Value::BFloat16(v) => v.to_string(),
             Value::Bool(v) => v.to_string(),
             Value::String(v) => {
                 let s = if self.raw { v } else { v.escape() };
@@ -158,6 +200,19 @@ impl TsvFormatter {
                     s
                 }
             }
+            Value::Bytes(v) | Value::FixedString(v) | Value::AggregateFunction(v) => {
+                // NB: the bytes which are not valid UTF-8 are written as `\xNN`
+                let s = if self.raw {
+                    escape_bytes(&v, str::to_string)
+                } else {
+                    escape_bytes(&v, |s| s.escape())
+                };
+                if is_within_array {
+                    s.enclose()
+                } else {
+                    s
+                }
+            }
             Value::UUID(_) => {
                 let uuid: Uuid = value.try_into().unwrap();
                 uuid.as_hyphenated().to_string()
@@ -171,32 +226,57 @@ impl TsvFormatter {
                     s
                 }
             }
-            Value::DateTime(_) => {
-                let dt: OffsetDateTime = value.try_into().unwrap();
-                let s = dt.format_yyyy_mm_dd_hh_mm_ss();
+            Value::DateTime(v, tz) => {
+                // NB: the datetime is written in the column timezone
+                let s = format_unix_seconds_tz(v.into(), tz.as_deref());
+                if is_within_array {
+                    s.enclose()
+                } else {
+                    s
+                }
+            }
+            Value::DateTime64(v, p, tz) => {
+                let s = format_ticks_tz(v, p, tz.as_deref());
+                if is_within_array {
+                    s.enclose()
+                } else {
+                    s
+                }
+            }
+            Value::Time(v) => {
+                let s = format_time_ticks(v.into(), 0);
+                if is_within_array {
+                    s.enclose()
+                } else {
+                    s
+                }
+            }
+            Value::Time64(v, p) => {
+                let s = format_time_ticks(v, p);
                 if is_within_array {
                     s.enclose()
                 } else {
                     s
                 }
             }
-            Value::DateTime64(_) => {
-                let dt: OffsetDateTime = value.try_into().unwrap();
-                let s = dt.format_yyyy_mm_dd_hh_mm_ss_ns();
+            Value::Interval(v, _) => v.to_string(),
+            Value::Enum8(_, Some(name)) | Value::Enum16(_, Some(name)) => {
+                // NB: enum values are written with their variant names when they are known
+                let s = if self.raw { name } else { name.escape() };
                 if is_within_array {
                     s.enclose()
                 } else {
                     s
                 }
             }
-            Value::Enum8(v) => v.to_string(),
-            Value::Enum16(v) => v.to_string(),
+            Value::Enum8(v, None) => v.to_string(),
+            Value::Enum16(v, None) => v.to_string(),
             Value::Array(v) => {
                 format!(
                     "[{}]",
                     v.into_iter()
                         .map(|v| self.format_value_iter(v, true))
-                        .collect::<Vec<_>>()
+                        .collect::<Result<Vec<_>, Error>>()?
                         .join(", ")
                 )
             }
@@ -205,22 +285,49 @@ impl TsvFormatter {
                     "({})",
                     v.into_iter()
                         .map(|v| self.format_value_iter(v, true))
-                        .collect::<Vec<_>>()
+                        .collect::<Result<Vec<_>, Error>>()?
+                        .join(", ")
+                )
+            }
+            Value::NamedTuple(v) => {
+                format!(
+                    "({})",
+                    v.into_iter()
+                        .map(|(_, v)| self.format_value_iter(v, true))
+                        .collect::<Result<Vec<_>, Error>>()?
                         .join(", ")
                 )
             }
             Value::Map(v) => {
-                let mut kv = v
+                let kv = v
                     .into_iter()
-                    .map(|(k, v)| format!("'{}': {}", k, self.format_value_iter(v, true)))
-                    .collect::<Vec<_>>();
-                kv.sort();
+                    .map(|(k, v)| {
+                        Ok(format!(
+                            "{}: {}",
+                            self.format_value_iter(k, true)?,
+                            self.format_value_iter(v, true)?
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
                 format!("{{{}}}", kv.join(", "))
             }
+            // NB: a single nested value is written as Array(Tuple(..))
             Value::Nested(fields) => {
-                let values = fields.into_values().collect::<Vec<_>>();
-                let value = Value::Array(values);
-                self.format_value_iter(value, false)
+                self.format_value_iter(Value::nested_into_tuples(fields)?, is_within_array)?
+            }
+            Value::Variant(Some((_, v))) | Value::Dynamic(Some((_, v))) => {
+                self.format_value_iter(*v, is_within_array)?
+            }
+            Value::Variant(None) | Value::Dynamic(None) => NULL.to_string(),
+            // NB: a JSON object is written as JSON text
+            Value::Json(_) => {
+                let s = value.to_json_string();
+                let s = if self.raw { s } else { s.escape() };
+                if is_within_array {
+                    s.enclose()
+                } else {
+                    s
+                }
             }
             Value::NullableUInt8(v) => impl_nullable!(v, UInt8),
             Value::NullableUInt16(v) => impl_nullable!(v, UInt16),
@@ -236,16 +343,25 @@ impl TsvFormatter {
             Value::NullableInt256(v) => impl_nullable!(v, Int256),
             Value::NullableFloat32(v) => impl_nullable!(v, Float32),
             Value::NullableFloat64(v) => impl_nullable!(v, Float64),
+            Value::NullableBFloat16(v) => impl_nullable!(v, BFloat16),
             Value::NullableBool(v) => impl_nullable!(v, Bool),
             Value::NullableString(v) => impl_nullable!(v, String),
+            Value::NullableBytes(v) => impl_nullable!(v, Bytes),
+            Value::NullableFixedString(v) => impl_nullable!(v, FixedString),
             Value::NullableUUID(v) => impl_nullable!(v, UUID),
             Value::NullableDate(v) => impl_nullable!(v, Date),
             Value::NullableDate32(v) => impl_nullable!(v, Date32),
-            Value::NullableDateTime(v) => impl_nullable!(v, DateTime),
-            Value::NullableDateTime64(v) => impl_nullable!(v, DateTime64),
-            Value::NullableEnum8(v) => impl_nullable!(v, Enum8),
-            Value::NullableEnum16(v) => impl_nullable!(v, Enum16),
-        }
+            Value::NullableDateTime(v, tz) => impl_nullable!(v, DateTime, tz),
+            Value::NullableDateTime64(v, p, tz) => impl_nullable!(v, DateTime64, p, tz),
+            Value::NullableTime(v) => impl_nullable!(v, Time),
+            Value::NullableTime64(v, p) => impl_nullable!(v, Time64, p),
+            Value::NullableInterval(v, kind) => impl_nullable!(v, Interval, kind),
+            Value::NullableEnum8(v, name) => impl_nullable!(v, Enum8, name),
+            Value::NullableEnum16(v, name) => impl_nullable!(v, Enum16, name),
+            Value::Null => NULL.to_string(),
+            // NB: NULL is read as the column default (setting `input_format_null_as_default`)
+            Value::Default => NULL.to_string(),
+        })
     }
 
     /// Formats a [QueryTable]
@@ -253,6 +369,10 @@ impl TsvFormatter {
         // each value is followed by a tab (except the last value in a row)
         // the last value is followed by a newline (inc. last row)
         let mut buf = String::new();
+        let data = match self.flatten_nested {
+            true => data.flatten_nested()?,
+            false => data,
+        };
         let parts = data.into_parts();
         if self.with_names {
             if let Some(names) = parts.names {
@@ -264,8 +384,8 @@ impl TsvFormatter {
         }
 
         if self.with_types {
-            if let Some(types) = parts.types {
-                let types = types.into_iter().map(|t| t.to_string()).collect();
+            if let Some(types) = &parts.types {
+                let types = types.iter().map(|t| t.to_string()).collect();
                 let row = self.format_table_row(types)?;
                 buf.push_str(row.as_str());
             } else {
@@ -276,8 +396,15 @@ impl TsvFormatter {
         for row in parts.rows {
             let values = row
                 .into_iter()
-                .map(|value| self.format_value(value))
-                .collect::<Vec<_>>();
+                .enumerate()
+                .map(|(i, value)| {
+                    // NB: values are conformed to the column types (eg DateTime64 precision)
+                    match parts.types.as_ref().and_then(|types| types.get(i)) {
+                        Some(ty) => self.format_value(value.conform_to(ty)?),
+                        None => self.format_value(value),
+                    }
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
             let row = self.format_table_row(values)?;
             buf.push_str(row.as_str());
         }
@@ -301,13 +428,13 @@ impl TsvFormatter {
     }
 
     /// Parses a [Value]
-    fn parse_value(&self, value: &str, ty: Type) -> Result<Value, Error> {
+    pub(super) fn parse_value(&self, value: &str, ty: Type) -> Result<Value, Error> {
         self.parse_value_iter(value, ty, false)
     }
 
     /// Parses a [Value] recursively
     #[allow(clippy::only_used_in_recursion)]
-    fn parse_value_iter(
+    pub(super) fn parse_value_iter(
         &self,
         value: &str,
         ty: Type,
@@ -370,6 +497,10 @@ impl TsvFormatter {
                 let v = value.parse::<f64>()?;
                 Ok(v.into())
             }
+            Type::BFloat16 => {
+                let v = value.parse::<f32>()?;
+                Ok(Value::BFloat16(v))
+            }
             Type::Decimal(_, _)
             | Type::Decimal32(_)
             | Type::Decimal64(_)
@@ -383,14 +514,23 @@ impl TsvFormatter {
                 Ok(v.into())
             }
             Type::String => {
-                let v = value.unescape();
-                let v = if is_within_array { v.unenclose() } else { v };
-                Ok(v.into())
+                let v = if is_within_array {
+                    value.unenclose()
+                } else {
+                    value.to_string()
+                };
+                Ok(Value::from_string_bytes(
+                    v.unescape_bytes(),
+                    self.utf8_lossy,
+                ))
             }
             Type::FixedString(_) => {
-                let v = value.unescape();
-                let v = if is_within_array { v.unenclose() } else { v };
-                Ok(v.into())
+                let v = if is_within_array {
+                    value.unenclose()
+                } else {
+                    value.to_string()
+                };
+                Ok(Value::FixedString(v.unescape_bytes()))
             }
             Type::UUID => {
                 let v = value.parse::<Uuid>()?;
@@ -402,34 +542,79 @@ impl TsvFormatter {
                 let date = Date::parse_yyyy_mm_dd(&v)?;
                 Ok(date.into())
             }
-            Type::DateTime => {
+            Type::DateTime(tz) => {
+                // NB: the datetime is read in the column timezone
                 let v = value.to_string();
                 let v = if is_within_array { v.unenclose() } else { v };
-                let dt = OffsetDateTime::parse_yyyy_mm_dd_hh_mm_ss(&v)?;
-                Ok(dt.into())
+                let secs = parse_unix_seconds_tz(&v, tz.as_deref())?;
+                Ok(Value::DateTime(secs.try_into()?, tz))
             }
-            Type::DateTime64(_) => {
+            Type::DateTime64(p, tz) => {
                 let v = value.to_string();
                 let v = if is_within_array { v.unenclose() } else { v };
-                let dt = OffsetDateTime::parse_yyyy_mm_dd_hh_mm_ss_ns(&v)?;
-                Ok(dt.into())
+                let ticks = parse_ticks_tz(&v, p, tz.as_deref())?;
+                Ok(Value::DateTime64(ticks, p, tz))
+            }
+            Type::Time => {
+                let v = value.to_string();
+                let v = if is_within_array { v.unenclose() } else { v };
+                let secs = parse_time_ticks(&v, 0)?;
+                Ok(Value::Time(secs.try_into()?))
+            }
+            Type::Time64(p) => {
+                let v = value.to_string();
+                let v = if is_within_array { v.unenclose() } else { v };
+                let ticks = parse_time_ticks(&v, p)?;
+                Ok(Value::Time64(ticks, p))
+            }
+            Type::Interval(kind) => {
+                let v = value.parse::<i64>()?;
+                Ok(Value::Interval(v, kind))
+            }
+            Type::Nothing => Err(Error::new("Cannot parse a Nothing value")),
+            Type::Enum8(variants) => {
+                // NB: the enum is read either from its variant name or its value
+                let v = if is_within_array {
+                    value.unenclose()
+                } else {
+                    value.to_string()
+                };
+                let v = v.unescape();
+                let variant = match variants.get_key_value(&v) {
+                    Some(variant) => Some(variant),
+                    None => v
+                        .parse::<i8>()
+                        .ok()
+                        .and_then(|i| variants.iter().find(|(_, vi)| **vi == i)),
+                };
+                match variant {
+                    Some((name, i)) => Ok(Value::Enum8(*i, Some(name.clone()))),
+                    None => Err(Error::new(format!("Invalid enum variant: {v}").as_str())),
+                }
+            }
+            Type::Enum16(variants) => {
+                let v = if is_within_array {
+                    value.unenclose()
+                } else {
+                    value.to_string()
+                };
+                let v = v.unescape();
+                let variant = match variants.get_key_value(&v) {
+                    Some(variant) => Some(variant),
+                    None => v
+                        .parse::<i16>()
+                        .ok()
+                        .and_then(|i| variants.iter().find(|(_, vi)| **vi == i)),
+                };
+                match variant {
+                    Some((name, i)) => Ok(Value::Enum16(*i, Some(name.clone()))),
+                    None => Err(Error::new(format!("Invalid enum variant: {v}").as_str())),
+                }
             }
-            Type::Enum8(variants) => match variants.get(value) {
-                Some(i) => Ok(Value::Enum8(*i)),
-                None => Err(Error::new(
-                    format!("Invalid enum variant: {value}").as_str(),
-                )),
-            },
-            Type::Enum16(variants) => match variants.get(value) {
-                Some(i) => Ok(Value::Enum16(*i)),
-                None => Err(Error::new(
-                    format!("Invalid enum variant: {value}").as_str(),
-                )),
-            },
             Type::Array(ty) => {
                 if let Some(s) = value.trim().strip_prefix('[') {
                     if let Some(s) = s.strip_suffix(']') {
-                        let parts = s.trim().split(',').collect::<Vec<_>>();
+                        let parts = split_top_level(s, ',');
                         let mut values = vec![];
                         for part in parts {
                             let value = self.parse_value_iter(part.trim(), *ty.clone(), true)?;
@@ -446,7 +631,7 @@ impl TsvFormatter {
             Type::Tuple(types) => {
                 if let Some(s) = value.trim().strip_prefix('(') {
                     if let Some(s) = s.strip_suffix(')') {
-                        let parts = s.trim().split(',').collect::<Vec<_>>();
+                        let parts = split_top_level(s, ',');
                         if parts.len() != types.len() {
                             return Err(Error::new("Invalid tuple"));
                         }
@@ -464,20 +649,29 @@ impl TsvFormatter {
                     Err(Error::new("Invalid tuple"))
                 }
             }
-            Type::Map(_ty_key, ty_val) => {
+            Type::NamedTuple(elements) => {
+                let (names, types): (Vec<_>, Vec<_>) = elements.into_iter().unzip();
+                match self.parse_value_iter(value, Type::Tuple(types), is_within_array)? {
+                    Value::Tuple(values) => {
+                        Ok(Value::NamedTuple(names.into_iter().zip(values).collect()))
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            Type::Map(ty_key, ty_val) => {
                 if let Some(s) = value.trim().strip_prefix('{') {
                     if let Some(s) = s.strip_suffix('}') {
-                        let mut map = HashMap::new();
-                        let kv_pairs = s.trim().split(',').collect::<Vec<_>>();
-                        for kv in kv_pairs {
-                            let parts = kv.trim().split(':').collect::<Vec<_>>();
+                        let mut map = vec![];
+                        for kv in split_top_level(s, ',') {
+                            let parts = split_top_level(kv, ':');
                             if parts.len() != 2 {
                                 return Err(Error::new("Invalid map"));
                             }
-                            let key = parts[0].trim().unenclose();
-                            let value_str = parts[1].trim();
-                            let value = self.parse_value_iter(value_str, *ty_val.clone(), true)?;
-                            map.insert(key, value);
+                            let key =
+                                self.parse_value_iter(parts[0].trim(), *ty_key.clone(), true)?;
+                            let value =
+                                self.parse_value_iter(parts[1].trim(), *ty_val.clone(), true)?;
+                            map.push((key, value));
                         }
                         Ok(Value::Map(map))
                     } else {
@@ -487,24 +681,60 @@ impl TsvFormatter {
                     Err(Error::new("Invalid map"))
                 }
             }
+            Type::LowCardinality(ty) => self.parse_value_iter(value, *ty, is_within_array),
+            Type::SimpleAggregateFunction(_, ty) => {
+                self.parse_value_iter(value, *ty, is_within_array)
+            }
+            Type::AggregateFunction(..) => {
+                let v = if is_within_array {
+                    value.unenclose()
+                } else {
+                    value.to_string()
+                };
+                Ok(Value::AggregateFunction(v.unescape_bytes()))
+            }
             Type::Nested(fields) => {
-                if let Some(s) = value.trim().strip_prefix('[') {
-                    if let Some(s) = s.strip_suffix(']') {
-                        let parts = s.trim().split(',').collect::<Vec<_>>();
-                        let mut map = HashMap::new();
-                        for (i, part) in parts.into_iter().enumerate() {
-                            let (key, ty) =
-                                fields.get(i).ok_or(Error::new("Invalid nested value"))?;
-                            let value = self.parse_value_iter(part.trim(), ty.clone(), true)?;
-                            map.insert(key.to_string(), value);
-                        }
-                        Ok(Value::Nested(map))
-                    } else {
-                        Err(Error::new("Invalid array"))
+                let (names, types) = fields.into_iter().unzip();
+                let ty = Type::Array(Box::new(Type::Tuple(types)));
+                let value = self.parse_value_iter(value, ty, is_within_array)?;
+                Value::nested_from_tuples(names, value)
+            }
+            Type::Variant(_) => {
+                if value == NULL {
+                    return Ok(Value::Variant(None));
+                }
+                // NB: the value is parsed with the first matching type, strings are tried last
+                let (strings, others): (Vec<_>, Vec<_>) = ty
+                    .variant_types()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .enumerate()
+                    .partition(|(_, ty)| matches!(ty, Type::String | Type::FixedString(_)));
+                for (i, var_ty) in others.into_iter().chain(strings) {
+                    if let Ok(v) = self.parse_value_iter(value, var_ty.clone(), is_within_array) {
+                        return Ok(Value::Variant(Some((i.try_into()?, Box::new(v)))));
                     }
-                } else {
-                    Err(Error::new("Invalid array"))
                 }
+                Err(Error::new(
+                    format!("Invalid variant value: {value}").as_str(),
+                ))
+            }
+            // NB: the type of a dynamic value is unknown, it is parsed as a string
+            Type::Dynamic(_) => match value {
+                NULL => Ok(Value::Dynamic(None)),
+                _ => {
+                    let v = self.parse_value_iter(value, Type::String, is_within_array)?;
+                    Ok(Value::Dynamic(Some((Type::String, Box::new(v)))))
+                }
+            },
+            // NB: the object is written as JSON text
+            ty @ Type::Json(..) => {
+                let v = if is_within_array {
+                    value.unenclose()
+                } else {
+                    value.to_string()
+                };
+                JsonFormatter::new().parse_value(&v.unescape(), ty)
             }
             Type::NullableUInt8 => match value {
                 NULL => Ok(Value::NullableUInt8(None)),
@@ -604,6 +834,13 @@ impl TsvFormatter {
                     Ok(v.into_nullable().unwrap())
                 }
             },
+            Type::NullableBFloat16 => match value {
+                NULL => Ok(Value::NullableBFloat16(None)),
+                _ => {
+                    let v = self.parse_value_iter(value, Type::BFloat16, false)?;
+                    Ok(v.into_nullable().unwrap())
+                }
+            },
             Type::NullableDecimal(p, s) => match value {
                 NULL => Ok(Value::NullableFloat64(None)),
                 _ => {
@@ -654,7 +891,7 @@ impl TsvFormatter {
                 }
             },
             Type::NullableFixedString(n) => match value {
-                NULL => Ok(Value::NullableString(None)),
+                NULL => Ok(Value::NullableFixedString(None)),
                 _ => {
                     let v = self.parse_value_iter(value, Type::FixedString(n), false)?;
                     Ok(v.into_nullable().unwrap())
@@ -681,34 +918,59 @@ impl TsvFormatter {
                     Ok(v.into_nullable().unwrap())
                 }
             },
-            Type::NullableDateTime => match value {
-                NULL => Ok(Value::NullableDateTime(None)),
+            Type::NullableDateTime(tz) => match value {
+                NULL => Ok(Value::NullableDateTime(None, tz)),
+                _ => {
+                    let v = self.parse_value_iter(value, Type::DateTime(tz), false)?;
+                    Ok(v.into_nullable().unwrap())
+                }
+            },
+            Type::NullableDateTime64(p, tz) => match value {
+                NULL => Ok(Value::NullableDateTime64(None, p, tz)),
                 _ => {
-                    let v = self.parse_value_iter(value, Type::DateTime, false)?;
+                    let v = self.parse_value_iter(value, Type::DateTime64(p, tz), false)?;
                     Ok(v.into_nullable().unwrap())
                 }
             },
-            Type::NullableDateTime64(p) => match value {
-                NULL => Ok(Value::NullableDateTime64(None)),
+            Type::NullableTime => match value {
+                NULL => Ok(Value::NullableTime(None)),
                 _ => {
-                    let v = self.parse_value_iter(value, Type::DateTime64(p), false)?;
+                    let v = self.parse_value_iter(value, Type::Time, false)?;
+                    Ok(v.into_nullable().unwrap())
+                }
+            },
+            Type::NullableTime64(p) => match value {
+                NULL => Ok(Value::NullableTime64(None, p)),
+                _ => {
+                    let v = self.parse_value_iter(value, Type::Time64(p), false)?;
+                    Ok(v.into_nullable().unwrap())
+                }
+            },
+            Type::NullableInterval(kind) => match value {
+                NULL => Ok(Value::NullableInterval(None, kind)),
+                _ => {
+                    let v = self.parse_value_iter(value, Type::Interval(kind), false)?;
                     Ok(v.into_nullable().unwrap())
                 }
             },
             Type::NullableEnum8(variants) => match value {
-                NULL => Ok(Value::NullableEnum8(None)),
+                NULL => Ok(Value::NullableEnum8(None, None)),
                 _ => {
                     let v = self.parse_value_iter(value, Type::Enum8(variants.clone()), false)?;
                     Ok(v.into_nullable().unwrap())
                 }
             },
             Type::NullableEnum16(variants) => match value {
-                NULL => Ok(Value::NullableEnum16(None)),
+                NULL => Ok(Value::NullableEnum16(None, None)),
                 _ => {
                     let v = self.parse_value_iter(value, Type::Enum16(variants.clone()), false)?;
                     Ok(v.into_nullable().unwrap())
                 }
             },
+            Type::NullableNothing => match value {
+                NULL => Ok(Value::Null),
+                _ => Err(Error::new("Invalid Nullable(Nothing) value")),
+            },
         }
     }
 
@@ -752,10 +1014,19 @@ impl TsvFormatter {
             QueryData::no_headers()
         };
 
+        // nested columns are received as 1 array column per nested column
+        let flat_mapping = mapping.map(|mapping| match self.flatten_nested {
+            true => flatten_nested_mapping(mapping),
+            false => mapping
+                .iter()
+                .map(|(n, t)| (n.to_string(), t.clone()))
+                .collect(),
+        });
+
         // parse rows from the buffer
         let types = if let Some(types) = data.get_types() {
             types
-        } else if let Some(mapping) = mapping {
+        } else if let Some(mapping) = &flat_mapping {
             mapping.iter().map(|(_, t)| t.clone()).collect()
         } else {
             return Err(Error::new("Deserializing data requires a mapping table"));
@@ -777,18 +1048,61 @@ impl TsvFormatter {
             data.add_row(row);
         }
 
-        Ok(data)
+        match mapping {
+            Some(mapping) if self.flatten_nested => data.unflatten_nested(mapping),
+            _ => Ok(data),
+        }
     }
 }
 
+/// Splits a composite value (array, tuple, map) on a separator
+///
+/// Separators within quotes or brackets are ignored, so that nested values are not split.
+fn split_top_level(s: &str, sep: char) -> Vec<&str> {
+    if s.trim().is_empty() {
+        return vec![];
+    }
+
+    let mut parts = vec![];
+    let mut depth = 0_usize;
+    let mut in_quotes = false;
+    let mut is_escaped = false;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        if is_escaped {
+            is_escaped = false;
+            continue;
+        }
+        match c {
+            '\\' => is_escaped = true,
+            '\'' => in_quotes = !in_quotes,
+            _ if in_quotes => {}
+            '[' | '(' | '{' => depth += 1,
+            ']' | ')' | '}' => depth = depth.saturating_sub(1),
+            c if c == sep && depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
 /// Extension trait for strings
+///
+/// Strings are escaped as specified by Clickhouse for the TabSeparated format.
 trait StringExt {
     /// Escapes a string
     fn escape(&self) -> String;
 
-    /// Unescapes a string
+    /// Unescapes a string (invalid UTF-8 sequences are replaced)
     fn unescape(&self) -> String;
 
+    /// Unescapes a string to bytes (bytes can be escaped as `\xNN`)
+    fn unescape_bytes(&self) -> Vec<u8>;
+
     /// Encloses a string
     fn enclose(&self) -> String;
 
@@ -798,17 +1112,63 @@ trait StringExt {
 
 impl StringExt for &str {
     fn escape(&self) -> String {
-        self.replace('\\', r"\b")
-            .replace(' ', r"\n")
-            .replace('\t', r"\t")
-            .replace('\'', r"\'")
+        let mut s = String::with_capacity(self.len());
+        for c in self.chars() {
+            match c {
+                '\\' => s.push_str(r"\\"),
+                '\'' => s.push_str(r"\'"),
+                '\u{08}' => s.push_str(r"\b"),
+                '\u{0C}' => s.push_str(r"\f"),
+                '\n' => s.push_str(r"\n"),
+                '\r' => s.push_str(r"\r"),
+                '\t' => s.push_str(r"\t"),
+                '\0' => s.push_str(r"\0"),
+                c => s.push(c),
+            }
+        }
+        s
     }
 
     fn unescape(&self) -> String {
-        self.replace(r"\b", "\\")
-            .replace(r"\n", " ")
-            .replace(r"\t", "\t")
-            .replace(r"\'", "\'")
+        String::from_utf8_lossy(&self.unescape_bytes()).into_owned()
+    }
+
+    fn unescape_bytes(&self) -> Vec<u8> {
+        let s = self.as_bytes();
+        let mut bytes = Vec::with_capacity(s.len());
+        let mut i = 0;
+        while i < s.len() {
+            if s[i] != b'\\' || i + 1 == s.len() {
+                bytes.push(s[i]);
+                i += 1;
+                continue;
+            }
+
+            let hex = |b: u8| (b as char).to_digit(16);
+            let (b, n) = match s[i + 1] {
+                b'x' => match (
+                    s.get(i + 2).and_then(|b| hex(*b)),
+                    s.get(i + 3).and_then(|b| hex(*b)),
+                ) {
+                    (Some(h), Some(l)) => ((h * 16 + l) as u8, 4),
+                    _ => (b'x', 2),
+                },
+                b'0' => (0x00, 2),
+                b'a' => (0x07, 2),
+                b'b' => (0x08, 2),
+                b'e' => (0x1B, 2),
+                b'f' => (0x0C, 2),
+                b'n' => (b'\n', 2),
+                b'r' => (b'\r', 2),
+                b't' => (b'\t', 2),
+                b'v' => (0x0B, 2),
+                // NB: other escaped characters are unescaped as is (eg. `\\`, `\'`)
+                b => (b, 2),
+            };
+            bytes.push(b);
+            i += n;
+        }
+        bytes
     }
 
     fn enclose(&self) -> String {
@@ -816,9 +1176,8 @@ impl StringExt for &str {
     }
 
     fn unenclose(&self) -> String {
-        self.trim_start_matches('\'')
-            .trim_end_matches('\'')
-            .to_string()
+        let s = self.strip_prefix('\'').unwrap_or(self);
+        s.strip_suffix('\'').unwrap_or(s).to_string()
     }
 }
 
@@ -831,6 +1190,10 @@ impl StringExt for String {
         self.as_str().unescape()
     }
 
+    fn unescape_bytes(&self) -> Vec<u8> {
+        self.as_str().unescape_bytes()
+    }
+
     fn enclose(&self) -> String {
         self.as_str().enclose()
     }
@@ -1,15 +1,16 @@
 //! Tests
 
 use core::str::FromStr;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
+use bytes::Bytes;
 use ethnum::{I256, U256};
 use time::{Date, OffsetDateTime};
 use uuid::Uuid;
 
 use crate::{
-    query::{Format, QueryData, TsvFormatter},
-    value::{time::DateExt, ChValue, Type},
+    query::{Format, Formatter, QueryData, TsvFormatter},
+    value::{time::DateExt, ChValue, IntervalKind, Type, Value},
 };
 
 /// Sets a test
@@ -22,7 +23,7 @@ macro_rules! set_test {
             let ty = <$TY as ChValue>::ch_type();
             let value = x.into_ch_value();
             let formatter = TsvFormatter::default();
-            let value_str = formatter.format_value(value.clone());
+            let value_str = formatter.format_value(value.clone()).unwrap();
             assert_eq!(value_str, $STR);
 
             let value_parsed = formatter.parse_value(&value_str, ty).unwrap();
@@ -46,13 +47,25 @@ set_test!(
     fmt_tsv_string,
     String,
     "hello world".to_string(),
-    r"hello\nworld"
+    "hello world"
 );
 set_test!(
     fmt_tsv_string_2,
     String,
     "with\\backslash".to_string(),
-    r"with\bbackslash"
+    r"with\\backslash"
+);
+set_test!(
+    fmt_tsv_string_3,
+    String,
+    "a\tb\nc'd\0".to_string(),
+    r"a\tb\nc\'d\0"
+);
+set_test!(
+    fmt_tsv_bytes,
+    Bytes,
+    Bytes::from_static(&[b'a', 0xff, b'\t']),
+    r"a\xFF\t"
 );
 set_test!(
     fmt_tsv_uuid,
@@ -70,9 +83,15 @@ set_test!(
     fmt_tsv_datetime,
     OffsetDateTime,
     OffsetDateTime::from_unix_timestamp(1).unwrap(),
-    "1970-01-01 00:00:01.0"
+    "1970-01-01 00:00:01.000000000"
 );
 set_test!(fmt_tsv_array, Vec<u8>, vec![0, 1, 2], "[0, 1, 2]");
+set_test!(
+    fmt_tsv_fixed_str,
+    [u8; 4],
+    [b'a', 0x00, 0xff, b'b'],
+    r"a\x00\xFFb"
+);
 set_test!(fmt_tsv_tuple, (u8, u8, u8), (1, 2, 3), "(1, 2, 3)");
 set_test!(
     fmt_tsv_map,
@@ -96,7 +115,7 @@ fn sample_table() -> QueryData {
         ("string", Type::String),
         ("uuid", Type::UUID),
         ("date", Type::Date32),
-        ("datetime", Type::DateTime64(9)),
+        ("datetime", Type::DateTime64(9, None)),
         ("array", Type::Array(Box::new(Type::UInt8))),
     ])
     .row(vec![
@@ -127,3 +146,371 @@ fn fmt_tsv_table() {
     let table_parsed = QueryData::from_bytes(&bytes, format, None).unwrap();
     assert_eq!(table_parsed, table);
 }
+
+#[test]
+fn fmt_tsv_datetime_utc() {
+    let formatter = TsvFormatter::default();
+    let ty = Type::DateTime(Some("UTC".to_string()));
+    let value = formatter.parse_value("1970-01-01 01:00:00", ty).unwrap();
+    assert_eq!(value, Value::DateTime(3600, Some("UTC".to_string())));
+    assert_eq!(
+        formatter.format_value(value).unwrap(),
+        "1970-01-01 01:00:00"
+    );
+}
+
+#[cfg(feature = "tz")]
+#[test]
+fn fmt_tsv_datetime_tz() {
+    let formatter = TsvFormatter::default();
+    let tz = Some("Europe/Paris".to_string());
+
+    // 2024-01-01 12:00:00 CET (UTC+1)
+    let value = formatter
+        .parse_value("2024-01-01 12:00:00", Type::DateTime(tz.clone()))
+        .unwrap();
+    assert_eq!(value, Value::DateTime(1704106800, tz.clone()));
+    assert_eq!(
+        formatter.format_value(value).unwrap(),
+        "2024-01-01 12:00:00"
+    );
+
+    // 2024-07-01 12:00:00 CEST (UTC+2)
+    let value = formatter
+        .parse_value("2024-07-01 12:00:00.5", Type::DateTime64(9, tz.clone()))
+        .unwrap();
+    assert_eq!(
+        value,
+        Value::DateTime64(1_719_828_000_500_000_000, 9, tz.clone())
+    );
+}
+
+#[cfg(feature = "tz")]
+#[test]
+fn fmt_tsv_zoned_datetime() {
+    use crate::value::time::ZonedDateTime;
+
+    let dt = ZonedDateTime::new(
+        OffsetDateTime::from_unix_timestamp(1704106800).unwrap(),
+        "Europe/Paris",
+    )
+    .unwrap();
+    assert_eq!(dt.datetime.offset().whole_hours(), 1);
+
+    let formatter = TsvFormatter::default();
+    let value = dt.clone().into_ch_value();
+    let value_str = formatter.format_value(value.clone()).unwrap();
+    assert_eq!(value_str, "2024-01-01 12:00:00.000000000");
+    let value_parsed = formatter
+        .parse_value(
+            &value_str,
+            Type::DateTime64(9, Some("Europe/Paris".to_string())),
+        )
+        .unwrap();
+    assert_eq!(ZonedDateTime::from_ch_value(value_parsed).unwrap(), dt);
+}
+
+#[test]
+fn fmt_tsv_datetime64_precision() {
+    let cases = [
+        (0, 1_700_000_000, "2023-11-14 22:13:20"),
+        (3, 1_700_000_000_123, "2023-11-14 22:13:20.123"),
+        (6, 1_700_000_000_123_456, "2023-11-14 22:13:20.123456"),
+        (
+            9,
+            1_700_000_000_123_456_789,
+            "2023-11-14 22:13:20.123456789",
+        ),
+    ];
+    let formatter = TsvFormatter::default();
+    for (p, ticks, s) in cases {
+        let value = Value::DateTime64(ticks, p, None);
+        assert_eq!(formatter.format_value(value.clone()).unwrap(), s);
+
+        let value_parsed = formatter.parse_value(s, Type::DateTime64(p, None)).unwrap();
+        assert_eq!(value_parsed, value);
+    }
+
+    // extra digits are truncated, missing digits are padded
+    let value = formatter
+        .parse_value("2023-11-14 22:13:20.1234", Type::DateTime64(3, None))
+        .unwrap();
+    assert_eq!(value, Value::DateTime64(1_700_000_000_123, 3, None));
+    let value = formatter
+        .parse_value("2023-11-14 22:13:20.1", Type::DateTime64(6, None))
+        .unwrap();
+    assert_eq!(value, Value::DateTime64(1_700_000_000_100_000, 6, None));
+
+    // before 1970, in UTC and as a UNIX timestamp when the timezone cannot be resolved
+    let value = Value::DateTime64(-1_500, 3, None);
+    assert_eq!(
+        formatter.format_value(value.clone()).unwrap(),
+        "1969-12-31 23:59:58.500"
+    );
+    let value_parsed = formatter
+        .parse_value("1969-12-31 23:59:58.500", Type::DateTime64(3, None))
+        .unwrap();
+    assert_eq!(value_parsed, value);
+    let tz = Some("Unknown/Zone".to_string());
+    for (ticks, s) in [(-1_500, "-1.500"), (-500, "-0.500"), (1_500, "1.500")] {
+        let value = Value::DateTime64(ticks, 3, tz.clone());
+        assert_eq!(formatter.format_value(value.clone()).unwrap(), s);
+        let value_parsed = formatter
+            .parse_value(s, Type::DateTime64(3, tz.clone()))
+            .unwrap();
+        assert_eq!(value_parsed, value);
+    }
+}
+
+#[test]
+fn fmt_tsv_enum() {
+    let formatter = TsvFormatter::default();
+    let ty = Type::Enum8(BTreeMap::from([("a".to_string(), 1), ("b".to_string(), 2)]));
+
+    let value = formatter.parse_value("b", ty.clone()).unwrap();
+    assert_eq!(value, Value::Enum8(2, Some("b".to_string())));
+    assert_eq!(formatter.format_value(value).unwrap(), "b");
+
+    // the enum value is also accepted
+    let value = formatter.parse_value("1", ty.clone()).unwrap();
+    assert_eq!(value, Value::Enum8(1, Some("a".to_string())));
+    assert!(formatter.parse_value("c", ty.clone()).is_err());
+
+    let value = formatter
+        .parse_value("['a', 'b']", Type::Array(Box::new(ty)))
+        .unwrap();
+    assert_eq!(formatter.format_value(value).unwrap(), "['a', 'b']");
+}
+
+#[test]
+fn fmt_tsv_map_typed_keys() {
+    let formatter = TsvFormatter::default();
+    let ty = Type::Map(
+        Box::new(Type::UInt64),
+        Box::new(Type::Map(Box::new(Type::String), Box::new(Type::String))),
+    );
+    let value = Value::Map(vec![
+        (
+            Value::UInt64(2),
+            Value::Map(vec![(
+                Value::String("k:2".to_string()),
+                Value::String("a,b".to_string()),
+            )]),
+        ),
+        (Value::UInt64(1), Value::Map(vec![])),
+    ]);
+    let value_str = formatter.format_value(value.clone()).unwrap();
+    assert_eq!(value_str, "{2: {'k:2': 'a,b'}, 1: {}}");
+
+    let value_parsed = formatter.parse_value(&value_str, ty).unwrap();
+    assert_eq!(value_parsed, value);
+}
+
+#[test]
+fn fmt_tsv_low_cardinality() {
+    let formatter = TsvFormatter::default();
+    let ty = Type::Array(Box::new(Type::LowCardinality(Box::new(Type::String))));
+    let value = formatter.parse_value("['a', 'b']", ty).unwrap();
+    assert_eq!(
+        value,
+        Value::Array(vec!["a".to_string().into(), "b".to_string().into()])
+    );
+}
+
+#[test]
+fn fmt_tsv_nested() {
+    let ty = Type::Nested(vec![
+        ("a".to_string(), Type::UInt8),
+        ("b".to_string(), Type::String),
+    ]);
+    let value = Value::Nested(vec![
+        ("a".to_string(), vec![Value::UInt8(1), Value::UInt8(2)]),
+        (
+            "b".to_string(),
+            vec!["x".to_string().into(), "y".to_string().into()],
+        ),
+    ]);
+    let mapping = vec![("id", Type::UInt8), ("n", ty.clone())];
+    let table =
+        QueryData::with_names_and_types(mapping.clone()).row(vec![Value::UInt8(0), value.clone()]);
+
+    // flattened columns
+    let format = Format::TabSepWithNamesAndTypes;
+    let bytes = table.clone().to_bytes(format).unwrap();
+    assert_eq!(
+        String::from_utf8(bytes.clone()).unwrap(),
+        "id\tn.a\tn.b\nUInt8\tArray(UInt8)\tArray(String)\n0\t[1, 2]\t['x', 'y']\n"
+    );
+    let table_parsed = QueryData::from_bytes(&bytes, format, Some(&mapping)).unwrap();
+    assert_eq!(table_parsed, table);
+
+    // array of tuples
+    let formatter = TsvFormatter::default().flatten_nested(false);
+    let value_str = formatter.format_value(value.clone()).unwrap();
+    assert_eq!(value_str, "[(1, 'x'), (2, 'y')]");
+    let value_parsed = formatter.parse_value(&value_str, ty).unwrap();
+    assert_eq!(value_parsed, value);
+}
+
+#[test]
+fn fmt_tsv_bytes_raw() {
+    // NB: Clickhouse writes the bytes which are not valid UTF-8 as is
+    let formatter = TsvFormatter::default();
+    let bytes = [b'[', b'\'', b'a', 0xff, b'\'', b']'];
+    let ty = Type::Array(Box::new(Type::String));
+    let value = formatter.deserialize_value(&bytes, ty.clone()).unwrap();
+    assert_eq!(value, Value::Array(vec![Value::Bytes(vec![b'a', 0xff])]));
+
+    let formatter = TsvFormatter::default().utf8_lossy(true);
+    let value = formatter.deserialize_value(&bytes, ty).unwrap();
+    assert_eq!(value, Value::Array(vec!["a\u{FFFD}".to_string().into()]));
+}
+
+#[test]
+fn fmt_tsv_variant() {
+    let formatter = TsvFormatter::default();
+    let ty = Type::Variant(vec![Type::UInt64, Type::String]);
+    let value = formatter.parse_value("12", ty.clone()).unwrap();
+    assert_eq!(
+        value,
+        Value::Variant(Some((1, Box::new(Value::UInt64(12)))))
+    );
+    assert_eq!(formatter.format_value(value).unwrap(), "12");
+
+    let value = formatter.parse_value("a\\tb", ty.clone()).unwrap();
+    assert_eq!(value, Value::Variant(Some((0, Box::new("a\tb".into())))));
+    assert_eq!(formatter.format_value(value).unwrap(), "a\\tb");
+
+    let value = formatter.parse_value("\\N", ty).unwrap();
+    assert_eq!(value, Value::Variant(None));
+    assert_eq!(formatter.format_value(value).unwrap(), "\\N");
+}
+
+#[test]
+fn fmt_tsv_dynamic() {
+    let formatter = TsvFormatter::default();
+    let value = Value::dynamic(vec![1_u8, 2]);
+    assert_eq!(formatter.format_value(value).unwrap(), "[1, 2]");
+
+    let value = formatter.parse_value("x", Type::Dynamic(None)).unwrap();
+    assert_eq!(value, Value::dynamic("x".to_string()));
+}
+
+#[test]
+fn fmt_tsv_json() {
+    let formatter = TsvFormatter::default();
+    let value = Value::Json(vec![
+        ("a.b".to_string(), Value::UInt8(1)),
+        ("a.c".to_string(), Value::dynamic("x\ty".to_string())),
+        ("d".to_string(), Value::Dynamic(None)),
+    ]);
+    assert_eq!(
+        formatter.format_value(value.clone()).unwrap(),
+        r#"{"a":{"b":1,"c":"x\\ty"}}"#
+    );
+
+    let ty = Type::from_str("JSON(a.b UInt8)").unwrap();
+    let value_parsed = formatter
+        .parse_value(r#"{"a":{"b":1,"c":"x\\ty"}}"#, ty)
+        .unwrap();
+    assert_eq!(
+        value_parsed,
+        Value::Json(vec![
+            ("a.b".to_string(), Value::UInt8(1)),
+            ("a.c".to_string(), Value::dynamic("x\ty".to_string())),
+        ])
+    );
+}
+
+#[test]
+fn fmt_tsv_aggregate_function_state() {
+    let formatter = TsvFormatter::default();
+    let ty = Type::from_str("AggregateFunction(uniq, UInt64)").unwrap();
+    let value = formatter.parse_value(r"\x01\0a\xFF", ty.clone()).unwrap();
+    assert_eq!(
+        value,
+        Value::AggregateFunction(vec![0x01, 0x00, b'a', 0xff])
+    );
+    let value_str = formatter.format_value(value.clone()).unwrap();
+    // NB: only the invalid UTF-8 bytes and the zero bytes are escaped
+    assert_eq!(value_str, "\x01\\x00a\\xFF");
+    assert_eq!(formatter.parse_value(&value_str, ty).unwrap(), value);
+    assert_eq!(
+        Bytes::from_ch_value(value).unwrap(),
+        Bytes::from_static(&[0x01, 0x00, b'a', 0xff])
+    );
+
+    let ty = Type::from_str("SimpleAggregateFunction(anyLast, Nullable(String))").unwrap();
+    let value = formatter.parse_value(r"\N", ty).unwrap();
+    assert_eq!(value, Value::NullableString(None));
+}
+
+#[test]
+fn fmt_tsv_time_interval() {
+    let formatter = TsvFormatter::default();
+    let cases = [
+        (Value::Time(-3723), Type::Time, "-01:02:03"),
+        (Value::Time(360_000), Type::Time, "100:00:00"),
+        (Value::Time64(3_723_050, 3), Type::Time64(3), "01:02:03.050"),
+        (
+            Value::NullableTime64(None, 3),
+            Type::NullableTime64(3),
+            r"\N",
+        ),
+        (
+            Value::Interval(-5, IntervalKind::Minute),
+            Type::Interval(IntervalKind::Minute),
+            "-5",
+        ),
+        (Value::BFloat16(-2.5), Type::BFloat16, "-2.5"),
+        (Value::Null, Type::NullableNothing, r"\N"),
+    ];
+    for (value, ty, value_str) in cases {
+        assert_eq!(formatter.format_value(value.clone()).unwrap(), value_str);
+        assert_eq!(formatter.parse_value(value_str, ty).unwrap(), value);
+    }
+
+    let value = formatter.parse_value("02:03.5", Type::Time64(1)).unwrap();
+    assert_eq!(value, Value::Time64(1235, 1));
+    let value = formatter
+        .parse_value("['00:00:01']", Type::Array(Box::new(Type::Time)))
+        .unwrap();
+    assert_eq!(value, Value::Array(vec![Value::Time(1)]));
+    assert!(formatter.parse_value("1:xx", Type::Time).is_err());
+
+    let value = formatter
+        .parse_value("90", Type::Interval(IntervalKind::Second))
+        .unwrap();
+    assert_eq!(
+        time::Duration::from_ch_value(value).unwrap(),
+        time::Duration::seconds(90)
+    );
+    assert_eq!(
+        time::Duration::from_ch_value(Value::Time64(1500, 3)).unwrap(),
+        time::Duration::milliseconds(1500)
+    );
+}
+
+#[test]
+fn fmt_tsv_named_tuple() {
+    let formatter = TsvFormatter::default();
+    let ty: Type = "Tuple(name String, values Array(UInt8))".parse().unwrap();
+    let value = Value::NamedTuple(vec![
+        ("name".to_string(), Value::String("a, b".to_string())),
+        (
+            "values".to_string(),
+            Value::Array(vec![Value::UInt8(1), Value::UInt8(2)]),
+        ),
+    ]);
+    assert_eq!(
+        formatter.format_value(value.clone()).unwrap(),
+        "('a, b', [1, 2])"
+    );
+    let value_parsed = formatter.parse_value("('a, b', [1, 2])", ty).unwrap();
+    assert_eq!(value_parsed, value);
+    assert_eq!(
+        value_parsed.get_element("values"),
+        Some(&Value::Array(vec![Value::UInt8(1), Value::UInt8(2)]))
+    );
+    assert_eq!(value.to_string(), "(name: a, b, values: [1, 2])");
+}
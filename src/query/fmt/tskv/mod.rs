@@ -0,0 +1,235 @@
+//! TSKV format
+//!
+//! Each row is a line of `key=value` fields separated by tabs, eg. `id=1\tname=a`. The values
+//! are escaped as with the `TabSeparated` format.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    error::Error,
+    query::{flatten_nested_mapping, QueryData},
+    value::{escape_bytes, Type, Value},
+};
+
+use super::{Formatter, TsvFormatter};
+
+/// TSKV formatter
+#[derive(Debug, Clone)]
+pub struct TskvFormatter {
+    /// Nested columns are flattened
+    flatten_nested: bool,
+    /// Unknown fields are skipped when parsing
+    skip_unknown_fields: bool,
+}
+
+impl Default for TskvFormatter {
+    fn default() -> Self {
+        Self {
+            flatten_nested: true,
+            skip_unknown_fields: false,
+        }
+    }
+}
+
+impl TskvFormatter {
+    /// Creates a new [TskvFormatter]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets if `Nested` columns are flattened (`true` by default)
+    ///
+    /// This must match the Clickhouse setting `flatten_nested`.
+    pub fn flatten_nested(mut self, flatten: bool) -> Self {
+        self.flatten_nested = flatten;
+        self
+    }
+
+    /// Sets if the fields which are not in the mapping are skipped (`false` by default)
+    pub fn skip_unknown_fields(mut self, skip: bool) -> Self {
+        self.skip_unknown_fields = skip;
+        self
+    }
+}
+
+impl Formatter for TskvFormatter {
+    fn serialize_value(&self, value: Value) -> Result<Vec<u8>, Error> {
+        self.format_value(value).map(String::into_bytes)
+    }
+
+    fn serialize_query_data(&self, data: QueryData) -> Result<Vec<u8>, Error> {
+        self.format_data(data).map(|s| s.into_bytes())
+    }
+
+    fn deserialize_value(&self, bytes: &[u8], ty: Type) -> Result<Value, Error> {
+        let value = escape_bytes(bytes, str::to_string);
+        self.parse_value(&value, ty)
+    }
+
+    fn deserialize_query_data(
+        &self,
+        bytes: &[u8],
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        // NB: the bytes which are not valid UTF-8 are escaped, and unescaped with the values
+        let value = escape_bytes(bytes, str::to_string);
+        self.parse_data(&value, mapping)
+    }
+
+    fn settings(&self) -> Vec<(String, String)> {
+        vec![(
+            "input_format_skip_unknown_fields".to_string(),
+            if self.skip_unknown_fields { "1" } else { "0" }.to_string(),
+        )]
+    }
+}
+
+impl TskvFormatter {
+    /// Formats a [Value]
+    pub fn format_value(&self, value: Value) -> Result<String, Error> {
+        TsvFormatter::new().format_value(value)
+    }
+
+    /// Parses a [Value]
+    pub fn parse_value(&self, value: &str, ty: Type) -> Result<Value, Error> {
+        TsvFormatter::new().parse_value(value, ty)
+    }
+
+    /// Formats a [QueryData]
+    ///
+    /// NB: the fields with a [Value::Default] are omitted, so that the server writes the column
+    /// default.
+    pub fn format_data(&self, data: QueryData) -> Result<String, Error> {
+        let data = match self.flatten_nested {
+            true => data.flatten_nested()?,
+            false => data,
+        };
+        let parts = data.into_parts();
+        let names = parts
+            .names
+            .ok_or(Error::new("Table is missing the column names"))?;
+
+        let mut buf = String::new();
+        for row in parts.rows {
+            let mut fields = vec![];
+            for (i, value) in row.into_iter().enumerate() {
+                if let Value::Default = value {
+                    continue;
+                }
+                let name = names
+                    .get(i)
+                    .ok_or(Error(format!("No column name for value at index {i}")))?;
+                // NB: values are conformed to the column types (eg DateTime64 precision)
+                let value = match parts.types.as_ref().and_then(|types| types.get(i)) {
+                    Some(ty) => value.conform_to(ty)?,
+                    None => value,
+                };
+                fields.push(format!(
+                    "{}={}",
+                    escape_key(name),
+                    self.format_value(value)?
+                ));
+            }
+            buf.push_str(&fields.join("\t"));
+            buf.push('\n');
+        }
+        Ok(buf)
+    }
+
+    /// Parses a [QueryData]
+    ///
+    /// The fields can be in any order. The missing fields are the column defaults
+    /// ([Value::Default]), and the fields without `=` are ignored (eg. a `tskv` prefix).
+    pub fn parse_data(
+        &self,
+        value: &str,
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        let mapping = mapping.ok_or(Error::new("Deserializing data requires a mapping table"))?;
+
+        // nested columns are received as 1 array column per nested column
+        let flat_mapping = match self.flatten_nested {
+            true => flatten_nested_mapping(mapping),
+            false => mapping
+                .iter()
+                .map(|(n, t)| (n.to_string(), t.clone()))
+                .collect(),
+        };
+        let mut data = QueryData::with_names_and_types(
+            flat_mapping
+                .iter()
+                .map(|(n, t)| (n.as_str(), t.clone()))
+                .collect(),
+        );
+
+        for line in value.split('\n').filter(|line| !line.is_empty()) {
+            let mut row = vec![Value::Default; flat_mapping.len()];
+            for field in line.split('\t') {
+                let Some((key, value)) = split_field(field) else {
+                    continue;
+                };
+                let key = unescape_key(key);
+                match flat_mapping.iter().position(|(name, _)| *name == key) {
+                    Some(i) => row[i] = self.parse_value(value, flat_mapping[i].1.clone())?,
+                    None if self.skip_unknown_fields => {}
+                    None => return Err(Error(format!("Unknown TSKV field: {key}"))),
+                }
+            }
+            data.add_row(row);
+        }
+
+        match self.flatten_nested {
+            true => data.unflatten_nested(mapping),
+            false => Ok(data),
+        }
+    }
+}
+
+/// Escapes a key (`=` ends the key)
+fn escape_key(key: &str) -> String {
+    let mut s = String::with_capacity(key.len());
+    for c in key.chars() {
+        match c {
+            '\\' => s.push_str(r"\\"),
+            '=' => s.push_str(r"\="),
+            '\t' => s.push_str(r"\t"),
+            '\n' => s.push_str(r"\n"),
+            c => s.push(c),
+        }
+    }
+    s
+}
+
+/// Unescapes a key
+fn unescape_key(key: &str) -> String {
+    let mut s = String::with_capacity(key.len());
+    let mut chars = key.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('t') => s.push('\t'),
+                Some('n') => s.push('\n'),
+                Some(c) => s.push(c),
+                None => s.push('\\'),
+            },
+            c => s.push(c),
+        }
+    }
+    s
+}
+
+/// Splits a field on the 1st `=` which is not escaped
+fn split_field(field: &str) -> Option<(&str, &str)> {
+    let mut chars = field.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '=' => return Some((&field[..i], &field[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
@@ -0,0 +1,67 @@
+//! Tests
+
+use crate::{
+    query::{Format, QueryData, TskvFormatter},
+    value::{Type, Value},
+};
+
+#[test]
+fn fmt_tskv_table() {
+    let mapping = vec![
+        ("id", Type::UInt32),
+        ("msg", Type::String),
+        ("level", Type::NullableString),
+    ];
+    let table = QueryData::with_names_and_types(mapping.clone())
+        .row(vec![
+            Value::UInt32(1),
+            "a=b\tc".to_string().into(),
+            Value::NullableString(None),
+        ])
+        .row(vec![
+            Value::UInt32(2),
+            "d".to_string().into(),
+            Value::NullableString(Some("info".to_string())),
+        ]);
+
+    let bytes = table.clone().to_bytes(Format::TSKV).unwrap();
+    assert_eq!(
+        String::from_utf8(bytes.clone()).unwrap(),
+        "id=1\tmsg=a=b\\tc\tlevel=\\N\nid=2\tmsg=d\tlevel=info\n"
+    );
+    let table_parsed = QueryData::from_bytes(&bytes, Format::TSKV, Some(&mapping)).unwrap();
+    assert_eq!(table_parsed, table);
+}
+
+#[test]
+fn fmt_tskv_parse() {
+    let mapping = vec![("id", Type::UInt32), ("a=b", Type::String)];
+
+    // NB: the fields are in any order, the missing fields are the defaults and the fields
+    // without `=` are ignored
+    let value = "tskv\ta\\=b=x\tid=1\nid=2\n";
+    let formatter = TskvFormatter::new();
+    let table = formatter.parse_data(value, Some(&mapping)).unwrap();
+    assert_eq!(
+        table.get_rows(),
+        &vec![
+            vec![Value::UInt32(1), "x".to_string().into()],
+            vec![Value::UInt32(2), Value::Default],
+        ]
+    );
+
+    // NB: the defaults are omitted
+    assert_eq!(
+        formatter.format_data(table).unwrap(),
+        "id=1\ta\\=b=x\nid=2\n"
+    );
+
+    let value = "id=1\tother=x\n";
+    let err = formatter.parse_data(value, Some(&mapping)).unwrap_err();
+    assert_eq!(err.0, "Unknown TSKV field: other");
+    let table = formatter
+        .skip_unknown_fields(true)
+        .parse_data(value, Some(&mapping))
+        .unwrap();
+    assert_eq!(table.get_rows().len(), 1);
+}
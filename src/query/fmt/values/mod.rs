@@ -0,0 +1,376 @@
+//! Values and SQLInsert formats
+//!
+//! The rows are written as tuples of SQL literals, eg. `(1, 'a'), (2, 'b')`. The `SQLInsert`
+//! format writes the tuples within `INSERT INTO` statements.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    error::Error,
+    query::{flatten_nested_mapping, QueryData, QueryDataParts},
+    value::{Type, Value},
+};
+
+use super::{CustomFormatter, EscapingRule, Formatter};
+
+/// Values formatter
+///
+/// The values are SQL literals, escaped as with the `Quoted` escaping rule.
+#[derive(Debug, Clone)]
+pub struct ValuesFormatter {
+    /// `INSERT INTO` statements (`SQLInsert` format)
+    sql_insert: bool,
+    /// Table name of the statements
+    table_name: String,
+    /// The statements have the column names
+    include_column_names: bool,
+    /// The column names are quoted with backquotes
+    quote_names: bool,
+    /// `REPLACE INTO` statements (instead of `INSERT INTO`)
+    use_replace: bool,
+    /// Maximum number of rows per statement
+    max_batch_size: usize,
+    /// Nested columns are flattened
+    flatten_nested: bool,
+}
+
+impl Default for ValuesFormatter {
+    fn default() -> Self {
+        Self {
+            sql_insert: false,
+            table_name: "table".to_string(),
+            include_column_names: true,
+            quote_names: true,
+            use_replace: false,
+            max_batch_size: 65505,
+            flatten_nested: true,
+        }
+    }
+}
+
+impl ValuesFormatter {
+    /// Creates a new [ValuesFormatter] (`Values` format)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new [ValuesFormatter] for the `SQLInsert` format
+    pub fn sql_insert() -> Self {
+        Self {
+            sql_insert: true,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the table name of the statements (`table` by default)
+    pub fn table_name(mut self, name: &str) -> Self {
+        self.table_name = name.to_string();
+        self
+    }
+
+    /// Sets if the statements have the column names (`true` by default)
+    pub fn include_column_names(mut self, include: bool) -> Self {
+        self.include_column_names = include;
+        self
+    }
+
+    /// Sets if the column names are quoted with backquotes (`true` by default)
+    pub fn quote_names(mut self, quote: bool) -> Self {
+        self.quote_names = quote;
+        self
+    }
+
+    /// Sets if the statements are `REPLACE INTO` statements (`false` by default)
+    pub fn use_replace(mut self, replace: bool) -> Self {
+        self.use_replace = replace;
+        self
+    }
+
+    /// Sets the maximum number of rows per statement (`65505` by default)
+    pub fn max_batch_size(mut self, size: usize) -> Self {
+        self.max_batch_size = size;
+        self
+    }
+
+    /// Sets if `Nested` columns are flattened (`true` by default)
+    ///
+    /// This must match the Clickhouse setting `flatten_nested`.
+    pub fn flatten_nested(mut self, flatten: bool) -> Self {
+        self.flatten_nested = flatten;
+        self
+    }
+}
+
+impl Formatter for ValuesFormatter {
+    fn serialize_value(&self, value: Value) -> Result<Vec<u8>, Error> {
+        self.format_value(value).map(String::into_bytes)
+    }
+
+    fn serialize_query_data(&self, data: QueryData) -> Result<Vec<u8>, Error> {
+        self.format_data(data).map(|s| s.into_bytes())
+    }
+
+    fn deserialize_value(&self, bytes: &[u8], ty: Type) -> Result<Value, Error> {
+        let value = String::from_utf8_lossy(bytes);
+        self.parse_value(&value, ty)
+    }
+
+    fn deserialize_query_data(
+        &self,
+        bytes: &[u8],
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        let value = String::from_utf8_lossy(bytes);
+        self.parse_data(&value, mapping)
+    }
+
+    fn settings(&self) -> Vec<(String, String)> {
+        if !self.sql_insert {
+            return vec![];
+        }
+        let flag = |b: bool| if b { "1" } else { "0" }.to_string();
+        vec![
+            (
+                "output_format_sql_insert_table_name".to_string(),
+                self.table_name.clone(),
+            ),
+            (
+                "output_format_sql_insert_include_column_names".to_string(),
+                flag(self.include_column_names),
+            ),
+            (
+                "output_format_sql_insert_quote_names".to_string(),
+                flag(self.quote_names),
+            ),
+            (
+                "output_format_sql_insert_use_replace".to_string(),
+                flag(self.use_replace),
+            ),
+            (
+                "output_format_sql_insert_max_batch_size".to_string(),
+                self.max_batch_size.to_string(),
+            ),
+        ]
+    }
+}
+
+impl ValuesFormatter {
+    /// Returns the formatter of the rows
+    ///
+    /// NB: whitespaces are ignored when parsing, as in SQL.
+    fn rows_formatter(&self) -> CustomFormatter {
+        CustomFormatter::new()
+            .escaping_rule(EscapingRule::Quoted)
+            .field_delimiter(", ")
+            .row_before_delimiter("(")
+            .row_after_delimiter(")")
+            .row_between_delimiter(", ")
+            .ignore_spaces(true)
+    }
+
+    /// Formats a [Value] as a SQL literal
+    pub fn format_value(&self, value: Value) -> Result<String, Error> {
+        self.rows_formatter().format_value(value)
+    }
+
+    /// Parses a SQL literal
+    pub fn parse_value(&self, value: &str, ty: Type) -> Result<Value, Error> {
+        self.rows_formatter().parse_value(value, ty)
+    }
+
+    /// Formats a [QueryData]
+    pub fn format_data(&self, data: QueryData) -> Result<String, Error> {
+        let formatter = self.rows_formatter().flatten_nested(false);
+        let data = match self.flatten_nested {
+            true => data.flatten_nested()?,
+            false => data,
+        };
+        if !self.sql_insert {
+            return formatter.format_data(data);
+        }
+
+        let parts = data.into_parts();
+        let columns = match (self.include_column_names, &parts.names) {
+            (false, _) => String::new(),
+            (true, Some(names)) => {
+                let names = names
+                    .iter()
+                    .map(|name| match self.quote_names {
+                        true => format!("`{}`", name.replace('\\', r"\\").replace('`', r"\`")),
+                        false => name.clone(),
+                    })
+                    .collect::<Vec<_>>();
+                format!(" ({})", names.join(", "))
+            }
+            (true, None) => return Err(Error::new("Table is missing the column names")),
+        };
+        let keyword = match self.use_replace {
+            true => "REPLACE",
+            false => "INSERT",
+        };
+
+        let mut statements = String::new();
+        for rows in parts.rows.chunks(self.max_batch_size.max(1)) {
+            let batch = QueryData::from_parts(QueryDataParts {
+                names: parts.names.clone(),
+                types: parts.types.clone(),
+                rows: rows.to_vec(),
+            });
+            statements.push_str(&format!(
+                "{keyword} INTO {}{columns} VALUES {};\n",
+                self.table_name,
+                formatter.format_data(batch)?
+            ));
+        }
+        Ok(statements)
+    }
+
+    /// Parses a [QueryData]
+    ///
+    /// For the `SQLInsert` format, the rows of all the statements are concatenated, and the
+    /// column names of the statements must match the mapping.
+    pub fn parse_data(
+        &self,
+        value: &str,
+        mapping: Option<&[(&str, Type)]>,
+    ) -> Result<QueryData, Error> {
+        let formatter = self.rows_formatter().flatten_nested(self.flatten_nested);
+        if !self.sql_insert {
+            return formatter.parse_data(value, mapping);
+        }
+
+        let mut data: Option<QueryData> = None;
+        let mut reader = StatementReader { s: value };
+        while let Some(Statement { names, values }) = reader.read_statement()? {
+            if let (Some(names), Some(mapping)) = (&names, mapping) {
+                let mapping_names: Vec<String> = match self.flatten_nested {
+                    true => flatten_nested_mapping(mapping)
+                        .into_iter()
+                        .map(|(name, _)| name)
+                        .collect(),
+                    false => mapping.iter().map(|(name, _)| name.to_string()).collect(),
+                };
+                if *names != mapping_names {
+                    return Err(Error::new("SQLInsert columns do not match the mapping"));
+                }
+            }
+
+            let statement_data = formatter.parse_data(values, mapping)?;
+            match &mut data {
+                Some(data) => {
+                    data.add_rows(statement_data.into_parts().rows);
+                }
+                None => data = Some(statement_data),
+            }
+        }
+        Ok(data.unwrap_or_default())
+    }
+}
+
+/// Statement of a SQLInsert buffer
+struct Statement<'a> {
+    /// Column names
+    names: Option<Vec<String>>,
+    /// Values (the rows)
+    values: &'a str,
+}
+
+/// Reader of the statements of a SQLInsert buffer
+struct StatementReader<'a> {
+    /// Remaining text
+    s: &'a str,
+}
+
+impl<'a> StatementReader<'a> {
+    /// Consumes a keyword (case insensitive)
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), Error> {
+        self.s = self.s.trim_start();
+        match self.s.get(..keyword.len()) {
+            Some(prefix) if prefix.eq_ignore_ascii_case(keyword) => {
+                self.s = &self.s[keyword.len()..];
+                Ok(())
+            }
+            _ => Err(Error(format!("Expected SQLInsert keyword '{keyword}'"))),
+        }
+    }
+
+    /// Reads a statement
+    ///
+    /// Returns `None` at the end of the text.
+    fn read_statement(&mut self) -> Result<Option<Statement<'a>>, Error> {
+        self.s = self.s.trim_start();
+        if self.s.is_empty() {
+            return Ok(None);
+        }
+        if self.expect_keyword("INSERT").is_err() {
+            self.expect_keyword("REPLACE")?;
+        }
+        self.expect_keyword("INTO")?;
+
+        // NB: the table name is skipped
+        self.s = self.s.trim_start();
+        let table_end = self
+            .s
+            .find(|c: char| c.is_whitespace() || c == '(')
+            .ok_or(Error::new("Missing SQLInsert values"))?;
+        self.s = self.s[table_end..].trim_start();
+
+        let names = match self.s.strip_prefix('(') {
+            Some(rest) => {
+                let end = rest
+                    .find(')')
+                    .ok_or(Error::new("Missing SQLInsert column names"))?;
+                self.s = &rest[end + 1..];
+                Some(rest[..end].split(',').map(parse_identifier).collect())
+            }
+            None => None,
+        };
+        self.expect_keyword("VALUES")?;
+
+        let end = self.scan_statement_end();
+        let values = &self.s[..end];
+        self.s = self.s[end..].strip_prefix(';').unwrap_or_default();
+        Ok(Some(Statement { names, values }))
+    }
+
+    /// Returns the position of the end of the statement (`;` which is not within a value)
+    fn scan_statement_end(&self) -> usize {
+        let mut chars = self.s.char_indices();
+        let mut in_quotes = false;
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' if in_quotes => {
+                    chars.next();
+                }
+                '\'' => in_quotes = !in_quotes,
+                ';' if !in_quotes => return i,
+                _ => {}
+            }
+        }
+        self.s.len()
+    }
+}
+
+/// Parses an identifier (with optional backquotes or double quotes)
+fn parse_identifier(s: &str) -> String {
+    let s = s.trim();
+    let unquoted = s
+        .strip_prefix('`')
+        .and_then(|s| s.strip_suffix('`'))
+        .or_else(|| s.strip_prefix('"').and_then(|s| s.strip_suffix('"')));
+    match unquoted {
+        Some(s) => {
+            let mut name = String::with_capacity(s.len());
+            let mut chars = s.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => name.extend(chars.next()),
+                    c => name.push(c),
+                }
+            }
+            name
+        }
+        None => s.to_string(),
+    }
+}
@@ -0,0 +1,111 @@
+//! Tests
+
+use crate::{
+    query::{Format, Formatter, QueryData, ValuesFormatter},
+    value::{Type, Value},
+};
+
+/// Creates a sample table
+fn sample_table() -> (Vec<(&'static str, Type)>, QueryData) {
+    let mapping = vec![
+        ("id", Type::UInt32),
+        ("name", Type::NullableString),
+        ("tags", Type::Array(Box::new(Type::String))),
+    ];
+    let table = QueryData::with_names_and_types(mapping.clone())
+        .row(vec![
+            Value::UInt32(1),
+            Value::NullableString(Some("it's (a), b".to_string())),
+            vec!["x\\y".to_string()].into(),
+        ])
+        .row(vec![
+            Value::UInt32(2),
+            Value::NullableString(None),
+            Value::Array(vec![]),
+        ]);
+    (mapping, table)
+}
+
+#[test]
+fn fmt_values_table() {
+    let (mapping, table) = sample_table();
+
+    let format = Format::Values;
+    let bytes = table.clone().to_bytes(format).unwrap();
+    assert_eq!(
+        String::from_utf8(bytes.clone()).unwrap(),
+        r"(1, 'it\'s (a), b', ['x\\y']), (2, NULL, [])"
+    );
+    let table_parsed = QueryData::from_bytes(&bytes, format, Some(&mapping)).unwrap();
+    assert_eq!(table_parsed.get_rows(), table.get_rows());
+
+    // NB: whitespaces are ignored
+    let table_parsed = ValuesFormatter::new()
+        .parse_data("(1,'a',[]) ,\n( 2 , NULL , ['b'] )", Some(&mapping))
+        .unwrap();
+    assert_eq!(
+        table_parsed.get_rows(),
+        &vec![
+            vec![
+                Value::UInt32(1),
+                Value::NullableString(Some("a".to_string())),
+                Value::Array(vec![]),
+            ],
+            vec![
+                Value::UInt32(2),
+                Value::NullableString(None),
+                vec!["b".to_string()].into(),
+            ],
+        ]
+    );
+}
+
+#[test]
+fn fmt_values_sql_insert() {
+    let (mapping, table) = sample_table();
+
+    let formatter = ValuesFormatter::sql_insert()
+        .table_name("db.t")
+        .max_batch_size(1);
+    let value = formatter.format_data(table.clone()).unwrap();
+    assert_eq!(
+        value,
+        concat!(
+            "INSERT INTO db.t (`id`, `name`, `tags`) VALUES (1, 'it\\'s (a), b', ['x\\\\y']);\n",
+            "INSERT INTO db.t (`id`, `name`, `tags`) VALUES (2, NULL, []);\n",
+        )
+    );
+    let table_parsed = formatter.parse_data(&value, Some(&mapping)).unwrap();
+    assert_eq!(table_parsed.get_rows(), table.get_rows());
+
+    let formatter = ValuesFormatter::sql_insert()
+        .include_column_names(false)
+        .use_replace(true);
+    let value = formatter.format_data(table.clone()).unwrap();
+    assert!(value.starts_with("REPLACE INTO table VALUES (1, "));
+    let table_parsed = formatter.parse_data(&value, Some(&mapping)).unwrap();
+    assert_eq!(table_parsed.get_rows(), table.get_rows());
+
+    let err = formatter
+        .parse_data("insert into t (id, x) values (1, 'a', [])", Some(&mapping))
+        .unwrap_err();
+    assert_eq!(err.0, "SQLInsert columns do not match the mapping");
+}
+
+#[test]
+fn fmt_values_settings() {
+    assert!(ValuesFormatter::new().settings().is_empty());
+
+    let settings = ValuesFormatter::sql_insert()
+        .table_name("t")
+        .quote_names(false)
+        .settings();
+    assert!(settings.contains(&(
+        "output_format_sql_insert_table_name".to_string(),
+        "t".to_string()
+    )));
+    assert!(settings.contains(&(
+        "output_format_sql_insert_quote_names".to_string(),
+        "0".to_string()
+    )));
+}
@@ -0,0 +1,195 @@
+//! JSON
+
+use crate::value::Value;
+
+impl Value {
+    /// Converts a [Value] to a JSON string
+    ///
+    /// Strings, UUIDs, dates and named enums are written as JSON strings, and the paths of a
+    /// JSON object are written as nested objects (eg `a.b` is written as `{"a":{"b":..}}`).
+    pub fn to_json_string(&self) -> String {
+        /// Implements the nullable variant for formatting
+        macro_rules! impl_nullable {
+            ($VAL:expr, $VAR:ident $(, $ARG:expr)*) => {
+                match $VAL {
+                    Some(v) => Value::$VAR(v $(, $ARG)*).to_json_string(),
+                    None => "null".to_string(),
+                }
+            };
+        }
+
+        match self {
+            Value::UInt8(v) => v.to_string(),
+            Value::UInt16(v) => v.to_string(),
+            Value::UInt32(v) => v.to_string(),
+            Value::UInt64(v) => v.to_string(),
+            Value::UInt128(v) => v.to_string(),
+            Value::Int8(v) => v.to_string(),
+            Value::Int16(v) => v.to_string(),
+            Value::Int32(v) => v.to_string(),
+            Value::Int64(v) => v.to_string(),
+            Value::Int128(v) => v.to_string(),
+            Value::UInt256(_) | Value::Int256(_) => self.to_string(),
+            // NB: NaN and infinite numbers are not valid JSON numbers
+            Value::Float32(v) if v.is_finite() => v.to_string(),
+            Value::Float64(v) if v.is_finite() => v.to_string(),
+            Value::BFloat16(v) if v.is_finite() => v.to_string(),
+            Value::Float32(_) | Value::Float64(_) | Value::BFloat16(_) => "null".to_string(),
+            Value::Bool(v) => v.to_string(),
+            Value::String(v) => quote_json(v),
+            Value::Bytes(v) | Value::FixedString(v) | Value::AggregateFunction(v) => {
+                quote_json(&String::from_utf8_lossy(v))
+            }
+            Value::UUID(_)
+            | Value::Date(_)
+            | Value::Date32(_)
+            | Value::DateTime(..)
+            | Value::DateTime64(..)
+            | Value::Time(_)
+            | Value::Time64(..)
+            | Value::Enum8(_, Some(_))
+            | Value::Enum16(_, Some(_)) => quote_json(&self.to_string()),
+            Value::Interval(v, _) => v.to_string(),
+            Value::Enum8(v, None) => v.to_string(),
+            Value::Enum16(v, None) => v.to_string(),
+            Value::Array(values) | Value::Tuple(values) => format!(
+                "[{}]",
+                values
+                    .iter()
+                    .map(|v| v.to_json_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            Value::NamedTuple(values) => format!(
+                "{{{}}}",
+                values
+                    .iter()
+                    .map(|(name, v)| format!("{}:{}", quote_json(name), v.to_json_string()))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            Value::Map(map) => format!(
+                "{{{}}}",
+                map.iter()
+                    .map(|(k, v)| {
+                        let key = match k {
+                            Value::String(k) => quote_json(k),
+                            k => quote_json(&k.to_string()),
+                        };
+                        format!("{key}:{}", v.to_json_string())
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            Value::Nested(fields) => format!(
+                "{{{}}}",
+                fields
+                    .iter()
+                    .map(|(name, col)| {
+                        let col = Value::Array(col.clone()).to_json_string();
+                        format!("{}:{col}", quote_json(name))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            Value::Variant(Some((_, v))) | Value::Dynamic(Some((_, v))) => v.to_json_string(),
+            Value::Variant(None) | Value::Dynamic(None) => "null".to_string(),
+            Value::Json(values) => {
+                // NB: NULL dynamic paths are not stored
+                let entries = values
+                    .iter()
+                    .filter(|(_, v)| !matches!(v, Value::Dynamic(None)))
+                    .map(|(path, v)| (path.as_str(), v.to_json_string()))
+                    .collect::<Vec<_>>();
+                format_json_object(&entries)
+            }
+            Value::NullableUInt8(v) => impl_nullable!(*v, UInt8),
+            Value::NullableUInt16(v) => impl_nullable!(*v, UInt16),
+            Value::NullableUInt32(v) => impl_nullable!(*v, UInt32),
+            Value::NullableUInt64(v) => impl_nullable!(*v, UInt64),
+            Value::NullableUInt128(v) => impl_nullable!(*v, UInt128),
+            Value::NullableUInt256(v) => impl_nullable!(*v, UInt256),
+            Value::NullableInt8(v) => impl_nullable!(*v, Int8),
+            Value::NullableInt16(v) => impl_nullable!(*v, Int16),
+            Value::NullableInt32(v) => impl_nullable!(*v, Int32),
+            Value::NullableInt64(v) => impl_nullable!(*v, Int64),
+            Value::NullableInt128(v) => impl_nullable!(*v, Int128),
+            Value::NullableInt256(v) => impl_nullable!(*v, Int256),
+            Value::NullableFloat32(v) => impl_nullable!(*v, Float32),
+            Value::NullableFloat64(v) => impl_nullable!(*v, Float64),
+            Value::NullableBFloat16(v) => impl_nullable!(*v, BFloat16),
+            Value::NullableBool(v) => impl_nullable!(*v, Bool),
+            Value::NullableString(v) => impl_nullable!(v.clone(), String),
+            Value::NullableBytes(v) => impl_nullable!(v.clone(), Bytes),
+            Value::NullableFixedString(v) => impl_nullable!(v.clone(), FixedString),
+            Value::NullableUUID(v) => impl_nullable!(*v, UUID),
+            Value::NullableDate(v) => impl_nullable!(*v, Date),
+            Value::NullableDate32(v) => impl_nullable!(*v, Date32),
+            Value::NullableDateTime(v, tz) => impl_nullable!(*v, DateTime, tz.clone()),
+            Value::NullableDateTime64(v, p, tz) => impl_nullable!(*v, DateTime64, *p, tz.clone()),
+            Value::NullableTime(v) => impl_nullable!(*v, Time),
+            Value::NullableTime64(v, p) => impl_nullable!(*v, Time64, *p),
+            Value::NullableInterval(v, kind) => impl_nullable!(*v, Interval, *kind),
+            Value::NullableEnum8(v, name) => impl_nullable!(*v, Enum8, name.clone()),
+            Value::NullableEnum16(v, name) => impl_nullable!(*v, Enum16, name.clone()),
+            Value::Null => "null".to_string(),
+            // NB: null is read as the column default (setting `input_format_null_as_default`)
+            Value::Default => "null".to_string(),
+        }
+    }
+}
+
+/// Formats a JSON object from (path, JSON value) entries
+///
+/// Entries are grouped by the first segment of their path, in order of appearance.
+fn format_json_object(entries: &[(&str, String)]) -> String {
+    let mut keys = vec![];
+    for (path, _) in entries {
+        let key = path.split_once('.').map_or(*path, |(key, _)| key);
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    let fields = keys
+        .into_iter()
+        .map(|key| {
+            let mut leaf = None;
+            let mut children = vec![];
+            for (path, value) in entries {
+                match path.split_once('.') {
+                    Some((k, rest)) if k == key => children.push((rest, value.clone())),
+                    None if *path == key => leaf = Some(value.clone()),
+                    _ => {}
+                }
+            }
+            let value = match leaf {
+                Some(value) if children.is_empty() => value,
+                _ => format_json_object(&children),
+            };
+            format!("{}:{value}", quote_json(key))
+        })
+        .collect::<Vec<_>>();
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Quotes and escapes a JSON string
+pub(crate) fn quote_json(s: &str) -> String {
+    let mut buf = String::with_capacity(s.len() + 2);
+    buf.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => buf.push_str(r#"\""#),
+            '\\' => buf.push_str(r"\\"),
+            '\n' => buf.push_str(r"\n"),
+            '\r' => buf.push_str(r"\r"),
+            '\t' => buf.push_str(r"\t"),
+            '\u{08}' => buf.push_str(r"\b"),
+            '\u{0C}' => buf.push_str(r"\f"),
+            c if c.is_control() => buf.push_str(&format!("\\u{:04x}", c as u32)),
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+    buf
+}
@@ -1,10 +1,12 @@
 //! Queries
 
+mod columns;
 mod comp;
 mod crud;
 mod data;
 mod exec;
 mod fmt;
+mod json;
 mod result;
 mod sql;
 mod stmt;
@@ -12,6 +14,7 @@ mod stmt;
 #[cfg(test)]
 mod tests;
 
+pub use columns::*;
 pub use comp::*;
 pub use crud::*;
 pub use data::*;
@@ -21,6 +24,8 @@ pub use result::*;
 pub use sql::*;
 pub use stmt::*;
 
+use std::sync::Arc;
+
 use crate::value::{ChValue, Value};
 
 /// Query
@@ -32,12 +37,20 @@ pub struct Query {
     pub statement: String,
     /// Data
     pub data: Option<QueryData>,
+    /// Raw data (already formatted, takes precedence over the data)
+    pub raw_data: Option<Vec<u8>>,
     /// Target DB
     pub db: Option<String>,
     /// Credentials (username, password)
     pub credentials: Option<(String, String)>,
     /// Format
     pub format: Option<Format>,
+    /// Formatter (takes precedence over the default formatter of the format)
+    pub formatter: Option<Arc<dyn Formatter>>,
+    /// Custom format name (resolved with the formatters registered on the client)
+    pub custom_format: Option<String>,
+    /// Settings (eg `max_threads`)
+    pub settings: Vec<(String, String)>,
     /// Compress the request
     pub compress_request: Option<Compression>,
     /// Compress the HTTP response
@@ -50,9 +63,13 @@ impl Query {
         Query {
             statement: stmt.to_string(),
             data: None,
+            raw_data: None,
             db: None,
             credentials: None,
             format: None,
+            formatter: None,
+            custom_format: None,
+            settings: vec![],
             compress_request: None,
             compress_response: None,
         }
@@ -70,6 +87,12 @@ impl Query {
         self
     }
 
+    /// Asssigns the raw query data, already formatted
+    pub fn raw_data(mut self, bytes: Vec<u8>) -> Self {
+        self.raw_data = Some(bytes);
+        self
+    }
+
     /// Binds the statement with a [ChValue]
     ///
     /// Query parameters are defined by `??`
@@ -120,6 +143,32 @@ impl Query {
         self
     }
 
+    /// Assigns a formatter
+    ///
+    /// The formatter serializes the query data and deserializes the response, and its options
+    /// are sent as settings (eg. `format_custom_*` for a [CustomFormatter]).
+    pub fn formatter(mut self, formatter: impl Formatter + 'static) -> Self {
+        self.formatter = Some(Arc::new(formatter));
+        self
+    }
+
+    /// Assigns a custom format
+    ///
+    /// The custom format must be registered on the client (see [FormatterRegistry]), and it
+    /// sets the format and the formatter of the query.
+    pub fn custom_format(mut self, name: &str) -> Self {
+        self.custom_format = Some(name.to_string());
+        self
+    }
+
+    /// Adds a setting
+    ///
+    /// NB: settings override the settings of the formatter with the same name.
+    pub fn setting(mut self, name: &str, value: &str) -> Self {
+        self.settings.push((name.to_string(), value.to_string()));
+        self
+    }
+
     /// Compress the HTTP request
     ///
     /// Eg. RowBinary
@@ -1,8 +1,12 @@
 //! Query result
 
+use std::sync::Arc;
+
 use crate::{error::Error, value::Type};
 
-use super::{Format, QueryData};
+use super::{
+    Format, Formatter, JsonFormatter, JsonMetadata, NativeFormatter, QueryColumns, QueryData,
+};
 
 /// Query response
 #[derive(Debug, Clone)]
@@ -11,16 +15,103 @@ pub struct QueryResponse {
     pub format: Format,
     /// Raw data
     pub data: Vec<u8>,
+    /// Formatter (takes precedence over the default formatter of the format)
+    pub formatter: Option<Arc<dyn Formatter>>,
+    /// Metadata of a JSON document (see [QueryResponse::with_metadata])
+    ///
+    /// The metadata has the column names and types, the number of rows and the query statistics.
+    pub metadata: Option<JsonMetadata>,
 }
 
 impl QueryResponse {
     /// Creates a query response
     pub fn new(format: Format, data: Vec<u8>) -> Self {
-        Self { format, data }
+        Self {
+            format,
+            data,
+            formatter: None,
+            metadata: None,
+        }
+    }
+
+    /// Parses the metadata of a JSON document (the other formats have no metadata)
+    pub fn with_metadata(mut self) -> Result<Self, Error> {
+        self.metadata = match self.format {
+            Format::JSON
+            | Format::JSONStrings
+            | Format::JSONCompact
+            | Format::JSONCompactStrings
+            | Format::JSONColumnsWithMetadata => {
+                let value = String::from_utf8_lossy(&self.data);
+                Some(JsonFormatter::document().parse_metadata(&value)?)
+            }
+            _ => None,
+        };
+        Ok(self)
     }
 
     /// Converts into a table
     pub fn into_table(self, mapping: Option<&[(&str, Type)]>) -> Result<QueryData, Error> {
-        QueryData::from_bytes(&self.data, self.format, mapping)
+        match &self.formatter {
+            Some(formatter) => formatter.deserialize_query_data(&self.data, mapping),
+            None => QueryData::from_bytes(&self.data, self.format, mapping),
+        }
+    }
+
+    /// Converts into columns
+    ///
+    /// NB: the `Native` format is parsed directly to columns.
+    pub fn into_columns(self) -> Result<QueryColumns, Error> {
+        match (self.format, &self.formatter) {
+            (Format::Native, None) => NativeFormatter::new().parse_columns(&self.data),
+            _ => self.into_table(None)?.into_columns(),
+        }
+    }
+
+    /// Converts into Arrow record batches
+    ///
+    /// NB: the `Arrow`, `ArrowStream` and `Parquet` formats are read directly, the other formats
+    /// are converted to 1 record batch.
+    #[cfg(feature = "arrow")]
+    pub fn into_record_batches(self) -> Result<Vec<arrow::record_batch::RecordBatch>, Error> {
+        match (self.format, &self.formatter) {
+            (Format::Arrow, None) => super::ArrowFormatter::new().read_batches(&self.data),
+            (Format::ArrowStream, None) => super::ArrowFormatter::stream().read_batches(&self.data),
+            #[cfg(feature = "parquet")]
+            (Format::Parquet, None) => super::ParquetFormatter::new().read_batches(&self.data),
+            _ => Ok(vec![self.into_columns()?.into_record_batch()?]),
+        }
+    }
+
+    /// Returns a cursor over the `serde` rows (RowBinary formats only)
+    ///
+    /// The rows borrow from the response. The column types are required for the `RowBinary` and
+    /// `RowBinaryWithNames` formats, and are read from the header otherwise.
+    #[cfg(feature = "serde")]
+    pub fn cursor<'a, T: serde::Deserialize<'a>>(
+        &'a self,
+        types: Option<&[Type]>,
+    ) -> Result<super::RowCursor<'a, T>, Error> {
+        match self.format {
+            Format::RowBinary => match types {
+                Some(types) => Ok(super::RowCursor::new(&self.data, types)),
+                None => Err(Error::new("Missing column types to parse rows")),
+            },
+            Format::RowBinaryWithNames => super::RowCursor::with_header(&self.data, false, types),
+            Format::RowBinaryWithNamesAndTypes => {
+                super::RowCursor::with_header(&self.data, true, types)
+            }
+            format => Err(Error(format!(
+                "Cannot deserialize serde rows from the {format} format"
+            ))),
+        }
+    }
+
+    /// Deserializes the `serde` rows (RowBinary formats only)
+    ///
+    /// Strings and bytes can be borrowed from the response.
+    #[cfg(feature = "serde")]
+    pub fn rows<'a, T: serde::Deserialize<'a>>(&'a self, types: &[Type]) -> Result<Vec<T>, Error> {
+        self.cursor(Some(types))?.collect()
     }
 }
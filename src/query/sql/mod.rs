@@ -4,11 +4,12 @@
 mod tests;
 
 use ethnum::{I256, U256};
-use time::{Date, OffsetDateTime};
+use time::Date;
 use uuid::Uuid;
 
 use crate::value::{
-    time::{DateExt, DateTimeExt},
+    escape_bytes,
+    time::{format_ticks_tz, format_time_ticks, format_unix_seconds_tz, DateExt},
     Value,
 };
 
@@ -17,9 +18,9 @@ impl Value {
     pub fn to_sql_string(&self) -> String {
         /// Implements the nullable variant for formatting
         macro_rules! impl_nullable {
-            ($VAL:expr, $VAR:ident) => {
+            ($VAL:expr, $VAR:ident $(, $ARG:expr)*) => {
                 match $VAL {
-                    Some(v) => Value::$VAR(v).to_sql_string(),
+                    Some(v) => Value::$VAR(v $(, $ARG)*).to_sql_string(),
                     None => "NULL".to_string(),
                 }
             };
@@ -46,11 +47,15 @@ impl Value {
             }
             Value::Float32(v) => v.to_string(),
             Value::Float64(v) => v.to_string(),
+            Value::BFloat16(v) => v.to_string(),
             Value::Bool(v) => match v {
                 false => "0".to_string(),
                 true => "1".to_string(),
             },
-            Value::String(v) => format!("'{}'", v),
+            Value::String(v) => format!("'{}'", escape_bytes(v.as_bytes(), escape_str)),
+            Value::Bytes(v) | Value::FixedString(v) | Value::AggregateFunction(v) => {
+                format!("'{}'", escape_bytes(v, escape_str))
+            }
             Value::UUID(_) => {
                 // UUID string uses a 8-4-4-4-12 representation
                 let uuid = self.clone().try_into::<Uuid>().unwrap();
@@ -62,18 +67,23 @@ impl Value {
                 let date_str = date.format_yyyy_mm_dd();
                 format!("'{date_str}'")
             }
-            Value::DateTime(_) => {
-                let dt = self.clone().try_into::<OffsetDateTime>().unwrap();
-                let dt_str = dt.format_yyyy_mm_dd_hh_mm_ss();
+            Value::DateTime(v, tz) => {
+                // NB: the datetime is written in the column timezone
+                let dt_str = format_unix_seconds_tz((*v).into(), tz.as_deref());
                 format!("'{dt_str}'")
             }
-            Value::DateTime64(_) => {
-                let dt = self.clone().try_into::<OffsetDateTime>().unwrap();
-                let dt_str = dt.format_yyyy_mm_dd_hh_mm_ss_ns();
+            Value::DateTime64(v, p, tz) => {
+                let dt_str = format_ticks_tz(*v, *p, tz.as_deref());
                 format!("'{dt_str}'")
             }
-            Value::Enum8(v) => v.to_string(),
-            Value::Enum16(v) => v.to_string(),
+            Value::Time(v) => format!("'{}'", format_time_ticks((*v).into(), 0)),
+            Value::Time64(v, p) => format!("'{}'", format_time_ticks(*v, *p)),
+            Value::Interval(v, kind) => format!("INTERVAL {v} {}", kind.name().to_uppercase()),
+            Value::Enum8(_, Some(name)) | Value::Enum16(_, Some(name)) => {
+                format!("'{}'", escape_str(name))
+            }
+            Value::Enum8(v, None) => v.to_string(),
+            Value::Enum16(v, None) => v.to_string(),
             Value::Array(values) => format!(
                 "[{}]",
                 values
@@ -90,22 +100,41 @@ impl Value {
                     .collect::<Vec<_>>()
                     .join(", ")
             ),
-            Value::Map(values) => format!(
-                "{{{}}}",
+            Value::NamedTuple(values) => format!(
+                "({})",
                 values
                     .iter()
-                    .map(|(k, v)| format!("'{}': {}", k, v.to_sql_string()))
+                    .map(|(_, v)| v.to_sql_string())
                     .collect::<Vec<_>>()
                     .join(", ")
             ),
-            Value::Nested(values) => format!(
+            Value::Map(values) => format!(
                 "{{{}}}",
                 values
                     .iter()
-                    .map(|(k, v)| format!("'{}': {}", k, v.to_sql_string()))
+                    .map(|(k, v)| format!("{}: {}", k.to_sql_string(), v.to_sql_string()))
                     .collect::<Vec<_>>()
                     .join(", ")
             ),
+            Value::Nested(values) => match Value::nested_into_tuples(values.clone()) {
+                Ok(rows) => rows.to_sql_string(),
+                // NB: columns of different lengths are written as a tuple of arrays, which the
+                // server rejects, rather than truncated
+                Err(_) => Value::Tuple(
+                    values
+                        .iter()
+                        .map(|(_, col)| Value::Array(col.clone()))
+                        .collect(),
+                )
+                .to_sql_string(),
+            },
+            Value::Variant(Some((_, v))) | Value::Dynamic(Some((_, v))) => v.to_sql_string(),
+            Value::Variant(None) | Value::Dynamic(None) => "NULL".to_string(),
+            Value::Json(_) => {
+                // NB: the JSON object is written as a JSON string
+                let json = self.to_json_string();
+                format!("'{}'", escape_str(&json))
+            }
             Value::NullableUInt8(v) => impl_nullable!(*v, UInt8),
             Value::NullableUInt16(v) => impl_nullable!(*v, UInt16),
             Value::NullableUInt32(v) => impl_nullable!(*v, UInt32),
@@ -120,15 +149,39 @@ impl Value {
             Value::NullableInt256(v) => impl_nullable!(*v, Int256),
             Value::NullableFloat32(v) => impl_nullable!(*v, Float32),
             Value::NullableFloat64(v) => impl_nullable!(*v, Float64),
+            Value::NullableBFloat16(v) => impl_nullable!(*v, BFloat16),
             Value::NullableBool(v) => impl_nullable!(*v, Bool),
             Value::NullableString(v) => impl_nullable!(v.clone(), String),
+            Value::NullableBytes(v) => impl_nullable!(v.clone(), Bytes),
+            Value::NullableFixedString(v) => impl_nullable!(v.clone(), FixedString),
             Value::NullableUUID(v) => impl_nullable!(*v, UUID),
             Value::NullableDate(v) => impl_nullable!(*v, Date),
             Value::NullableDate32(v) => impl_nullable!(*v, Date32),
-            Value::NullableDateTime(v) => impl_nullable!(*v, DateTime),
-            Value::NullableDateTime64(v) => impl_nullable!(*v, DateTime64),
-            Value::NullableEnum8(v) => impl_nullable!(*v, Enum8),
-            Value::NullableEnum16(v) => impl_nullable!(*v, Enum16),
+            Value::NullableDateTime(v, tz) => impl_nullable!(*v, DateTime, tz.clone()),
+            Value::NullableDateTime64(v, p, tz) => impl_nullable!(*v, DateTime64, *p, tz.clone()),
+            Value::NullableTime(v) => impl_nullable!(*v, Time),
+            Value::NullableTime64(v, p) => impl_nullable!(*v, Time64, *p),
+            Value::NullableInterval(v, kind) => impl_nullable!(*v, Interval, *kind),
+            Value::NullableEnum8(v, name) => impl_nullable!(*v, Enum8, name.clone()),
+            Value::NullableEnum16(v, name) => impl_nullable!(*v, Enum16, name.clone()),
+            Value::Null => "NULL".to_string(),
+            Value::Default => "DEFAULT".to_string(),
+        }
+    }
+}
+
+/// Escapes a string within a SQL string literal
+fn escape_str(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str(r"\\"),
+            '\'' => escaped.push_str(r"\'"),
+            '\n' => escaped.push_str(r"\n"),
+            '\r' => escaped.push_str(r"\r"),
+            '\t' => escaped.push_str(r"\t"),
+            c => escaped.push(c),
         }
     }
+    escaped
 }
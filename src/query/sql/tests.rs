@@ -4,7 +4,7 @@ use ethnum::U256;
 use time::{Date, Month, OffsetDateTime};
 use uuid::Uuid;
 
-use crate::value::ChValue;
+use crate::value::{ChValue, IntervalKind, Value};
 
 #[test]
 fn sql_u8() {
@@ -40,6 +40,9 @@ fn sql_bool() {
 fn sql_string() {
     let value = "abcd".into_ch_value();
     assert_eq!(value.to_sql_string(), "'abcd'");
+
+    let value = "it's a\\b\n".into_ch_value();
+    assert_eq!(value.to_sql_string(), r"'it\'s a\\b\n'");
 }
 
 #[test]
@@ -66,7 +69,25 @@ fn sql_datetime() {
     let value = OffsetDateTime::from_unix_timestamp(0)
         .unwrap()
         .into_ch_value();
-    assert_eq!(value.to_sql_string(), "'1970-01-01 00:00:00.0'");
+    assert_eq!(value.to_sql_string(), "'1970-01-01 00:00:00.000000000'");
+}
+
+#[test]
+fn sql_datetime64_precision() {
+    let cases = [
+        (0, 1_700_000_000, "'2023-11-14 22:13:20'"),
+        (3, 1_700_000_000_123, "'2023-11-14 22:13:20.123'"),
+        (6, 1_700_000_000_123_456, "'2023-11-14 22:13:20.123456'"),
+        (
+            9,
+            1_700_000_000_123_456_789,
+            "'2023-11-14 22:13:20.123456789'",
+        ),
+    ];
+    for (p, ticks, sql) in cases {
+        let value = Value::DateTime64(ticks, p, None);
+        assert_eq!(value.to_sql_string(), sql);
+    }
 }
 
 #[test]
@@ -80,3 +101,14 @@ fn sql_tuple() {
     let value = (1_u8, 2, 3).into_ch_value();
     assert_eq!(value.to_sql_string(), "(1, 2, 3)");
 }
+
+#[test]
+fn sql_interval_time() {
+    let value = std::time::Duration::from_millis(1500).into_ch_value();
+    assert_eq!(value.to_sql_string(), "INTERVAL 1500000000 NANOSECOND");
+    let value = Value::NullableInterval(Some(-2), IntervalKind::Day);
+    assert_eq!(value.to_sql_string(), "INTERVAL -2 DAY");
+    let value = Value::Time64(-3_723_500, 3);
+    assert_eq!(value.to_sql_string(), "'-01:02:03.500'");
+    assert_eq!(Value::Null.to_sql_string(), "NULL");
+}
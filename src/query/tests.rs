@@ -45,6 +45,6 @@ fn query_bind_datetime() {
     let query = Query::new("SELECT * FROM tests WHERE datetime = [??]").bind_val(dt);
     assert_eq!(
         query.statement,
-        "SELECT * FROM tests WHERE datetime = '1970-01-01 00:00:00.0'"
+        "SELECT * FROM tests WHERE datetime = '1970-01-01 00:00:00.000000000'"
     );
 }
@@ -1,10 +1,14 @@
 //! Core implementations
 
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    time::Duration,
+};
 
 use impl_trait_for_tuples::impl_for_tuples;
 
-use super::{ChValue, Type, Value};
+use super::{ChValue, IntervalKind, Type, Value};
 use crate::error::Error;
 
 /// Implements the [Value] trait for built-in types
@@ -40,10 +44,8 @@ impl_ch_type!(i16, Type::Int16, Int16);
 impl_ch_type!(i32, Type::Int32, Int32);
 impl_ch_type!(i64, Type::Int64, Int64);
 impl_ch_type!(i128, Type::Int128, Int128);
-impl_ch_type!(f32, Type::Float32, Float32);
 impl_ch_type!(f64, Type::Float64, Float64);
 impl_ch_type!(bool, Type::Bool, Bool);
-impl_ch_type!(String, Type::String, String);
 // nullable std types
 impl_ch_type!(Option<u8>, Type::NullableUInt8, NullableUInt8);
 impl_ch_type!(Option<u16>, Type::NullableUInt16, NullableUInt16);
@@ -55,10 +57,143 @@ impl_ch_type!(Option<i16>, Type::NullableInt16, NullableInt16);
 impl_ch_type!(Option<i32>, Type::NullableInt32, NullableInt32);
 impl_ch_type!(Option<i64>, Type::NullableInt64, NullableInt64);
 impl_ch_type!(Option<i128>, Type::NullableInt128, NullableInt128);
-impl_ch_type!(Option<f32>, Type::NullableFloat32, NullableFloat32);
 impl_ch_type!(Option<f64>, Type::NullableFloat64, NullableFloat64);
 impl_ch_type!(Option<bool>, Type::NullableBool, NullableBool);
-impl_ch_type!(Option<String>, Type::NullableString, NullableString);
+
+// f32
+//
+// NB: a `f32` can also be read from a `BFloat16`
+impl ChValue for f32 {
+    fn ch_type() -> Type {
+        Type::Float32
+    }
+
+    fn into_ch_value(self) -> Value {
+        Value::Float32(self)
+    }
+
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Float32(v) | Value::BFloat16(v) => Ok(v),
+            _ => Err(Error::new("Cannot convert Value to base type")),
+        }
+    }
+}
+
+// Option<f32>
+impl ChValue for Option<f32> {
+    fn ch_type() -> Type {
+        Type::NullableFloat32
+    }
+
+    fn into_ch_value(self) -> Value {
+        Value::NullableFloat32(self)
+    }
+
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::NullableFloat32(v) | Value::NullableBFloat16(v) => Ok(v),
+            _ => Err(Error::new("Cannot convert Value to base type")),
+        }
+    }
+}
+
+// Duration
+//
+// NB: a duration is a nanoseconds interval, and can be read from any interval with a fixed
+// length which is not negative
+impl ChValue for Duration {
+    fn ch_type() -> Type {
+        Type::Interval(IntervalKind::Nanosecond)
+    }
+
+    fn into_ch_value(self) -> Value {
+        let nanos = self.as_nanos().min(i64::MAX as u128) as i64;
+        Value::Interval(nanos, IntervalKind::Nanosecond)
+    }
+
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Interval(n, kind) => {
+                let nanos = kind.nanoseconds().ok_or(Error::new(
+                    format!("Interval{kind} does not have a fixed duration").as_str(),
+                ))?;
+                let nanos = u64::try_from(i128::from(n) * i128::from(nanos))?;
+                Ok(Duration::from_nanos(nanos))
+            }
+            _ => Err(Error::new("Cannot convert Value to base type")),
+        }
+    }
+}
+
+/// Converts fixed string bytes to a string, without the zero padding
+fn fixed_string_to_string(mut bytes: Vec<u8>) -> Result<String, Error> {
+    while bytes.last() == Some(&0x00) {
+        bytes.pop();
+    }
+    Ok(String::from_utf8(bytes)?)
+}
+
+// String
+//
+// NB: a `String` can also be parsed from bytes or a fixed string, if they are valid UTF-8
+impl ChValue for String {
+    fn ch_type() -> Type {
+        Type::String
+    }
+
+    fn into_ch_value(self) -> Value {
+        Value::String(self)
+    }
+
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::String(v) => Ok(v),
+            Value::Bytes(v) => Ok(String::from_utf8(v)?),
+            Value::FixedString(v) => fixed_string_to_string(v),
+            _ => Err(Error::new("Cannot convert Value to base type")),
+        }
+    }
+}
+
+// Option<String>
+impl ChValue for Option<String> {
+    fn ch_type() -> Type {
+        Type::NullableString
+    }
+
+    fn into_ch_value(self) -> Value {
+        Value::NullableString(self)
+    }
+
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::NullableString(v) => Ok(v),
+            Value::NullableBytes(v) => Ok(v.map(String::from_utf8).transpose()?),
+            Value::NullableFixedString(v) => v.map(fixed_string_to_string).transpose(),
+            _ => Err(Error::new("Cannot convert Value to base type")),
+        }
+    }
+}
+
+// [u8; N]
+impl<const N: usize> ChValue for [u8; N] {
+    fn ch_type() -> Type {
+        Type::FixedString(N)
+    }
+
+    fn into_ch_value(self) -> Value {
+        Value::FixedString(self.to_vec())
+    }
+
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::FixedString(v) => Ok(v.as_slice().try_into()?),
+            Value::Array(_) => Ok(Vec::<u8>::from_ch_value(value)?.as_slice().try_into()?),
+            _ => Err(Error::new("Cannot convert Value to fixed string")),
+        }
+    }
+}
 
 // &str
 impl ChValue for &str {
@@ -76,6 +211,9 @@ impl ChValue for &str {
 }
 
 // Vec<T>
+//
+// NB: a `Vec<(K, V)>` can also be parsed from a map, and a `Vec<u8>` from bytes, a fixed string or
+// an aggregate function state
 impl<T> ChValue for Vec<T>
 where
     T: ChValue,
@@ -97,24 +235,43 @@ where
                 }
                 Ok(ts)
             }
+            Value::Map(entries) => {
+                let mut ts = vec![];
+                for (key, value) in entries {
+                    ts.push(T::from_ch_value(Value::Tuple(vec![key, value]))?);
+                }
+                Ok(ts)
+            }
+            Value::Bytes(bytes) | Value::FixedString(bytes) | Value::AggregateFunction(bytes) => {
+                bytes
+                    .into_iter()
+                    .map(|b| T::from_ch_value(Value::UInt8(b)))
+                    .collect()
+            }
             _ => Err(Error::new("Cannot convert Value to array ")),
         }
     }
 }
 
-// HashMap<String, T>
-impl<T> ChValue for HashMap<String, T>
+// HashMap<K, V>
+//
+// NB: entries are sorted by key, so that the encoding is deterministic
+impl<K, V> ChValue for HashMap<K, V>
 where
-    T: ChValue,
+    K: ChValue + Eq + Hash + Ord,
+    V: ChValue,
 {
     fn ch_type() -> Type {
-        Type::Map(Box::new(String::ch_type()), Box::new(T::ch_type()))
+        Type::Map(Box::new(K::ch_type()), Box::new(V::ch_type()))
     }
 
     fn into_ch_value(self) -> Value {
+        let mut entries = self.into_iter().collect::<Vec<_>>();
+        entries.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
         Value::Map(
-            self.into_iter()
-                .map(|(k, v)| (k, v.into_ch_value()))
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.into_ch_value(), v.into_ch_value()))
                 .collect(),
         )
     }
@@ -124,28 +281,29 @@ where
             Value::Map(values) => {
                 let mut map = HashMap::new();
                 for (key, value) in values {
-                    map.insert(key, T::from_ch_value(value)?);
+                    map.insert(K::from_ch_value(key)?, V::from_ch_value(value)?);
                 }
                 Ok(map)
             }
-            _ => Err(Error::new("Cannot convert Value to array ")),
+            _ => Err(Error::new("Cannot convert Value to map")),
         }
     }
 }
 
-// BTreeMap<String, T>
-impl<T> ChValue for BTreeMap<String, T>
+// BTreeMap<K, V>
+impl<K, V> ChValue for BTreeMap<K, V>
 where
-    T: ChValue,
+    K: ChValue + Ord,
+    V: ChValue,
 {
     fn ch_type() -> Type {
-        Type::Map(Box::new(String::ch_type()), Box::new(T::ch_type()))
+        Type::Map(Box::new(K::ch_type()), Box::new(V::ch_type()))
     }
 
     fn into_ch_value(self) -> Value {
         Value::Map(
             self.into_iter()
-                .map(|(k, v)| (k, v.into_ch_value()))
+                .map(|(k, v)| (k.into_ch_value(), v.into_ch_value()))
                 .collect(),
         )
     }
@@ -155,11 +313,11 @@ where
             Value::Map(values) => {
                 let mut map = BTreeMap::new();
                 for (key, value) in values {
-                    map.insert(key, T::from_ch_value(value)?);
+                    map.insert(K::from_ch_value(key)?, V::from_ch_value(value)?);
                 }
                 Ok(map)
             }
-            _ => Err(Error::new("Cannot convert Value to array ")),
+            _ => Err(Error::new("Cannot convert Value to map")),
         }
     }
 }
@@ -181,13 +339,63 @@ impl ChValue for Tuple {
         Value::Tuple(values)
     }
 
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        let values = match value {
+            Value::Tuple(values) => values,
+            // NB: named tuples are converted by position
+            Value::NamedTuple(values) => values.into_iter().map(|(_, v)| v).collect(),
+            _ => return Err(Error::new("Cannot convert Value to tuple")),
+        };
+        let n = for_tuples!( #( 1 )+* );
+        if values.len() != n {
+            return Err(Error(format!(
+                "Cannot convert a tuple of {} values to a tuple of {n} values",
+                values.len()
+            )));
+        }
+        let mut iter = values.into_iter();
+        Ok((for_tuples!( #( Tuple::from_ch_value(iter.next().unwrap())?),* )))
+    }
+}
+
+/// Value of a field, or the default value of the column
+///
+/// NB: the default is written as [Value::Default], which requires the `RowBinaryWithDefaults`
+/// format.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum OrDefault<T> {
+    /// Value
+    Value(T),
+    /// Default value of the column
+    #[default]
+    Default,
+}
+
+impl<T> From<T> for OrDefault<T> {
+    fn from(value: T) -> Self {
+        OrDefault::Value(value)
+    }
+}
+
+impl<T> ChValue for OrDefault<T>
+where
+    T: ChValue,
+{
+    fn ch_type() -> Type {
+        T::ch_type()
+    }
+
+    fn into_ch_value(self) -> Value {
+        match self {
+            OrDefault::Value(v) => v.into_ch_value(),
+            OrDefault::Default => Value::Default,
+        }
+    }
+
     fn from_ch_value(value: Value) -> Result<Self, Error> {
         match value {
-            Value::Tuple(values) => {
-                let mut iter = values.into_iter();
-                Ok((for_tuples!( #( Tuple::from_ch_value(iter.next().unwrap())?),* )))
-            }
-            _ => Err(Error::new("Cannot convert Value to array ")),
+            Value::Default => Ok(OrDefault::Default),
+            value => T::from_ch_value(value).map(OrDefault::Value),
         }
     }
 }
@@ -0,0 +1,46 @@
+//! Extension for the `bytes` crate
+
+pub use bytes::Bytes;
+
+use crate::{
+    error::Error,
+    value::{ChValue, Type, Value},
+};
+
+impl ChValue for Bytes {
+    fn ch_type() -> Type {
+        Type::String
+    }
+
+    fn into_ch_value(self) -> Value {
+        Value::Bytes(self.to_vec())
+    }
+
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Bytes(v) | Value::FixedString(v) | Value::AggregateFunction(v) => {
+                Ok(Bytes::from(v))
+            }
+            Value::String(v) => Ok(Bytes::from(v)),
+            _ => Err(Error::new("Cannot convert Value to base type")),
+        }
+    }
+}
+
+impl ChValue for Option<Bytes> {
+    fn ch_type() -> Type {
+        Type::NullableString
+    }
+
+    fn into_ch_value(self) -> Value {
+        Value::NullableBytes(self.map(|v| v.to_vec()))
+    }
+
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::NullableBytes(v) | Value::NullableFixedString(v) => Ok(v.map(Bytes::from)),
+            Value::NullableString(v) => Ok(v.map(Bytes::from)),
+            _ => Err(Error::new("Cannot convert Value to base type")),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! Extension for `chrono` crate
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+
+use crate::{
+    error::Error,
+    value::{ChValue, Type, Value},
+};
+
+use super::time::{ticks_to_unix_nanoseconds, unix_nanoseconds_to_ticks};
+
+/// Number of days from 0001-01-01 (CE) to 1970-01-01
+const UNIX_EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+/// Number of nanoseconds per second
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+/// Returns the UNIX nanoseconds of a UTC datetime
+fn unix_nanoseconds(dt: &DateTime<Utc>) -> i128 {
+    i128::from(dt.timestamp()) * NANOS_PER_SEC + i128::from(dt.timestamp_subsec_nanos())
+}
+
+/// Creates a UTC datetime from UNIX nanoseconds
+fn from_unix_nanoseconds(nanos: i128) -> Result<DateTime<Utc>, Error> {
+    i64::try_from(nanos.div_euclid(NANOS_PER_SEC))
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, nanos.rem_euclid(NANOS_PER_SEC) as u32))
+        .ok_or(Error::new("Datetime out of range"))
+}
+
+/// Converts a `DateTime` or `DateTime64` value to a UTC datetime, with the value timezone
+fn from_datetime_value(value: Value) -> Result<(DateTime<Utc>, Option<String>), Error> {
+    match value {
+        Value::DateTime(secs, tz) => {
+            Ok((from_unix_nanoseconds(i128::from(secs) * NANOS_PER_SEC)?, tz))
+        }
+        Value::DateTime64(ticks, p, tz) => Ok((
+            from_unix_nanoseconds(ticks_to_unix_nanoseconds(ticks, p))?,
+            tz,
+        )),
+        _ => Err(Error::new("Cannot convert Value to base type")),
+    }
+}
+
+/// Implements [ChValue] for the `Option` of a `chrono` type, with the base type conversions
+macro_rules! impl_nullable {
+    ($TY:ty) => {
+        impl ChValue for Option<$TY> {
+            fn ch_type() -> Type {
+                <$TY>::ch_type().into_nullable().unwrap()
+            }
+
+            fn into_ch_value(self) -> Value {
+                match self {
+                    Some(v) => v.into_ch_value().into_nullable().unwrap(),
+                    None => Value::null_of(&Self::ch_type()).unwrap(),
+                }
+            }
+
+            fn from_ch_value(value: Value) -> Result<Self, Error> {
+                match value.into_non_nullable() {
+                    Some(v) => <$TY>::from_ch_value(v).map(Some),
+                    None => Ok(None),
+                }
+            }
+        }
+    };
+}
+
+// -- NaiveDate --
+
+// NB: NaiveDate is mapped to Value::Date32
+impl ChValue for NaiveDate {
+    fn ch_type() -> Type {
+        Type::Date32
+    }
+
+    fn into_ch_value(self) -> Value {
+        Value::Date32(self.num_days_from_ce() - UNIX_EPOCH_DAYS_FROM_CE)
+    }
+
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        let days = match value {
+            Value::Date(v) => i32::from(v),
+            Value::Date32(v) => v,
+            _ => return Err(Error::new("Cannot convert Value to base type")),
+        };
+        days.checked_add(UNIX_EPOCH_DAYS_FROM_CE)
+            .and_then(NaiveDate::from_num_days_from_ce_opt)
+            .ok_or(Error::new("Date out of range"))
+    }
+}
+
+impl_nullable!(NaiveDate);
+
+// -- NaiveDateTime --
+
+// NB: NaiveDateTime is mapped to Value::DateTime64 (in UTC)
+impl ChValue for NaiveDateTime {
+    fn ch_type() -> Type {
+        Type::DateTime64(9, None)
+    }
+
+    fn into_ch_value(self) -> Value {
+        self.and_utc().into_ch_value()
+    }
+
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        let (dt, _) = from_datetime_value(value)?;
+        Ok(dt.naive_utc())
+    }
+}
+
+impl_nullable!(NaiveDateTime);
+
+// -- DateTime<Utc> --
+
+// NB: DateTime<Utc> is mapped to Value::DateTime64
+impl ChValue for DateTime<Utc> {
+    fn ch_type() -> Type {
+        Type::DateTime64(9, None)
+    }
+
+    fn into_ch_value(self) -> Value {
+        Value::DateTime64(
+            unix_nanoseconds_to_ticks(unix_nanoseconds(&self), 9),
+            9,
+            None,
+        )
+    }
+
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        let (dt, _) = from_datetime_value(value)?;
+        Ok(dt)
+    }
+}
+
+impl_nullable!(DateTime<Utc>);
+
+// -- DateTime<Tz> --
+
+// NB: DateTime<Tz> is mapped to a Value::DateTime64 which carries the timezone
+//
+// When the value has no timezone, the datetime is returned in UTC.
+impl ChValue for DateTime<Tz> {
+    fn ch_type() -> Type {
+        Type::DateTime64(9, None)
+    }
+
+    fn into_ch_value(self) -> Value {
+        let tz = self.timezone().name().to_string();
+        let ticks = unix_nanoseconds_to_ticks(unix_nanoseconds(&self.with_timezone(&Utc)), 9);
+        Value::DateTime64(ticks, 9, Some(tz))
+    }
+
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        let (dt, tz) = from_datetime_value(value)?;
+        let tz = match tz {
+            Some(tz) => tz
+                .parse::<Tz>()
+                .map_err(|_| Error::new(format!("Unknown timezone '{tz}'").as_str()))?,
+            None => Tz::UTC,
+        };
+        Ok(dt.with_timezone(&tz))
+    }
+}
+
+impl_nullable!(DateTime<Tz>);
@@ -0,0 +1,35 @@
+//! Extension for the `serde_json` crate
+//!
+//! A JSON object is converted to a `JSON` value, with its nested objects flattened to paths
+//! (eg `{"a":{"b":1}}` has the path `a.b`), and the values of its paths stored as `Dynamic`.
+
+use serde_json::Value as JsonValue;
+
+use crate::{
+    error::Error,
+    query::json_object_into_value,
+    value::{ChValue, Type, Value},
+};
+
+impl ChValue for JsonValue {
+    fn ch_type() -> Type {
+        Type::Json(vec![], vec![])
+    }
+
+    fn into_ch_value(self) -> Value {
+        // NB: a JSON column only stores objects, other values are converted to an empty object
+        match self {
+            JsonValue::Object(obj) => json_object_into_value(obj),
+            _ => Value::Json(vec![]),
+        }
+    }
+
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Json(_) => Ok(serde_json::from_str(&value.to_json_string())?),
+            // NB: a JSON object can be read as a string
+            Value::String(v) => Ok(serde_json::from_str(&v)?),
+            _ => Err(Error::new("Cannot convert Value to base type")),
+        }
+    }
+}
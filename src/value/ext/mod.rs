@@ -1,5 +1,10 @@
 //! Extension for non-std types
 
+pub mod bytes;
+#[cfg(feature = "chrono")]
+pub mod chrono;
+#[cfg(feature = "json")]
+pub mod json;
 pub mod time;
 pub mod uuid;
 pub mod x256;
@@ -2,13 +2,14 @@
 
 use crate::{
     error::Error,
-    value::{ChValue, Type, Value},
+    value::{ChValue, IntervalKind, Type, Value},
 };
 
-use time::PrimitiveDateTime;
 pub use time::{
-    format_description::FormatItem, macros::format_description, Date, Month, OffsetDateTime,
+    format_description::FormatItem, macros::format_description, Date, Duration, Month,
+    OffsetDateTime,
 };
+use time::{PrimitiveDateTime, UtcOffset};
 
 // -- Date --
 
@@ -158,17 +159,19 @@ impl DateTimeExt for PrimitiveDateTime {
 // NB: OffsetDateTime is mapped to Value::DateTime64
 impl ChValue for PrimitiveDateTime {
     fn ch_type() -> Type {
-        Type::DateTime64(9)
+        Type::DateTime64(9, None)
     }
 
     fn into_ch_value(self) -> Value {
-        Value::DateTime64(self.unix_nanoseconds())
+        Value::DateTime64(self.unix_nanoseconds(), 9, None)
     }
 
     fn from_ch_value(value: Value) -> Result<Self, Error> {
         match value {
-            Value::DateTime(secs) => Ok(Self::from_unix_seconds(secs.into())),
-            Value::DateTime64(nanosecs) => Ok(Self::from_unix_nanoseconds(nanosecs.into())),
+            Value::DateTime(secs, _) => Ok(Self::from_unix_seconds(secs.into())),
+            Value::DateTime64(ticks, p, _) => Ok(Self::from_unix_nanoseconds(
+                ticks_to_unix_nanoseconds(ticks, p),
+            )),
             _ => Err(Error::new("Cannot convert Value to base type")),
         }
     }
@@ -218,24 +221,362 @@ impl DateTimeExt for OffsetDateTime {
 }
 
 // NB: OffsetDateTime is mapped to Value::DateTime64
+//
+// When the value has a timezone, the datetime is returned with the local offset
+// if the timezone can be resolved (see `to_timezone`), and in UTC otherwise.
 impl ChValue for OffsetDateTime {
     fn ch_type() -> Type {
-        Type::DateTime64(9)
+        Type::DateTime64(9, None)
     }
 
     fn into_ch_value(self) -> Value {
-        Value::DateTime64(self.unix_nanoseconds())
+        Value::DateTime64(self.unix_nanoseconds(), 9, None)
+    }
+
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        let (dt, tz) = match value {
+            Value::DateTime(secs, tz) => (Self::from_unix_seconds(secs.into()), tz),
+            Value::DateTime64(ticks, p, tz) => (
+                Self::from_unix_nanoseconds(ticks_to_unix_nanoseconds(ticks, p)),
+                tz,
+            ),
+            _ => return Err(Error::new("Cannot convert Value to base type")),
+        };
+        Ok(to_timezone(dt, tz.as_deref()).unwrap_or(dt))
+    }
+}
+
+// -- Timezones --
+
+/// Checks if a timezone name is an alias of UTC
+fn is_utc(tz: &str) -> bool {
+    matches!(
+        tz,
+        "UTC" | "Etc/UTC" | "UCT" | "Etc/UCT" | "GMT" | "Etc/GMT" | "Universal" | "Zulu"
+    )
+}
+
+/// Finds a timezone in the timezone database
+#[cfg(feature = "tz")]
+fn find_timezone(tz: &str) -> Result<&'static time_tz::Tz, Error> {
+    time_tz::timezones::get_by_name(tz)
+        .ok_or(Error::new(format!("Unknown timezone '{tz}'").as_str()))
+}
+
+/// Converts a datetime to the local datetime of a timezone (eg `Europe/Paris`)
+///
+/// No timezone means UTC. Timezones other than UTC require the `tz` feature.
+pub fn to_timezone(dt: OffsetDateTime, tz: Option<&str>) -> Result<OffsetDateTime, Error> {
+    match tz {
+        None => Ok(dt.to_offset(UtcOffset::UTC)),
+        Some(tz) if is_utc(tz) => Ok(dt.to_offset(UtcOffset::UTC)),
+        #[cfg(feature = "tz")]
+        Some(tz) => {
+            use time_tz::OffsetDateTimeExt;
+            Ok(dt.to_timezone(find_timezone(tz)?))
+        }
+        #[cfg(not(feature = "tz"))]
+        Some(tz) => Err(Error::new(
+            format!("Timezone '{tz}' requires the 'tz' feature").as_str(),
+        )),
+    }
+}
+
+/// Interprets a local datetime in a timezone (eg `Europe/Paris`)
+///
+/// No timezone means UTC. Timezones other than UTC require the `tz` feature.
+///
+/// For ambiguous local datetimes (eg. DST transitions), the earliest datetime is returned.
+pub fn assume_timezone(dt: PrimitiveDateTime, tz: Option<&str>) -> Result<OffsetDateTime, Error> {
+    match tz {
+        None => Ok(dt.assume_utc()),
+        Some(tz) if is_utc(tz) => Ok(dt.assume_utc()),
+        #[cfg(feature = "tz")]
+        Some(tz) => {
+            use time_tz::PrimitiveDateTimeExt;
+            dt.assume_timezone(find_timezone(tz)?)
+                .take_first()
+                .ok_or(Error::new(
+                    format!("Datetime {dt} does not exist in timezone '{tz}'").as_str(),
+                ))
+        }
+        #[cfg(not(feature = "tz"))]
+        Some(tz) => Err(Error::new(
+            format!("Timezone '{tz}' requires the 'tz' feature").as_str(),
+        )),
+    }
+}
+
+/// Formats UNIX seconds as `YYYY-MM-DD HH:MM:SS` in a timezone
+///
+/// If the timezone cannot be resolved, the UNIX timestamp is returned (Clickhouse parses it
+/// regardless of the column timezone).
+pub(crate) fn format_unix_seconds_tz(secs: i64, tz: Option<&str>) -> String {
+    let dt = OffsetDateTime::from_unix_seconds(secs);
+    match to_timezone(dt, tz) {
+        Ok(dt) => dt.format_yyyy_mm_dd_hh_mm_ss(),
+        Err(_) => secs.to_string(),
+    }
+}
+
+/// Formats `DateTime64` ticks as `YYYY-MM-DD HH:MM:SS[.X]` in a timezone
+///
+/// If the timezone cannot be resolved, the UNIX timestamp is returned (Clickhouse parses it
+/// regardless of the column timezone).
+pub(crate) fn format_ticks_tz(ticks: i64, precision: u8, tz: Option<&str>) -> String {
+    let (secs, _) = split_ticks(ticks, precision);
+    let dt = OffsetDateTime::from_unix_seconds(secs);
+    match to_timezone(dt, tz) {
+        Ok(dt) => format_ticks(dt, ticks, precision),
+        // NB: the sign applies to the whole timestamp (-1.5 is 1.5 seconds before the epoch)
+        Err(_) => {
+            let sign = if ticks < 0 { "-" } else { "" };
+            let tps = ticks_per_second(precision).unsigned_abs();
+            let (secs, frac) = (ticks.unsigned_abs() / tps, ticks.unsigned_abs() % tps);
+            match precision.min(MAX_PRECISION) {
+                0 => format!("{sign}{secs}"),
+                p => format!("{sign}{secs}.{frac:0width$}", width = p as usize),
+            }
+        }
+    }
+}
+
+/// Parses a `YYYY-MM-DD HH:MM:SS` local datetime in a timezone, and returns the UNIX seconds
+///
+/// A UNIX timestamp is also accepted.
+pub(crate) fn parse_unix_seconds_tz(value: &str, tz: Option<&str>) -> Result<i64, Error> {
+    if let Ok(secs) = value.parse::<i64>() {
+        return Ok(secs);
+    }
+    let dt = PrimitiveDateTime::parse_yyyy_mm_dd_hh_mm_ss(value)?;
+    Ok(assume_timezone(dt, tz)?.unix_seconds())
+}
+
+/// Parses a `YYYY-MM-DD HH:MM:SS[.X]` local datetime in a timezone, and returns the `DateTime64` ticks
+///
+/// A UNIX timestamp is also accepted. Extra fractional digits are truncated, and missing ones
+/// are padded with zeros.
+pub(crate) fn parse_ticks_tz(value: &str, precision: u8, tz: Option<&str>) -> Result<i64, Error> {
+    let precision = precision.min(MAX_PRECISION);
+    let (secs_str, frac_str) = value.split_once('.').unwrap_or((value, ""));
+    if !frac_str.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(Error::new(
+            format!("Invalid fractional seconds: {value}").as_str(),
+        ));
+    }
+
+    let secs = parse_unix_seconds_tz(secs_str, tz)?;
+    let mut frac = 0;
+    for i in 0..precision as usize {
+        let digit = frac_str.as_bytes().get(i).map(|b| b - b'0').unwrap_or(0);
+        frac = frac * 10 + i64::from(digit);
+    }
+    // NB: the sign of a UNIX timestamp applies to the fraction too (-1.5 is -1 s - 0.5 s)
+    if secs_str.starts_with('-') && secs_str.parse::<i64>().is_ok() {
+        frac = -frac;
+    }
+    secs.checked_mul(ticks_per_second(precision))
+        .and_then(|ticks| ticks.checked_add(frac))
+        .ok_or(Error::new(
+            format!("DateTime64 value out of range: {value}").as_str(),
+        ))
+}
+
+// -- DateTime64 ticks --
+
+/// Maximum precision of a `DateTime64`
+const MAX_PRECISION: u8 = 9;
+
+/// Returns the number of `DateTime64` ticks per second for a precision
+fn ticks_per_second(precision: u8) -> i64 {
+    10_i64.pow(precision.min(MAX_PRECISION).into())
+}
+
+/// Splits `DateTime64` ticks into UNIX seconds and the fractional ticks
+fn split_ticks(ticks: i64, precision: u8) -> (i64, i64) {
+    let tps = ticks_per_second(precision);
+    (ticks.div_euclid(tps), ticks.rem_euclid(tps))
+}
+
+/// Converts `DateTime64` ticks (10^-precision seconds) to UNIX nanoseconds
+pub fn ticks_to_unix_nanoseconds(ticks: i64, precision: u8) -> i128 {
+    i128::from(ticks)
+        * i128::from(10_i64.pow((MAX_PRECISION - precision.min(MAX_PRECISION)).into()))
+}
+
+/// Converts UNIX nanoseconds to `DateTime64` ticks (10^-precision seconds)
+///
+/// Sub-tick nanoseconds are truncated.
+pub fn unix_nanoseconds_to_ticks(nanosecs: i128, precision: u8) -> i64 {
+    let ns_per_tick = i128::from(10_i64.pow((MAX_PRECISION - precision.min(MAX_PRECISION)).into()));
+    nanosecs
+        .div_euclid(ns_per_tick)
+        .clamp(i64::MIN.into(), i64::MAX.into()) as i64
+}
+
+/// Rescales `DateTime64` ticks from a precision to another
+pub fn rescale_ticks(ticks: i64, from: u8, to: u8) -> i64 {
+    unix_nanoseconds_to_ticks(ticks_to_unix_nanoseconds(ticks, from), to)
+}
+
+/// Formats a datetime as `YYYY-MM-DD HH:MM:SS[.X]`, with the fractional part of `DateTime64` ticks
+///
+/// The fractional part has exactly `precision` digits, and is omitted for a precision of 0.
+pub(crate) fn format_ticks(dt: OffsetDateTime, ticks: i64, precision: u8) -> String {
+    let precision = precision.min(MAX_PRECISION);
+    let (_, frac) = split_ticks(ticks, precision);
+    let dt = dt.format_yyyy_mm_dd_hh_mm_ss();
+    match precision {
+        0 => dt,
+        p => format!("{dt}.{frac:0width$}", width = p as usize),
+    }
+}
+
+// -- Time ticks --
+
+/// Formats `Time64` ticks as `[-]HH:MM:SS[.X]` (hours can exceed 24)
+///
+/// The fractional part has exactly `precision` digits, and is omitted for a precision of 0.
+pub(crate) fn format_time_ticks(ticks: i64, precision: u8) -> String {
+    let precision = precision.min(MAX_PRECISION);
+    let sign = if ticks < 0 { "-" } else { "" };
+    let tps = ticks_per_second(precision).unsigned_abs();
+    let ticks = ticks.unsigned_abs();
+    let (secs, frac) = (ticks / tps, ticks % tps);
+    let hms = format!(
+        "{sign}{:02}:{:02}:{:02}",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    );
+    match precision {
+        0 => hms,
+        p => format!("{hms}.{frac:0width$}", width = p as usize),
+    }
+}
+
+/// Parses `[-]HH:MM:SS[.X]` (or `[-]MM:SS[.X]`, `[-]SS[.X]`) as `Time64` ticks
+///
+/// Extra fractional digits are truncated, and missing ones are padded with zeros.
+pub(crate) fn parse_time_ticks(value: &str, precision: u8) -> Result<i64, Error> {
+    let precision = precision.min(MAX_PRECISION);
+    let err = || Error::new(format!("Invalid time: {value}").as_str());
+    let (neg, abs) = match value.strip_prefix('-') {
+        Some(abs) => (true, abs),
+        None => (false, value),
+    };
+    let (hms, frac_str) = abs.split_once('.').unwrap_or((abs, ""));
+    if !frac_str.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(err());
+    }
+
+    let parts = hms.split(':').collect::<Vec<_>>();
+    if parts.is_empty() || parts.len() > 3 {
+        return Err(err());
+    }
+    let mut secs = 0_i64;
+    for part in parts {
+        let n = part.parse::<u32>().map_err(|_| err())?;
+        secs = secs * 60 + i64::from(n);
+    }
+    let mut frac = 0;
+    for i in 0..precision as usize {
+        let digit = frac_str.as_bytes().get(i).map(|b| b - b'0').unwrap_or(0);
+        frac = frac * 10 + i64::from(digit);
+    }
+    let ticks = secs
+        .checked_mul(ticks_per_second(precision))
+        .and_then(|ticks| ticks.checked_add(frac))
+        .ok_or_else(err)?;
+    Ok(if neg { -ticks } else { ticks })
+}
+
+// -- Duration --
+
+// NB: a duration is a nanoseconds interval, and can be read from any interval with a fixed
+// length, or from a time
+impl ChValue for Duration {
+    fn ch_type() -> Type {
+        Type::Interval(IntervalKind::Nanosecond)
+    }
+
+    fn into_ch_value(self) -> Value {
+        let nanos = self.whole_nanoseconds();
+        let nanos = nanos.clamp(i64::MIN.into(), i64::MAX.into()) as i64;
+        Value::Interval(nanos, IntervalKind::Nanosecond)
     }
 
     fn from_ch_value(value: Value) -> Result<Self, Error> {
         match value {
-            Value::DateTime(secs) => Ok(Self::from_unix_seconds(secs.into())),
-            Value::DateTime64(nanosecs) => Ok(Self::from_unix_nanoseconds(nanosecs.into())),
+            Value::Interval(n, kind) => {
+                let nanos = kind.nanoseconds().ok_or(Error::new(
+                    format!("Interval{kind} does not have a fixed duration").as_str(),
+                ))?;
+                let nanos = i128::from(n) * i128::from(nanos);
+                Ok(Duration::new(
+                    (nanos / 1_000_000_000) as i64,
+                    (nanos % 1_000_000_000) as i32,
+                ))
+            }
+            Value::Time(secs) => Ok(Duration::seconds(secs.into())),
+            Value::Time64(ticks, p) => {
+                let nanos = ticks_to_unix_nanoseconds(ticks, p);
+                Ok(Duration::new(
+                    (nanos / 1_000_000_000) as i64,
+                    (nanos % 1_000_000_000) as i32,
+                ))
+            }
             _ => Err(Error::new("Cannot convert Value to base type")),
         }
     }
 }
 
+// -- ZonedDateTime --
+
+/// A datetime attached to a named timezone
+///
+/// It maps to a `DateTime64(9)` value which carries the timezone. Timezones other than UTC
+/// require the `tz` feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZonedDateTime {
+    /// Datetime, with the local offset of the timezone
+    pub datetime: OffsetDateTime,
+    /// Timezone (eg `Europe/Paris`)
+    pub tz: String,
+}
+
+impl ZonedDateTime {
+    /// Creates a new [ZonedDateTime] and converts the datetime to the timezone local offset
+    pub fn new(datetime: OffsetDateTime, tz: &str) -> Result<Self, Error> {
+        Ok(Self {
+            datetime: to_timezone(datetime, Some(tz))?,
+            tz: tz.to_string(),
+        })
+    }
+}
+
+impl ChValue for ZonedDateTime {
+    fn ch_type() -> Type {
+        Type::DateTime64(9, None)
+    }
+
+    fn into_ch_value(self) -> Value {
+        Value::DateTime64(self.datetime.unix_nanoseconds(), 9, Some(self.tz))
+    }
+
+    fn from_ch_value(value: Value) -> Result<Self, Error> {
+        let (dt, tz) = match value {
+            Value::DateTime(secs, tz) => (OffsetDateTime::from_unix_seconds(secs.into()), tz),
+            Value::DateTime64(ticks, p, tz) => (
+                OffsetDateTime::from_unix_nanoseconds(ticks_to_unix_nanoseconds(ticks, p)),
+                tz,
+            ),
+            _ => return Err(Error::new("Cannot convert Value to base type")),
+        };
+        Self::new(dt, tz.as_deref().unwrap_or("UTC"))
+    }
+}
+
 // -- Option<Date> --
 
 impl ChValue for Option<Date> {
@@ -269,25 +610,25 @@ impl ChValue for Option<Date> {
 
 impl ChValue for Option<PrimitiveDateTime> {
     fn ch_type() -> Type {
-        Type::NullableDateTime64(9)
+        Type::NullableDateTime64(9, None)
     }
 
     fn into_ch_value(self) -> Value {
         match self {
-            Some(v) => PrimitiveDateTime::into_ch_value(v),
-            None => Value::NullableDateTime64(None),
+            Some(v) => Value::NullableDateTime64(Some(v.unix_nanoseconds()), 9, None),
+            None => Value::NullableDateTime64(None, 9, None),
         }
     }
 
     fn from_ch_value(value: Value) -> Result<Self, Error> {
         match value {
-            Value::NullableDateTime(secs) => match secs {
+            Value::NullableDateTime(secs, _) => match secs {
                 Some(secs) => Ok(Some(PrimitiveDateTime::from_unix_seconds(secs.into()))),
                 None => Ok(None),
             },
-            Value::NullableDateTime64(nanosecs) => match nanosecs {
-                Some(nanosecs) => Ok(Some(PrimitiveDateTime::from_unix_nanoseconds(
-                    nanosecs.into(),
+            Value::NullableDateTime64(ticks, p, _) => match ticks {
+                Some(ticks) => Ok(Some(PrimitiveDateTime::from_unix_nanoseconds(
+                    ticks_to_unix_nanoseconds(ticks, p),
                 ))),
                 None => Ok(None),
             },
@@ -300,24 +641,26 @@ impl ChValue for Option<PrimitiveDateTime> {
 
 impl ChValue for Option<OffsetDateTime> {
     fn ch_type() -> Type {
-        Type::NullableDateTime64(9)
+        Type::NullableDateTime64(9, None)
     }
 
     fn into_ch_value(self) -> Value {
         match self {
-            Some(v) => OffsetDateTime::into_ch_value(v),
-            None => Value::NullableDateTime64(None),
+            Some(v) => Value::NullableDateTime64(Some(v.unix_nanoseconds()), 9, None),
+            None => Value::NullableDateTime64(None, 9, None),
         }
     }
 
     fn from_ch_value(value: Value) -> Result<Self, Error> {
         match value {
-            Value::NullableDateTime(secs) => match secs {
-                Some(secs) => Ok(Some(OffsetDateTime::from_unix_seconds(secs.into()))),
+            Value::NullableDateTime(secs, tz) => match secs {
+                Some(secs) => OffsetDateTime::from_ch_value(Value::DateTime(secs, tz)).map(Some),
                 None => Ok(None),
             },
-            Value::NullableDateTime64(nanosecs) => match nanosecs {
-                Some(nanosecs) => Ok(Some(OffsetDateTime::from_unix_nanoseconds(nanosecs.into()))),
+            Value::NullableDateTime64(ticks, p, tz) => match ticks {
+                Some(ticks) => {
+                    OffsetDateTime::from_ch_value(Value::DateTime64(ticks, p, tz)).map(Some)
+                }
                 None => Ok(None),
             },
             _ => Err(Error::new("Cannot convert Value to base type")),
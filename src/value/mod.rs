@@ -11,14 +11,12 @@ pub use core::*;
 pub use ext::*;
 pub use ty::*;
 
-use std::collections::HashMap;
-
 use ::time::{Date, OffsetDateTime};
 use ::uuid::Uuid;
 use ethnum::{I256, U256};
 
 use crate::error::Error;
-use ext::time::{DateExt, DateTimeExt};
+use ext::time::{format_ticks, format_time_ticks, rescale_ticks, DateExt, DateTimeExt};
 
 /// Trait to represent a Clickhouse value
 pub trait ChValue: Sized {
@@ -78,6 +76,8 @@ pub enum Value {
     Float32(f32),
     /// f64
     Float64(f64),
+    /// bf16 (stored as the equivalent f32)
+    BFloat16(f32),
     // > decimals
     // Decimal(p,s)
     // Decimal32(i32),
@@ -87,28 +87,57 @@ pub enum Value {
     Bool(bool),
     /// string
     String(String),
+    /// Bytes of a string which is not valid UTF-8
+    Bytes(Vec<u8>),
+    /// Fixed-length bytes (zero-padded)
+    FixedString(Vec<u8>),
     /// UUID
     UUID([u8; 16]),
     /// Number of days since 01-01-1970
     Date(u16),
     /// Number of days since 01-01-1970 (signed int)
     Date32(i32),
-    /// Number of seconds since 01-01-1970
-    DateTime(u32),
-    /// Number of nanosecs since 01-01-1970
-    DateTime64(i64),
-    /// Enum8
-    Enum8(i8),
-    /// Enum16
-    Enum16(i16),
+    /// Number of seconds since 01-01-1970 (with the optional column timezone)
+    DateTime(u32, Option<String>),
+    /// Number of ticks since 01-01-1970, with the precision (a tick is 10^-precision seconds)
+    /// and the optional column timezone
+    DateTime64(i64, u8, Option<String>),
+    /// Time (signed number of seconds)
+    Time(i32),
+    /// Time64 (signed number of ticks, with the precision)
+    Time64(i64, u8),
+    /// Interval (number of units, with the interval kind)
+    Interval(i64, IntervalKind),
+    /// Enum8 (with the optional variant name)
+    Enum8(i8, Option<String>),
+    /// Enum16 (with the optional variant name)
+    Enum16(i16, Option<String>),
     /// Array
     Array(Vec<Value>),
     /// Tuple
     Tuple(Vec<Value>),
+    /// Named tuple (ordered elements, with their names)
+    NamedTuple(Vec<(String, Value)>),
     /// Map
-    Map(HashMap<String, Value>),
+    ///
+    /// Entries are ordered, and keys can have any type
+    Map(Vec<(Value, Value)>),
     /// Nested
-    Nested(HashMap<String, Value>),
+    ///
+    /// Ordered columns, each column being an array of values (all columns have the same length)
+    Nested(Vec<(String, Vec<Value>)>),
+    /// Variant (with the discriminator of the value type, or NULL)
+    ///
+    /// The discriminator is the index of the type in the sorted `Variant` types.
+    Variant(Option<(u8, Box<Value>)>),
+    /// Dynamic (with the value type, or NULL)
+    Dynamic(Option<(Type, Box<Value>)>),
+    /// JSON object
+    ///
+    /// Values of typed paths have the path type, values of other paths are [Value::Dynamic].
+    Json(Vec<(String, Value)>),
+    /// Opaque state of an aggregate function
+    AggregateFunction(Vec<u8>),
     /// Nullable u8
     NullableUInt8(Option<u8>),
     /// Nullable u16
@@ -137,6 +166,8 @@ pub enum Value {
     NullableFloat32(Option<f32>),
     /// Nullable f64
     NullableFloat64(Option<f64>),
+    /// Nullable bf16
+    NullableBFloat16(Option<f32>),
     // > decimal
     // NullableDecimal32(Option<i32>),
     // NullableDecimal64(Option<i64>),
@@ -145,6 +176,10 @@ pub enum Value {
     NullableBool(Option<bool>),
     /// Nullable string
     NullableString(Option<String>),
+    /// Nullable bytes
+    NullableBytes(Option<Vec<u8>>),
+    /// Nullable fixed-length bytes
+    NullableFixedString(Option<Vec<u8>>),
     /// Nullable UUID
     NullableUUID(Option<[u8; 16]>),
     /// Nullable date
@@ -152,13 +187,26 @@ pub enum Value {
     /// Nullable date32
     NullableDate32(Option<i32>),
     /// Nullable datetime
-    NullableDateTime(Option<u32>),
+    NullableDateTime(Option<u32>, Option<String>),
     /// Nullable datetime64
-    NullableDateTime64(Option<i64>),
-    /// Nullable Enum8
-    NullableEnum8(Option<i8>),
-    /// Nullable Enum16
-    NullableEnum16(Option<i16>),
+    NullableDateTime64(Option<i64>, u8, Option<String>),
+    /// Nullable time
+    NullableTime(Option<i32>),
+    /// Nullable time64
+    NullableTime64(Option<i64>, u8),
+    /// Nullable interval
+    NullableInterval(Option<i64>, IntervalKind),
+    /// Nullable Enum8 (with the optional variant name)
+    NullableEnum8(Option<i8>, Option<String>),
+    /// Nullable Enum16 (with the optional variant name)
+    NullableEnum16(Option<i16>, Option<String>),
+    /// NULL (the only value of `Nullable(Nothing)`)
+    Null,
+    /// Default value of the column (eg. `DEFAULT now()`)
+    ///
+    /// NB: it is only written by the `RowBinaryWithDefaults` format, the server computes the
+    /// value from the column expression.
+    Default,
 }
 
 impl Value {
@@ -172,6 +220,26 @@ impl Value {
 
     /// Checks if a [Value] corresponds to a type
     pub fn is_same_type_as(&self, ty: &Type) -> bool {
+        // NB: low cardinality values have the inner type
+        if let Type::LowCardinality(ty) = ty {
+            return self.is_same_type_as(ty);
+        }
+
+        // NB: simple aggregate function values have the inner type
+        if let Type::SimpleAggregateFunction(_, ty) = ty {
+            return self.is_same_type_as(ty);
+        }
+
+        // NB: a plain (or nullable) value can be written to a variant column of its type
+        if let Type::Variant(types) = ty {
+            if !matches!(self, Value::Variant(_)) {
+                return types.iter().any(|ty| {
+                    self.is_same_type_as(ty)
+                        || (ty.clone().into_nullable()).is_some_and(|ty| self.is_same_type_as(&ty))
+                });
+            }
+        }
+
         match self {
             Value::UInt8(_) => matches!(ty, Type::UInt8),
             Value::UInt16(_) => matches!(ty, Type::UInt16),
@@ -187,23 +255,46 @@ impl Value {
             Value::Int256(_) => matches!(ty, Type::Int256),
             Value::Float32(_) => matches!(ty, Type::Float32),
             Value::Float64(_) => matches!(ty, Type::Float64),
+            Value::BFloat16(_) => matches!(ty, Type::BFloat16),
             Value::Bool(_) => matches!(ty, Type::Bool),
-            Value::String(_) => matches!(ty, Type::String),
+            // NB: a string is written as bytes to a fixed string column
+            Value::String(_) => matches!(ty, Type::String | Type::FixedString(_)),
+            Value::Bytes(v) => match ty {
+                Type::String => true,
+                Type::FixedString(n) => v.len() == *n,
+                _ => false,
+            },
+            Value::FixedString(v) => matches!(ty, Type::FixedString(n) if v.len() == *n),
             Value::UUID(_) => matches!(ty, Type::UUID),
             Value::Date(_) => matches!(ty, Type::Date),
             Value::Date32(_) => matches!(ty, Type::Date32),
-            Value::DateTime(_) => matches!(ty, Type::DateTime),
-            Value::DateTime64(_) => matches!(ty, Type::DateTime64(_)),
-            Value::Enum8(i) => match ty {
+            Value::DateTime(..) => matches!(ty, Type::DateTime(_)),
+            Value::DateTime64(..) => matches!(ty, Type::DateTime64(..)),
+            Value::Time(_) => matches!(ty, Type::Time),
+            Value::Time64(..) => matches!(ty, Type::Time64(_)),
+            Value::Interval(_, kind) => matches!(ty, Type::Interval(k) if k == kind),
+            Value::Enum8(i, _) => match ty {
                 Type::Enum8(variants) => variants.values().any(|v| v == i),
                 _ => false,
             },
-            Value::Enum16(i) => match ty {
+            Value::Enum16(i, _) => match ty {
                 Type::Enum16(variants) => variants.values().any(|v| v == i),
                 _ => false,
             },
             Value::Array(values) => match ty {
                 Type::Array(arr_ty) => values.iter().all(|v| v.is_same_type_as(arr_ty.as_ref())),
+                // NB: an array of bytes can be written as a string or a fixed string
+                Type::String => values.iter().all(|v| matches!(v, Value::UInt8(_))),
+                Type::FixedString(n) => {
+                    values.len() == *n && values.iter().all(|v| matches!(v, Value::UInt8(_)))
+                }
+                // NB: an array of (key, value) tuples can be written as a map
+                Type::Map(key_ty, val_ty) => values.iter().all(|v| match v {
+                    Value::Tuple(kv) if kv.len() == 2 => {
+                        kv[0].is_same_type_as(key_ty) && kv[1].is_same_type_as(val_ty)
+                    }
+                    _ => false,
+                }),
                 _ => false,
             },
             Value::Tuple(values) => match ty {
@@ -213,25 +304,64 @@ impl Value {
                     }
                     values.iter().zip(types).all(|(n, t)| n.is_same_type_as(t))
                 }
+                // NB: a tuple can be written to a named tuple column, by position
+                Type::NamedTuple(elements) => {
+                    values.len() == elements.len()
+                        && values
+                            .iter()
+                            .zip(elements)
+                            .all(|(v, (_, ty))| v.is_same_type_as(ty))
+                }
                 _ => false,
             },
-            Value::Map(map) => match ty {
-                Type::Map(_key_ty, val_ty) => {
-                    map.values().all(|v| v.is_same_type_as(val_ty.as_ref()))
+            Value::NamedTuple(values) => match ty {
+                Type::NamedTuple(elements) => {
+                    values.len() == elements.len()
+                        && values
+                            .iter()
+                            .zip(elements)
+                            .all(|((name, v), (el_name, ty))| {
+                                name == el_name && v.is_same_type_as(ty)
+                            })
                 }
                 _ => false,
             },
+            Value::Map(map) => match ty {
+                Type::Map(key_ty, val_ty) => map
+                    .iter()
+                    .all(|(k, v)| k.is_same_type_as(key_ty) && v.is_same_type_as(val_ty)),
+                _ => false,
+            },
             Value::Nested(values) => match ty {
                 Type::Nested(fields) => {
-                    let keys = fields.iter().map(|(k, _v)| k.as_str()).collect::<Vec<_>>();
-                    values.keys().all(|k| keys.contains(&k.as_str()))
-                        && values
-                            .iter()
-                            .zip(fields)
-                            .all(|((_, v), (_, t))| v.is_same_type_as(t))
+                    let n_rows = values.first().map(|(_, col)| col.len()).unwrap_or(0);
+                    values.len() == fields.len()
+                        && values.iter().zip(fields).all(|((name, col), (field, ty))| {
+                            name == field
+                                && col.len() == n_rows
+                                && col.iter().all(|v| v.is_same_type_as(ty))
+                        })
                 }
                 _ => false,
             },
+            Value::Variant(None) => matches!(ty, Type::Variant(_)),
+            Value::Variant(Some((i, v))) => ty
+                .variant_types()
+                .and_then(|types| types.get(*i as usize).copied())
+                .is_some_and(|ty| v.is_same_type_as(ty)),
+            Value::Dynamic(_) => matches!(ty, Type::Dynamic(_)),
+            Value::Json(values) => match ty {
+                Type::Json(paths, _) => {
+                    values
+                        .iter()
+                        .all(|(path, v)| match paths.iter().find(|(p, _)| p == path) {
+                            Some((_, ty)) => v.is_same_type_as(ty),
+                            None => matches!(v, Value::Dynamic(_)),
+                        })
+                }
+                _ => false,
+            },
+            Value::AggregateFunction(_) => matches!(ty, Type::AggregateFunction(..)),
             Value::NullableUInt8(_) => matches!(ty, Type::NullableUInt8),
             Value::NullableUInt16(_) => matches!(ty, Type::NullableUInt16),
             Value::NullableUInt32(_) => matches!(ty, Type::NullableUInt32),
@@ -246,15 +376,50 @@ impl Value {
             Value::NullableInt256(_) => matches!(ty, Type::NullableInt256),
             Value::NullableFloat32(_) => matches!(ty, Type::NullableFloat32),
             Value::NullableFloat64(_) => matches!(ty, Type::NullableFloat64),
+            Value::NullableBFloat16(_) => matches!(ty, Type::NullableBFloat16),
             Value::NullableBool(_) => matches!(ty, Type::NullableBool),
-            Value::NullableString(_) => matches!(ty, Type::NullableString),
+            Value::NullableString(_) => {
+                matches!(ty, Type::NullableString | Type::NullableFixedString(_))
+            }
+            Value::NullableBytes(v) => match ty {
+                Type::NullableString => true,
+                Type::NullableFixedString(n) => v.as_ref().is_none_or(|v| v.len() == *n),
+                _ => false,
+            },
+            Value::NullableFixedString(v) => match ty {
+                Type::NullableFixedString(n) => v.as_ref().is_none_or(|v| v.len() == *n),
+                _ => false,
+            },
             Value::NullableUUID(_) => matches!(ty, Type::NullableUUID),
             Value::NullableDate(_) => matches!(ty, Type::NullableDate),
             Value::NullableDate32(_) => matches!(ty, Type::NullableDate32),
-            Value::NullableDateTime(_) => matches!(ty, Type::NullableDateTime),
-            Value::NullableDateTime64(_) => matches!(ty, Type::NullableDateTime64(_)),
-            Value::NullableEnum8(_) => matches!(ty, Type::NullableEnum8(_)),
-            Value::NullableEnum16(_) => matches!(ty, Type::NullableEnum16(_)),
+            Value::NullableDateTime(..) => matches!(ty, Type::NullableDateTime(_)),
+            Value::NullableDateTime64(..) => matches!(ty, Type::NullableDateTime64(..)),
+            Value::NullableTime(_) => matches!(ty, Type::NullableTime),
+            Value::NullableTime64(..) => matches!(ty, Type::NullableTime64(_)),
+            Value::NullableInterval(_, kind) => {
+                matches!(ty, Type::NullableInterval(k) if k == kind)
+            }
+            Value::NullableEnum8(..) => matches!(ty, Type::NullableEnum8(_)),
+            Value::NullableEnum16(..) => matches!(ty, Type::NullableEnum16(_)),
+            // NB: NULL can be written to any nullable column
+            Value::Null => Value::null_of(ty).is_some(),
+            // NB: a default value is only valid as a top-level cell (see `QueryData`)
+            Value::Default => false,
+        }
+    }
+
+    /// Creates the value of a string column from its bytes
+    ///
+    /// Bytes which are not valid UTF-8 are kept as [Value::Bytes], unless `lossy` is set, in which
+    /// case the invalid sequences are replaced with `U+FFFD`.
+    pub(crate) fn from_string_bytes(bytes: Vec<u8>, lossy: bool) -> Value {
+        match String::from_utf8(bytes) {
+            Ok(s) => Value::String(s),
+            Err(err) if lossy => {
+                Value::String(String::from_utf8_lossy(err.as_bytes()).into_owned())
+            }
+            Err(err) => Value::Bytes(err.into_bytes()),
         }
     }
 
@@ -275,32 +440,396 @@ impl Value {
             Value::Int256(v) => Some(Value::NullableInt256(Some(v))),
             Value::Float32(v) => Some(Value::NullableFloat32(Some(v))),
             Value::Float64(v) => Some(Value::NullableFloat64(Some(v))),
+            Value::BFloat16(v) => Some(Value::NullableBFloat16(Some(v))),
             Value::Bool(v) => Some(Value::NullableBool(Some(v))),
             Value::String(v) => Some(Value::NullableString(Some(v))),
+            Value::Bytes(v) => Some(Value::NullableBytes(Some(v))),
+            Value::FixedString(v) => Some(Value::NullableFixedString(Some(v))),
             Value::UUID(v) => Some(Value::NullableUUID(Some(v))),
             Value::Date(v) => Some(Value::NullableDate(Some(v))),
             Value::Date32(v) => Some(Value::NullableDate32(Some(v))),
-            Value::DateTime(v) => Some(Value::NullableDateTime(Some(v))),
-            Value::DateTime64(v) => Some(Value::NullableDateTime64(Some(v))),
-            Value::Enum8(v) => Some(Value::NullableEnum8(Some(v))),
-            Value::Enum16(v) => Some(Value::NullableEnum16(Some(v))),
+            Value::DateTime(v, tz) => Some(Value::NullableDateTime(Some(v), tz)),
+            Value::DateTime64(v, p, tz) => Some(Value::NullableDateTime64(Some(v), p, tz)),
+            Value::Time(v) => Some(Value::NullableTime(Some(v))),
+            Value::Time64(v, p) => Some(Value::NullableTime64(Some(v), p)),
+            Value::Interval(v, kind) => Some(Value::NullableInterval(Some(v), kind)),
+            Value::Enum8(v, name) => Some(Value::NullableEnum8(Some(v), name)),
+            Value::Enum16(v, name) => Some(Value::NullableEnum16(Some(v), name)),
             Value::Array(_) => None,
             Value::Tuple(_) => None,
+            Value::NamedTuple(_) => None,
             Value::Map(_) => None,
             Value::Nested(_) => None,
+            Value::Variant(_) => None,
+            Value::Dynamic(_) => None,
+            Value::Json(_) => None,
+            Value::AggregateFunction(_) => None,
             // > nullable
             _ => Some(self),
         }
     }
+
+    /// Returns the inner value of a nullable value
+    ///
+    /// `None` is returned for a NULL value, and values which are not nullable are returned
+    /// unchanged.
+    pub(crate) fn into_non_nullable(self) -> Option<Value> {
+        match self {
+            Value::NullableUInt8(v) => v.map(Value::UInt8),
+            Value::NullableUInt16(v) => v.map(Value::UInt16),
+            Value::NullableUInt32(v) => v.map(Value::UInt32),
+            Value::NullableUInt64(v) => v.map(Value::UInt64),
+            Value::NullableUInt128(v) => v.map(Value::UInt128),
+            Value::NullableUInt256(v) => v.map(Value::UInt256),
+            Value::NullableInt8(v) => v.map(Value::Int8),
+            Value::NullableInt16(v) => v.map(Value::Int16),
+            Value::NullableInt32(v) => v.map(Value::Int32),
+            Value::NullableInt64(v) => v.map(Value::Int64),
+            Value::NullableInt128(v) => v.map(Value::Int128),
+            Value::NullableInt256(v) => v.map(Value::Int256),
+            Value::NullableFloat32(v) => v.map(Value::Float32),
+            Value::NullableFloat64(v) => v.map(Value::Float64),
+            Value::NullableBFloat16(v) => v.map(Value::BFloat16),
+            Value::NullableBool(v) => v.map(Value::Bool),
+            Value::NullableString(v) => v.map(Value::String),
+            Value::NullableBytes(v) => v.map(Value::Bytes),
+            Value::NullableFixedString(v) => v.map(Value::FixedString),
+            Value::NullableUUID(v) => v.map(Value::UUID),
+            Value::NullableDate(v) => v.map(Value::Date),
+            Value::NullableDate32(v) => v.map(Value::Date32),
+            Value::NullableDateTime(v, tz) => v.map(|v| Value::DateTime(v, tz)),
+            Value::NullableDateTime64(v, p, tz) => v.map(|v| Value::DateTime64(v, p, tz)),
+            Value::NullableTime(v) => v.map(Value::Time),
+            Value::NullableTime64(v, p) => v.map(|v| Value::Time64(v, p)),
+            Value::NullableInterval(v, kind) => v.map(|v| Value::Interval(v, kind)),
+            Value::NullableEnum8(v, name) => v.map(|v| Value::Enum8(v, name)),
+            Value::NullableEnum16(v, name) => v.map(|v| Value::Enum16(v, name)),
+            Value::Null => None,
+            _ => Some(self),
+        }
+    }
+
+    /// Creates a `Variant` value from a value of one of the variant types
+    pub fn variant(ty: &Type, value: Value) -> Result<Value, Error> {
+        let types = ty
+            .variant_types()
+            .ok_or(Error::new(format!("{ty} is not a Variant type").as_str()))?;
+        let i = types
+            .iter()
+            .position(|var_ty| value.is_same_type_as(var_ty))
+            .ok_or(Error::new(
+                format!("Value {value} is not a variant of {ty}").as_str(),
+            ))?;
+        Ok(Value::Variant(Some((i.try_into()?, Box::new(value)))))
+    }
+
+    /// Creates a `Dynamic` value
+    ///
+    /// A NULL value has no type, and a nullable value has the inner type.
+    pub fn dynamic<T>(value: T) -> Value
+    where
+        T: ChValue,
+    {
+        let ty = T::ch_type();
+        let ty = ty.clone().into_non_nullable().unwrap_or(ty);
+        match value.into_ch_value().into_non_nullable() {
+            Some(value) => Value::Dynamic(Some((ty, Box::new(value)))),
+            None => Value::Dynamic(None),
+        }
+    }
+
+    /// Returns the NULL value of a nullable type
+    pub(crate) fn null_of(ty: &Type) -> Option<Value> {
+        Some(match ty {
+            Type::LowCardinality(ty) | Type::SimpleAggregateFunction(_, ty) => {
+                return Value::null_of(ty)
+            }
+            Type::NullableUInt8 => Value::NullableUInt8(None),
+            Type::NullableUInt16 => Value::NullableUInt16(None),
+            Type::NullableUInt32 => Value::NullableUInt32(None),
+            Type::NullableUInt64 => Value::NullableUInt64(None),
+            Type::NullableUInt128 => Value::NullableUInt128(None),
+            Type::NullableUInt256 => Value::NullableUInt256(None),
+            Type::NullableInt8 => Value::NullableInt8(None),
+            Type::NullableInt16 => Value::NullableInt16(None),
+            Type::NullableInt32 => Value::NullableInt32(None),
+            Type::NullableInt64 => Value::NullableInt64(None),
+            Type::NullableInt128 => Value::NullableInt128(None),
+            Type::NullableInt256 => Value::NullableInt256(None),
+            Type::NullableFloat32 => Value::NullableFloat32(None),
+            Type::NullableFloat64 => Value::NullableFloat64(None),
+            Type::NullableBFloat16 => Value::NullableBFloat16(None),
+            // NB: decimals are held as f64
+            Type::NullableDecimal(..)
+            | Type::NullableDecimal32(_)
+            | Type::NullableDecimal64(_)
+            | Type::NullableDecimal128(_)
+            | Type::NullableDecimal256(_) => Value::NullableFloat64(None),
+            Type::NullableBool => Value::NullableBool(None),
+            Type::NullableString => Value::NullableString(None),
+            Type::NullableFixedString(_) => Value::NullableFixedString(None),
+            Type::NullableUUID => Value::NullableUUID(None),
+            Type::NullableDate => Value::NullableDate(None),
+            Type::NullableDate32 => Value::NullableDate32(None),
+            Type::NullableDateTime(tz) => Value::NullableDateTime(None, tz.clone()),
+            Type::NullableDateTime64(p, tz) => Value::NullableDateTime64(None, *p, tz.clone()),
+            Type::NullableTime => Value::NullableTime(None),
+            Type::NullableTime64(p) => Value::NullableTime64(None, *p),
+            Type::NullableInterval(kind) => Value::NullableInterval(None, *kind),
+            Type::NullableEnum8(_) => Value::NullableEnum8(None, None),
+            Type::NullableEnum16(_) => Value::NullableEnum16(None, None),
+            Type::NullableNothing => Value::Null,
+            Type::Variant(_) => Value::Variant(None),
+            Type::Dynamic(_) => Value::Dynamic(None),
+            _ => return None,
+        })
+    }
+
+    /// Returns an element of a named tuple, by name
+    pub fn get_element(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::NamedTuple(values) => values.iter().find(|(n, _)| n == name).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Resolves the variant names of enum values from their type
+    ///
+    /// Values which are not enums are returned unchanged.
+    pub fn with_enum_names(self, ty: &Type) -> Value {
+        match self {
+            Value::Enum8(v, _) => Value::Enum8(v, ty.enum_variant_name(v.into()).map(String::from)),
+            Value::Enum16(v, _) => Value::Enum16(v, ty.enum_variant_name(v).map(String::from)),
+            Value::NullableEnum8(v, _) => {
+                let name = v.and_then(|v| ty.enum_variant_name(v.into()));
+                Value::NullableEnum8(v, name.map(String::from))
+            }
+            Value::NullableEnum16(v, _) => {
+                let name = v.and_then(|v| ty.enum_variant_name(v));
+                Value::NullableEnum16(v, name.map(String::from))
+            }
+            Value::Array(values) => match ty {
+                Type::Array(ty) => {
+                    Value::Array(values.into_iter().map(|v| v.with_enum_names(ty)).collect())
+                }
+                _ => Value::Array(values),
+            },
+            value => value,
+        }
+    }
+
+    /// Converts `Nested` columns to an array of tuples (1 tuple per nested row)
+    ///
+    /// An error is returned if the nested columns do not have the same length.
+    pub fn nested_into_tuples(fields: Vec<(String, Vec<Value>)>) -> Result<Value, Error> {
+        let n_rows = Value::nested_len(&fields)?;
+        let mut rows = vec![vec![]; n_rows];
+        for (_, col) in fields {
+            for (row, value) in rows.iter_mut().zip(col) {
+                row.push(value);
+            }
+        }
+        Ok(Value::Array(rows.into_iter().map(Value::Tuple).collect()))
+    }
+
+    /// Returns the number of rows of `Nested` columns
+    ///
+    /// An error is returned if the nested columns do not have the same length.
+    pub(crate) fn nested_len(fields: &[(String, Vec<Value>)]) -> Result<usize, Error> {
+        let n_rows = fields.first().map(|(_, col)| col.len()).unwrap_or(0);
+        match fields.iter().find(|(_, col)| col.len() != n_rows) {
+            Some((name, col)) => Err(Error::new(
+                format!(
+                    "Nested column '{name}' has {} values, expected {n_rows}",
+                    col.len()
+                )
+                .as_str(),
+            )),
+            None => Ok(n_rows),
+        }
+    }
+
+    /// Converts an array of tuples (1 tuple per nested row) to `Nested` columns
+    pub fn nested_from_tuples(names: Vec<String>, value: Value) -> Result<Value, Error> {
+        let rows = match value {
+            Value::Array(rows) => rows,
+            _ => return Err(Error::new("Nested rows must be an array")),
+        };
+        let mut fields = names
+            .into_iter()
+            .map(|name| (name, Vec::with_capacity(rows.len())))
+            .collect::<Vec<_>>();
+        for row in rows {
+            match row {
+                Value::Tuple(values) if values.len() == fields.len() => {
+                    for ((_, col), value) in fields.iter_mut().zip(values) {
+                        col.push(value);
+                    }
+                }
+                _ => return Err(Error::new("Invalid nested row")),
+            }
+        }
+        Ok(Value::Nested(fields))
+    }
+
+    /// Conforms a value to a column type before it is written
+    ///
+    /// `DateTime64` and `Time64` ticks are rescaled to the column precision, NULL is converted to
+    /// the NULL of the nullable column type, arrays of (key, value)
+    /// tuples are converted to maps, tuples are named after the named tuple elements, and arrays
+    /// of bytes are converted to bytes or fixed strings. Other values are returned unchanged.
+    ///
+    /// An error is returned if a fixed string does not have the exact column length.
+    pub(crate) fn conform_to(self, ty: &Type) -> Result<Value, Error> {
+        Ok(match (self, ty) {
+            (value, Type::LowCardinality(ty)) => value.conform_to(ty)?,
+            (value, Type::SimpleAggregateFunction(_, ty)) => value.conform_to(ty)?,
+            (Value::DateTime64(v, p, tz), Type::DateTime64(to, _)) => {
+                Value::DateTime64(rescale_ticks(v, p, *to), *to, tz)
+            }
+            (Value::NullableDateTime64(v, p, tz), Type::NullableDateTime64(to, _)) => {
+                Value::NullableDateTime64(v.map(|v| rescale_ticks(v, p, *to)), *to, tz)
+            }
+            (Value::Time64(v, p), Type::Time64(to)) => Value::Time64(rescale_ticks(v, p, *to), *to),
+            (Value::NullableTime64(v, p), Type::NullableTime64(to)) => {
+                Value::NullableTime64(v.map(|v| rescale_ticks(v, p, *to)), *to)
+            }
+            (Value::Null, ty) => Value::null_of(ty).unwrap_or(Value::Null),
+            (Value::FixedString(v), Type::FixedString(n)) => {
+                if v.len() != *n {
+                    return Err(Error::new(
+                        format!("FixedString({n}) value has {} bytes", v.len()).as_str(),
+                    ));
+                }
+                Value::FixedString(v)
+            }
+            (Value::String(v), Type::FixedString(_)) => {
+                Value::FixedString(v.into_bytes()).conform_to(ty)?
+            }
+            (Value::Bytes(v), Type::FixedString(_)) => Value::FixedString(v).conform_to(ty)?,
+            (Value::Array(values), Type::String | Type::FixedString(_))
+                if values.iter().all(|v| matches!(v, Value::UInt8(_))) =>
+            {
+                let bytes = values
+                    .into_iter()
+                    .filter_map(|v| match v {
+                        Value::UInt8(b) => Some(b),
+                        _ => None,
+                    })
+                    .collect();
+                Value::Bytes(bytes).conform_to(ty)?
+            }
+            (Value::NullableFixedString(Some(v)), Type::NullableFixedString(n)) => {
+                let v = Value::FixedString(v).conform_to(&Type::FixedString(*n))?;
+                v.into_nullable()
+                    .ok_or(Error::new("Invalid nullable value"))?
+            }
+            (Value::NullableString(v), Type::NullableFixedString(_)) => {
+                Value::NullableFixedString(v.map(String::into_bytes)).conform_to(ty)?
+            }
+            (Value::NullableBytes(v), Type::NullableFixedString(_)) => {
+                Value::NullableFixedString(v).conform_to(ty)?
+            }
+            (Value::Array(values), Type::Array(ty)) => Value::Array(
+                values
+                    .into_iter()
+                    .map(|v| v.conform_to(ty))
+                    .collect::<Result<_, _>>()?,
+            ),
+            (Value::Tuple(values), Type::Tuple(types)) if values.len() == types.len() => {
+                Value::Tuple(
+                    values
+                        .into_iter()
+                        .zip(types)
+                        .map(|(v, ty)| v.conform_to(ty))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            (Value::Tuple(values), Type::NamedTuple(elements))
+                if values.len() == elements.len() =>
+            {
+                Value::NamedTuple(
+                    values
+                        .into_iter()
+                        .zip(elements)
+                        .map(|(v, (name, ty))| Ok((name.clone(), v.conform_to(ty)?)))
+                        .collect::<Result<_, Error>>()?,
+                )
+            }
+            (Value::NamedTuple(values), Type::NamedTuple(elements))
+                if values.len() == elements.len() =>
+            {
+                Value::NamedTuple(
+                    values
+                        .into_iter()
+                        .zip(elements)
+                        .map(|((name, v), (_, ty))| Ok((name, v.conform_to(ty)?)))
+                        .collect::<Result<_, Error>>()?,
+                )
+            }
+            (Value::Array(values), Type::Map(..)) => {
+                let is_kv = |v: &Value| matches!(v, Value::Tuple(kv) if kv.len() == 2);
+                if !values.iter().all(is_kv) {
+                    return Ok(Value::Array(values));
+                }
+                let map = values
+                    .into_iter()
+                    .filter_map(|v| match v {
+                        Value::Tuple(kv) => {
+                            let mut kv = kv.into_iter();
+                            Some((kv.next()?, kv.next()?))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                Value::Map(map).conform_to(ty)?
+            }
+            (Value::Map(map), Type::Map(key_ty, val_ty)) => Value::Map(
+                map.into_iter()
+                    .map(|(k, v)| Ok((k.conform_to(key_ty)?, v.conform_to(val_ty)?)))
+                    .collect::<Result<_, Error>>()?,
+            ),
+            (Value::Variant(Some((i, v))), Type::Variant(_)) => {
+                let types = ty.variant_types().unwrap_or_default();
+                let var_ty = types.get(i as usize).ok_or(Error::new(
+                    format!("Invalid discriminator {i} for {ty}").as_str(),
+                ))?;
+                Value::Variant(Some((i, Box::new(v.conform_to(var_ty)?))))
+            }
+            (Value::Variant(None), Type::Variant(_)) => Value::Variant(None),
+            // NB: a plain value is wrapped in the variant of its type
+            (value, Type::Variant(_)) => match value.into_non_nullable() {
+                Some(value) => Value::variant(ty, value)?.conform_to(ty)?,
+                None => Value::Variant(None),
+            },
+            (Value::Dynamic(Some((dyn_ty, v))), Type::Dynamic(_)) => {
+                let v = v.conform_to(&dyn_ty)?;
+                Value::Dynamic(Some((dyn_ty, Box::new(v))))
+            }
+            (Value::Json(values), Type::Json(paths, _)) => Value::Json(
+                values
+                    .into_iter()
+                    .map(|(path, v)| {
+                        let v = match paths.iter().find(|(p, _)| *p == path) {
+                            Some((_, ty)) => v.conform_to(ty)?,
+                            None => v.conform_to(&Type::Dynamic(None))?,
+                        };
+                        Ok((path, v))
+                    })
+                    .collect::<Result<_, Error>>()?,
+            ),
+            (value, _) => value,
+        })
+    }
 }
 
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         /// Implements the nullable variant for formatting
         macro_rules! impl_nullable {
-            ($VAL:expr, $VAR:ident) => {
+            ($VAL:expr, $VAR:ident $(, $ARG:expr)*) => {
                 match $VAL {
-                    Some(v) => Value::$VAR(v).to_string(),
+                    Some(v) => Value::$VAR(v $(, $ARG)*).to_string(),
                     None => "NULL".to_string(),
                 }
             };
@@ -327,8 +856,12 @@ impl std::fmt::Display for Value {
             }
             Value::Float32(v) => v.to_string(),
             Value::Float64(v) => v.to_string(),
+            Value::BFloat16(v) => v.to_string(),
             Value::Bool(v) => v.to_string(),
             Value::String(v) => v.to_string(),
+            Value::Bytes(v) | Value::FixedString(v) | Value::AggregateFunction(v) => {
+                escape_bytes(v, str::to_string)
+            }
             Value::UUID(_) => {
                 let uuid = self.clone().try_into::<Uuid>().unwrap();
                 uuid.to_string()
@@ -337,16 +870,20 @@ impl std::fmt::Display for Value {
                 let date = self.clone().try_into::<Date>().unwrap();
                 date.format_yyyy_mm_dd()
             }
-            Value::DateTime(_) => {
+            Value::DateTime(..) => {
                 let dt = self.clone().try_into::<OffsetDateTime>().unwrap();
                 dt.format_yyyy_mm_dd_hh_mm_ss()
             }
-            Value::DateTime64(_) => {
+            Value::DateTime64(v, p, _) => {
                 let dt = self.clone().try_into::<OffsetDateTime>().unwrap();
-                dt.format_yyyy_mm_dd_hh_mm_ss_ns()
+                format_ticks(dt, *v, *p)
             }
-            Value::Enum8(v) => v.to_string(),
-            Value::Enum16(v) => v.to_string(),
+            Value::Time(v) => format_time_ticks((*v).into(), 0),
+            Value::Time64(v, p) => format_time_ticks(*v, *p),
+            Value::Interval(v, kind) => format!("{v} {kind}"),
+            Value::Enum8(_, Some(name)) | Value::Enum16(_, Some(name)) => name.clone(),
+            Value::Enum8(v, None) => v.to_string(),
+            Value::Enum16(v, None) => v.to_string(),
             Value::Array(v) => {
                 format!(
                     "[{}]",
@@ -365,6 +902,15 @@ impl std::fmt::Display for Value {
                         .join(", ")
                 )
             }
+            Value::NamedTuple(v) => {
+                format!(
+                    "({})",
+                    v.iter()
+                        .map(|(name, v)| format!("{name}: {v}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
             Value::Map(v) => {
                 format!(
                     "{{{}}}",
@@ -378,11 +924,14 @@ impl std::fmt::Display for Value {
                 format!(
                     "{{{}}}",
                     v.iter()
-                        .map(|(k, v)| format!("{}: {}", k, v))
+                        .map(|(k, v)| format!("{}: {}", k, Value::Array(v.clone())))
                         .collect::<Vec<String>>()
                         .join(", ")
                 )
             }
+            Value::Variant(Some((_, v))) | Value::Dynamic(Some((_, v))) => v.to_string(),
+            Value::Variant(None) | Value::Dynamic(None) => "NULL".to_string(),
+            Value::Json(_) => self.to_json_string(),
             Value::NullableUInt8(v) => impl_nullable!(*v, UInt8),
             Value::NullableUInt16(v) => impl_nullable!(*v, UInt16),
             Value::NullableUInt32(v) => impl_nullable!(*v, UInt32),
@@ -397,17 +946,44 @@ impl std::fmt::Display for Value {
             Value::NullableInt256(v) => impl_nullable!(*v, Int256),
             Value::NullableFloat32(v) => impl_nullable!(*v, Float32),
             Value::NullableFloat64(v) => impl_nullable!(*v, Float64),
+            Value::NullableBFloat16(v) => impl_nullable!(*v, BFloat16),
             Value::NullableBool(v) => impl_nullable!(*v, Bool),
             Value::NullableString(v) => impl_nullable!(v.clone(), String),
+            Value::NullableBytes(v) => impl_nullable!(v.clone(), Bytes),
+            Value::NullableFixedString(v) => impl_nullable!(v.clone(), FixedString),
             Value::NullableUUID(v) => impl_nullable!(*v, UUID),
             Value::NullableDate(v) => impl_nullable!(*v, Date),
             Value::NullableDate32(v) => impl_nullable!(*v, Date32),
-            Value::NullableDateTime(v) => impl_nullable!(*v, DateTime),
-            Value::NullableDateTime64(v) => impl_nullable!(*v, DateTime64),
-            Value::NullableEnum8(v) => impl_nullable!(*v, Enum8),
-            Value::NullableEnum16(v) => impl_nullable!(*v, Enum16),
+            Value::NullableDateTime(v, tz) => impl_nullable!(*v, DateTime, tz.clone()),
+            Value::NullableDateTime64(v, p, tz) => impl_nullable!(*v, DateTime64, *p, tz.clone()),
+            Value::NullableTime(v) => impl_nullable!(*v, Time),
+            Value::NullableTime64(v, p) => impl_nullable!(*v, Time64, *p),
+            Value::NullableInterval(v, kind) => impl_nullable!(*v, Interval, *kind),
+            Value::NullableEnum8(v, name) => impl_nullable!(*v, Enum8, name.clone()),
+            Value::NullableEnum16(v, name) => impl_nullable!(*v, Enum16, name.clone()),
+            Value::Null => "NULL".to_string(),
+            Value::Default => "DEFAULT".to_string(),
         };
 
         write!(f, "{s}")
     }
 }
+
+/// Formats bytes as a string, with the invalid UTF-8 bytes and the zero bytes written as `\xNN`
+///
+/// The valid UTF-8 parts are escaped with the provided function.
+pub(crate) fn escape_bytes(bytes: &[u8], escape: impl Fn(&str) -> String) -> String {
+    let mut s = String::new();
+    for chunk in bytes.utf8_chunks() {
+        for (i, part) in chunk.valid().split('\0').enumerate() {
+            if i > 0 {
+                s.push_str(r"\x00");
+            }
+            s.push_str(&escape(part));
+        }
+        for b in chunk.invalid() {
+            s.push_str(&format!("\\x{b:02X}"));
+        }
+    }
+    s
+}
@@ -1,12 +1,15 @@
 //! Tests for Value
 
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
 
 use ethnum::{I256, U256};
 use time::{Date, OffsetDateTime};
 use uuid::Uuid;
 
-use super::ChValue;
+use super::{ChValue, Value};
 
 /// Sets a test
 macro_rules! set_test {
@@ -46,6 +49,7 @@ set_test!(value_float32, f32, -1.1);
 set_test!(value_float64, f64, -1.1);
 set_test!(value_bool, bool, true);
 set_test!(value_string, String, "test".to_string());
+set_test!(value_fixed_string, [u8; 4], [0xde, 0xad, 0xbe, 0xef]);
 set_test!(
     value_uuid,
     Uuid,
@@ -64,3 +68,167 @@ set_test!(value_map, HashMap<String, u8>, HashMap::from([("key0".to_string(), 0)
 set_test!(value_uint8_null, Option<u8>, None);
 set_test!(value_uint16_null, Option<u16>, Some(300));
 // ... other tests
+set_test!(
+    value_map_uint64,
+    BTreeMap<u64, String>,
+    BTreeMap::from([(2, "b".to_string()), (1, "a".to_string())])
+);
+
+#[test]
+fn value_map_ordering() {
+    let map = HashMap::from([(3_u8, 30_u8), (1, 10), (2, 20)]);
+    assert_eq!(
+        map.into_ch_value(),
+        Value::Map(vec![
+            (Value::UInt8(1), Value::UInt8(10)),
+            (Value::UInt8(2), Value::UInt8(20)),
+            (Value::UInt8(3), Value::UInt8(30)),
+        ])
+    );
+}
+
+#[test]
+fn value_map_vec() {
+    let value = Value::Map(vec![
+        (Value::UInt64(2), Value::String("b".to_string())),
+        (Value::UInt64(1), Value::String("a".to_string())),
+    ]);
+    let entries = Vec::<(u64, String)>::from_ch_value(value).unwrap();
+    assert_eq!(entries, vec![(2, "b".to_string()), (1, "a".to_string())]);
+}
+
+#[test]
+fn value_tuple_arity() {
+    let value = Value::Tuple(vec![Value::UInt8(1), Value::UInt8(2)]);
+    assert_eq!(<(u8, u8)>::from_ch_value(value).unwrap(), (1, 2));
+    let value = Value::NamedTuple(vec![
+        ("a".to_string(), Value::UInt8(1)),
+        ("b".to_string(), Value::UInt8(2)),
+    ]);
+    assert_eq!(<(u8, u8)>::from_ch_value(value).unwrap(), (1, 2));
+
+    // NB: the number of elements must match
+    let value = Value::NamedTuple(vec![("a".to_string(), Value::UInt8(1))]);
+    assert!(<(u8, u8)>::from_ch_value(value).is_err());
+    let value = Value::Tuple(vec![Value::UInt8(1)]);
+    assert!(<(u8, u8)>::from_ch_value(value).is_err());
+    let value = Value::Tuple(vec![Value::UInt8(1), Value::UInt8(2), Value::UInt8(3)]);
+    assert!(<(u8, u8)>::from_ch_value(value).is_err());
+}
+
+#[test]
+fn value_fixed_string_conversions() {
+    let value = Value::FixedString(vec![b'a', b'b', 0x00, 0x00]);
+    assert_eq!(value.clone().try_into::<String>().unwrap(), "ab");
+    assert_eq!(
+        value.clone().try_into::<Vec<u8>>().unwrap(),
+        vec![b'a', b'b', 0x00, 0x00]
+    );
+    assert!(value.try_into::<[u8; 2]>().is_err());
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn value_serde_json() {
+    use super::Type;
+
+    let json = serde_json::json!({"a": {"b": 1, "c": [true, "x"]}, "d": null, "e": 1.5});
+    let value = json.clone().into_ch_value();
+    assert_eq!(
+        value,
+        Value::Json(vec![
+            ("a.b".to_string(), Value::dynamic(1_i64)),
+            (
+                "a.c".to_string(),
+                Value::Dynamic(Some((
+                    Type::Array(Box::new(Type::Dynamic(None))),
+                    Box::new(Value::Array(vec![
+                        Value::dynamic(true),
+                        Value::dynamic("x".to_string())
+                    ]))
+                )))
+            ),
+            ("e".to_string(), Value::dynamic(1.5_f64)),
+        ])
+    );
+    assert!(value.is_same_type_as(&serde_json::Value::ch_type()));
+
+    // NB: NULL paths are not stored
+    let value_parsed = value.try_into::<serde_json::Value>().unwrap();
+    assert_eq!(
+        value_parsed,
+        serde_json::json!({"a": {"b": 1, "c": [true, "x"]}, "e": 1.5})
+    );
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn value_chrono() {
+    use ::chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+    use chrono_tz::{Europe::Paris, Tz};
+
+    // Date, Date32
+    let date = NaiveDate::from_ymd_opt(1969, 12, 31).unwrap();
+    assert_eq!(date.into_ch_value(), Value::Date32(-1));
+    assert_eq!(NaiveDate::from_ch_value(Value::Date32(-1)).unwrap(), date);
+    assert_eq!(
+        NaiveDate::from_ch_value(Value::Date(1)).unwrap(),
+        NaiveDate::from_ymd_opt(1970, 1, 2).unwrap()
+    );
+    assert_eq!(Some(date).into_ch_value(), Value::NullableDate32(Some(-1)));
+    assert_eq!(
+        Option::<NaiveDate>::from_ch_value(Value::NullableDate(None)).unwrap(),
+        None
+    );
+
+    // DateTime, DateTime64 (with precision)
+    let dt = Utc.timestamp_opt(1_700_000_000, 123_456_789).unwrap();
+    assert_eq!(
+        dt.into_ch_value(),
+        Value::DateTime64(1_700_000_000_123_456_789, 9, None)
+    );
+    assert_eq!(
+        DateTime::<Utc>::from_ch_value(Value::DateTime64(1_700_000_000_123, 3, None)).unwrap(),
+        Utc.timestamp_opt(1_700_000_000, 123_000_000).unwrap()
+    );
+    assert_eq!(
+        DateTime::<Utc>::from_ch_value(Value::DateTime(1_700_000_000, None)).unwrap(),
+        Utc.timestamp_opt(1_700_000_000, 0).unwrap()
+    );
+    assert_eq!(
+        NaiveDateTime::from_ch_value(Value::DateTime64(-1, 1, None)).unwrap(),
+        Utc.timestamp_opt(-1, 900_000_000).unwrap().naive_utc()
+    );
+    assert_eq!(
+        Option::<NaiveDateTime>::from_ch_value(dt.naive_utc().into_ch_value()).unwrap(),
+        Some(dt.naive_utc())
+    );
+    assert_eq!(
+        Option::<DateTime<Utc>>::None.into_ch_value(),
+        Value::NullableDateTime64(None, 9, None)
+    );
+
+    // timezones
+    let dt_paris = dt.with_timezone(&Paris);
+    let value = dt_paris.into_ch_value();
+    assert_eq!(
+        value,
+        Value::DateTime64(
+            1_700_000_000_123_456_789,
+            9,
+            Some("Europe/Paris".to_string())
+        )
+    );
+    let dt_parsed = DateTime::<Tz>::from_ch_value(value).unwrap();
+    assert_eq!(dt_parsed, dt_paris);
+    assert_eq!(dt_parsed.timezone(), Paris);
+    assert_eq!(
+        DateTime::<Tz>::from_ch_value(Value::DateTime(0, None))
+            .unwrap()
+            .timezone(),
+        Tz::UTC
+    );
+    assert!(
+        DateTime::<Tz>::from_ch_value(Value::DateTime(0, Some("Mars/Base".to_string()))).is_err()
+    );
+}
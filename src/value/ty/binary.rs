@@ -0,0 +1,492 @@
+//! Binary encoding of data types
+//!
+//! This is the self-describing encoding of a type, which precedes the values of `Dynamic` and
+//! `JSON` columns in binary formats.
+
+use std::io::Read;
+
+use super::{
+    parse::{FunctionParam, TypeParser},
+    IntervalKind, Type,
+};
+use crate::error::Error;
+
+/// Default max number of types of a `Dynamic` type
+const DYNAMIC_MAX_TYPES: u8 = 32;
+
+/// Default max number of dynamic paths of a `JSON` type
+const JSON_MAX_DYNAMIC_PATHS: i64 = 1024;
+
+/// Default max number of dynamic types of a `JSON` type
+const JSON_MAX_DYNAMIC_TYPES: u8 = 32;
+
+impl Type {
+    /// Encodes a type to its binary representation
+    ///
+    /// An error is returned if the parameters of an aggregate function cannot be encoded.
+    pub fn to_binary(&self) -> Result<Vec<u8>, Error> {
+        let mut buf = vec![];
+        self.write_binary(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Decodes a type from its binary representation
+    pub fn from_binary(bytes: &mut &[u8]) -> Result<Type, Error> {
+        let code = read_u8(bytes)?;
+        let ty = match code {
+            0x00 => Type::Nothing,
+            0x01 => Type::UInt8,
+            0x02 => Type::UInt16,
+            0x03 => Type::UInt32,
+            0x04 => Type::UInt64,
+            0x05 => Type::UInt128,
+            0x06 => Type::UInt256,
+            0x07 => Type::Int8,
+            0x08 => Type::Int16,
+            0x09 => Type::Int32,
+            0x0A => Type::Int64,
+            0x0B => Type::Int128,
+            0x0C => Type::Int256,
+            0x0D => Type::Float32,
+            0x0E => Type::Float64,
+            0x0F => Type::Date,
+            0x10 => Type::Date32,
+            0x11 => Type::DateTime(None),
+            0x12 => Type::DateTime(Some(read_str(bytes)?)),
+            0x13 => Type::DateTime64(read_u8(bytes)?, None),
+            0x14 => {
+                let p = read_u8(bytes)?;
+                Type::DateTime64(p, Some(read_str(bytes)?))
+            }
+            0x15 => Type::String,
+            0x16 => Type::FixedString(read_uint(bytes)?.try_into()?),
+            0x17 => {
+                let mut variants = std::collections::BTreeMap::new();
+                for _i in 0..read_uint(bytes)? {
+                    let name = read_str(bytes)?;
+                    variants.insert(name, read_u8(bytes)? as i8);
+                }
+                Type::Enum8(variants)
+            }
+            0x18 => {
+                let mut variants = std::collections::BTreeMap::new();
+                for _i in 0..read_uint(bytes)? {
+                    let name = read_str(bytes)?;
+                    let mut buf = [0x00_u8; 2];
+                    bytes.read_exact(&mut buf)?;
+                    variants.insert(name, i16::from_le_bytes(buf));
+                }
+                Type::Enum16(variants)
+            }
+            0x19..=0x1C => {
+                let p = read_u8(bytes)?;
+                Type::Decimal(p, read_u8(bytes)?)
+            }
+            0x1D => Type::UUID,
+            0x1E => Type::Array(Box::new(Type::from_binary(bytes)?)),
+            0x1F => {
+                let mut types = vec![];
+                for _i in 0..read_uint(bytes)? {
+                    types.push(Type::from_binary(bytes)?);
+                }
+                Type::Tuple(types)
+            }
+            0x20 => {
+                let mut elements = vec![];
+                for _i in 0..read_uint(bytes)? {
+                    let name = read_str(bytes)?;
+                    elements.push((name, Type::from_binary(bytes)?));
+                }
+                Type::NamedTuple(elements)
+            }
+            0x22 => {
+                let kind = read_u8(bytes)?;
+                match IntervalKind::ALL.get(kind as usize) {
+                    Some(kind) => Type::Interval(*kind),
+                    None => return Err(Error::new("invalid Interval kind")),
+                }
+            }
+            0x23 => Type::from_binary(bytes)?
+                .into_nullable()
+                .ok_or(Error::new("invalid Nullable type"))?,
+            0x25 => {
+                let _version = read_uint(bytes)?;
+                let func = read_function(bytes)?;
+                let mut types = vec![];
+                for _i in 0..read_uint(bytes)? {
+                    types.push(Type::from_binary(bytes)?);
+                }
+                Type::AggregateFunction(func, types)
+            }
+            0x26 => Type::LowCardinality(Box::new(Type::from_binary(bytes)?)),
+            0x27 => {
+                let key_ty = Type::from_binary(bytes)?;
+                let val_ty = Type::from_binary(bytes)?;
+                Type::Map(Box::new(key_ty), Box::new(val_ty))
+            }
+            0x2A => {
+                let mut types = vec![];
+                for _i in 0..read_uint(bytes)? {
+                    types.push(Type::from_binary(bytes)?);
+                }
+                Type::Variant(types)
+            }
+            0x2B => match read_u8(bytes)? {
+                DYNAMIC_MAX_TYPES => Type::Dynamic(None),
+                n => Type::Dynamic(Some(n)),
+            },
+            0x2D => Type::Bool,
+            0x2E => {
+                let func = read_function(bytes)?;
+                let mut types = vec![];
+                for _i in 0..read_uint(bytes)? {
+                    types.push(Type::from_binary(bytes)?);
+                }
+                match <[Type; 1]>::try_from(types) {
+                    Ok([ty]) => Type::SimpleAggregateFunction(func, Box::new(ty)),
+                    Err(_) => return Err(Error::new("invalid SimpleAggregateFunction type")),
+                }
+            }
+            0x2F => {
+                let mut fields = vec![];
+                for _i in 0..read_uint(bytes)? {
+                    let name = read_str(bytes)?;
+                    fields.push((name, Type::from_binary(bytes)?));
+                }
+                Type::Nested(fields)
+            }
+            0x30 => {
+                let _version = read_u8(bytes)?;
+                let mut params = vec![];
+                let max_paths = read_int(bytes)?;
+                if max_paths != JSON_MAX_DYNAMIC_PATHS {
+                    params.push(format!("max_dynamic_paths={max_paths}"));
+                }
+                let max_types = read_u8(bytes)?;
+                if max_types != JSON_MAX_DYNAMIC_TYPES {
+                    params.push(format!("max_dynamic_types={max_types}"));
+                }
+                let mut paths = vec![];
+                for _i in 0..read_uint(bytes)? {
+                    let path = read_str(bytes)?;
+                    paths.push((path, Type::from_binary(bytes)?));
+                }
+                for _i in 0..read_uint(bytes)? {
+                    params.push(format!("SKIP {}", read_str(bytes)?));
+                }
+                for _i in 0..read_uint(bytes)? {
+                    params.push(format!("SKIP REGEXP '{}'", read_str(bytes)?));
+                }
+                Type::Json(paths, params)
+            }
+            0x31 => Type::BFloat16,
+            0x32 => Type::Time,
+            0x34 => Type::Time64(read_u8(bytes)?),
+            _ => {
+                return Err(Error::new(
+                    format!("Unsupported binary type 0x{code:02X}").as_str(),
+                ))
+            }
+        };
+        Ok(ty)
+    }
+
+    /// Writes the binary representation of a type
+    fn write_binary(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        match self {
+            Type::Nothing => buf.push(0x00),
+            Type::UInt8 => buf.push(0x01),
+            Type::UInt16 => buf.push(0x02),
+            Type::UInt32 => buf.push(0x03),
+            Type::UInt64 => buf.push(0x04),
+            Type::UInt128 => buf.push(0x05),
+            Type::UInt256 => buf.push(0x06),
+            Type::Int8 => buf.push(0x07),
+            Type::Int16 => buf.push(0x08),
+            Type::Int32 => buf.push(0x09),
+            Type::Int64 => buf.push(0x0A),
+            Type::Int128 => buf.push(0x0B),
+            Type::Int256 => buf.push(0x0C),
+            Type::Float32 => buf.push(0x0D),
+            Type::Float64 => buf.push(0x0E),
+            Type::BFloat16 => buf.push(0x31),
+            Type::Date => buf.push(0x0F),
+            Type::Date32 => buf.push(0x10),
+            Type::DateTime(None) => buf.push(0x11),
+            Type::DateTime(Some(tz)) => {
+                buf.push(0x12);
+                write_str(buf, tz);
+            }
+            Type::DateTime64(p, None) => buf.extend([0x13, *p]),
+            Type::DateTime64(p, Some(tz)) => {
+                buf.extend([0x14, *p]);
+                write_str(buf, tz);
+            }
+            Type::Time => buf.push(0x32),
+            Type::Time64(p) => buf.extend([0x34, *p]),
+            Type::Interval(kind) => {
+                let kind = IntervalKind::ALL
+                    .iter()
+                    .position(|k| k == kind)
+                    .unwrap_or(0);
+                buf.extend([0x22, kind as u8]);
+            }
+            Type::String => buf.push(0x15),
+            Type::FixedString(n) => {
+                buf.push(0x16);
+                write_uint(buf, *n as u64);
+            }
+            Type::Enum8(variants) => {
+                buf.push(0x17);
+                write_uint(buf, variants.len() as u64);
+                for (name, v) in variants {
+                    write_str(buf, name);
+                    buf.push(*v as u8);
+                }
+            }
+            Type::Enum16(variants) => {
+                buf.push(0x18);
+                write_uint(buf, variants.len() as u64);
+                for (name, v) in variants {
+                    write_str(buf, name);
+                    buf.extend(v.to_le_bytes());
+                }
+            }
+            Type::Decimal(p, s) => {
+                let code = match p {
+                    0..=9 => 0x19,
+                    10..=18 => 0x1A,
+                    19..=38 => 0x1B,
+                    _ => 0x1C,
+                };
+                buf.extend([code, *p, *s]);
+            }
+            Type::Decimal32(s) => buf.extend([0x19, 9, *s]),
+            Type::Decimal64(s) => buf.extend([0x1A, 18, *s]),
+            Type::Decimal128(s) => buf.extend([0x1B, 38, *s]),
+            Type::Decimal256(s) => buf.extend([0x1C, 76, *s]),
+            Type::UUID => buf.push(0x1D),
+            Type::Array(ty) => {
+                buf.push(0x1E);
+                ty.write_binary(buf)?;
+            }
+            Type::Tuple(types) => {
+                buf.push(0x1F);
+                write_uint(buf, types.len() as u64);
+                for ty in types {
+                    ty.write_binary(buf)?;
+                }
+            }
+            Type::NamedTuple(elements) => {
+                buf.push(0x20);
+                write_uint(buf, elements.len() as u64);
+                for (name, ty) in elements {
+                    write_str(buf, name);
+                    ty.write_binary(buf)?;
+                }
+            }
+            Type::LowCardinality(ty) => {
+                buf.push(0x26);
+                ty.write_binary(buf)?;
+            }
+            Type::SimpleAggregateFunction(func, ty) => {
+                buf.push(0x2E);
+                write_function(buf, func)?;
+                write_uint(buf, 1);
+                ty.write_binary(buf)?;
+            }
+            // NB: the serialization version is not part of the type (it is written as 0)
+            Type::AggregateFunction(func, types) => {
+                buf.extend([0x25, 0x00]);
+                write_function(buf, func)?;
+                write_uint(buf, types.len() as u64);
+                for ty in types {
+                    ty.write_binary(buf)?;
+                }
+            }
+            Type::Map(key_ty, val_ty) => {
+                buf.push(0x27);
+                key_ty.write_binary(buf)?;
+                val_ty.write_binary(buf)?;
+            }
+            Type::Variant(types) => {
+                buf.push(0x2A);
+                write_uint(buf, types.len() as u64);
+                for ty in self.variant_types().unwrap_or_default() {
+                    ty.write_binary(buf)?;
+                }
+            }
+            Type::Dynamic(n) => buf.extend([0x2B, n.unwrap_or(DYNAMIC_MAX_TYPES)]),
+            Type::Bool => buf.push(0x2D),
+            Type::Nested(fields) => {
+                buf.push(0x2F);
+                write_uint(buf, fields.len() as u64);
+                for (name, ty) in fields {
+                    write_str(buf, name);
+                    ty.write_binary(buf)?;
+                }
+            }
+            Type::Json(paths, params) => {
+                let mut max_paths = JSON_MAX_DYNAMIC_PATHS;
+                let mut max_types = JSON_MAX_DYNAMIC_TYPES;
+                let mut skips = vec![];
+                let mut skip_regexps = vec![];
+                for param in params {
+                    if let Some(regexp) = param.strip_prefix("SKIP REGEXP ") {
+                        skip_regexps.push(regexp.trim().trim_matches('\''));
+                    } else if let Some(path) = param.strip_prefix("SKIP ") {
+                        skips.push(path.trim());
+                    } else if let Some((key, val)) = param.split_once('=') {
+                        match key.trim() {
+                            "max_dynamic_paths" => {
+                                max_paths = val.trim().parse().unwrap_or(max_paths);
+                            }
+                            "max_dynamic_types" => {
+                                max_types = val.trim().parse().unwrap_or(max_types);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+
+                buf.extend([0x30, 0x00]);
+                write_int(buf, max_paths);
+                buf.push(max_types);
+                write_uint(buf, paths.len() as u64);
+                for (path, ty) in paths {
+                    write_str(buf, path);
+                    ty.write_binary(buf)?;
+                }
+                write_uint(buf, skips.len() as u64);
+                for path in skips {
+                    write_str(buf, path);
+                }
+                write_uint(buf, skip_regexps.len() as u64);
+                for regexp in skip_regexps {
+                    write_str(buf, regexp);
+                }
+            }
+            // > nullable
+            ty => match ty.clone().into_non_nullable() {
+                Some(ty) => {
+                    buf.push(0x23);
+                    ty.write_binary(buf)?;
+                }
+                None => return Err(Error(format!("Unsupported binary encoding of {ty}"))),
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Reads a byte
+fn read_u8(bytes: &mut &[u8]) -> Result<u8, Error> {
+    let mut buf = [0x00_u8; 1];
+    bytes.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+/// Reads a variable-length unsigned integer
+fn read_uint(bytes: &mut &[u8]) -> Result<u64, Error> {
+    Ok(leb128::read::unsigned(bytes)?)
+}
+
+/// Reads a variable-length signed integer (zigzag encoded)
+fn read_int(bytes: &mut &[u8]) -> Result<i64, Error> {
+    let n = read_uint(bytes)?;
+    Ok((n >> 1) as i64 ^ -((n & 1) as i64))
+}
+
+/// Reads a string (prefixed with its length)
+fn read_str(bytes: &mut &[u8]) -> Result<String, Error> {
+    let n: usize = read_uint(bytes)?.try_into()?;
+    let mut buf = vec![0x00_u8; n];
+    bytes.read_exact(&mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+/// Reads an aggregate function, with its parameters (eg `quantiles(0.5, 0.9)`)
+fn read_function(bytes: &mut &[u8]) -> Result<String, Error> {
+    let name = read_str(bytes)?;
+    let mut params = vec![];
+    for _i in 0..read_uint(bytes)? {
+        params.push(read_field(bytes)?);
+    }
+    Ok(match params.is_empty() {
+        true => name,
+        false => format!("{name}({})", params.join(", ")),
+    })
+}
+
+/// Writes an aggregate function, with its parameters
+fn write_function(buf: &mut Vec<u8>, func: &str) -> Result<(), Error> {
+    let (name, params) = TypeParser::new(func).parse_function_params()?;
+    write_str(buf, &name);
+    write_uint(buf, params.len() as u64);
+    for param in params {
+        write_field(buf, &param)?;
+    }
+    Ok(())
+}
+
+/// Reads a field (an aggregate function parameter), and returns it as written in a type string
+fn read_field(bytes: &mut &[u8]) -> Result<String, Error> {
+    let code = read_u8(bytes)?;
+    Ok(match code {
+        0x01 => read_uint(bytes)?.to_string(),
+        0x02 => read_int(bytes)?.to_string(),
+        0x07 => {
+            let mut buf = [0x00_u8; 8];
+            bytes.read_exact(&mut buf)?;
+            f64::from_le_bytes(buf).to_string()
+        }
+        0x0C => format!(
+            "'{}'",
+            read_str(bytes)?.replace('\\', r"\\").replace('\'', r"\'")
+        ),
+        _ => return Err(Error(format!("Unsupported binary field 0x{code:02X}"))),
+    })
+}
+
+/// Writes a field (an aggregate function parameter)
+///
+/// Integers are written as `UInt64` or `Int64`, other numbers as `Float64`.
+fn write_field(buf: &mut Vec<u8>, param: &FunctionParam) -> Result<(), Error> {
+    match param {
+        FunctionParam::Number(n) => {
+            if let Ok(n) = n.parse::<u64>() {
+                buf.push(0x01);
+                write_uint(buf, n);
+            } else if let Ok(n) = n.parse::<i64>() {
+                buf.push(0x02);
+                write_int(buf, n);
+            } else if let Ok(n) = n.parse::<f64>() {
+                buf.push(0x07);
+                buf.extend(n.to_le_bytes());
+            } else {
+                return Err(Error(format!("Invalid aggregate function parameter '{n}'")));
+            }
+        }
+        FunctionParam::Str(s) => {
+            buf.push(0x0C);
+            write_str(buf, s);
+        }
+    }
+    Ok(())
+}
+
+/// Writes a variable-length unsigned integer
+fn write_uint(buf: &mut Vec<u8>, n: u64) {
+    leb128::write::unsigned(buf, n).unwrap();
+}
+
+/// Writes a variable-length signed integer (zigzag encoded)
+fn write_int(buf: &mut Vec<u8>, n: i64) {
+    write_uint(buf, ((n << 1) ^ (n >> 63)) as u64);
+}
+
+/// Writes a string (prefixed with its length)
+fn write_str(buf: &mut Vec<u8>, s: &str) {
+    write_uint(buf, s.len() as u64);
+    buf.extend(s.as_bytes());
+}
@@ -3,9 +3,13 @@
 #[cfg(test)]
 mod tests;
 
+mod binary;
+mod parse;
+
 use std::{collections::BTreeMap, str::FromStr};
 
 use crate::error::Error;
+use parse::TypeParser;
 
 /// Data type
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -38,6 +42,8 @@ pub enum Type {
     Float32,
     /// f64
     Float64,
+    /// bf16 (the 16 most significant bits of a f32)
+    BFloat16,
     /// Decimal(P,S) (precision ∈ [1:76], scale ∈ [0:P], range ( -1 * 10^(P - S), 1 * 10^(P - S) )
     Decimal(u8, u8),
     /// Decimal(P ∈ [1:9])
@@ -52,8 +58,8 @@ pub enum Type {
     Bool,
     /// String
     String,
-    /// Fixed string
-    FixedString(u8),
+    /// Fixed string (N bytes)
+    FixedString(usize),
     /// UUID (16 bytes)
     UUID,
     /// Date (number of days since 1970-01-01, 2 bytes)
@@ -61,11 +67,23 @@ pub enum Type {
     /// Date32 (number of days since 1970-01-01, signed i32)
     Date32,
     /// DateTime (seconds since EPOCH, [1970-01-01 00:00:00, 2106-02-07 06:28:15])
-    DateTime,
+    ///
+    /// The optional timezone (eg `Europe/Paris`) is used for text representations
+    DateTime(Option<String>),
     /// Ticks since since epoch start (1970-01-01 00:00:00 UTC)
     ///
     /// Precision [0:9] defines the resolution, eg 3=ms, 6=us, 9=ns
-    DateTime64(u8),
+    ///
+    /// The optional timezone (eg `Europe/Paris`) is used for text representations
+    DateTime64(u8, Option<String>),
+    /// Time (signed seconds, [-999:59:59, 999:59:59])
+    Time,
+    /// Time with ticks (signed i64)
+    ///
+    /// Precision [0:9] defines the resolution, eg 3=ms, 6=us, 9=ns
+    Time64(u8),
+    /// Interval (number of units of the given kind, signed i64)
+    Interval(IntervalKind),
     /// Enum (256 values, i8)
     ///
     /// Keys and indices must be unique
@@ -82,6 +100,10 @@ pub enum Type {
     ///
     /// Each element can have a different type
     Tuple(Vec<Type>),
+    /// Named tuple
+    ///
+    /// Each element has a name, and can have a different type
+    NamedTuple(Vec<(String, Type)>),
     /// Map
     ///
     /// - key: String, Integer, LowCardinality, FixedString, UUID, Date, DateTime, Date32, Enum
@@ -89,10 +111,38 @@ pub enum Type {
     Map(Box<Type>, Box<Type>),
     /// Nested
     ///
-    /// A nested structure is a table inside a cell.
+    /// A nested structure is a table inside a cell, with named columns of the given types.
     ///
-    /// Each type can have a name (optional)
+    /// With `flatten_nested=1` (default), it is stored as 1 `Array` column per nested column
+    /// (eg `n.a Array(T)`), otherwise as an `Array(Tuple(..))` column.
     Nested(Vec<(String, Type)>),
+    /// Low cardinality
+    ///
+    /// The dictionary encoding is transparent, values have the inner type
+    LowCardinality(Box<Type>),
+    /// Simple aggregate function (with the function name, eg `sum`)
+    ///
+    /// The aggregation is transparent, values have the inner type
+    SimpleAggregateFunction(String, Box<Type>),
+    /// Aggregate function (with the function, eg `quantiles(0.5, 0.9)`, and the argument types)
+    ///
+    /// Values are the opaque states of the aggregation
+    AggregateFunction(String, Vec<Type>),
+    /// Variant (a value of one of the types, or NULL)
+    ///
+    /// The discriminator of a value is the index of its type, in the types sorted by name.
+    Variant(Vec<Type>),
+    /// Dynamic (a value of any type, with the optional max number of types)
+    Dynamic(Option<u8>),
+    /// JSON object
+    ///
+    /// Typed paths (eg `a.b UInt32`) have a fixed type, other paths are `Dynamic`. The other
+    /// parameters (eg `max_dynamic_paths=N`, `SKIP a.c`) are kept as is.
+    Json(Vec<(String, Type)>, Vec<String>),
+    /// Nothing (the type of a NULL literal)
+    ///
+    /// It has no values, and is only used as `Nullable(Nothing)`
+    Nothing,
     /// Nullable u8
     NullableUInt8,
     /// Nullable u16
@@ -121,6 +171,8 @@ pub enum Type {
     NullableFloat32,
     /// Nullable f64
     NullableFloat64,
+    /// Nullable bf16
+    NullableBFloat16,
     /// Nullable decimal
     NullableDecimal(u8, u8),
     /// Nullable decimal32
@@ -135,8 +187,8 @@ pub enum Type {
     NullableBool,
     /// Nullable string
     NullableString,
-    /// Nullable fixed string
-    NullableFixedString(u8),
+    /// Nullable fixed string (N bytes)
+    NullableFixedString(usize),
     /// Nullbale UUID
     NullableUUID,
     /// Nullable date
@@ -144,13 +196,216 @@ pub enum Type {
     /// Nullable date32
     NullableDate32,
     /// Nullable datetime
-    NullableDateTime,
+    NullableDateTime(Option<String>),
     /// Nullable datetime64
-    NullableDateTime64(u8),
+    NullableDateTime64(u8, Option<String>),
+    /// Nullable time
+    NullableTime,
+    /// Nullable time64
+    NullableTime64(u8),
+    /// Nullable interval
+    NullableInterval(IntervalKind),
     /// Nullable Enum8
     NullableEnum8(BTreeMap<String, i8>),
     /// Nullable Enum16
     NullableEnum16(BTreeMap<String, i16>),
+    /// Nullable nothing (NULL)
+    NullableNothing,
+}
+
+/// Interval kind (the unit of an `Interval` type)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntervalKind {
+    /// Nanoseconds
+    Nanosecond,
+    /// Microseconds
+    Microsecond,
+    /// Milliseconds
+    Millisecond,
+    /// Seconds
+    Second,
+    /// Minutes
+    Minute,
+    /// Hours
+    Hour,
+    /// Days
+    Day,
+    /// Weeks
+    Week,
+    /// Months
+    Month,
+    /// Quarters
+    Quarter,
+    /// Years
+    Year,
+}
+
+impl IntervalKind {
+    /// All interval kinds, ordered by their binary encoding
+    pub const ALL: [IntervalKind; 11] = [
+        IntervalKind::Nanosecond,
+        IntervalKind::Microsecond,
+        IntervalKind::Millisecond,
+        IntervalKind::Second,
+        IntervalKind::Minute,
+        IntervalKind::Hour,
+        IntervalKind::Day,
+        IntervalKind::Week,
+        IntervalKind::Month,
+        IntervalKind::Quarter,
+        IntervalKind::Year,
+    ];
+
+    /// Returns the name of the kind (eg `Second`, as in `IntervalSecond`)
+    pub fn name(&self) -> &'static str {
+        match self {
+            IntervalKind::Nanosecond => "Nanosecond",
+            IntervalKind::Microsecond => "Microsecond",
+            IntervalKind::Millisecond => "Millisecond",
+            IntervalKind::Second => "Second",
+            IntervalKind::Minute => "Minute",
+            IntervalKind::Hour => "Hour",
+            IntervalKind::Day => "Day",
+            IntervalKind::Week => "Week",
+            IntervalKind::Month => "Month",
+            IntervalKind::Quarter => "Quarter",
+            IntervalKind::Year => "Year",
+        }
+    }
+
+    /// Returns the number of nanoseconds of 1 unit
+    ///
+    /// NB: months, quarters and years do not have a fixed length
+    pub fn nanoseconds(&self) -> Option<i64> {
+        match self {
+            IntervalKind::Nanosecond => Some(1),
+            IntervalKind::Microsecond => Some(1_000),
+            IntervalKind::Millisecond => Some(1_000_000),
+            IntervalKind::Second => Some(1_000_000_000),
+            IntervalKind::Minute => Some(60 * 1_000_000_000),
+            IntervalKind::Hour => Some(3_600 * 1_000_000_000),
+            IntervalKind::Day => Some(86_400 * 1_000_000_000),
+            IntervalKind::Week => Some(7 * 86_400 * 1_000_000_000),
+            IntervalKind::Month | IntervalKind::Quarter | IntervalKind::Year => None,
+        }
+    }
+}
+
+impl std::fmt::Display for IntervalKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Type {
+    /// Returns the variant name of an enum value (for `Enum8` and `Enum16` types)
+    pub fn enum_variant_name(&self, value: i16) -> Option<&str> {
+        match self {
+            Type::Enum8(variants) | Type::NullableEnum8(variants) => variants
+                .iter()
+                .find(|(_, v)| i16::from(**v) == value)
+                .map(|(name, _)| name.as_str()),
+            Type::Enum16(variants) | Type::NullableEnum16(variants) => variants
+                .iter()
+                .find(|(_, v)| **v == value)
+                .map(|(name, _)| name.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Returns the types of a `Variant`, ordered by discriminator (sorted by name)
+    pub fn variant_types(&self) -> Option<Vec<&Type>> {
+        match self {
+            Type::Variant(types) => {
+                let mut types = types.iter().collect::<Vec<_>>();
+                types.sort_by_key(|ty| ty.to_string());
+                Some(types)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the nullable variant of a type (if it can be nullable)
+    pub(crate) fn into_nullable(self) -> Option<Type> {
+        Some(match self {
+            Type::UInt8 => Type::NullableUInt8,
+            Type::UInt16 => Type::NullableUInt16,
+            Type::UInt32 => Type::NullableUInt32,
+            Type::UInt64 => Type::NullableUInt64,
+            Type::UInt128 => Type::NullableUInt128,
+            Type::UInt256 => Type::NullableUInt256,
+            Type::Int8 => Type::NullableInt8,
+            Type::Int16 => Type::NullableInt16,
+            Type::Int32 => Type::NullableInt32,
+            Type::Int64 => Type::NullableInt64,
+            Type::Int128 => Type::NullableInt128,
+            Type::Int256 => Type::NullableInt256,
+            Type::Float32 => Type::NullableFloat32,
+            Type::Float64 => Type::NullableFloat64,
+            Type::BFloat16 => Type::NullableBFloat16,
+            Type::Decimal(p, s) => Type::NullableDecimal(p, s),
+            Type::Decimal32(s) => Type::NullableDecimal32(s),
+            Type::Decimal64(s) => Type::NullableDecimal64(s),
+            Type::Decimal128(s) => Type::NullableDecimal128(s),
+            Type::Decimal256(s) => Type::NullableDecimal256(s),
+            Type::Bool => Type::NullableBool,
+            Type::String => Type::NullableString,
+            Type::FixedString(n) => Type::NullableFixedString(n),
+            Type::UUID => Type::NullableUUID,
+            Type::Date => Type::NullableDate,
+            Type::Date32 => Type::NullableDate32,
+            Type::DateTime(tz) => Type::NullableDateTime(tz),
+            Type::DateTime64(p, tz) => Type::NullableDateTime64(p, tz),
+            Type::Time => Type::NullableTime,
+            Type::Time64(p) => Type::NullableTime64(p),
+            Type::Interval(kind) => Type::NullableInterval(kind),
+            Type::Enum8(keys) => Type::NullableEnum8(keys),
+            Type::Enum16(keys) => Type::NullableEnum16(keys),
+            Type::Nothing => Type::NullableNothing,
+            _ => return None,
+        })
+    }
+
+    /// Returns the inner type of a nullable type
+    pub(crate) fn into_non_nullable(self) -> Option<Type> {
+        Some(match self {
+            Type::NullableUInt8 => Type::UInt8,
+            Type::NullableUInt16 => Type::UInt16,
+            Type::NullableUInt32 => Type::UInt32,
+            Type::NullableUInt64 => Type::UInt64,
+            Type::NullableUInt128 => Type::UInt128,
+            Type::NullableUInt256 => Type::UInt256,
+            Type::NullableInt8 => Type::Int8,
+            Type::NullableInt16 => Type::Int16,
+            Type::NullableInt32 => Type::Int32,
+            Type::NullableInt64 => Type::Int64,
+            Type::NullableInt128 => Type::Int128,
+            Type::NullableInt256 => Type::Int256,
+            Type::NullableFloat32 => Type::Float32,
+            Type::NullableFloat64 => Type::Float64,
+            Type::NullableBFloat16 => Type::BFloat16,
+            Type::NullableDecimal(p, s) => Type::Decimal(p, s),
+            Type::NullableDecimal32(s) => Type::Decimal32(s),
+            Type::NullableDecimal64(s) => Type::Decimal64(s),
+            Type::NullableDecimal128(s) => Type::Decimal128(s),
+            Type::NullableDecimal256(s) => Type::Decimal256(s),
+            Type::NullableBool => Type::Bool,
+            Type::NullableString => Type::String,
+            Type::NullableFixedString(n) => Type::FixedString(n),
+            Type::NullableUUID => Type::UUID,
+            Type::NullableDate => Type::Date,
+            Type::NullableDate32 => Type::Date32,
+            Type::NullableDateTime(tz) => Type::DateTime(tz),
+            Type::NullableDateTime64(p, tz) => Type::DateTime64(p, tz),
+            Type::NullableTime => Type::Time,
+            Type::NullableTime64(p) => Type::Time64(p),
+            Type::NullableInterval(kind) => Type::Interval(kind),
+            Type::NullableEnum8(keys) => Type::Enum8(keys),
+            Type::NullableEnum16(keys) => Type::Enum16(keys),
+            Type::NullableNothing => Type::Nothing,
+            _ => return None,
+        })
+    }
 }
 
 impl std::fmt::Display for Type {
@@ -170,6 +425,7 @@ impl std::fmt::Display for Type {
             Type::Int256 => "Int256".into(),
             Type::Float32 => "Float32".into(),
             Type::Float64 => "Float64".into(),
+            Type::BFloat16 => "BFloat16".into(),
             Type::Decimal(p, s) => format!("Decimal({p},{s})"),
             Type::Decimal32(s) => format!("Decimal32({s})"),
             Type::Decimal64(s) => format!("Decimal64({s})"),
@@ -180,14 +436,19 @@ impl std::fmt::Display for Type {
             Type::FixedString(n) => format!("FixedString({n})"),
             Type::Date => "Date".into(),
             Type::Date32 => "Date32".into(),
-            Type::DateTime => "DateTime".into(),
-            Type::DateTime64(p) => format!("DateTime64({p})"),
+            Type::DateTime(None) => "DateTime".into(),
+            Type::DateTime(Some(tz)) => format!("DateTime('{tz}')"),
+            Type::DateTime64(p, None) => format!("DateTime64({p})"),
+            Type::DateTime64(p, Some(tz)) => format!("DateTime64({p}, '{tz}')"),
+            Type::Time => "Time".into(),
+            Type::Time64(p) => format!("Time64({p})"),
+            Type::Interval(kind) => format!("Interval{kind}"),
             Type::UUID => "UUID".into(),
             Type::Enum8(vars) => {
                 format!(
                     "Enum8({})",
                     vars.iter()
-                        .map(|(key, idx)| { format!("'{key}' = {idx}") })
+                        .map(|(key, idx)| format!("{} = {idx}", quote(key)))
                         .collect::<Vec<_>>()
                         .join(", ")
                 )
@@ -196,7 +457,7 @@ impl std::fmt::Display for Type {
                 format!(
                     "Enum16({})",
                     vars.iter()
-                        .map(|(key, idx)| { format!("'{key}' = {idx}") })
+                        .map(|(key, idx)| format!("{} = {idx}", quote(key)))
                         .collect::<Vec<_>>()
                         .join(", ")
                 )
@@ -213,6 +474,16 @@ impl std::fmt::Display for Type {
                         .join(", ")
                 )
             }
+            Type::NamedTuple(elements) => {
+                format!(
+                    "Tuple({})",
+                    elements
+                        .iter()
+                        .map(|(name, ty)| format!("{} {ty}", quote_ident(name)))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
             Type::Nested(fields) => {
                 format!(
                     "Nested({})",
@@ -223,6 +494,45 @@ impl std::fmt::Display for Type {
                         .join(", ")
                 )
             }
+            Type::LowCardinality(t) => format!("LowCardinality({t})"),
+            Type::SimpleAggregateFunction(func, t) => {
+                format!("SimpleAggregateFunction({func}, {t})")
+            }
+            Type::AggregateFunction(func, types) => {
+                let args = std::iter::once(func.clone())
+                    .chain(types.iter().map(|ty| ty.to_string()))
+                    .collect::<Vec<_>>();
+                format!("AggregateFunction({})", args.join(", "))
+            }
+            Type::Variant(types) => {
+                format!(
+                    "Variant({})",
+                    types
+                        .iter()
+                        .map(|ty| ty.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+            Type::Dynamic(None) => "Dynamic".into(),
+            Type::Dynamic(Some(n)) => format!("Dynamic(max_types={n})"),
+            Type::Json(paths, params) if paths.is_empty() && params.is_empty() => "JSON".into(),
+            Type::Json(paths, params) => {
+                // NB: settings come first, then typed paths, then skipped paths
+                let (skips, settings): (Vec<_>, Vec<_>) =
+                    params.iter().cloned().partition(|p| p.starts_with("SKIP "));
+                let paths = paths.iter().map(|(path, ty)| format!("{path} {ty}"));
+                format!(
+                    "JSON({})",
+                    settings
+                        .into_iter()
+                        .chain(paths)
+                        .chain(skips)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+            Type::Nothing => "Nothing".into(),
             Type::NullableUInt8 => "Nullable(UInt8)".into(),
             Type::NullableUInt16 => "Nullable(UInt16)".into(),
             Type::NullableUInt32 => "Nullable(UInt32)".into(),
@@ -237,6 +547,7 @@ impl std::fmt::Display for Type {
             Type::NullableInt256 => "Nullable(Int256)".into(),
             Type::NullableFloat32 => "Nullable(Float32)".into(),
             Type::NullableFloat64 => "Nullable(Float64)".into(),
+            Type::NullableBFloat16 => "Nullable(BFloat16)".into(),
             Type::NullableDecimal(p, s) => format!("Nullable(Decimal({p},{s}))"),
             Type::NullableDecimal32(s) => format!("Nullable(Decimal32({s}))"),
             Type::NullableDecimal64(s) => format!("Nullable(Decimal64({s}))"),
@@ -247,11 +558,17 @@ impl std::fmt::Display for Type {
             Type::NullableFixedString(n) => format!("Nullable(FixedString({n}))"),
             Type::NullableDate => "Nullable(Date)".into(),
             Type::NullableDate32 => "Nullable(Date32)".into(),
-            Type::NullableDateTime => "Nullable(DateTime)".into(),
-            Type::NullableDateTime64(p) => format!("Nullable(DateTime64({p}))"),
+            Type::NullableDateTime(tz) => format!("Nullable({})", Type::DateTime(tz.clone())),
+            Type::NullableDateTime64(p, tz) => {
+                format!("Nullable({})", Type::DateTime64(*p, tz.clone()))
+            }
+            Type::NullableTime => "Nullable(Time)".into(),
+            Type::NullableTime64(p) => format!("Nullable(Time64({p}))"),
+            Type::NullableInterval(kind) => format!("Nullable(Interval{kind})"),
             Type::NullableUUID => "Nullable(UUID)".into(),
             Type::NullableEnum8(keys) => format!("Nullable({})", Type::Enum8(keys.clone())),
             Type::NullableEnum16(keys) => format!("Nullable({})", Type::Enum16(keys.clone())),
+            Type::NullableNothing => "Nullable(Nothing)".into(),
         };
 
         write!(f, "{s}")
@@ -262,278 +579,25 @@ impl FromStr for Type {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        // base types
-        match s {
-            "UInt8" => return Ok(Type::UInt8),
-            "UInt16" => return Ok(Type::UInt16),
-            "UInt32" => return Ok(Type::UInt32),
-            "UInt64" => return Ok(Type::UInt64),
-            "UInt128" => return Ok(Type::UInt128),
-            "UInt256" => return Ok(Type::UInt256),
-            "Int8" => return Ok(Type::Int8),
-            "Int16" => return Ok(Type::Int16),
-            "Int32" => return Ok(Type::Int32),
-            "Int64" => return Ok(Type::Int64),
-            "Int128" => return Ok(Type::Int128),
-            "Int256" => return Ok(Type::Int256),
-            "Float32" => return Ok(Type::Float32),
-            "Float64" => return Ok(Type::Float64),
-            "Bool" => return Ok(Type::Bool),
-            "String" => return Ok(Type::String),
-            "UUID" => return Ok(Type::UUID),
-            "Date" => return Ok(Type::Date),
-            "Date32" => return Ok(Type::Date32),
-            "DateTime" => return Ok(Type::DateTime),
-            _ => {}
-        }
-
-        // > Decimal(P, S)
-        if let Some(s) = s.strip_prefix("Decimal(") {
-            if let Some(s) = s.strip_suffix(')') {
-                let parts = s.split(',').collect::<Vec<_>>();
-                if parts.len() != 2 {
-                    return Err(Error::new("invalid Decimal type"));
-                }
-                let p = parts[0].trim().parse::<u8>()?;
-                let s = parts[1].trim().parse::<u8>()?;
-                return Ok(Type::Decimal(p, s));
-            } else {
-                return Err(Error::new("invalid Decimal type"));
-            }
-        }
-
-        // > Decimal32(S)
-        if let Some(s) = s.strip_prefix("Decimal32(") {
-            if let Some(s) = s.strip_suffix(')') {
-                let s = s.trim().parse::<u8>()?;
-                return Ok(Type::Decimal32(s));
-            } else {
-                return Err(Error::new("invalid Decimal32 type"));
-            }
-        }
-
-        // > Decimal64(S)
-        if let Some(s) = s.strip_prefix("Decimal64(") {
-            if let Some(s) = s.strip_suffix(')') {
-                let s = s.trim().parse::<u8>()?;
-                return Ok(Type::Decimal64(s));
-            } else {
-                return Err(Error::new("invalid Decimal64 type"));
-            }
-        }
-
-        // > Decimal128(S)
-        if let Some(s) = s.strip_prefix("Decimal128(") {
-            if let Some(s) = s.strip_suffix(')') {
-                let s = s.trim().parse::<u8>()?;
-                return Ok(Type::Decimal128(s));
-            } else {
-                return Err(Error::new("invalid Decimal128 type"));
-            }
-        }
-
-        // > Decimal256(S)
-        if let Some(s) = s.strip_prefix("Decimal256(") {
-            if let Some(s) = s.strip_suffix(')') {
-                let s = s.trim().parse::<u8>()?;
-                return Ok(Type::Decimal256(s));
-            } else {
-                return Err(Error::new("invalid Decimal256 type"));
-            }
-        }
-
-        // > FixedString(N)
-        if let Some(s) = s.strip_prefix("FixedString(") {
-            if let Some(s) = s.strip_suffix(')') {
-                let n = s.trim().parse::<u8>()?;
-                return Ok(Type::FixedString(n));
-            } else {
-                return Err(Error::new("invalid FixedString type"));
-            }
-        }
-
-        // > DateTime64(P)
-        if let Some(s) = s.strip_prefix("DateTime64(") {
-            if let Some(s) = s.strip_suffix(')') {
-                let p = s.trim().parse::<u8>()?;
-                return Ok(Type::DateTime64(p));
-            } else {
-                return Err(Error::new("invalid DateTime64 type"));
-            }
-        }
-
-        // > Enum8(...)
-        if let Some(s) = s.strip_prefix("Enum8(") {
-            if let Some(s) = s.strip_suffix(')') {
-                let mut map = BTreeMap::new();
-                for variant in s.split(',') {
-                    let parts = variant.trim().split('=').collect::<Vec<_>>();
-                    if parts.len() != 2 {
-                        return Err(Error::new("invalid Enum8 type"));
-                    }
-                    let name = parts[0]
-                        .trim()
-                        .trim_start_matches('\'')
-                        .trim_end_matches('\'')
-                        .to_string();
-                    let index = parts[1].trim().parse::<i8>()?;
-                    map.insert(name, index);
-                }
-                return Ok(Type::Enum8(map));
-            } else {
-                return Err(Error::new("invalid DateTime64 type"));
-            }
-        }
-
-        // > Enum16(...)
-        if let Some(s) = s.strip_prefix("Enum16(") {
-            if let Some(s) = s.strip_suffix(')') {
-                let mut map = BTreeMap::new();
-                for variant in s.split(',') {
-                    let parts = variant.trim().split('=').collect::<Vec<_>>();
-                    if parts.len() != 2 {
-                        return Err(Error::new("invalid Enum8 type"));
-                    }
-                    let name = parts[0]
-                        .trim()
-                        .trim_start_matches('\'')
-                        .trim_end_matches('\'')
-                        .to_string();
-                    let index = parts[1].trim().parse::<i16>()?;
-                    map.insert(name, index);
-                }
-                return Ok(Type::Enum16(map));
-            } else {
-                return Err(Error::new("invalid DateTime64 type"));
-            }
-        }
-
-        // > Enum(...)
-        if let Some(s) = s.strip_prefix("Enum(") {
-            if let Some(s) = s.strip_suffix(')') {
-                let mut map = BTreeMap::new();
-                for variant in s.split(',') {
-                    let parts = variant.trim().split('=').collect::<Vec<_>>();
-                    if parts.len() != 2 {
-                        return Err(Error::new("invalid Enum8 type"));
-                    }
-                    let name = parts[0]
-                        .trim()
-                        .trim_start_matches('\'')
-                        .trim_end_matches('\'')
-                        .to_string();
-                    let index = parts[1].trim().parse::<i16>()?;
-                    map.insert(name, index);
-                }
-                return Ok(Type::Enum16(map));
-            } else {
-                return Err(Error::new("invalid DateTime64 type"));
-            }
-        }
-
-        // > Array
-        if let Some(s) = s.strip_prefix("Array(") {
-            if let Some(s) = s.strip_suffix(')') {
-                let ty = s.trim().parse::<Type>()?;
-                return Ok(Type::Array(Box::new(ty)));
-            } else {
-                return Err(Error::new("invalid Array type"));
-            }
-        }
-
-        // > Tuple
-        if let Some(s) = s.strip_prefix("Tuple(") {
-            if let Some(s) = s.strip_suffix(')') {
-                let mut types = vec![];
-                for ty_str in s.split(',') {
-                    let ty = ty_str.trim().parse::<Type>()?;
-                    types.push(ty);
-                }
-                return Ok(Type::Tuple(types));
-            } else {
-                return Err(Error::new("invalid Tuple type"));
-            }
-        }
-
-        // > Map
-        if let Some(s) = s.strip_prefix("Map(") {
-            if let Some(s) = s.strip_suffix(')') {
-                let parts = s.split(',').collect::<Vec<_>>();
-                if parts.len() != 2 {
-                    return Err(Error::new("invalid Map type"));
-                }
-                let key_ty = parts[0].trim().parse::<Type>()?;
-                let val_ty = parts[1].trim().parse::<Type>()?;
-                return Ok(Type::Map(Box::new(key_ty), Box::new(val_ty)));
-            } else {
-                return Err(Error::new("invalid Map type"));
-            }
-        }
-
-        // > Nested
-        if let Some(s) = s.strip_prefix("Nested(") {
-            if let Some(s) = s.strip_suffix(')') {
-                let mut fields = vec![];
-                for field_str in s.split(',').collect::<Vec<_>>() {
-                    let parts = field_str.trim().split(' ').collect::<Vec<_>>();
-                    if parts.len() != 2 {
-                        return Err(Error::new("invalid Nested type"));
-                    }
-                    let name = parts[0].trim();
-                    let ty = parts[1].trim().parse::<Type>()?;
-                    fields.push((name.to_string(), ty));
-                }
-
-                return Ok(Type::Nested(fields));
-            } else {
-                return Err(Error::new("invalid Nested type"));
-            }
-        }
+        TypeParser::new(s).parse()
+    }
+}
 
-        // > Nullable(T)
-        if let Some(s) = s.strip_prefix("Nullable(") {
-            if let Some(s) = s.strip_suffix(')') {
-                let ty = s.parse::<Type>()?;
-                let ty = match ty {
-                    Type::UInt8 => Type::NullableUInt8,
-                    Type::UInt16 => Type::NullableUInt16,
-                    Type::UInt32 => Type::NullableUInt32,
-                    Type::UInt64 => Type::NullableUInt64,
-                    Type::UInt128 => Type::NullableUInt128,
-                    Type::UInt256 => Type::NullableUInt256,
-                    Type::Int8 => Type::NullableInt8,
-                    Type::Int16 => Type::NullableInt16,
-                    Type::Int32 => Type::NullableInt32,
-                    Type::Int64 => Type::NullableInt64,
-                    Type::Int128 => Type::NullableInt128,
-                    Type::Int256 => Type::NullableInt256,
-                    Type::Float32 => Type::NullableFloat32,
-                    Type::Float64 => Type::NullableFloat64,
-                    Type::Decimal(p, s) => Type::NullableDecimal(p, s),
-                    Type::Decimal32(s) => Type::NullableDecimal32(s),
-                    Type::Decimal64(s) => Type::NullableDecimal64(s),
-                    Type::Decimal128(s) => Type::NullableDecimal128(s),
-                    Type::Decimal256(s) => Type::NullableDecimal256(s),
-                    Type::Bool => Type::NullableBool,
-                    Type::String => Type::NullableString,
-                    Type::FixedString(n) => Type::NullableFixedString(n),
-                    Type::UUID => Type::NullableUUID,
-                    Type::Date => Type::NullableDate,
-                    Type::Date32 => Type::NullableDate32,
-                    Type::DateTime => Type::NullableDateTime,
-                    Type::DateTime64(p) => Type::NullableDateTime64(p),
-                    Type::Enum8(keys) => Type::NullableEnum8(keys),
-                    Type::Enum16(keys) => Type::NullableEnum16(keys),
-                    _ => return Err(Error::new("invalid Nullable type")),
-                };
-                return Ok(ty);
-            } else {
-                return Err(Error::new("invalid Nullable type"));
-            }
-        }
+/// Quotes a string (eg an enum variant name), with its quotes and backslashes escaped
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
 
-        Err(Error::new(
-            format!("'{s}' is not a valid Clickhouse type").as_str(),
-        ))
+/// Quotes an identifier with backquotes (eg a tuple element name), if it is not a plain identifier
+fn quote_ident(s: &str) -> String {
+    let mut chars = s.chars();
+    let is_plain = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_plain {
+        s.to_string()
+    } else {
+        format!("`{}`", s.replace('\\', "\\\\").replace('`', "\\`"))
     }
 }
@@ -0,0 +1,577 @@
+//! Parsing of type strings
+//!
+//! Type strings (eg `Array(Tuple(a UInt8, b Nullable(String)))`) are split into tokens, and
+//! parsed recursively. Errors report the position (in bytes) of the invalid token.
+
+use std::collections::BTreeMap;
+
+use super::{IntervalKind, Type};
+use crate::error::Error;
+
+/// Token of a type string
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'a> {
+    /// Identifier (eg `UInt8`, a tuple element name or a JSON path)
+    ///
+    /// Backquoted or double-quoted identifiers are unquoted.
+    Ident(String),
+    /// Number (eg `3`, `-1` or `0.5`)
+    Number(&'a str),
+    /// Quoted string (eg `'Europe/Paris'`), unescaped
+    Str(String),
+    /// `(`
+    LParen,
+    /// `)`
+    RParen,
+    /// `,`
+    Comma,
+    /// `=`
+    Eq,
+    /// End of the type string
+    End,
+}
+
+impl std::fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident(s) => write!(f, "'{s}'"),
+            Token::Number(s) => write!(f, "'{s}'"),
+            Token::Str(s) => write!(f, "string '{s}'"),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+            Token::Eq => write!(f, "'='"),
+            Token::End => write!(f, "end of input"),
+        }
+    }
+}
+
+/// Argument of a `JSON` type
+enum JsonArg {
+    /// Typed path (eg `a.b UInt32`)
+    Path(String, Type),
+    /// Other parameter (eg `max_dynamic_paths=N`, `SKIP a.c`, `SKIP REGEXP 'x'`)
+    Param(String),
+}
+
+/// Parameter of an aggregate function (eg `0.5` in `quantile(0.5)`)
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum FunctionParam {
+    /// Number, as written
+    Number(String),
+    /// Quoted string, unescaped
+    Str(String),
+}
+
+/// Recursive-descent parser of a type string
+pub(super) struct TypeParser<'a> {
+    /// Type string
+    s: &'a str,
+    /// Current position (in bytes)
+    pos: usize,
+}
+
+impl<'a> TypeParser<'a> {
+    /// Creates a new parser
+    pub(super) fn new(s: &'a str) -> Self {
+        Self { s, pos: 0 }
+    }
+
+    /// Parses the whole type string
+    pub(super) fn parse(mut self) -> Result<Type, Error> {
+        let ty = self.parse_type()?;
+        let (pos, token) = self.next()?;
+        if token != Token::End {
+            return Err(self.error(pos, &format!("unexpected {token} after the type")));
+        }
+        Ok(ty)
+    }
+
+    /// Returns an error at a position
+    fn error(&self, pos: usize, msg: &str) -> Error {
+        Error(format!(
+            "Invalid type '{}': {msg} at position {pos}",
+            self.s
+        ))
+    }
+
+    /// Reads the next token, and returns it with its position
+    fn next(&mut self) -> Result<(usize, Token<'a>), Error> {
+        let bytes = self.s.as_bytes();
+        while self.pos < bytes.len() && bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+
+        let start = self.pos;
+        let Some(&b) = bytes.get(start) else {
+            return Ok((start, Token::End));
+        };
+        let token = match b {
+            b'(' => Token::LParen,
+            b')' => Token::RParen,
+            b',' => Token::Comma,
+            b'=' => Token::Eq,
+            b'\'' => Token::Str(self.read_quoted(b'\'')?),
+            b'`' | b'"' => Token::Ident(self.read_quoted(b)?),
+            b'0'..=b'9' | b'-' | b'+' => {
+                let mut end = start + 1;
+                while end < bytes.len()
+                    && (bytes[end].is_ascii_alphanumeric() || bytes[end] == b'.')
+                {
+                    end += 1;
+                }
+                self.pos = end;
+                return Ok((start, Token::Number(&self.s[start..end])));
+            }
+            b if b.is_ascii_alphabetic() || b == b'_' => {
+                let mut end = start + 1;
+                while end < bytes.len()
+                    && (bytes[end].is_ascii_alphanumeric() || matches!(bytes[end], b'_' | b'.'))
+                {
+                    end += 1;
+                }
+                self.pos = end;
+                return Ok((start, Token::Ident(self.s[start..end].to_string())));
+            }
+            _ => {
+                let c = self.s[start..].chars().next().unwrap_or_default();
+                return Err(self.error(start, &format!("unexpected character '{c}'")));
+            }
+        };
+        if matches!(
+            token,
+            Token::LParen | Token::RParen | Token::Comma | Token::Eq
+        ) {
+            self.pos += 1;
+        }
+        Ok((start, token))
+    }
+
+    /// Reads a quoted string, and returns it unescaped
+    ///
+    /// A quote is escaped with a backslash, or doubled.
+    fn read_quoted(&mut self, quote: u8) -> Result<String, Error> {
+        let start = self.pos;
+        let mut s = vec![];
+        let mut i = start + 1;
+        let bytes = self.s.as_bytes();
+        loop {
+            match bytes.get(i) {
+                None => return Err(self.error(start, "unterminated quoted string")),
+                Some(b'\\') => {
+                    let c = bytes
+                        .get(i + 1)
+                        .ok_or_else(|| self.error(start, "unterminated quoted string"))?;
+                    s.push(match c {
+                        b'n' => b'\n',
+                        b't' => b'\t',
+                        b'r' => b'\r',
+                        b'0' => b'\0',
+                        c => *c,
+                    });
+                    i += 2;
+                }
+                Some(&b) if b == quote && bytes.get(i + 1) == Some(&quote) => {
+                    s.push(quote);
+                    i += 2;
+                }
+                Some(&b) if b == quote => break,
+                Some(&b) => {
+                    s.push(b);
+                    i += 1;
+                }
+            }
+        }
+        self.pos = i + 1;
+        String::from_utf8(s).map_err(|_| self.error(start, "invalid UTF-8 string"))
+    }
+
+    /// Returns the next token and its position, without consuming it
+    fn peek(&mut self) -> Result<(usize, Token<'a>), Error> {
+        let pos = self.pos;
+        let next = self.next()?;
+        self.pos = pos;
+        Ok(next)
+    }
+
+    /// Consumes the next token, which must be the expected one
+    fn expect(&mut self, expected: Token) -> Result<(), Error> {
+        let (pos, token) = self.next()?;
+        if token != expected {
+            return Err(self.error(pos, &format!("expected {expected}, found {token}")));
+        }
+        Ok(())
+    }
+
+    /// Consumes an identifier
+    fn parse_ident(&mut self, what: &str) -> Result<(usize, String), Error> {
+        match self.next()? {
+            (pos, Token::Ident(s)) => Ok((pos, s)),
+            (pos, token) => Err(self.error(pos, &format!("expected {what}, found {token}"))),
+        }
+    }
+
+    /// Consumes a quoted string
+    fn parse_str(&mut self, what: &str) -> Result<String, Error> {
+        match self.next()? {
+            (_, Token::Str(s)) => Ok(s),
+            (pos, token) => Err(self.error(pos, &format!("expected {what}, found {token}"))),
+        }
+    }
+
+    /// Consumes a number
+    fn parse_number<T: std::str::FromStr>(&mut self, what: &str) -> Result<T, Error> {
+        match self.next()? {
+            (pos, Token::Number(s)) => s
+                .parse::<T>()
+                .map_err(|_| self.error(pos, &format!("invalid {what} '{s}'"))),
+            (pos, token) => Err(self.error(pos, &format!("expected {what}, found {token}"))),
+        }
+    }
+
+    /// Parses a parenthesized list of comma-separated items (which can be empty)
+    fn parse_list<T>(
+        &mut self,
+        mut parse_item: impl FnMut(&mut Self) -> Result<T, Error>,
+    ) -> Result<Vec<T>, Error> {
+        self.expect(Token::LParen)?;
+        let mut items = vec![];
+        if self.peek()?.1 == Token::RParen {
+            self.next()?;
+            return Ok(items);
+        }
+        loop {
+            items.push(parse_item(self)?);
+            match self.next()? {
+                (_, Token::Comma) => {}
+                (_, Token::RParen) => return Ok(items),
+                (pos, token) => {
+                    return Err(self.error(pos, &format!("expected ',' or ')', found {token}")))
+                }
+            }
+        }
+    }
+
+    /// Parses a single parenthesized type
+    fn parse_inner_type(&mut self) -> Result<Type, Error> {
+        self.expect(Token::LParen)?;
+        let ty = self.parse_type()?;
+        self.expect(Token::RParen)?;
+        Ok(ty)
+    }
+
+    /// Parses a type
+    fn parse_type(&mut self) -> Result<Type, Error> {
+        let (pos, name) = self.parse_ident("a type")?;
+        let has_args = self.peek()?.1 == Token::LParen;
+
+        // > base types
+        if !has_args {
+            let ty = match name.as_str() {
+                "UInt8" => Some(Type::UInt8),
+                "UInt16" => Some(Type::UInt16),
+                "UInt32" => Some(Type::UInt32),
+                "UInt64" => Some(Type::UInt64),
+                "UInt128" => Some(Type::UInt128),
+                "UInt256" => Some(Type::UInt256),
+                "Int8" => Some(Type::Int8),
+                "Int16" => Some(Type::Int16),
+                "Int32" => Some(Type::Int32),
+                "Int64" => Some(Type::Int64),
+                "Int128" => Some(Type::Int128),
+                "Int256" => Some(Type::Int256),
+                "Float32" => Some(Type::Float32),
+                "Float64" => Some(Type::Float64),
+                "BFloat16" => Some(Type::BFloat16),
+                "Bool" => Some(Type::Bool),
+                "String" => Some(Type::String),
+                "UUID" => Some(Type::UUID),
+                "Date" => Some(Type::Date),
+                "Date32" => Some(Type::Date32),
+                "DateTime" => Some(Type::DateTime(None)),
+                "Time" => Some(Type::Time),
+                "Nothing" => Some(Type::Nothing),
+                "Dynamic" => Some(Type::Dynamic(None)),
+                "JSON" => Some(Type::Json(vec![], vec![])),
+                _ => name
+                    .strip_prefix("Interval")
+                    .and_then(|kind| IntervalKind::ALL.iter().find(|k| k.name() == kind))
+                    .map(|kind| Type::Interval(*kind)),
+            };
+            return ty.ok_or_else(|| self.error(pos, &format!("unknown type '{name}'")));
+        }
+
+        // > parametric types
+        let ty = match name.as_str() {
+            "Decimal" => {
+                // NB: the scale defaults to 0
+                self.expect(Token::LParen)?;
+                let p = self.parse_number("precision")?;
+                let s = match self.next()? {
+                    (_, Token::Comma) => {
+                        let s = self.parse_number("scale")?;
+                        self.expect(Token::RParen)?;
+                        s
+                    }
+                    (_, Token::RParen) => 0,
+                    (pos, token) => {
+                        return Err(self.error(pos, &format!("expected ',' or ')', found {token}")))
+                    }
+                };
+                Type::Decimal(p, s)
+            }
+            "Decimal32" | "Decimal64" | "Decimal128" | "Decimal256" => {
+                self.expect(Token::LParen)?;
+                let s = self.parse_number("scale")?;
+                self.expect(Token::RParen)?;
+                match name.as_str() {
+                    "Decimal32" => Type::Decimal32(s),
+                    "Decimal64" => Type::Decimal64(s),
+                    "Decimal128" => Type::Decimal128(s),
+                    _ => Type::Decimal256(s),
+                }
+            }
+            "FixedString" => {
+                self.expect(Token::LParen)?;
+                let n = self.parse_number("length")?;
+                self.expect(Token::RParen)?;
+                Type::FixedString(n)
+            }
+            "DateTime" => {
+                self.expect(Token::LParen)?;
+                let tz = self.parse_str("a timezone")?;
+                self.expect(Token::RParen)?;
+                Type::DateTime(Some(tz))
+            }
+            "DateTime64" => {
+                self.expect(Token::LParen)?;
+                let p = self.parse_number("precision")?;
+                let tz = match self.next()? {
+                    (_, Token::Comma) => {
+                        let tz = self.parse_str("a timezone")?;
+                        self.expect(Token::RParen)?;
+                        Some(tz)
+                    }
+                    (_, Token::RParen) => None,
+                    (pos, token) => {
+                        return Err(self.error(pos, &format!("expected ',' or ')', found {token}")))
+                    }
+                };
+                Type::DateTime64(p, tz)
+            }
+            "Time64" => {
+                self.expect(Token::LParen)?;
+                let p = self.parse_number("precision")?;
+                self.expect(Token::RParen)?;
+                Type::Time64(p)
+            }
+            "Enum8" => Type::Enum8(self.parse_enum_variants()?),
+            // NB: `Enum` is an `Enum16`
+            "Enum16" | "Enum" => Type::Enum16(self.parse_enum_variants()?),
+            "Array" => Type::Array(Box::new(self.parse_inner_type()?)),
+            "Tuple" => {
+                // NB: either all elements are named, or none of them
+                let (list_pos, _) = self.peek()?;
+                let elements = self.parse_list(|p| p.parse_maybe_named_type())?;
+                if elements.iter().all(|(name, _)| name.is_some()) && !elements.is_empty() {
+                    Type::NamedTuple(
+                        elements
+                            .into_iter()
+                            .filter_map(|(name, ty)| Some((name?, ty)))
+                            .collect(),
+                    )
+                } else if elements.iter().all(|(name, _)| name.is_none()) {
+                    Type::Tuple(elements.into_iter().map(|(_, ty)| ty).collect())
+                } else {
+                    return Err(self.error(list_pos, "mixed named and unnamed tuple elements"));
+                }
+            }
+            "Map" => {
+                self.expect(Token::LParen)?;
+                let key_ty = self.parse_type()?;
+                self.expect(Token::Comma)?;
+                let val_ty = self.parse_type()?;
+                self.expect(Token::RParen)?;
+                Type::Map(Box::new(key_ty), Box::new(val_ty))
+            }
+            "Nested" => Type::Nested(self.parse_list(|p| {
+                let (_, name) = p.parse_ident("a column name")?;
+                Ok((name, p.parse_type()?))
+            })?),
+            "LowCardinality" => Type::LowCardinality(Box::new(self.parse_inner_type()?)),
+            "SimpleAggregateFunction" => {
+                self.expect(Token::LParen)?;
+                let func = self.parse_function()?;
+                self.expect(Token::Comma)?;
+                let ty = self.parse_type()?;
+                self.expect(Token::RParen)?;
+                Type::SimpleAggregateFunction(func, Box::new(ty))
+            }
+            "AggregateFunction" => {
+                self.expect(Token::LParen)?;
+                let func = self.parse_function()?;
+                let mut types = vec![];
+                loop {
+                    match self.next()? {
+                        (_, Token::Comma) => types.push(self.parse_type()?),
+                        (_, Token::RParen) => break,
+                        (pos, token) => {
+                            return Err(
+                                self.error(pos, &format!("expected ',' or ')', found {token}"))
+                            )
+                        }
+                    }
+                }
+                Type::AggregateFunction(func, types)
+            }
+            "Variant" => Type::Variant(self.parse_list(|p| p.parse_type())?),
+            "Dynamic" => {
+                self.expect(Token::LParen)?;
+                let (key_pos, key) = self.parse_ident("'max_types'")?;
+                if key != "max_types" {
+                    return Err(self.error(key_pos, &format!("unknown parameter '{key}'")));
+                }
+                self.expect(Token::Eq)?;
+                let n = self.parse_number("max types")?;
+                self.expect(Token::RParen)?;
+                Type::Dynamic(Some(n))
+            }
+            "JSON" => {
+                let mut paths = vec![];
+                let mut params = vec![];
+                for arg in self.parse_list(|p| p.parse_json_arg())? {
+                    match arg {
+                        JsonArg::Path(path, ty) => paths.push((path, ty)),
+                        JsonArg::Param(param) => params.push(param),
+                    }
+                }
+                Type::Json(paths, params)
+            }
+            "Nullable" => {
+                self.expect(Token::LParen)?;
+                let (inner_pos, _) = self.peek()?;
+                let ty = self.parse_type()?;
+                self.expect(Token::RParen)?;
+                let ty_str = ty.to_string();
+                ty.into_nullable().ok_or_else(|| {
+                    self.error(
+                        inner_pos,
+                        &format!("{ty_str} cannot be inside a Nullable type"),
+                    )
+                })?
+            }
+            _ => return Err(self.error(pos, &format!("unknown type '{name}'"))),
+        };
+        Ok(ty)
+    }
+
+    /// Parses a type which can be preceded by a name (eg a tuple element `a UInt8`)
+    fn parse_maybe_named_type(&mut self) -> Result<(Option<String>, Type), Error> {
+        let pos = self.pos;
+        if let (_, Token::Ident(name)) = self.next()? {
+            if let (_, Token::Ident(_)) = self.peek()? {
+                return Ok((Some(name), self.parse_type()?));
+            }
+        }
+        self.pos = pos;
+        Ok((None, self.parse_type()?))
+    }
+
+    /// Parses the variants of an enum (eg `'a' = 1, 'b' = 2`)
+    ///
+    /// Variants without a value are numbered after the previous one (starting at 1).
+    fn parse_enum_variants<T>(&mut self) -> Result<BTreeMap<String, T>, Error>
+    where
+        T: std::str::FromStr + TryFrom<i64> + Copy + Into<i64>,
+    {
+        let mut next_value = 1_i64;
+        let variants = self.parse_list(|p| {
+            let name = p.parse_str("an enum variant name")?;
+            let value = if p.peek()?.1 == Token::Eq {
+                p.next()?;
+                p.parse_number::<T>("enum value")?
+            } else {
+                let pos = p.pos;
+                T::try_from(next_value)
+                    .map_err(|_| p.error(pos, &format!("enum value {next_value} out of range")))?
+            };
+            next_value = value.into() + 1;
+            Ok((name, value))
+        })?;
+        Ok(variants.into_iter().collect())
+    }
+
+    /// Parses an aggregate function, with its optional parameters (eg `quantiles(0.5, 0.9)`)
+    ///
+    /// The function is returned as written.
+    fn parse_function(&mut self) -> Result<String, Error> {
+        let (start, _) = self.parse_ident("an aggregate function")?;
+        if self.peek()?.1 == Token::LParen {
+            let mut depth = 0_usize;
+            loop {
+                match self.next()? {
+                    (_, Token::LParen) => depth += 1,
+                    (_, Token::RParen) => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    (pos, Token::End) => {
+                        return Err(self.error(pos, "unterminated function parameters"))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(self.s[start..self.pos].to_string())
+    }
+
+    /// Parses a whole aggregate function to its name and parameters (eg `quantiles(0.5, 0.9)`)
+    pub(super) fn parse_function_params(mut self) -> Result<(String, Vec<FunctionParam>), Error> {
+        let (_, name) = self.parse_ident("an aggregate function")?;
+        let params = match self.peek()?.1 {
+            Token::LParen => self.parse_list(|p| match p.next()? {
+                (_, Token::Number(n)) => Ok(FunctionParam::Number(n.to_string())),
+                (_, Token::Str(s)) => Ok(FunctionParam::Str(s)),
+                (pos, token) => Err(p.error(pos, &format!("unsupported parameter {token}"))),
+            })?,
+            _ => vec![],
+        };
+        let (pos, token) = self.next()?;
+        if token != Token::End {
+            return Err(self.error(pos, &format!("unexpected {token} after the function")));
+        }
+        Ok((name, params))
+    }
+
+    /// Parses an argument of a `JSON` type
+    ///
+    /// `SKIP` parameters are returned as written.
+    fn parse_json_arg(&mut self) -> Result<JsonArg, Error> {
+        let (start, name) = self.parse_ident("a JSON path or parameter")?;
+        if name == "SKIP" {
+            loop {
+                match self.peek()?.1 {
+                    Token::Comma | Token::RParen | Token::End => break,
+                    _ => {
+                        self.next()?;
+                    }
+                }
+            }
+            return Ok(JsonArg::Param(self.s[start..self.pos].trim().to_string()));
+        }
+        if self.peek()?.1 == Token::Eq {
+            self.next()?;
+            let value = match self.next()? {
+                (_, Token::Number(n)) => n.to_string(),
+                (_, Token::Ident(s)) => s,
+                (pos, token) => {
+                    return Err(self.error(pos, &format!("expected a value, found {token}")))
+                }
+            };
+            return Ok(JsonArg::Param(format!("{name}={value}")));
+        }
+        Ok(JsonArg::Path(name, self.parse_type()?))
+    }
+}
@@ -2,7 +2,7 @@
 
 use std::collections::BTreeMap;
 
-use super::Type;
+use super::{IntervalKind, Type};
 
 /// Sets a test
 macro_rules! set_test {
@@ -49,8 +49,22 @@ set_test!(
 set_test!(type_str_uuid, Type::UUID, "UUID");
 set_test!(type_str_date, Type::Date, "Date");
 set_test!(type_str_date32, Type::Date32, "Date32");
-set_test!(type_str_datetime, Type::DateTime, "DateTime");
-set_test!(type_str_datetime64, Type::DateTime64(1), "DateTime64(1)");
+set_test!(type_str_datetime, Type::DateTime(None), "DateTime");
+set_test!(
+    type_str_datetime_tz,
+    Type::DateTime(Some("Europe/Paris".to_string())),
+    "DateTime('Europe/Paris')"
+);
+set_test!(
+    type_str_datetime64,
+    Type::DateTime64(1, None),
+    "DateTime64(1)"
+);
+set_test!(
+    type_str_datetime64_tz,
+    Type::DateTime64(3, Some("UTC".to_string())),
+    "DateTime64(3, 'UTC')"
+);
 set_test!(
     type_str_enum8,
     Type::Enum8(BTreeMap::from([
@@ -77,11 +91,53 @@ set_test!(
     Type::Tuple(vec![Type::UInt8, Type::UInt16]),
     "Tuple(UInt8, UInt16)"
 );
+set_test!(
+    type_str_named_tuple,
+    Type::NamedTuple(vec![
+        ("lat".to_string(), Type::Float64),
+        ("lon".to_string(), Type::Float64)
+    ]),
+    "Tuple(lat Float64, lon Float64)"
+);
 set_test!(
     type_str_map,
     Type::Map(Box::new(Type::String), Box::new(Type::UInt8)),
     "Map(String, UInt8)"
 );
+set_test!(
+    type_str_map_uint64,
+    Type::Map(Box::new(Type::UInt64), Box::new(Type::String)),
+    "Map(UInt64, String)"
+);
+set_test!(
+    type_str_map_low_cardinality,
+    Type::Map(
+        Box::new(Type::LowCardinality(Box::new(Type::String))),
+        Box::new(Type::Array(Box::new(Type::UInt8)))
+    ),
+    "Map(LowCardinality(String), Array(UInt8))"
+);
+set_test!(
+    type_str_map_nested,
+    Type::Map(
+        Box::new(Type::String),
+        Box::new(Type::Map(Box::new(Type::UInt8), Box::new(Type::UInt8)))
+    ),
+    "Map(String, Map(UInt8, UInt8))"
+);
+set_test!(
+    type_str_tuple_map,
+    Type::Tuple(vec![
+        Type::UInt8,
+        Type::Map(Box::new(Type::String), Box::new(Type::UInt8))
+    ]),
+    "Tuple(UInt8, Map(String, UInt8))"
+);
+set_test!(
+    type_str_low_cardinality,
+    Type::LowCardinality(Box::new(Type::String)),
+    "LowCardinality(String)"
+);
 set_test!(
     type_str_nested,
     Type::Nested(vec![
@@ -90,6 +146,49 @@ set_test!(
     ]),
     "Nested(a UInt8, b UInt16)"
 );
+set_test!(
+    type_str_variant,
+    Type::Variant(vec![Type::String, Type::Array(Box::new(Type::UInt64))]),
+    "Variant(String, Array(UInt64))"
+);
+set_test!(
+    type_str_simple_aggregate_function,
+    Type::SimpleAggregateFunction("sum".to_string(), Box::new(Type::UInt64)),
+    "SimpleAggregateFunction(sum, UInt64)"
+);
+set_test!(
+    type_str_aggregate_function,
+    Type::AggregateFunction("uniq".to_string(), vec![Type::UInt64]),
+    "AggregateFunction(uniq, UInt64)"
+);
+set_test!(
+    type_str_aggregate_function_params,
+    Type::AggregateFunction(
+        "quantiles(0.5, 0.9)".to_string(),
+        vec![Type::Float64, Type::UInt8]
+    ),
+    "AggregateFunction(quantiles(0.5, 0.9), Float64, UInt8)"
+);
+set_test!(
+    type_str_aggregate_function_no_args,
+    Type::AggregateFunction("count".to_string(), vec![]),
+    "AggregateFunction(count)"
+);
+set_test!(type_str_dynamic, Type::Dynamic(None), "Dynamic");
+set_test!(
+    type_str_dynamic_max_types,
+    Type::Dynamic(Some(8)),
+    "Dynamic(max_types=8)"
+);
+set_test!(type_str_json, Type::Json(vec![], vec![]), "JSON");
+set_test!(
+    type_str_json_params,
+    Type::Json(
+        vec![("a.b".to_string(), Type::UInt32)],
+        vec!["max_dynamic_paths=10".to_string(), "SKIP a.c".to_string()]
+    ),
+    "JSON(max_dynamic_paths=10, a.b UInt32, SKIP a.c)"
+);
 set_test!(type_str_uint8_null, Type::NullableUInt8, "Nullable(UInt8)");
 set_test!(
     type_str_uint16_null,
@@ -185,11 +284,295 @@ set_test!(
 );
 set_test!(
     type_str_datetime_null,
-    Type::NullableDateTime,
+    Type::NullableDateTime(None),
     "Nullable(DateTime)"
 );
 set_test!(
     type_str_datetime64_null,
-    Type::NullableDateTime64(1),
+    Type::NullableDateTime64(1, None),
     "Nullable(DateTime64(1))"
 );
+set_test!(
+    type_str_datetime64_tz_null,
+    Type::NullableDateTime64(6, Some("Asia/Tokyo".to_string())),
+    "Nullable(DateTime64(6, 'Asia/Tokyo'))"
+);
+set_test!(type_str_bfloat16, Type::BFloat16, "BFloat16");
+set_test!(type_str_time, Type::Time, "Time");
+set_test!(type_str_time64, Type::Time64(3), "Time64(3)");
+set_test!(
+    type_str_interval,
+    Type::Interval(IntervalKind::Second),
+    "IntervalSecond"
+);
+set_test!(
+    type_str_interval_null,
+    Type::NullableInterval(IntervalKind::Quarter),
+    "Nullable(IntervalQuarter)"
+);
+set_test!(
+    type_str_nothing_null,
+    Type::NullableNothing,
+    "Nullable(Nothing)"
+);
+
+#[test]
+fn type_interval_kinds() {
+    for kind in IntervalKind::ALL {
+        let ty = Type::Interval(kind);
+        assert_eq!(ty.to_string().parse::<Type>().unwrap(), ty);
+    }
+    assert!("IntervalFortnight".parse::<Type>().is_err());
+    assert_eq!(IntervalKind::Week.nanoseconds(), Some(604_800_000_000_000));
+    assert_eq!(IntervalKind::Month.nanoseconds(), None);
+}
+
+#[test]
+fn type_variant_discriminators() {
+    let ty = "Variant(UInt64, String, Array(UInt64))"
+        .parse::<Type>()
+        .unwrap();
+    assert_eq!(
+        ty.variant_types().unwrap(),
+        vec![
+            &Type::Array(Box::new(Type::UInt64)),
+            &Type::String,
+            &Type::UInt64
+        ]
+    );
+    assert_eq!(Type::UInt64.variant_types(), None);
+}
+
+#[test]
+fn type_binary() {
+    let types = [
+        (Type::UInt8, vec![0x01]),
+        (Type::NullableString, vec![0x23, 0x15]),
+        (
+            Type::DateTime64(3, Some("UTC".to_string())),
+            vec![0x14, 0x03, 0x03, b'U', b'T', b'C'],
+        ),
+        (Type::Decimal(10, 2), vec![0x1A, 10, 2]),
+        (
+            Type::Map(
+                Box::new(Type::String),
+                Box::new(Type::Array(Box::new(Type::Int32))),
+            ),
+            vec![0x27, 0x15, 0x1E, 0x09],
+        ),
+        (
+            Type::Enum8(BTreeMap::from([("a".to_string(), -1)])),
+            vec![0x17, 0x01, 0x01, b'a', 0xFF],
+        ),
+        (
+            Type::Variant(vec![Type::UInt64, Type::String]),
+            vec![0x2A, 0x02, 0x15, 0x04],
+        ),
+        (Type::Dynamic(None), vec![0x2B, 32]),
+        (
+            Type::NamedTuple(vec![("a".to_string(), Type::UInt8)]),
+            vec![0x20, 0x01, 0x01, b'a', 0x01],
+        ),
+        (Type::NullableNothing, vec![0x23, 0x00]),
+        (Type::BFloat16, vec![0x31]),
+        (Type::Time64(6), vec![0x34, 0x06]),
+        (Type::Interval(IntervalKind::Day), vec![0x22, 0x06]),
+        (
+            Type::AggregateFunction("uniq".to_string(), vec![Type::UInt64]),
+            vec![0x25, 0x00, 0x04, b'u', b'n', b'i', b'q', 0x00, 0x01, 0x04],
+        ),
+        (
+            Type::AggregateFunction("quantiles(0.5, 9)".to_string(), vec![Type::Float64]),
+            [
+                &[0x25, 0x00, 0x09][..],
+                b"quantiles",
+                &[0x02, 0x07],
+                &0.5_f64.to_le_bytes(),
+                &[0x01, 0x09, 0x01, 0x0E],
+            ]
+            .concat(),
+        ),
+        (
+            Type::AggregateFunction(
+                "sequenceMatch('(?1)')".to_string(),
+                vec![Type::DateTime(None), Type::Bool],
+            ),
+            [
+                &[0x25, 0x00, 0x0D][..],
+                b"sequenceMatch",
+                &[0x01, 0x0C, 0x04],
+                b"(?1)",
+                &[0x02, 0x11, 0x2D],
+            ]
+            .concat(),
+        ),
+        (
+            Type::SimpleAggregateFunction("sum".to_string(), Box::new(Type::UInt64)),
+            vec![0x2E, 0x03, b's', b'u', b'm', 0x00, 0x01, 0x04],
+        ),
+        (
+            Type::SimpleAggregateFunction(
+                "groupArrayArray(10)".to_string(),
+                Box::new(Type::Array(Box::new(Type::String))),
+            ),
+            [
+                &[0x2E, 0x0F][..],
+                b"groupArrayArray",
+                &[0x01, 0x01, 0x0A, 0x01, 0x1E, 0x15],
+            ]
+            .concat(),
+        ),
+        (
+            Type::Json(
+                vec![("a".to_string(), Type::UInt8)],
+                vec!["max_dynamic_types=4".to_string(), "SKIP b".to_string()],
+            ),
+            vec![
+                0x30, 0x00, 0x80, 0x10, 0x04, 0x01, 0x01, b'a', 0x01, 0x01, 0x01, b'b', 0x00,
+            ],
+        ),
+    ];
+    for (ty, bytes) in types {
+        assert_eq!(ty.to_binary().unwrap(), bytes, "{ty}");
+        let ty_parsed = Type::from_binary(&mut bytes.as_slice()).unwrap();
+        // NB: variant types are encoded in discriminator order
+        match &ty {
+            Type::Variant(_) => {
+                assert_eq!(ty_parsed.variant_types(), ty.variant_types())
+            }
+            _ => assert_eq!(ty_parsed, ty),
+        }
+    }
+
+    assert!(Type::from_binary(&mut [0x1E].as_slice()).is_err());
+    assert!(Type::from_binary(&mut [0x2E, 0x03, b's', b'u', b'm', 0x00, 0x00].as_slice()).is_err());
+    assert!(Type::AggregateFunction("f(x)".to_string(), vec![])
+        .to_binary()
+        .is_err());
+}
+
+/// Type strings, as listed in `system.columns`
+const SYSTEM_COLUMNS_TYPES: &[&str] = &[
+    "UInt64",
+    "LowCardinality(String)",
+    "DateTime64(6)",
+    "DateTime('Europe/Paris')",
+    "DateTime64(3, 'Asia/Tokyo')",
+    "Decimal(18, 2)",
+    "Nullable(Decimal(9, 4))",
+    "FixedString(16)",
+    "Array(LowCardinality(String))",
+    "Map(LowCardinality(String), UInt64)",
+    "Map(String, Array(Nullable(Float64)))",
+    "Enum8('QueryStart' = 1, 'QueryFinish' = 2, 'ExceptionBeforeStart' = 3)",
+    "Enum16('a, b' = -1, 'c = d' = 1000, 'it\\'s' = 2)",
+    "Tuple(UInt8, String)",
+    "Tuple(lat Float64, lon Float64)",
+    "Tuple(\n    id UInt64,\n    tags Array(String))",
+    "Array(Tuple(name String, value Nullable(Int32)))",
+    "Nested(a UInt8, b String)",
+    "Array(Nested(x UInt32, y Array(String)))",
+    "SimpleAggregateFunction(anyLast, Nullable(String))",
+    "AggregateFunction(quantiles(0.5, 0.9), UInt64)",
+    "AggregateFunction(sumMap, Array(UInt8), Array(UInt64))",
+    "Variant(String, UInt64, Array(UInt64))",
+    "Dynamic(max_types=10)",
+    "JSON(max_dynamic_paths=10, a.b UInt32, SKIP a.c, SKIP REGEXP 'x.*')",
+    "Time64(3)",
+    "Nullable(IntervalSecond)",
+    "Nullable(Nothing)",
+    "  Array( Nullable ( UInt8 ) )  ",
+];
+
+#[test]
+fn type_parse_corpus() {
+    for ty_str in SYSTEM_COLUMNS_TYPES {
+        let ty = ty_str
+            .parse::<Type>()
+            .unwrap_or_else(|err| panic!("{ty_str}: {err}"));
+        assert_eq!(ty.to_string().parse::<Type>().unwrap(), ty, "{ty_str}");
+    }
+
+    let ty = "Enum16('a, b' = -1, 'c = d' = 1000, 'it\\'s' = 2)"
+        .parse::<Type>()
+        .unwrap();
+    assert_eq!(
+        ty,
+        Type::Enum16(BTreeMap::from([
+            ("a, b".to_string(), -1),
+            ("c = d".to_string(), 1000),
+            ("it's".to_string(), 2),
+        ]))
+    );
+    let ty = "Enum8('a', 'b' = 5, 'c')".parse::<Type>().unwrap();
+    assert_eq!(
+        ty,
+        Type::Enum8(BTreeMap::from([
+            ("a".to_string(), 1),
+            ("b".to_string(), 5),
+            ("c".to_string(), 6),
+        ]))
+    );
+    let ty = "Array(Nested(x UInt32, y Array(String)))"
+        .parse::<Type>()
+        .unwrap();
+    assert_eq!(
+        ty,
+        Type::Array(Box::new(Type::Nested(vec![
+            ("x".to_string(), Type::UInt32),
+            ("y".to_string(), Type::Array(Box::new(Type::String))),
+        ])))
+    );
+    let ty = "AggregateFunction(quantiles(0.5, 0.9), UInt64)"
+        .parse::<Type>()
+        .unwrap();
+    assert_eq!(
+        ty,
+        Type::AggregateFunction("quantiles(0.5, 0.9)".to_string(), vec![Type::UInt64])
+    );
+    assert_eq!("Decimal(10)".parse::<Type>().unwrap(), Type::Decimal(10, 0));
+}
+
+#[test]
+fn type_parse_errors() {
+    let cases = [
+        (
+            "Array(UInt8",
+            "expected ')', found end of input at position 11",
+        ),
+        (
+            "Map(String UInt8)",
+            "expected ',', found 'UInt8' at position 11",
+        ),
+        (
+            "Nullable(Array(UInt8))",
+            "Array(UInt8) cannot be inside a Nullable type at position 9",
+        ),
+        ("Tuple(UInt8, Foo)", "unknown type 'Foo' at position 13"),
+        ("UInt8 x", "unexpected 'x' after the type at position 6"),
+        (
+            "Tuple(a UInt8, String)",
+            "mixed named and unnamed tuple elements at position 5",
+        ),
+        ("UInt8(1)", "unknown type 'UInt8' at position 0"),
+        (
+            "Enum8('a' = 300)",
+            "invalid enum value '300' at position 12",
+        ),
+        ("Enum8('a = 1)", "unterminated quoted string at position 6"),
+        (
+            "DateTime64(3, UTC)",
+            "expected a timezone, found 'UTC' at position 14",
+        ),
+        ("Array(#)", "unexpected character '#' at position 6"),
+        ("", "expected a type, found end of input at position 0"),
+    ];
+    for (ty_str, msg) in cases {
+        let err = ty_str.parse::<Type>().unwrap_err();
+        assert_eq!(
+            err.message(),
+            format!("Invalid type '{ty_str}': {msg}"),
+            "{ty_str}"
+        );
+    }
+}